@@ -0,0 +1,54 @@
+use bytes::{Bytes, BytesMut};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use redis_clone::resp::{RESPDecoder, RESPValues};
+
+fn set_command() -> Bytes {
+    Bytes::from_static(b"*3\r\n$3\r\nSET\r\n$8\r\nuser:100\r\n$16\r\n{\"name\":\"alice\"}\r\n")
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    let command = set_command();
+    group.throughput(Throughput::Bytes(command.len() as u64));
+    group.bench_function("set_command", |b| {
+        b.iter(|| RESPValues::try_from(black_box(command.clone())).unwrap())
+    });
+
+    let integers = Bytes::from(format!("*100\r\n{}", ":1234567\r\n".repeat(100)));
+    group.throughput(Throughput::Bytes(integers.len() as u64));
+    group.bench_function("integer_array", |b| {
+        b.iter(|| RESPValues::try_from(black_box(integers.clone())).unwrap())
+    });
+    group.finish();
+}
+
+fn decode_pipeline(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    let pipeline = set_command().repeat(1000);
+    group.throughput(Throughput::Bytes(pipeline.len() as u64));
+    group.bench_function("pipelined_set_commands", |b| {
+        b.iter(|| {
+            let mut decoder = RESPDecoder::new();
+            decoder.feed(black_box(&pipeline));
+            while decoder.decode().is_ok() {}
+        })
+    });
+    group.finish();
+}
+
+fn encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+    let value = RESPValues::try_from(set_command()).unwrap();
+    group.throughput(Throughput::Bytes(set_command().len() as u64));
+    group.bench_function("set_command", |b| {
+        let mut out = BytesMut::new();
+        b.iter(|| {
+            out.clear();
+            black_box(&value).encode(&mut out);
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse, decode_pipeline, encode);
+criterion_main!(benches);
@@ -0,0 +1,18 @@
+#![no_main]
+
+use bytes::BytesMut;
+use libfuzzer_sys::fuzz_target;
+use redis_clone::resp::{decode_frame, RESPLimits, RESPValues};
+
+// Feeds arbitrary bytes through both entry points of the parser. Neither may
+// panic, and whatever decodes must survive being encoded again.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(value) = RESPValues::try_from(data) {
+        value.to_bytes();
+    }
+
+    let mut buffer = BytesMut::from(data);
+    while let Ok(value) = decode_frame(&mut buffer, &RESPLimits::default()) {
+        value.to_bytes();
+    }
+});
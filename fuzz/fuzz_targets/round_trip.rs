@@ -0,0 +1,11 @@
+#![no_main]
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use redis_clone::resp::RESPValues;
+
+fuzz_target!(|value: RESPValues| {
+    let encoded = Bytes::from(value.to_bytes());
+
+    assert_eq!(RESPValues::decode(&encoded), Ok((value, encoded.len())));
+});
@@ -0,0 +1,716 @@
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use bytes::{Bytes, BytesMut};
+
+use crate::{
+    config::{AppendFsync, LogLevel},
+    rdb,
+    resp::{decode_frame, RESPDecodeError, RESPLimits, RESPValues},
+    store::{Snapshot, Value},
+};
+
+// The append only file in the Redis 7 layout: a directory holding a base
+// file with the dataset as of the last rewrite, incremental files with every
+// write command since as the RESP array it came in, and a manifest listing
+// which of them make up the AOF
+#[derive(Default)]
+pub struct Aof {
+    state: Arc<Mutex<AofState>>,
+    rewriting: Arc<AtomicBool>,
+}
+
+// Where the AOF goes and how it's written, from the append* parameters
+#[derive(PartialEq, Debug, Clone)]
+pub struct Options {
+    pub dir: PathBuf,
+    // what the names of the files in `dir` start with
+    pub filename: String,
+    pub fsync: AppendFsync,
+    // bases are written as RDB dumps rather than commands
+    pub rdb_preamble: bool,
+    pub rdb: rdb::Options,
+}
+
+impl Options {
+    pub fn manifest_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.manifest", self.filename))
+    }
+}
+
+#[derive(Default)]
+struct AofState {
+    // the directory opened, None until the first write
+    dir: Option<PathBuf>,
+    manifest: Manifest,
+    // the last incremental file, where writes are appended
+    incr: Option<File>,
+    // of every file in the manifest
+    size: u64,
+    // the size right after the last rewrite, what auto rewrites measure growth against
+    base_size: u64,
+}
+
+impl Aof {
+    pub fn append(&self, options: &Options, args: &[Bytes]) -> io::Result<()> {
+        let mut command = Vec::new();
+        RESPValues::Array(args.iter().cloned().map(RESPValues::BulkString).collect())
+            .encode(&mut command);
+
+        let mut state = self.state.lock().unwrap();
+        let file = state.open(options)?;
+        file.write_all(&command)?;
+        if options.fsync == AppendFsync::Always {
+            file.sync_data()?;
+        }
+        state.size += command.len() as u64;
+        Ok(())
+    }
+
+    // Flushes the appended writes to disk, for appendfsync everysec
+    pub fn sync(&self) -> io::Result<()> {
+        let state = self.state.lock().unwrap();
+        match &state.incr {
+            Some(file) => file.sync_data(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.state.lock().unwrap().size
+    }
+
+    pub fn rewriting(&self) -> bool {
+        self.rewriting.load(Ordering::Acquire)
+    }
+
+    // Whether the AOF grew enough since the last rewrite for another one
+    pub fn due(&self, percentage: u64, min_size: u64) -> bool {
+        let state = self.state.lock().unwrap();
+        let base = state.base_size.max(1);
+        percentage > 0
+            && !self.rewriting()
+            && state.size >= min_size
+            && (state.size.saturating_sub(base)) * 100 / base >= percentage
+    }
+
+    // Writes a new base from `snapshot` on a thread of its own, false if a
+    // rewrite is still running. Appends move to a new incremental file right
+    // away, so `snapshot` must be taken while nothing writes, see
+    // Shared::bgrewriteaof. Once the base is written the manifest swaps to
+    // it and the files it replaces are deleted
+    pub fn rewrite(
+        &self,
+        options: Options,
+        snapshot: Snapshot,
+        loglevel: LogLevel,
+    ) -> io::Result<bool> {
+        if self.rewriting.swap(true, Ordering::AcqRel) {
+            return Ok(false);
+        }
+        let started = self.state.lock().unwrap().start_rewrite(&options);
+        let (base, first_incr) = match started {
+            Ok(started) => started,
+            Err(error) => {
+                self.rewriting.store(false, Ordering::Release);
+                return Err(error);
+            }
+        };
+
+        let state = self.state.clone();
+        let rewriting = self.rewriting.clone();
+        thread::spawn(move || {
+            let result = write_base(&options, &base, &snapshot.entries()).and_then(|()| {
+                let mut state = state.lock().unwrap();
+                state.finish_rewrite(&options, base, first_incr)
+            });
+            if let Err(error) = result {
+                if loglevel <= LogLevel::Warning {
+                    eprintln!("Background AOF rewrite error: {error}");
+                }
+            }
+            rewriting.store(false, Ordering::Release);
+        });
+        Ok(true)
+    }
+}
+
+impl AofState {
+    // The incremental file appends go to, opening the AOF on first use and
+    // again when dir changed
+    fn open(&mut self, options: &Options) -> io::Result<&mut File> {
+        if self.incr.is_none() || self.dir.as_ref() != Some(&options.dir) {
+            fs::create_dir_all(&options.dir)?;
+            let mut manifest = match fs::read_to_string(options.manifest_path()) {
+                Ok(text) => text.parse()?,
+                Err(error) if error.kind() == io::ErrorKind::NotFound => Manifest::default(),
+                Err(error) => return Err(error),
+            };
+            if manifest.incrs.is_empty() {
+                manifest.incrs.push(AofFile::incr(&options.filename, 1));
+                manifest.save(options)?;
+            }
+
+            let last = &manifest.incrs[manifest.incrs.len() - 1];
+            let incr = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(options.dir.join(&last.name))?;
+            self.size = manifest.size(&options.dir)?;
+            self.base_size = self.size;
+            self.manifest = manifest;
+            self.incr = Some(incr);
+            self.dir = Some(options.dir.clone());
+        }
+        Ok(self.incr.as_mut().unwrap())
+    }
+
+    // Moves appends to a new incremental file, in the manifest from the
+    // start so no write is lost if the rewrite fails. Returns the base to
+    // write and the sequence of the first incremental file after it
+    fn start_rewrite(&mut self, options: &Options) -> io::Result<(AofFile, u64)> {
+        self.open(options)?;
+        let seq = self.manifest.incrs.last().map_or(1, |incr| incr.seq + 1);
+        let incr = AofFile::incr(&options.filename, seq);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(options.dir.join(&incr.name))?;
+        self.manifest.incrs.push(incr);
+        self.manifest.save(options)?;
+        self.incr = Some(file);
+
+        let base_seq = self.manifest.base.as_ref().map_or(1, |base| base.seq + 1);
+        let kind = if options.rdb_preamble { "rdb" } else { "aof" };
+        let base = AofFile {
+            name: format!("{}.{base_seq}.base.{kind}", options.filename),
+            seq: base_seq,
+        };
+        Ok((base, seq))
+    }
+
+    fn finish_rewrite(
+        &mut self,
+        options: &Options,
+        base: AofFile,
+        first_incr: u64,
+    ) -> io::Result<()> {
+        let previous = self.manifest.clone();
+        self.manifest.base = Some(base);
+        self.manifest.incrs.retain(|incr| incr.seq >= first_incr);
+        self.manifest.save(options)?;
+
+        for file in previous.files() {
+            if !self.manifest.files().any(|kept| kept.name == file.name) {
+                let _ = fs::remove_file(options.dir.join(&file.name));
+            }
+        }
+        self.size = self.manifest.size(&options.dir)?;
+        self.base_size = self.size;
+        Ok(())
+    }
+}
+
+// The dataset as an RDB dump with rdb_preamble, as RESTORE commands otherwise
+fn write_base(options: &Options, base: &AofFile, entries: &[(Bytes, Value)]) -> io::Result<()> {
+    let temp = (options.dir).join(format!("temp-rewriteaof-bg-{}.aof", std::process::id()));
+    let result = (|| {
+        let mut out = BufWriter::new(File::create(&temp)?);
+        if options.rdb_preamble {
+            rdb::write(entries, options.rdb, &mut out)?;
+        } else {
+            let mut command = Vec::new();
+            for (key, value) in entries {
+                RESPValues::Array(vec![
+                    RESPValues::BulkString(Bytes::from_static(b"RESTORE")),
+                    RESPValues::BulkString(key.clone()),
+                    RESPValues::BulkString(Bytes::from_static(b"0")),
+                    RESPValues::BulkString(rdb::dump(value, options.rdb).into()),
+                ])
+                .encode(&mut command);
+                out.write_all(&command)?;
+                command.clear();
+            }
+        }
+        out.into_inner()?.sync_all()?;
+        fs::rename(&temp, options.dir.join(&base.name))
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct AofFile {
+    pub name: String,
+    pub seq: u64,
+}
+
+impl AofFile {
+    fn incr(filename: &str, seq: u64) -> Self {
+        Self {
+            name: format!("{filename}.{seq}.incr.aof"),
+            seq,
+        }
+    }
+}
+
+// The files making up the AOF, one `file <name> seq <seq> type <b|i>` line each
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Manifest {
+    pub base: Option<AofFile>,
+    // oldest first
+    pub incrs: Vec<AofFile>,
+}
+
+impl Manifest {
+    pub fn files(&self) -> impl Iterator<Item = &AofFile> {
+        self.base.iter().chain(&self.incrs)
+    }
+
+    fn size(&self, dir: &Path) -> io::Result<u64> {
+        let mut size = 0;
+        for file in self.files() {
+            size += match fs::metadata(dir.join(&file.name)) {
+                Ok(metadata) => metadata.len(),
+                Err(error) if error.kind() == io::ErrorKind::NotFound => 0,
+                Err(error) => return Err(error),
+            };
+        }
+        Ok(size)
+    }
+
+    // Through a temporary file, so the manifest is never seen halfway written
+    fn save(&self, options: &Options) -> io::Result<()> {
+        let temp = (options.dir).join(format!("temp-{}.manifest", options.filename));
+        let mut file = File::create(&temp)?;
+        file.write_all(self.to_string().as_bytes())?;
+        file.sync_all()?;
+        fs::rename(temp, options.manifest_path())
+    }
+}
+
+impl std::str::FromStr for Manifest {
+    type Err = io::Error;
+
+    // History files, which Redis lists until it gets to delete them, are skipped
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut manifest = Manifest::default();
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid AOF manifest line '{line}'"),
+                )
+            };
+            let words: Vec<_> = line.split_whitespace().collect();
+            let field = |name| {
+                words
+                    .chunks_exact(2)
+                    .find(|pair| pair[0] == name)
+                    .map(|pair| pair[1])
+            };
+            let file = AofFile {
+                name: field("file").ok_or_else(invalid)?.to_string(),
+                seq: field("seq")
+                    .and_then(|seq| seq.parse().ok())
+                    .ok_or_else(invalid)?,
+            };
+            match field("type") {
+                Some("b") => manifest.base = Some(file),
+                Some("i") => manifest.incrs.push(file),
+                Some("h") => {}
+                _ => return Err(invalid()),
+            }
+        }
+        manifest.incrs.sort_by_key(|incr| incr.seq);
+        Ok(manifest)
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(base) = &self.base {
+            writeln!(f, "file {} seq {} type b", base.name, base.seq)?;
+        }
+        for incr in &self.incrs {
+            writeln!(f, "file {} seq {} type i", incr.name, incr.seq)?;
+        }
+        Ok(())
+    }
+}
+
+// What the AOF holds, in the order it has to be applied
+#[derive(PartialEq, Debug, Clone)]
+pub enum Record {
+    // from a base written as an RDB dump
+    Entry(Bytes, Value),
+    // after the entries of such a base, how many lost their expire time, if any did
+    Persisted(usize),
+    Command(RESPValues),
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Loaded {
+    NotFound,
+    Whole,
+    // the last incremental file, named here, ended mid command and was cut
+    // back to the last whole one
+    Truncated(String),
+}
+
+// Reads the AOF in `options.dir`, handing every record to `apply`. A last
+// file ending mid command or inside a MULTI, as a crash leaves it, is cut
+// back to where that started when `load_truncated`, and fails the load
+// otherwise, as aof-load-truncated in Redis
+pub fn load(
+    options: &Options,
+    load_truncated: bool,
+    mut apply: impl FnMut(Record) -> io::Result<()>,
+) -> io::Result<Loaded> {
+    let manifest: Manifest = match fs::read_to_string(options.manifest_path()) {
+        Ok(text) => text.parse()?,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Loaded::NotFound),
+        Err(error) => return Err(error),
+    };
+    let files: Vec<_> = manifest.files().collect();
+    let mut loaded = Loaded::Whole;
+    for (i, file) in files.iter().enumerate() {
+        let path = options.dir.join(&file.name);
+        let bytes = match fs::read(&path) {
+            // incremental files are listed before anything is written to them
+            Err(error)
+                if error.kind() == io::ErrorKind::NotFound
+                    && manifest.base.as_ref() != Some(*file) =>
+            {
+                continue
+            }
+            result => result?,
+        };
+        if file.name.ends_with(".rdb") {
+            let (entries, persisted) = rdb::read(&bytes, options.rdb)?;
+            for (key, value) in entries {
+                apply(Record::Entry(key, value))?;
+            }
+            if persisted > 0 {
+                apply(Record::Persisted(persisted))?;
+            }
+            continue;
+        }
+
+        let valid = replay(&file.name, &bytes, &mut apply)?;
+        if valid < bytes.len() {
+            if !load_truncated || i + 1 < files.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!(
+                        "Unexpected end of file reading the append only file {}. \
+                         Set aof-load-truncated to yes to load it anyway",
+                        file.name
+                    ),
+                ));
+            }
+            OpenOptions::new()
+                .write(true)
+                .open(&path)?
+                .set_len(valid as u64)?;
+            loaded = Loaded::Truncated(file.name.clone());
+        }
+    }
+    Ok(loaded)
+}
+
+// Applies the commands in `bytes`, returning how many of them were whole
+// commands outside of a MULTI
+fn replay(
+    name: &str,
+    bytes: &[u8],
+    apply: &mut impl FnMut(Record) -> io::Result<()>,
+) -> io::Result<usize> {
+    let total = bytes.len();
+    let mut buffer = BytesMut::from(bytes);
+    let limits = RESPLimits::default();
+    let mut valid = 0;
+    let mut in_multi = false;
+    while !buffer.is_empty() {
+        let frame = match decode_frame(&mut buffer, &limits) {
+            Ok(frame) => frame,
+            Err(RESPDecodeError::NeedMoreData) => break,
+            Err(RESPDecodeError::Invalid(error)) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Bad file format reading the append only file {name}: {error}"),
+                ))
+            }
+        };
+        let command = command_name(&frame).map(|name| name.to_ascii_uppercase());
+        match command.as_deref() {
+            Some(b"MULTI") => in_multi = true,
+            Some(b"EXEC" | b"DISCARD") => in_multi = false,
+            _ => {}
+        }
+        apply(Record::Command(frame))?;
+        if !in_multi {
+            valid = total - buffer.len();
+        }
+    }
+    Ok(valid)
+}
+
+// args[0] of a command as the AOF has it, an array of bulk strings
+pub fn command_name(frame: &RESPValues) -> Option<&[u8]> {
+    match frame {
+        RESPValues::Array(values) => match values.first() {
+            Some(RESPValues::BulkString(name)) => Some(name),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod aof_tests {
+    use bytes::Bytes;
+
+    use super::{load, Aof, AofFile, Loaded, Manifest, Options, Record};
+    use crate::{
+        commands::{test_dir, TestDir},
+        config::{AppendFsync, LogLevel},
+        rdb,
+        resp::RESPValues,
+        store::{Store, Value},
+    };
+
+    fn temp_options(dir: &TestDir) -> Options {
+        Options {
+            dir: dir.path().to_path_buf(),
+            filename: "appendonly.aof".to_string(),
+            fsync: AppendFsync::No,
+            rdb_preamble: false,
+            rdb: Default::default(),
+        }
+    }
+
+    #[test]
+    fn append_commands_correctly() {
+        let dir = test_dir("aof-append");
+        let options = Options {
+            fsync: AppendFsync::Always,
+            ..temp_options(&dir)
+        };
+        let aof = Aof::default();
+        let args = [
+            Bytes::from_static(b"SET"),
+            Bytes::from_static(b"k"),
+            Bytes::from_static(b"v"),
+        ];
+        aof.append(&options, &args).unwrap();
+
+        let expected = b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$1\r\nv\r\n";
+        let incr = options.dir.join("appendonly.aof.1.incr.aof");
+        assert_eq!(std::fs::read(incr).unwrap(), expected);
+        assert_eq!(
+            std::fs::read_to_string(options.manifest_path()).unwrap(),
+            "file appendonly.aof.1.incr.aof seq 1 type i\n"
+        );
+        assert_eq!(aof.size(), expected.len() as u64);
+    }
+
+    #[test]
+    fn rewrite_into_a_new_base_correctly() {
+        let dir = test_dir("aof-rewrite");
+        let options = temp_options(&dir);
+        let aof = Aof::default();
+        let del = [Bytes::from_static(b"DEL"), Bytes::from_static(b"old")];
+        aof.append(&options, &del).unwrap();
+
+        let store = Store::default();
+        store.set(
+            Bytes::from_static(b"k"),
+            Value::String(Bytes::from_static(b"v")),
+        );
+        assert!(aof
+            .rewrite(options.clone(), store.snapshot(), LogLevel::Warning)
+            .unwrap());
+        let later = [Bytes::from_static(b"DEL"), Bytes::from_static(b"k")];
+        aof.append(&options, &later).unwrap();
+        while aof.rewriting() {
+            std::thread::yield_now();
+        }
+
+        let read = |name: &str| std::fs::read(options.dir.join(name)).unwrap();
+        let value = Value::String(Bytes::from_static(b"v"));
+        let mut restore = Vec::new();
+        RESPValues::Array(vec![
+            RESPValues::BulkString(Bytes::from_static(b"RESTORE")),
+            RESPValues::BulkString(Bytes::from_static(b"k")),
+            RESPValues::BulkString(Bytes::from_static(b"0")),
+            RESPValues::BulkString(rdb::dump(&value, options.rdb).into()),
+        ])
+        .encode(&mut restore);
+        assert_eq!(read("appendonly.aof.1.base.aof"), restore);
+        assert_eq!(
+            read("appendonly.aof.2.incr.aof"),
+            b"*2\r\n$3\r\nDEL\r\n$1\r\nk\r\n"
+        );
+        assert!(!options.dir.join("appendonly.aof.1.incr.aof").exists());
+        assert_eq!(
+            std::fs::read_to_string(options.manifest_path()).unwrap(),
+            "file appendonly.aof.1.base.aof seq 1 type b\n\
+             file appendonly.aof.2.incr.aof seq 2 type i\n"
+        );
+    }
+
+    #[test]
+    fn rewrite_with_an_rdb_preamble_correctly() {
+        let dir = test_dir("aof-preamble");
+        let options = Options {
+            rdb_preamble: true,
+            ..temp_options(&dir)
+        };
+        let aof = Aof::default();
+        let store = Store::default();
+        store.set(
+            Bytes::from_static(b"k"),
+            Value::String(Bytes::from_static(b"v")),
+        );
+        assert!(aof
+            .rewrite(options.clone(), store.snapshot(), LogLevel::Warning)
+            .unwrap());
+        while aof.rewriting() {
+            std::thread::yield_now();
+        }
+
+        let base = std::fs::read(options.dir.join("appendonly.aof.1.base.rdb")).unwrap();
+        assert!(base.starts_with(b"REDIS0011"));
+    }
+
+    #[test]
+    fn come_due_for_rewrite_correctly() {
+        let dir = test_dir("aof-due");
+        let options = temp_options(&dir);
+        let aof = Aof::default();
+        let args = [Bytes::from_static(b"DEL"), Bytes::from_static(b"k")];
+        aof.append(&options, &args).unwrap();
+
+        assert!(aof.due(100, 0));
+        assert!(!aof.due(100, 1024));
+        assert!(!aof.due(0, 0));
+    }
+
+    #[test]
+    fn load_base_and_incremental_files_correctly() {
+        let dir = test_dir("aof-load");
+        let options = Options {
+            rdb_preamble: true,
+            ..temp_options(&dir)
+        };
+        let aof = Aof::default();
+        let store = Store::default();
+        store.set(
+            Bytes::from_static(b"k"),
+            Value::String(Bytes::from_static(b"v")),
+        );
+        assert!(aof
+            .rewrite(options.clone(), store.snapshot(), LogLevel::Warning)
+            .unwrap());
+        let del = [Bytes::from_static(b"DEL"), Bytes::from_static(b"k")];
+        aof.append(&options, &del).unwrap();
+        while aof.rewriting() {
+            std::thread::yield_now();
+        }
+
+        let mut records = Vec::new();
+        let loaded = load(&options, false, |record| {
+            records.push(record);
+            Ok(())
+        });
+        assert!(loaded.is_ok_and(|l| l == Loaded::Whole));
+        assert_eq!(
+            records,
+            [
+                Record::Entry(
+                    Bytes::from_static(b"k"),
+                    Value::String(Bytes::from_static(b"v"))
+                ),
+                Record::Command(RESPValues::Array(
+                    del.into_iter().map(RESPValues::BulkString).collect()
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn load_truncated_file_correctly() {
+        let dir = test_dir("aof-truncated");
+        let options = temp_options(&dir);
+        let aof = Aof::default();
+        let del = [Bytes::from_static(b"DEL"), Bytes::from_static(b"k")];
+        aof.append(&options, &del).unwrap();
+        let incr = options.dir.join("appendonly.aof.1.incr.aof");
+        let whole = std::fs::read(&incr).unwrap();
+        let mut contents = whole.clone();
+        contents.extend_from_slice(
+            b"*1\r\n$5\r\nMULTI\r\n*2\r\n$3\r\nDEL\r\n$1\r\nk\r\n*1\r\n$4\r\nEX",
+        );
+        std::fs::write(&incr, contents).unwrap();
+
+        let loaded = load(&options, true, |_| Ok(()));
+        assert!(
+            loaded.is_ok_and(|l| l == Loaded::Truncated("appendonly.aof.1.incr.aof".to_string()))
+        );
+        assert_eq!(std::fs::read(&incr).unwrap(), whole);
+    }
+
+    #[test]
+    fn load_truncated_file_fails() {
+        let dir = test_dir("aof-truncated-fails");
+        let options = temp_options(&dir);
+        let aof = Aof::default();
+        let del = [Bytes::from_static(b"DEL"), Bytes::from_static(b"k")];
+        aof.append(&options, &del).unwrap();
+        let incr = options.dir.join("appendonly.aof.1.incr.aof");
+        let mut contents = std::fs::read(&incr).unwrap();
+        contents.extend_from_slice(b"*2\r\n$3\r\nDEL");
+        std::fs::write(&incr, &contents).unwrap();
+
+        let loaded = load(&options, false, |_| Ok(()));
+        assert!(loaded.is_err_and(|e| e.kind() == std::io::ErrorKind::UnexpectedEof));
+        assert_eq!(std::fs::read(&incr).unwrap(), contents);
+    }
+
+    #[test]
+    fn parse_manifest_correctly() {
+        let text = "file a.2.incr.aof seq 2 type i\nfile a.1.base.rdb seq 1 type b\n\
+                    file a.0.base.rdb seq 0 type h\nfile a.1.incr.aof seq 1 type i\n";
+        let manifest: Manifest = text.parse().unwrap();
+
+        let file = |name: &str, seq| AofFile {
+            name: name.to_string(),
+            seq,
+        };
+        assert_eq!(manifest.base, Some(file("a.1.base.rdb", 1)));
+        assert_eq!(
+            manifest.incrs,
+            [file("a.1.incr.aof", 1), file("a.2.incr.aof", 2)]
+        );
+    }
+
+    #[test]
+    fn parse_invalid_manifest_fails() {
+        assert!("file a.1.incr.aof type i".parse::<Manifest>().is_err());
+        assert!("file a.1.incr.aof seq 1 type x"
+            .parse::<Manifest>()
+            .is_err());
+    }
+}
@@ -0,0 +1,830 @@
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use bytes::{Bytes, BytesMut};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use crate::{
+    config::LogLevel,
+    crc16,
+    replication::random_id,
+    resp::{decode_frame, RESPDecodeError, RESPLimits, RESPValues},
+    server::Shared,
+};
+
+pub const SLOTS: usize = 16384;
+
+// What this node knows of the cluster: its own identity, the other nodes
+// and which of them serves each hash slot. It's kept in the cluster config
+// file, nodes.conf, in the format CLUSTER NODES lists it in
+pub struct Cluster {
+    state: RwLock<ClusterState>,
+}
+
+impl Default for Cluster {
+    fn default() -> Self {
+        let myself = Node {
+            id: random_id(),
+            ip: String::new(),
+            port: 0,
+            myself: true,
+            epoch: 0,
+        };
+        Self {
+            state: RwLock::new(ClusterState {
+                myself: myself.id.clone(),
+                current_epoch: 0,
+                nodes: BTreeMap::from([(myself.id.clone(), myself)]),
+                slots: vec![None; SLOTS],
+                migrating: BTreeMap::new(),
+                importing: BTreeMap::new(),
+                meets: Vec::new(),
+                file: None,
+            }),
+        }
+    }
+}
+
+struct ClusterState {
+    myself: String,
+    current_epoch: u64,
+    // keyed by node id, this node's own too
+    nodes: BTreeMap<String, Node>,
+    // the id of the node serving each slot
+    slots: Vec<Option<String>>,
+    // the slots this node hands over to another one while they're resharded,
+    // and the ones it takes over, by the id of the node at the other end
+    migrating: BTreeMap<usize, String>,
+    importing: BTreeMap<usize, String>,
+    // the addresses CLUSTER MEET was given that haven't answered yet
+    meets: Vec<(String, u16)>,
+    // where it's saved, None until the cluster starts
+    file: Option<PathBuf>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Node {
+    pub id: String,
+    pub ip: String,
+    // the one clients connect to
+    pub port: u16,
+    pub myself: bool,
+    // the config epoch its slots were claimed with
+    pub epoch: u64,
+}
+
+impl Node {
+    // Redis nodes talk over a bus port 10000 above the client one, which
+    // client tooling expects in the listings even though this server has no bus
+    pub fn bus_port(&self) -> u32 {
+        self.port as u32 + 10000
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum ClusterError {
+    OutOfRange,
+    Repeated(usize),
+    Busy(usize),
+    Unassigned(usize),
+    NotOwner(usize),
+    AlreadyOwner(usize),
+    HoldsKeys(usize),
+    // for SETSLOT NODE, and with the other actions
+    UnknownNode(String),
+    NotKnown(String),
+    // the change was made, but isn't in the cluster config file
+    Save(String),
+}
+
+impl fmt::Display for ClusterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfRange => write!(f, "Invalid or out of range slot"),
+            Self::Repeated(slot) => write!(f, "Slot {slot} specified multiple times"),
+            Self::Busy(slot) => write!(f, "Slot {slot} is already busy"),
+            Self::Unassigned(slot) => write!(f, "Slot {slot} is already unassigned"),
+            Self::NotOwner(slot) => write!(f, "I'm not the owner of hash slot {slot}"),
+            Self::AlreadyOwner(slot) => write!(f, "I'm already the owner of hash slot {slot}"),
+            Self::HoldsKeys(slot) => write!(
+                f,
+                "Can't assign hashslot {slot} to a different node while I still hold keys for this hash slot."
+            ),
+            Self::UnknownNode(id) => write!(f, "Unknown node {id}"),
+            Self::NotKnown(id) => write!(f, "I don't know about node {id}"),
+            Self::Save(error) => write!(f, "Error saving the cluster config file: {error}"),
+        }
+    }
+}
+
+// Where the keys of a command are served when it's not by this node
+#[derive(PartialEq, Debug)]
+pub enum Redirect {
+    // the slot and the address of the node serving it
+    Moved(usize, String),
+    // the same for just the next command, during a reshard
+    Ask(usize, String),
+    // some of the keys were moved already, and some not yet
+    TryAgain,
+    CrossSlot,
+    Unserved,
+}
+
+// CLUSTER SETSLOT, with the id of the node at the other end
+#[derive(PartialEq, Debug)]
+pub enum SetSlot {
+    Migrating(String),
+    Importing(String),
+    Stable,
+    Node(String),
+}
+
+// The slot of `key`, hashing only what's between its first `{` and the
+// `}` after it when that's not empty, so {tags} keep keys in one slot
+pub fn key_slot(key: &[u8]) -> usize {
+    let tag = key.iter().position(|&b| b == b'{').and_then(|open| {
+        let rest = &key[open + 1..];
+        let close = rest.iter().position(|&b| b == b'}')?;
+        (close > 0).then(|| &rest[..close])
+    });
+    crc16::checksum(tag.unwrap_or(key)) as usize % SLOTS
+}
+
+impl Cluster {
+    // Takes on the identity and slots saved in `file` when it exists, then
+    // saves them back with the address this node listens on
+    pub fn start(&self, file: &Path, ip: String, port: u16) -> io::Result<()> {
+        let mut state = self.state.write().unwrap();
+        if file.exists() {
+            let saved: Saved = fs::read_to_string(file)?.parse()?;
+            *state = saved.into_state();
+        }
+        let myself = state.myself.clone();
+        let node = state.nodes.get_mut(&myself).unwrap();
+        node.ip = ip;
+        node.port = port;
+        state.file = Some(file.to_path_buf());
+        state.save()
+    }
+
+    pub fn myself(&self) -> Node {
+        let state = self.state.read().unwrap();
+        state.nodes[&state.myself].clone()
+    }
+
+    pub fn nodes(&self) -> Vec<Node> {
+        self.state.read().unwrap().nodes.values().cloned().collect()
+    }
+
+    pub fn current_epoch(&self) -> u64 {
+        self.state.read().unwrap().current_epoch
+    }
+
+    pub fn owner(&self, slot: usize) -> Option<Node> {
+        let state = self.state.read().unwrap();
+        let id = state.slots[slot].as_ref()?;
+        state.nodes.get(id).cloned()
+    }
+
+    pub fn slots_assigned(&self) -> usize {
+        let state = self.state.read().unwrap();
+        state.slots.iter().filter(|owner| owner.is_some()).count()
+    }
+
+    // Every run of consecutive slots served by the same node, in slot order
+    pub fn ranges(&self) -> Vec<(usize, usize, Node)> {
+        let state = self.state.read().unwrap();
+        state
+            .ranges()
+            .into_iter()
+            .map(|(start, end, id)| (start, end, state.nodes[id].clone()))
+            .collect()
+    }
+
+    // CLUSTER ADDSLOTS, all of `slots` or none
+    pub fn add_slots(&self, slots: &[usize]) -> Result<(), ClusterError> {
+        let mut state = self.state.write().unwrap();
+        check(slots)?;
+        if let Some(&slot) = slots.iter().find(|&&slot| state.slots[slot].is_some()) {
+            return Err(ClusterError::Busy(slot));
+        }
+        let myself = state.myself.clone();
+        for &slot in slots {
+            state.slots[slot] = Some(myself.clone());
+        }
+        state.save().map_err(|e| ClusterError::Save(e.to_string()))
+    }
+
+    // CLUSTER DELSLOTS, all of `slots` or none
+    pub fn del_slots(&self, slots: &[usize]) -> Result<(), ClusterError> {
+        let mut state = self.state.write().unwrap();
+        check(slots)?;
+        if let Some(&slot) = slots.iter().find(|&&slot| state.slots[slot].is_none()) {
+            return Err(ClusterError::Unassigned(slot));
+        }
+        for &slot in slots {
+            state.slots[slot] = None;
+        }
+        state.save().map_err(|e| ClusterError::Save(e.to_string()))
+    }
+
+    // CLUSTER NODES, one line a node
+    pub fn describe(&self) -> String {
+        self.state.read().unwrap().describe()
+    }
+
+    // Keys are served by the node their slot is assigned to, and those of a
+    // command all have to be in the same slot. While the slot is migrating,
+    // the keys not here anymore are asked for at the node it goes to, which
+    // serves them to clients that said ASKING first
+    pub fn route(
+        &self,
+        keys: &[Bytes],
+        asking: bool,
+        exists: impl Fn(&[u8]) -> bool,
+    ) -> Result<(), Redirect> {
+        let Some(first) = keys.first() else {
+            return Ok(());
+        };
+        let slot = key_slot(first);
+        if keys.iter().any(|key| key_slot(key) != slot) {
+            return Err(Redirect::CrossSlot);
+        }
+        let state = self.state.read().unwrap();
+        let address = |id: &String| {
+            let node = &state.nodes[id];
+            format!("{}:{}", node.ip, node.port)
+        };
+        let resharded = state.migrating.contains_key(&slot) || state.importing.contains_key(&slot);
+        let missing = if resharded {
+            keys.iter().filter(|key| !exists(key)).count()
+        } else {
+            0
+        };
+        match &state.slots[slot] {
+            Some(id) if *id == state.myself => match state.migrating.get(&slot) {
+                Some(target) if missing == keys.len() => Err(Redirect::Ask(slot, address(target))),
+                Some(_) if missing > 0 => Err(Redirect::TryAgain),
+                _ => Ok(()),
+            },
+            _ if asking && state.importing.contains_key(&slot) => {
+                if missing > 0 && keys.len() > 1 {
+                    Err(Redirect::TryAgain)
+                } else {
+                    Ok(())
+                }
+            }
+            Some(id) => Err(Redirect::Moved(slot, address(id))),
+            None => Err(Redirect::Unserved),
+        }
+    }
+
+    // CLUSTER SETSLOT. Giving a slot away takes it having no keys left, and
+    // taking over an imported one bumps this node's config epoch so its
+    // claim wins over the one of the node it came from
+    pub fn set_slot(
+        &self,
+        slot: usize,
+        action: SetSlot,
+        holds_keys: impl Fn() -> bool,
+    ) -> Result<(), ClusterError> {
+        let mut state = self.state.write().unwrap();
+        let state = &mut *state;
+        let mine = state.slots[slot].as_ref() == Some(&state.myself);
+        match action {
+            SetSlot::Migrating(id) | SetSlot::Importing(id) if !state.nodes.contains_key(&id) => {
+                return Err(ClusterError::NotKnown(id));
+            }
+            SetSlot::Migrating(_) if !mine => return Err(ClusterError::NotOwner(slot)),
+            SetSlot::Importing(_) if mine => return Err(ClusterError::AlreadyOwner(slot)),
+            SetSlot::Migrating(id) => {
+                state.migrating.insert(slot, id);
+            }
+            SetSlot::Importing(id) => {
+                state.importing.insert(slot, id);
+            }
+            SetSlot::Stable => {
+                state.migrating.remove(&slot);
+                state.importing.remove(&slot);
+            }
+            SetSlot::Node(id) if !state.nodes.contains_key(&id) => {
+                return Err(ClusterError::UnknownNode(id));
+            }
+            SetSlot::Node(id) if mine && id != state.myself && holds_keys() => {
+                return Err(ClusterError::HoldsKeys(slot));
+            }
+            SetSlot::Node(id) => {
+                if id != state.myself {
+                    state.migrating.remove(&slot);
+                } else if state.importing.remove(&slot).is_some() {
+                    state.current_epoch += 1;
+                    let epoch = state.current_epoch;
+                    state.nodes.get_mut(&id).unwrap().epoch = epoch;
+                }
+                state.slots[slot] = Some(id);
+            }
+        }
+        state.save().map_err(|e| ClusterError::Save(e.to_string()))
+    }
+
+    // CLUSTER MEET, the node there is polled from then on
+    pub fn meet(&self, ip: String, port: u16) {
+        let mut state = self.state.write().unwrap();
+        let known = state
+            .nodes
+            .values()
+            .any(|node| node.ip == ip && node.port == port);
+        let address = (ip, port);
+        if !known && !state.meets.contains(&address) {
+            state.meets.push(address);
+        }
+    }
+
+    // The addresses of the other nodes, and of the ones met
+    fn peers(&self) -> Vec<(String, u16)> {
+        let state = self.state.read().unwrap();
+        let known = state.nodes.values().filter(|node| !node.myself);
+        known
+            .map(|node| (node.ip.clone(), node.port))
+            .chain(state.meets.iter().cloned())
+            .collect()
+    }
+
+    // Takes in the CLUSTER NODES of the node at `address`: the nodes it
+    // knows of, and the slots it claims for itself where its config epoch
+    // is ahead of the one of the node they were assigned to
+    fn learn(&self, address: &(String, u16), saved: Saved) -> io::Result<()> {
+        let mut state = self.state.write().unwrap();
+        state.meets.retain(|meet| meet != address);
+        let mut changed = false;
+        for (mut node, ranges) in saved.nodes {
+            if node.id == state.myself {
+                continue;
+            }
+            if !node.myself {
+                if !state.nodes.contains_key(&node.id) {
+                    state.nodes.insert(node.id.clone(), node);
+                    changed = true;
+                }
+                continue;
+            }
+            node.myself = false;
+            let mut claimed = vec![false; SLOTS];
+            for (start, end) in ranges {
+                claimed[start..=end].fill(true);
+            }
+            for (slot, claimed) in claimed.into_iter().enumerate() {
+                let owner = state.slots[slot].as_ref();
+                let taken = match owner {
+                    _ if !claimed => false,
+                    None => true,
+                    Some(id) => *id != node.id && state.nodes[id].epoch < node.epoch,
+                };
+                let released = !claimed && owner == Some(&node.id);
+                if taken {
+                    state.slots[slot] = Some(node.id.clone());
+                    state.migrating.remove(&slot);
+                } else if released {
+                    state.slots[slot] = None;
+                }
+                changed |= taken || released;
+            }
+            state.current_epoch = state.current_epoch.max(node.epoch);
+            if state.nodes.get(&node.id) != Some(&node) {
+                state.nodes.insert(node.id.clone(), node);
+                changed = true;
+            }
+        }
+        if changed {
+            state.save()?;
+        }
+        Ok(())
+    }
+}
+
+// Gossip of sorts, as there's no cluster bus: once a second every other
+// node is told about this one with CLUSTER MEET and asked for its CLUSTER
+// NODES, for as long as the server runs
+pub async fn link(shared: Arc<Shared>) {
+    if !shared.config.read().unwrap().cluster_enabled {
+        return;
+    }
+    let mut ticks = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => {}
+            _ = shared.shutdown.wait() => return,
+        }
+        for peer in shared.cluster.peers() {
+            let polled = tokio::time::timeout(Duration::from_secs(1), poll(&shared.cluster, &peer));
+            let result = match polled.await {
+                Ok(result) => result.and_then(|saved| shared.cluster.learn(&peer, saved)),
+                Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "timed out")),
+            };
+            if let Err(error) = result {
+                if shared.config.read().unwrap().loglevel <= LogLevel::Verbose {
+                    eprintln!("Unable to reach node {}:{}: {error}", peer.0, peer.1);
+                }
+            }
+        }
+    }
+}
+
+async fn poll(cluster: &Cluster, (ip, port): &(String, u16)) -> io::Result<Saved> {
+    let myself = cluster.myself();
+    let mut stream = TcpStream::connect((ip.as_str(), *port)).await?;
+    let listening_port = myself.port.to_string();
+    let requests: [&[&str]; 2] = [
+        &["CLUSTER", "MEET", &myself.ip, &listening_port],
+        &["CLUSTER", "NODES"],
+    ];
+    let mut bytes = Vec::new();
+    for args in requests {
+        let args = args
+            .iter()
+            .map(|arg| RESPValues::BulkString(Bytes::copy_from_slice(arg.as_bytes())))
+            .collect();
+        RESPValues::Array(args).encode(&mut bytes);
+    }
+    stream.write_all(&bytes).await?;
+
+    let mut buffer = BytesMut::new();
+    let mut replies = Vec::new();
+    while replies.len() < requests.len() {
+        match decode_frame(&mut buffer, &RESPLimits::default()) {
+            Ok(reply) => replies.push(reply),
+            Err(RESPDecodeError::NeedMoreData) => {
+                if stream.read_buf(&mut buffer).await? == 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Connection with the node lost",
+                    ));
+                }
+            }
+            Err(RESPDecodeError::Invalid(error)) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Protocol error from the node: {error}"),
+                ))
+            }
+        }
+    }
+    match replies.pop() {
+        Some(RESPValues::BulkString(nodes) | RESPValues::VerbatimString(_, nodes)) => {
+            String::from_utf8_lossy(&nodes).parse()
+        }
+        reply => Err(io::Error::other(format!(
+            "Unexpected reply to CLUSTER NODES: {reply:?}"
+        ))),
+    }
+}
+
+fn check(slots: &[usize]) -> Result<(), ClusterError> {
+    let mut seen = vec![false; SLOTS];
+    for &slot in slots {
+        if slot >= SLOTS {
+            return Err(ClusterError::OutOfRange);
+        }
+        if std::mem::replace(&mut seen[slot], true) {
+            return Err(ClusterError::Repeated(slot));
+        }
+    }
+    Ok(())
+}
+
+impl ClusterState {
+    fn ranges(&self) -> Vec<(usize, usize, &String)> {
+        let mut ranges: Vec<(usize, usize, &String)> = Vec::new();
+        for (slot, owner) in self.slots.iter().enumerate() {
+            let Some(owner) = owner else {
+                continue;
+            };
+            match ranges.last_mut() {
+                Some((_, end, id)) if *end + 1 == slot && *id == owner => *end = slot,
+                _ => ranges.push((slot, slot, owner)),
+            }
+        }
+        ranges
+    }
+
+    fn describe(&self) -> String {
+        let ranges = self.ranges();
+        let mut text = String::new();
+        for node in self.nodes.values() {
+            let flags = if node.myself {
+                "myself,master"
+            } else {
+                "master"
+            };
+            text.push_str(&format!(
+                "{} {}:{}@{} {flags} - 0 0 {} connected",
+                node.id,
+                node.ip,
+                node.port,
+                node.bus_port(),
+                node.epoch
+            ));
+            for (start, end, _) in ranges.iter().filter(|(.., id)| **id == node.id) {
+                if start == end {
+                    text.push_str(&format!(" {start}"));
+                } else {
+                    text.push_str(&format!(" {start}-{end}"));
+                }
+            }
+            if node.myself {
+                for (slot, id) in &self.migrating {
+                    text.push_str(&format!(" [{slot}->-{id}]"));
+                }
+                for (slot, id) in &self.importing {
+                    text.push_str(&format!(" [{slot}-<-{id}]"));
+                }
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    // Written next to the file first, so a failure never leaves it half written
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        let temporary = path.with_extension("tmp");
+        let mut file = fs::File::create(&temporary)?;
+        file.write_all(self.describe().as_bytes())?;
+        writeln!(
+            file,
+            "vars currentEpoch {} lastVoteEpoch 0",
+            self.current_epoch
+        )?;
+        file.sync_all()?;
+        fs::rename(temporary, path)
+    }
+}
+
+// The contents of a cluster config file
+struct Saved {
+    current_epoch: u64,
+    nodes: Vec<(Node, Vec<(usize, usize)>)>,
+    // those of the myself node
+    migrating: BTreeMap<usize, String>,
+    importing: BTreeMap<usize, String>,
+}
+
+impl Saved {
+    fn into_state(self) -> ClusterState {
+        let mut state = ClusterState {
+            myself: String::new(),
+            current_epoch: self.current_epoch,
+            nodes: BTreeMap::new(),
+            slots: vec![None; SLOTS],
+            migrating: self.migrating,
+            importing: self.importing,
+            meets: Vec::new(),
+            file: None,
+        };
+        for (node, ranges) in self.nodes {
+            for (start, end) in ranges {
+                state.slots[start..=end].fill(Some(node.id.clone()));
+            }
+            if node.myself {
+                state.myself = node.id.clone();
+            }
+            state.nodes.insert(node.id.clone(), node);
+        }
+        state
+    }
+}
+
+impl FromStr for Saved {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut saved = Saved {
+            current_epoch: 0,
+            nodes: Vec::new(),
+            migrating: BTreeMap::new(),
+            importing: BTreeMap::new(),
+        };
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid cluster config line '{line}'"),
+                )
+            };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields[0] == "vars" {
+                let vars = fields[1..].chunks(2);
+                for pair in vars.filter(|pair| pair[0] == "currentEpoch" && pair.len() == 2) {
+                    saved.current_epoch = pair[1].parse().map_err(|_| invalid())?;
+                }
+                continue;
+            }
+            if fields.len() < 8 {
+                return Err(invalid());
+            }
+            // ip:port@cport, with ,hostname after it in Redis 7
+            let address = fields[1].split(['@', ',']).next().unwrap_or_default();
+            let (ip, port) = address.rsplit_once(':').ok_or_else(invalid)?;
+            let node = Node {
+                id: fields[0].to_string(),
+                ip: ip.to_string(),
+                port: port.parse().map_err(|_| invalid())?,
+                myself: fields[2].split(',').any(|flag| flag == "myself"),
+                epoch: fields[6].parse().map_err(|_| invalid())?,
+            };
+            let mut ranges = Vec::new();
+            for range in &fields[8..] {
+                // [slot->-id] for a migrating slot, [slot-<-id] for an importing one
+                if let Some(migration) = range.strip_prefix('[').and_then(|r| r.strip_suffix(']')) {
+                    let (slots, slot, id) = match migration.split_once("->-") {
+                        Some((slot, id)) => (&mut saved.migrating, slot, id),
+                        None => {
+                            let (slot, id) = migration.split_once("-<-").ok_or_else(invalid)?;
+                            (&mut saved.importing, slot, id)
+                        }
+                    };
+                    let slot = slot.parse().ok().filter(|&slot| slot < SLOTS);
+                    if node.myself {
+                        slots.insert(slot.ok_or_else(invalid)?, id.to_string());
+                    }
+                    continue;
+                }
+                let (start, end) = range.split_once('-').unwrap_or((range, range));
+                let range = (start.parse(), end.parse());
+                let (Ok(start), Ok(end)) = range else {
+                    return Err(invalid());
+                };
+                if start > end || end >= SLOTS {
+                    return Err(invalid());
+                }
+                ranges.push((start, end));
+            }
+            saved.nodes.push((node, ranges));
+        }
+        if saved.nodes.iter().filter(|(node, _)| node.myself).count() != 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "cluster config file without a myself node",
+            ));
+        }
+        Ok(saved)
+    }
+}
+
+#[cfg(test)]
+mod cluster_tests {
+    use bytes::Bytes;
+
+    use super::{key_slot, Cluster, ClusterError, Redirect, SetSlot};
+    use crate::commands::test_dir;
+
+    #[test]
+    fn key_slot_correctly() {
+        assert_eq!(key_slot(b"foo"), 12182);
+        assert_eq!(key_slot(b"{user1000}.following"), key_slot(b"user1000"));
+        assert_eq!(key_slot(b"{user1000}.followers"), key_slot(b"user1000"));
+        // only the first tag counts, and only when it's not empty
+        assert_eq!(key_slot(b"foo{}{bar}"), 8363);
+        assert_eq!(key_slot(b"foo{{bar}}zap"), key_slot(b"{bar"));
+        assert_eq!(key_slot(b"foo{bar}{zap}"), key_slot(b"bar"));
+    }
+
+    #[test]
+    fn route_to_the_slot_owner_correctly() {
+        let cluster = Cluster::default();
+        cluster.add_slots(&[key_slot(b"foo")]).unwrap();
+        let peer = "e7d1eecce10fd6bb5eb35b9f99a514335d9ba9ca 10.0.0.2:7001@17001 myself,master - 0 0 1 connected 0-100\n";
+        cluster
+            .learn(&("10.0.0.2".to_string(), 7001), peer.parse().unwrap())
+            .unwrap();
+
+        let keys = |keys: &[&'static str]| keys.iter().map(|k| Bytes::from(*k)).collect::<Vec<_>>();
+        let route = |names| cluster.route(&keys(names), false, |_| true);
+        assert_eq!(route(&["foo"]), Ok(()));
+        assert_eq!(route(&[]), Ok(()));
+        assert_eq!(
+            route(&["k126"]),
+            Err(Redirect::Moved(58, "10.0.0.2:7001".to_string()))
+        );
+        assert_eq!(route(&["foo", "bar"]), Err(Redirect::CrossSlot));
+        assert_eq!(route(&["bar"]), Err(Redirect::Unserved));
+        assert_eq!(cluster.nodes().len(), 2);
+    }
+
+    #[test]
+    fn route_during_a_reshard_correctly() {
+        let dir = test_dir("reshard");
+        let (source, target) = (Cluster::default(), Cluster::default());
+        source
+            .start(&dir.join("source.conf"), "127.0.0.1".to_string(), 7000)
+            .unwrap();
+        target
+            .start(&dir.join("target.conf"), "127.0.0.1".to_string(), 7001)
+            .unwrap();
+        let slot = key_slot(b"foo");
+        source.add_slots(&[slot]).unwrap();
+        let exchange = |from: &Cluster, to: &Cluster| {
+            let address = (from.myself().ip, from.myself().port);
+            to.learn(&address, from.describe().parse().unwrap())
+                .unwrap();
+        };
+        exchange(&source, &target);
+        exchange(&target, &source);
+        let (source_id, target_id) = (source.myself().id, target.myself().id);
+        source
+            .set_slot(slot, SetSlot::Migrating(target_id.clone()), || true)
+            .unwrap();
+        target
+            .set_slot(slot, SetSlot::Importing(source_id), || false)
+            .unwrap();
+
+        let keys = |keys: &[&'static str]| keys.iter().map(|k| Bytes::from(*k)).collect::<Vec<_>>();
+        let exists = |key: &[u8]| key == b"{foo}here";
+        let ask = Err(Redirect::Ask(slot, "127.0.0.1:7001".to_string()));
+        assert_eq!(source.route(&keys(&["{foo}here"]), false, exists), Ok(()));
+        assert_eq!(source.route(&keys(&["{foo}gone"]), false, exists), ask);
+        assert_eq!(
+            source.route(&keys(&["{foo}here", "{foo}gone"]), false, exists),
+            Err(Redirect::TryAgain)
+        );
+        let moved = Err(Redirect::Moved(slot, "127.0.0.1:7000".to_string()));
+        assert_eq!(target.route(&keys(&["{foo}gone"]), false, exists), moved);
+        assert_eq!(target.route(&keys(&["{foo}gone"]), true, exists), Ok(()));
+
+        // the migration is saved with the rest
+        let restarted = Cluster::default();
+        restarted
+            .start(&dir.join("source.conf"), "127.0.0.1".to_string(), 7000)
+            .unwrap();
+        assert_eq!(restarted.describe(), source.describe());
+
+        let node = || SetSlot::Node(target_id.clone());
+        assert_eq!(
+            source.set_slot(slot, node(), || true),
+            Err(ClusterError::HoldsKeys(slot))
+        );
+        target.set_slot(slot, node(), || false).unwrap();
+        assert_eq!(target.myself().epoch, 1);
+        exchange(&target, &source);
+        let moved = Err(Redirect::Moved(slot, "127.0.0.1:7001".to_string()));
+        assert_eq!(source.route(&keys(&["{foo}gone"]), false, |_| false), moved);
+        assert!(!source.describe().contains('['));
+    }
+
+    #[test]
+    fn add_and_del_slots_correctly() {
+        let cluster = Cluster::default();
+        assert_eq!(cluster.add_slots(&[0, 1, 2, 5]), Ok(()));
+        assert_eq!(cluster.del_slots(&[1]), Ok(()));
+
+        let ranges: Vec<_> = cluster
+            .ranges()
+            .into_iter()
+            .map(|(start, end, _)| (start, end))
+            .collect();
+        assert_eq!(ranges, [(0, 0), (2, 2), (5, 5)]);
+        assert_eq!(cluster.slots_assigned(), 3);
+        assert_eq!(cluster.owner(5), Some(cluster.myself()));
+    }
+
+    #[test]
+    fn add_busy_slots_fails() {
+        let cluster = Cluster::default();
+        cluster.add_slots(&[3]).unwrap();
+
+        assert_eq!(cluster.add_slots(&[4, 3]), Err(ClusterError::Busy(3)));
+        assert_eq!(cluster.add_slots(&[4, 4]), Err(ClusterError::Repeated(4)));
+        assert_eq!(cluster.add_slots(&[16384]), Err(ClusterError::OutOfRange));
+        assert_eq!(cluster.del_slots(&[4]), Err(ClusterError::Unassigned(4)));
+        assert_eq!(cluster.slots_assigned(), 1);
+    }
+
+    #[test]
+    fn keep_identity_across_restarts_correctly() {
+        let dir = test_dir("nodes");
+        let file = dir.join("nodes.conf");
+        let cluster = Cluster::default();
+        cluster.start(&file, "127.0.0.1".to_string(), 7000).unwrap();
+        cluster.add_slots(&(0..100).collect::<Vec<_>>()).unwrap();
+
+        let restarted = Cluster::default();
+        restarted
+            .start(&file, "127.0.0.1".to_string(), 7000)
+            .unwrap();
+        assert_eq!(restarted.myself(), cluster.myself());
+        assert_eq!(restarted.describe(), cluster.describe());
+        assert!(restarted.describe().ends_with(" 0 0 0 connected 0-99\n"));
+    }
+}
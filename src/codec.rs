@@ -0,0 +1,158 @@
+use std::io;
+
+use bytes::BytesMut;
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::resp::{
+    resume_frame, FrameScan, RESPDecodeError, RESPLimits, RESPParseError, RESPValues,
+};
+
+#[derive(Debug)]
+pub enum RESPCodecError {
+    Io(io::Error),
+    Protocol(RESPParseError),
+}
+
+impl From<io::Error> for RESPCodecError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl std::fmt::Display for RESPCodecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::Protocol(e) => write!(f, "Protocol error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for RESPCodecError {}
+
+// Frames a byte stream into RESPValues, e.g. `Framed::new(stream, RESPCodec::default())`.
+// A frame that hasn't fully arrived is scanned on from where the last call stopped
+#[derive(Default, Debug, Clone)]
+pub struct RESPCodec {
+    limits: RESPLimits,
+    scan: FrameScan,
+}
+
+impl RESPCodec {
+    pub fn new(limits: RESPLimits) -> Self {
+        Self {
+            limits,
+            scan: FrameScan::default(),
+        }
+    }
+}
+
+impl Decoder for RESPCodec {
+    type Item = RESPValues;
+    type Error = RESPCodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match resume_frame(src, &self.limits, &mut self.scan) {
+            Ok(value) => Ok(Some(value)),
+            Err(RESPDecodeError::NeedMoreData) => Ok(None),
+            Err(RESPDecodeError::Invalid(e)) => {
+                self.scan = FrameScan::default();
+                Err(RESPCodecError::Protocol(e))
+            }
+        }
+    }
+}
+
+impl Encoder<RESPValues> for RESPCodec {
+    type Error = RESPCodecError;
+
+    fn encode(&mut self, item: RESPValues, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.encode(&item, dst)
+    }
+}
+
+impl Encoder<&RESPValues> for RESPCodec {
+    type Error = RESPCodecError;
+
+    fn encode(&mut self, item: &RESPValues, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        item.encode(dst);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod resp_codec_tests {
+    use bytes::{Bytes, BytesMut};
+    use tokio_util::codec::{Decoder, Encoder};
+
+    use super::{RESPCodec, RESPCodecError};
+    use crate::resp::{RESPParseError, RESPValues};
+
+    #[test]
+    fn decode_complete_frame_correctly() {
+        let mut buffer = BytesMut::from(&b"*1\r\n$4\r\nPING\r\n+OK"[..]);
+        let result = RESPCodec::default().decode(&mut buffer);
+
+        assert!(result.is_ok_and(|r| r
+            == Some(RESPValues::Array(vec![RESPValues::BulkString(
+                Bytes::from_static(b"PING")
+            )]))));
+        assert_eq!(&buffer[..], b"+OK");
+    }
+
+    #[test]
+    fn decode_partial_frame_keeps_buffer() {
+        let mut buffer = BytesMut::from(&b"$5\r\nhel"[..]);
+        let result = RESPCodec::default().decode(&mut buffer);
+
+        assert!(result.is_ok_and(|r| r.is_none()));
+        assert_eq!(&buffer[..], b"$5\r\nhel");
+    }
+
+    #[test]
+    fn decode_frame_fed_in_pieces_correctly() {
+        let frame = b"*2\r\n$4\r\nECHO\r\n*2\r\n:1\r\n$5\r\nhello\r\n";
+        let mut codec = RESPCodec::default();
+        let mut buffer = BytesMut::new();
+
+        for piece in frame[..frame.len() - 1].chunks(3) {
+            buffer.extend_from_slice(piece);
+            assert!(codec.decode(&mut buffer).is_ok_and(|r| r.is_none()));
+        }
+        buffer.extend_from_slice(&frame[frame.len() - 1..]);
+        let result = codec.decode(&mut buffer);
+
+        assert!(result.is_ok_and(|r| r
+            == Some(RESPValues::Array(vec![
+                RESPValues::BulkString(Bytes::from_static(b"ECHO")),
+                RESPValues::Array(vec![
+                    RESPValues::Integer(1),
+                    RESPValues::BulkString(Bytes::from_static(b"hello"))
+                ]),
+            ]))));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn decode_invalid_frame_fails() {
+        let mut buffer = BytesMut::from(&b"*x\r\n"[..]);
+        let result = RESPCodec::default().decode(&mut buffer);
+
+        assert!(result.is_err_and(|e| matches!(
+            e,
+            RESPCodecError::Protocol(RESPParseError::InvalidMultibulkLength)
+        )));
+    }
+
+    #[test]
+    fn encode_value_correctly() {
+        let mut buffer = BytesMut::new();
+        let mut codec = RESPCodec::default();
+        codec
+            .encode(RESPValues::SimpleString("OK".to_string()), &mut buffer)
+            .unwrap();
+        codec.encode(&RESPValues::Integer(1), &mut buffer).unwrap();
+
+        assert_eq!(&buffer[..], b"+OK\r\n:1\r\n");
+    }
+}
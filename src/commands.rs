@@ -1,111 +1,1174 @@
-use crate::resp::RESPValues;
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
 
-#[derive(PartialEq, Debug)]
-pub enum RedisCommand {
-    Ping(Option<String>),
-    Echo(String),
-    CommandDocs(Option<String>),
+use bytes::Bytes;
+
+use crate::{
+    cluster::Redirect,
+    config::ClientClass,
+    functions::{Engine, ScriptCall},
+    pubsub::{Subscriber, SubscriptionKind},
+    resp::{RESPValues, RESPVersion},
+    server::Shared,
+};
+
+mod asking;
+mod bgrewriteaof;
+mod bgsave;
+mod cluster;
+mod command;
+mod config;
+mod debug;
+mod del;
+mod discard;
+mod dump;
+mod echo;
+mod exec;
+mod fcall;
+mod function;
+mod hello;
+mod info;
+mod lastsave;
+mod migrate;
+mod multi;
+mod ping;
+mod psync;
+mod publish;
+mod pubsub;
+mod quit;
+mod replconf;
+mod replicaof;
+mod reset;
+mod restore;
+mod role;
+mod save;
+mod script;
+mod shutdown;
+mod subscribe;
+mod unsubscribe;
+mod unwatch;
+mod watch;
+
+// Per connection state any command may read or change, e.g. HELLO switching protocols
+pub struct ConnectionState {
+    pub id: u64,
+    pub protocol: RESPVersion,
+    // set by a command to close the connection without replying to it
+    pub closing: bool,
+    // set by QUIT, to close the connection once its reply is written
+    pub quit: bool,
+    pub class: ClientClass,
+    // commands queued since MULTI, run by EXEC
+    pub transaction: Option<Transaction>,
+    // the writes of the EXEC running, propagated together when it's done
+    pub exec_writes: Option<Vec<Vec<Bytes>>>,
+    // keys passed to WATCH with their Store::version at the time
+    pub watched: Vec<(Bytes, u64)>,
+    // the connection's own end of the messages published to it
+    pub messages: Subscriber,
+    pub channels: BTreeSet<Bytes>,
+    pub patterns: BTreeSet<Bytes>,
+    pub shard_channels: BTreeSet<Bytes>,
+    // sent right after a command's reply, see ConnectionState::replies
+    pub extra_replies: Vec<RESPValues>,
+    // the link to this server's master, whose writes a read only replica
+    // still applies
+    pub master: bool,
+    // the port a replica said it listens on, with REPLCONF listening-port
+    pub replica_port: Option<u16>,
+    // the replica loads dumps of unknown length, REPLCONF capa eof
+    pub replica_eof: bool,
+    // ASKING was sent, for the command after it only
+    pub asking: bool,
+    // set with HELLO SETNAME
+    pub name: Option<Bytes>,
+    // while FCALL runs a function, the commands it calls are held to it
+    pub script: Option<ScriptCall>,
+    // what the write running is propagated as instead of its own arguments,
+    // nothing when empty, as MIGRATE is by the DEL of the keys it moved
+    pub propagate_as: Option<Vec<Bytes>>,
+}
+
+impl ConnectionState {
+    pub fn new(id: u64, messages: Subscriber) -> Self {
+        Self {
+            id,
+            protocol: RESPVersion::default(),
+            closing: false,
+            quit: false,
+            class: ClientClass::default(),
+            transaction: None,
+            exec_writes: None,
+            watched: Vec::new(),
+            messages,
+            channels: BTreeSet::new(),
+            patterns: BTreeSet::new(),
+            shard_channels: BTreeSet::new(),
+            extra_replies: Vec::new(),
+            master: false,
+            replica_port: None,
+            replica_eof: false,
+            asking: false,
+            name: None,
+            script: None,
+            propagate_as: None,
+        }
+    }
+
+    pub fn subscriptions(&self) -> usize {
+        self.channels.len() + self.patterns.len() + self.shard_channels.len()
+    }
+
+    // The count subscription replies carry, shard channels are counted apart
+    pub fn subscription_count(&self, kind: SubscriptionKind) -> usize {
+        match kind {
+            SubscriptionKind::ShardChannel => self.shard_channels.len(),
+            _ => self.channels.len() + self.patterns.len(),
+        }
+    }
+
+    pub fn subscribed(&self, kind: SubscriptionKind) -> &BTreeSet<Bytes> {
+        match kind {
+            SubscriptionKind::Channel => &self.channels,
+            SubscriptionKind::Pattern => &self.patterns,
+            SubscriptionKind::ShardChannel => &self.shard_channels,
+        }
+    }
+
+    pub fn subscribed_mut(&mut self, kind: SubscriptionKind) -> &mut BTreeSet<Bytes> {
+        match kind {
+            SubscriptionKind::Channel => &mut self.channels,
+            SubscriptionKind::Pattern => &mut self.patterns,
+            SubscriptionKind::ShardChannel => &mut self.shard_channels,
+        }
+    }
+
+    // Replies with each of `replies` in order, for commands like SUBSCRIBE
+    // that confirm every argument separately. `replies` can't be empty
+    pub fn replies(&mut self, mut replies: Vec<RESPValues>) -> RESPValues {
+        let first = replies.remove(0);
+        self.extra_replies.extend(replies);
+        first
+    }
+
+    // Subscribed connections get the pub/sub output buffer limits
+    // RESP2 clients can't tell replies from messages while subscribed, so
+    // they are held to the subscription commands until they leave
+    pub fn subscriber_mode(&self) -> bool {
+        self.protocol == RESPVersion::RESP2 && self.subscriptions() > 0
+    }
+
+    pub fn update_class(&mut self) {
+        self.class = if self.class == ClientClass::Replica {
+            ClientClass::Replica
+        } else if self.subscriptions() > 0 {
+            ClientClass::PubSub
+        } else {
+            ClientClass::Normal
+        };
+    }
+}
+
+#[derive(PartialEq, Debug, Default)]
+pub struct Transaction {
+    pub queued: Vec<Vec<Bytes>>,
+    // a command was rejected while queueing, EXEC runs none of them
+    pub failed: bool,
 }
 
+// commands that run right away inside a transaction instead of being queued
+const UNQUEUED: &[&str] = &["multi", "exec", "discard", "watch", "quit", "reset"];
+
+// commands that run with every other command held off, see Shared::exec_lock
+const EXCLUSIVE: &[&str] = &["exec", "bgrewriteaof", "psync"];
+
+// all a RESP2 connection may run while subscribed
+const SUBSCRIBER_COMMANDS: &[&str] = &[
+    "subscribe",
+    "unsubscribe",
+    "psubscribe",
+    "punsubscribe",
+    "ssubscribe",
+    "sunsubscribe",
+    "ping",
+    "quit",
+    "reset",
+];
+
+#[derive(PartialEq, Debug, Clone)]
 pub enum RedisCommandError {
-    NotImplemented,
+    UnknownCommand(String, Vec<Bytes>),
+    UnknownSubcommand(&'static str, String),
+    WrongArity(&'static str),
+    WrongType,
+    InvalidProtocolVersion,
+    NoProto,
+    WrongPass,
+    ExecAbort,
+    NotBusy,
+    Busy,
+    Unkillable,
+    SubscriberMode(&'static str),
+    ReadOnly,
+    NoMasterLink,
+    NoReplicas,
+    BusyKey,
+    IoError(&'static str),
+    Moved(usize, String),
+    Ask(usize, String),
+    TryAgain,
+    CrossSlot,
+    ClusterDown,
+    // any other `ERR` reply
+    Invalid(String),
+}
+
+impl std::fmt::Display for RedisCommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownCommand(name, args) => {
+                // Redis caps the echoed name and arguments at 128 bytes each
+                write!(
+                    f,
+                    "ERR unknown command '{}', with args beginning with: ",
+                    truncate(name)
+                )?;
+                let mut written = 0;
+                for arg in args {
+                    if written >= 128 {
+                        break;
+                    }
+                    let arg = String::from_utf8_lossy(arg);
+                    write!(f, "'{}' ", truncate(&arg))?;
+                    written += arg.len();
+                }
+                Ok(())
+            }
+            Self::UnknownSubcommand(command, subcommand) => write!(
+                f,
+                "ERR unknown subcommand '{}'. Try {} HELP.",
+                truncate(subcommand),
+                command.to_ascii_uppercase()
+            ),
+            Self::WrongArity(command) => {
+                write!(f, "ERR wrong number of arguments for '{command}' command")
+            }
+            Self::WrongType => write!(
+                f,
+                "WRONGTYPE Operation against a key holding the wrong kind of value"
+            ),
+            Self::InvalidProtocolVersion => {
+                write!(f, "ERR Protocol version is not an integer or out of range")
+            }
+            Self::NoProto => write!(f, "NOPROTO unsupported protocol version"),
+            Self::WrongPass => write!(
+                f,
+                "WRONGPASS invalid username-password pair or user is disabled."
+            ),
+            Self::ExecAbort => write!(
+                f,
+                "EXECABORT Transaction discarded because of previous errors."
+            ),
+            Self::NotBusy => write!(f, "NOTBUSY No scripts in execution right now."),
+            Self::Busy => write!(
+                f,
+                "BUSY Redis is busy running a script. You can only call FUNCTION KILL or SHUTDOWN NOSAVE."
+            ),
+            Self::Unkillable => write!(
+                f,
+                "UNKILLABLE Sorry the script already executed write commands against the dataset. You can either wait the script termination or kill the server in a hard way using the SHUTDOWN NOSAVE command."
+            ),
+            Self::SubscriberMode(command) => write!(
+                f,
+                "ERR Can't execute '{command}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context"
+            ),
+            Self::ReadOnly => write!(f, "READONLY You can't write against a read only replica."),
+            Self::NoMasterLink => write!(
+                f,
+                "NOMASTERLINK Can't SYNC while not connected with my master"
+            ),
+            Self::NoReplicas => write!(f, "NOREPLICAS Not enough good replicas to write."),
+            Self::BusyKey => write!(f, "BUSYKEY Target key name already exists."),
+            Self::IoError(message) => write!(f, "IOERR {message}"),
+            Self::Moved(slot, address) => write!(f, "MOVED {slot} {address}"),
+            Self::Ask(slot, address) => write!(f, "ASK {slot} {address}"),
+            Self::TryAgain => write!(f, "TRYAGAIN Multiple keys request during rehashing of slot"),
+            Self::CrossSlot => write!(f, "CROSSSLOT Keys in request don't hash to the same slot"),
+            Self::ClusterDown => write!(f, "CLUSTERDOWN Hash slot not served"),
+            Self::Invalid(message) => write!(f, "ERR {message}"),
+        }
+    }
+}
+
+impl From<RedisCommandError> for RESPValues {
+    fn from(value: RedisCommandError) -> Self {
+        RESPValues::SimpleError(value.to_string())
+    }
+}
+
+impl From<Redirect> for RedisCommandError {
+    fn from(value: Redirect) -> Self {
+        match value {
+            Redirect::Moved(slot, address) => Self::Moved(slot, address),
+            Redirect::Ask(slot, address) => Self::Ask(slot, address),
+            Redirect::TryAgain => Self::TryAgain,
+            Redirect::CrossSlot => Self::CrossSlot,
+            Redirect::Unserved => Self::ClusterDown,
+        }
+    }
+}
+
+fn truncate(value: &str) -> &str {
+    match value.char_indices().nth(128) {
+        Some((i, _)) => &value[..i],
+        None => value,
+    }
+}
+
+// Everything a command can reach while it runs
+pub struct CommandContext<'a> {
+    pub connection: &'a mut ConnectionState,
+    pub server: &'a Shared,
+}
+
+impl CommandContext<'_> {
+    // Sends a write on to the AOF and the replicas, or with the writes of
+    // the EXEC running
+    pub fn propagate(&mut self, args: &[Bytes]) {
+        match &mut self.connection.exec_writes {
+            Some(writes) => writes.push(args.to_vec()),
+            None => self.server.propagate(args),
+        }
+    }
+}
+
+// A command receives its arguments the way Redis passes argv, with the command
+// name itself at args[0], and returns the reply to send back
+pub trait CommandHandler: Send + Sync {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError>;
+}
+
+// so embedders can register a closure, e.g. `registry.register(SPEC, |args, ctx| ...)`
+impl<F> CommandHandler for F
+where
+    F: Fn(&[Bytes], &mut CommandContext) -> Result<RESPValues, RedisCommandError> + Send + Sync,
+{
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        self(args, ctx)
+    }
+}
+
+// A set of commands, and function engines, compiled into an embedding program
+// and added to the server when it's built, see RedisServerBuilder::module.
+// Commands registered under a builtin name replace the builtin
+pub trait Module: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn register(&self, commands: &mut CommandRegistry);
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum CommandFlag {
+    Write,
+    ReadOnly,
+    DenyOom,
+    Admin,
+    PubSub,
+    NoScript,
+    Loading,
+    Stale,
+    Fast,
+    SkipMonitor,
+    NoMandatoryKeys,
+}
+
+impl CommandFlag {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Write => "write",
+            Self::ReadOnly => "readonly",
+            Self::DenyOom => "denyoom",
+            Self::Admin => "admin",
+            Self::PubSub => "pubsub",
+            Self::NoScript => "noscript",
+            Self::Loading => "loading",
+            Self::Stale => "stale",
+            Self::Fast => "fast",
+            Self::SkipMonitor => "skip_monitor",
+            Self::NoMandatoryKeys => "no_mandatory_keys",
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ArgumentType {
+    String,
+    Integer,
+    Double,
+    Key,
+    Pattern,
+    PureToken,
+}
+
+impl ArgumentType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Integer => "integer",
+            Self::Double => "double",
+            Self::Key => "key",
+            Self::Pattern => "pattern",
+            Self::PureToken => "pure-token",
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct CommandArgument {
+    pub name: &'static str,
+    pub kind: ArgumentType,
+    pub optional: bool,
+    pub multiple: bool,
 }
 
-impl TryFrom<RESPValues> for RedisCommand {
-    type Error = RedisCommandError;
-    fn try_from(value: RESPValues) -> Result<Self, Self::Error> {
-        let array = match value {
-            RESPValues::Array(v) if !v.is_empty() => v,
-            _ => todo!("Handle value not being array variant of RESPValues"),
+// What COMMAND DOCS tells clients about a command
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct CommandDocs {
+    pub summary: &'static str,
+    // first Redis version with the command
+    pub since: &'static str,
+    pub group: &'static str,
+    pub complexity: &'static str,
+    pub arguments: &'static [CommandArgument],
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    // number of arguments including the command name, negative meaning "at least"
+    pub arity: i64,
+    pub flags: &'static [CommandFlag],
+    // positions of the first and last key in argv and the step between keys, as
+    // in COMMAND INFO: a negative last key counts from the end, all 0 means no keys
+    pub first_key: i64,
+    pub last_key: i64,
+    pub step: i64,
+    pub docs: CommandDocs,
+}
+
+impl CommandSpec {
+    pub fn accepts(&self, argc: usize) -> bool {
+        let argc = argc as i64;
+        if self.arity < 0 {
+            argc >= -self.arity
+        } else {
+            argc == self.arity
+        }
+    }
+
+    pub fn has_flag(&self, flag: CommandFlag) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    // The key arguments of `args`, a full argv this spec accepts
+    pub fn keys<'a>(&self, args: &'a [Bytes]) -> Vec<&'a Bytes> {
+        if self.first_key <= 0 || self.step <= 0 {
+            return vec![];
+        }
+
+        let last_key = if self.last_key < 0 {
+            args.len() as i64 + self.last_key
+        } else {
+            self.last_key
         };
+        (self.first_key..=last_key.min(args.len() as i64 - 1))
+            .step_by(self.step as usize)
+            .map(|i| &args[i as usize])
+            .collect()
+    }
+
+    // The reply COMMAND INFO gives for this command
+    pub fn info(&self) -> RESPValues {
+        RESPValues::Array(vec![
+            RESPValues::BulkString(self.name.into()),
+            RESPValues::Integer(self.arity),
+            RESPValues::Set(
+                self.flags
+                    .iter()
+                    .map(|f| RESPValues::SimpleString(f.name().to_string()))
+                    .collect(),
+            ),
+            RESPValues::Integer(self.first_key),
+            RESPValues::Integer(self.last_key),
+            RESPValues::Integer(self.step),
+            // ACL categories, tips, key specs and subcommands
+            RESPValues::Set(vec![]),
+            RESPValues::Set(vec![]),
+            RESPValues::Array(vec![]),
+            RESPValues::Array(vec![]),
+        ])
+    }
+
+    // The reply COMMAND DOCS gives for this command
+    pub fn docs(&self) -> RESPValues {
+        let field = |name: &'static str| RESPValues::BulkString(name.into());
+        let arguments = self
+            .docs
+            .arguments
+            .iter()
+            .map(|argument| {
+                let mut fields = vec![
+                    (field("name"), field(argument.name)),
+                    (field("type"), field(argument.kind.name())),
+                ];
+                let flags: Vec<_> = [
+                    (argument.optional, "optional"),
+                    (argument.multiple, "multiple"),
+                ]
+                .into_iter()
+                .filter(|(set, _)| *set)
+                .map(|(_, flag)| RESPValues::SimpleString(flag.to_string()))
+                .collect();
+                if !flags.is_empty() {
+                    fields.push((field("flags"), RESPValues::Set(flags)));
+                }
+                RESPValues::Map(fields)
+            })
+            .collect();
+
+        RESPValues::Map(vec![
+            (field("summary"), field(self.docs.summary)),
+            (field("since"), field(self.docs.since)),
+            (field("group"), field(self.docs.group)),
+            (field("complexity"), field(self.docs.complexity)),
+            (field("arity"), RESPValues::Integer(self.arity)),
+            (field("arguments"), RESPValues::Array(arguments)),
+        ])
+    }
+}
+
+struct RegisteredCommand {
+    spec: CommandSpec,
+    handler: Box<dyn CommandHandler>,
+}
+
+#[derive(Default)]
+pub struct CommandRegistry {
+    commands: HashMap<String, RegisteredCommand>,
+    // the function engines FUNCTION LOAD finds by name, none built in
+    engines: HashMap<String, Arc<dyn Engine>>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        // match command docs
-        if array[0] == RESPValues::BulkString("COMMAND".to_string())
-            && array[1] == RESPValues::BulkString("DOCS".to_string())
-        {
-            let sub_command = array.get(2);
-            return Ok(Self::CommandDocs(sub_command.and_then(|v| match v {
-                RESPValues::BulkString(s) => Some(s.clone()),
-                _ => None,
-            })));
+    // A registry with every command this server implements
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(asking::SPEC, asking::Asking);
+        registry.register(bgrewriteaof::SPEC, bgrewriteaof::Bgrewriteaof);
+        registry.register(bgsave::SPEC, bgsave::Bgsave);
+        registry.register(cluster::SPEC, cluster::Cluster);
+        registry.register(command::SPEC, command::Command);
+        registry.register(config::SPEC, config::Config);
+        registry.register(debug::SPEC, debug::Debug);
+        registry.register(del::SPEC, del::Del);
+        registry.register(discard::SPEC, discard::Discard);
+        registry.register(dump::SPEC, dump::Dump);
+        registry.register(echo::SPEC, echo::Echo);
+        registry.register(exec::SPEC, exec::Exec);
+        registry.register(fcall::SPEC, fcall::Fcall);
+        registry.register(fcall::READ_ONLY_SPEC, fcall::Fcall);
+        registry.register(function::SPEC, function::Function);
+        registry.register(hello::SPEC, hello::Hello);
+        registry.register(info::SPEC, info::Info);
+        registry.register(lastsave::SPEC, lastsave::Lastsave);
+        registry.register(migrate::SPEC, migrate::Migrate);
+        registry.register(multi::SPEC, multi::Multi);
+        registry.register(ping::SPEC, ping::Ping);
+        registry.register(psync::SPEC, psync::Psync);
+        registry.register(publish::SPEC, publish::Publish);
+        registry.register(publish::SHARD_SPEC, publish::Publish);
+        registry.register(pubsub::SPEC, pubsub::PubSub);
+        registry.register(quit::SPEC, quit::Quit);
+        registry.register(replconf::SPEC, replconf::Replconf);
+        registry.register(replicaof::SPEC, replicaof::Replicaof);
+        registry.register(replicaof::SLAVE_SPEC, replicaof::Replicaof);
+        registry.register(reset::SPEC, reset::Reset);
+        registry.register(restore::SPEC, restore::Restore);
+        registry.register(restore::ASKING_SPEC, restore::Restore);
+        registry.register(role::SPEC, role::Role);
+        registry.register(save::SPEC, save::Save);
+        registry.register(script::SPEC, script::Script);
+        registry.register(shutdown::SPEC, shutdown::Shutdown);
+        registry.register(
+            subscribe::SPEC,
+            subscribe::Subscribe(SubscriptionKind::Channel),
+        );
+        registry.register(
+            subscribe::PATTERN_SPEC,
+            subscribe::Subscribe(SubscriptionKind::Pattern),
+        );
+        registry.register(
+            subscribe::SHARD_SPEC,
+            subscribe::Subscribe(SubscriptionKind::ShardChannel),
+        );
+        registry.register(
+            unsubscribe::SPEC,
+            unsubscribe::Unsubscribe(SubscriptionKind::Channel),
+        );
+        registry.register(
+            unsubscribe::PATTERN_SPEC,
+            unsubscribe::Unsubscribe(SubscriptionKind::Pattern),
+        );
+        registry.register(
+            unsubscribe::SHARD_SPEC,
+            unsubscribe::Unsubscribe(SubscriptionKind::ShardChannel),
+        );
+        registry.register(unwatch::SPEC, unwatch::Unwatch);
+        registry.register(watch::SPEC, watch::Watch);
+        registry
+    }
+
+    pub fn register(&mut self, spec: CommandSpec, handler: impl CommandHandler + 'static) {
+        let command = RegisteredCommand {
+            spec,
+            handler: Box::new(handler),
+        };
+        self.commands
+            .insert(spec.name.to_ascii_lowercase(), command);
+    }
+
+    // Engine names are case insensitive, as command names
+    pub fn register_engine(&mut self, name: &str, engine: impl Engine + 'static) {
+        self.engines
+            .insert(name.to_ascii_lowercase(), Arc::new(engine));
+    }
+
+    pub fn engine(&self, name: &str) -> Option<Arc<dyn Engine>> {
+        self.engines.get(&name.to_ascii_lowercase()).cloned()
+    }
+
+    // Command names are case insensitive
+    pub fn spec(&self, name: &[u8]) -> Option<&CommandSpec> {
+        self.get(name).map(|command| &command.spec)
+    }
+
+    pub fn specs(&self) -> impl Iterator<Item = &CommandSpec> {
+        self.commands.values().map(|command| &command.spec)
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    // Runs the command in `request`, turning any failure into its error reply.
+    // Commands run alongside each other but EXEC, which runs alone so no other
+    // command lands between the ones it queued
+    pub fn dispatch(&self, request: RESPValues, ctx: &mut CommandContext) -> RESPValues {
+        let reply = request_arguments(request).and_then(|args| {
+            let name = self.get(&args[0]).map(|command| command.spec.name);
+            // a function running too long keeps everything out but what stops it
+            let timeout = ctx.server.config.read().unwrap().busy_script_timeout;
+            if !ctx.connection.master
+                && ctx
+                    .server
+                    .functions
+                    .busy(ctx.connection.id, Duration::from_millis(timeout))
+                && !stops_a_function(name, &args)
+            {
+                return Err(RedisCommandError::Busy);
+            }
+            if ctx.connection.subscriber_mode() {
+                let name = self.lookup(&args)?.spec.name;
+                if !SUBSCRIBER_COMMANDS.contains(&name) {
+                    return Err(RedisCommandError::SubscriberMode(name));
+                }
+            }
+            // good for the one command after ASKING
+            let asking = (name != Some("asking") && std::mem::take(&mut ctx.connection.asking))
+                || name == Some("restore-asking");
+            // a cluster node serves the keys of its own slots, it's up to the
+            // master what its replicas apply and to the AOF what gets loaded
+            if ctx.server.config.read().unwrap().cluster_enabled
+                && !ctx.connection.master
+                && !ctx.server.loading.load(Ordering::Acquire)
+            {
+                let store = &ctx.server.store;
+                let keys = self.keys(&args, ctx);
+                let routed = ctx
+                    .server
+                    .cluster
+                    .route(&keys, asking, |key| store.contains(key));
+                if let (Err(_), Some("exec")) = (&routed, name) {
+                    ctx.connection.transaction = None;
+                    ctx.connection.watched.clear();
+                }
+                routed?;
+            }
+            let write = self
+                .get(&args[0])
+                .is_some_and(|command| command.spec.flags.contains(&CommandFlag::Write));
+            if write && !ctx.connection.master && ctx.server.read_only_replica() {
+                return Err(RedisCommandError::ReadOnly);
+            }
+            // checked again by EXEC, as the replicas may have fallen behind since
+            let queued_writes = name == Some("exec")
+                && ctx
+                    .connection
+                    .transaction
+                    .as_ref()
+                    .is_some_and(|transaction| {
+                        transaction.queued.iter().any(|args| {
+                            self.get(&args[0]).is_some_and(|command| {
+                                command.spec.flags.contains(&CommandFlag::Write)
+                            })
+                        })
+                    });
+            if (write || queued_writes) && ctx.server.too_few_replicas() {
+                // a rejected EXEC ends the transaction, as DISCARD would
+                if queued_writes {
+                    ctx.connection.transaction = None;
+                    ctx.connection.watched.clear();
+                }
+                return Err(RedisCommandError::NoReplicas);
+            }
+            if ctx.connection.transaction.is_some() && !name.is_some_and(|n| UNQUEUED.contains(&n))
+            {
+                return self.queue(args, ctx);
+            }
+
+            let exclusive = name.is_some_and(|n| EXCLUSIVE.contains(&n));
+            let _exclusive = exclusive.then(|| ctx.server.exec_lock.write().unwrap());
+            let _shared = (!exclusive).then(|| ctx.server.exec_lock.read().unwrap());
+            self.call(&args, ctx)
+        });
+
+        // whatever a client gets rejected inside a transaction fails all of it
+        if let (Err(_), Some(transaction)) = (&reply, &mut ctx.connection.transaction) {
+            transaction.failed = true;
         }
+        reply.unwrap_or_else(RESPValues::from)
+    }
 
-        // match ping
-        if array[0] == RESPValues::BulkString("PING".to_string()) {
-            let echoed_string = array.get(1).and_then(|v| match v {
-                RESPValues::BulkString(s) => Some(s.to_string()),
-                _ => None,
-            });
-            return Ok(Self::Ping(echoed_string));
+    // Runs a full argv as is, without the locking `dispatch` does around it
+    pub fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let command = self.lookup(args)?;
+        let write = command.spec.flags.contains(&CommandFlag::Write);
+        if let Some(script) = ctx.connection.script {
+            if ctx.server.functions.killed(ctx.connection.id) {
+                return Err(RedisCommandError::Invalid(
+                    "Script killed by user with FUNCTION KILL...".to_string(),
+                ));
+            }
+            if command.spec.flags.contains(&CommandFlag::NoScript) {
+                return Err(RedisCommandError::Invalid(
+                    "This Redis command is not allowed from script".to_string(),
+                ));
+            }
+            if write && script.no_writes {
+                return Err(RedisCommandError::Invalid(
+                    "Write commands are not allowed from read-only scripts.".to_string(),
+                ));
+            }
+            // a function that wrote can't be killed any more
+            if write {
+                ctx.server.functions.wrote(ctx.connection.id);
+            }
+        }
+        let reply = command.handler.call(args, ctx);
+        let propagated = ctx.connection.propagate_as.take();
+        let reply = reply?;
+        let args = propagated.as_deref().unwrap_or(args);
+        if write && !args.is_empty() {
+            ctx.propagate(args);
         }
+        Ok(reply)
+    }
+
+    // The keys `args` names, or for EXEC the ones of every queued command
+    fn keys(&self, args: &[Bytes], ctx: &CommandContext) -> Vec<Bytes> {
+        let commands: Vec<&[Bytes]> = match (&ctx.connection.transaction, self.get(&args[0])) {
+            (Some(transaction), Some(command)) if command.spec.name == "exec" => {
+                transaction.queued.iter().map(Vec::as_slice).collect()
+            }
+            _ => vec![args],
+        };
+        commands
+            .into_iter()
+            .filter_map(|args| self.get(&args[0]).map(|command| command.spec.keys(args)))
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    // Commands are checked when queued, so EXEC only runs ones that exist
+    fn queue(
+        &self,
+        args: Vec<Bytes>,
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        self.lookup(&args)?;
+        let transaction = ctx
+            .connection
+            .transaction
+            .get_or_insert_with(Default::default);
+        transaction.queued.push(args);
+        Ok(RESPValues::SimpleString("QUEUED".to_string()))
+    }
 
-        // match echo
-        if array[0] == RESPValues::BulkString("ECHO".to_string()) {
-            let echoed_string = match array.get(1) {
-                Some(RESPValues::BulkString(v)) => v.to_owned(),
-                _ => todo!("raise an error if echoed string is absent in echo command"),
-            };
-            return Ok(RedisCommand::Echo(echoed_string));
+    fn lookup(&self, args: &[Bytes]) -> Result<&RegisteredCommand, RedisCommandError> {
+        match self.get(&args[0]) {
+            Some(command) if !command.spec.accepts(args.len()) => {
+                Err(RedisCommandError::WrongArity(command.spec.name))
+            }
+            Some(command) => Ok(command),
+            None => Err(RedisCommandError::UnknownCommand(
+                String::from_utf8_lossy(&args[0]).to_string(),
+                args[1..].to_vec(),
+            )),
         }
+    }
+
+    fn get(&self, name: &[u8]) -> Option<&RegisteredCommand> {
+        let name = String::from_utf8_lossy(name).to_ascii_lowercase();
+        self.commands.get(&name)
+    }
+}
+
+#[cfg(test)]
+fn test_state() -> ConnectionState {
+    ConnectionState::new(1, tokio::sync::mpsc::unbounded_channel().0)
+}
+
+// A directory of a test's own under the system's temp dir. It's removed
+// with all the test left in it once dropped, a failed assertion included
+#[cfg(test)]
+pub(crate) struct TestDir(std::path::PathBuf);
 
-        return Err(RedisCommandError::NotImplemented);
+#[cfg(test)]
+impl TestDir {
+    pub(crate) fn path(&self) -> &std::path::Path {
+        &self.0
+    }
+
+    pub(crate) fn join(&self, path: impl AsRef<std::path::Path>) -> std::path::PathBuf {
+        self.0.join(path)
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
     }
 }
 
+// Names are unique among the tests, which run in the same process
+#[cfg(test)]
+pub(crate) fn test_dir(name: &str) -> TestDir {
+    let dir = std::env::temp_dir().join(format!("redis-clone-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    TestDir(dir)
+}
+
+// A context over a fresh server, leaked so tests can hold on to it freely
 #[cfg(test)]
-mod command_tests {
-    use crate::{commands::RedisCommand, resp::RESPValues};
+fn test_context(connection: &mut ConnectionState) -> CommandContext<'_> {
+    CommandContext {
+        connection,
+        server: Box::leak(Box::default()),
+    }
+}
+
+// A context over a fresh server that loads libraries with the "test" engine,
+// see functions::TestEngine
+#[cfg(test)]
+fn test_functions_context(connection: &mut ConnectionState) -> CommandContext<'_> {
+    let server: &mut Shared = Box::leak(Box::default());
+    server
+        .commands
+        .register_engine("test", crate::functions::TestEngine);
+    CommandContext { connection, server }
+}
+
+// The commands served while a function is busy, see Libraries::busy
+fn stops_a_function(name: Option<&str>, args: &[Bytes]) -> bool {
+    match name {
+        Some("script" | "function") => args
+            .get(1)
+            .is_some_and(|sub| sub.eq_ignore_ascii_case(b"KILL")),
+        Some("shutdown") => args[1..]
+            .iter()
+            .any(|arg| arg.eq_ignore_ascii_case(b"NOSAVE")),
+        _ => false,
+    }
+}
+
+// Requests are non empty arrays of bulk strings
+fn request_arguments(request: RESPValues) -> Result<Vec<Bytes>, RedisCommandError> {
+    let invalid = || {
+        RedisCommandError::Invalid("Protocol error: expected an array of bulk strings".to_string())
+    };
+    let args = match request {
+        RESPValues::Array(v) if !v.is_empty() => v,
+        _ => return Err(invalid()),
+    };
+
+    args.into_iter()
+        .map(|arg| match arg {
+            RESPValues::BulkString(v) => Ok(v),
+            _ => Err(invalid()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod command_registry_tests {
+    use bytes::Bytes;
+
+    use super::{
+        test_context, test_state, ArgumentType, CommandArgument, CommandContext, CommandDocs,
+        CommandFlag, CommandHandler, CommandRegistry, CommandSpec, RedisCommandError,
+    };
+    use crate::{
+        functions::ScriptCall,
+        resp::{RESPValues, RESPVersion},
+        server::Shared,
+    };
+
+    const COUNT: CommandSpec = CommandSpec {
+        name: "count",
+        arity: -1,
+        flags: &[CommandFlag::ReadOnly],
+        first_key: 1,
+        last_key: -1,
+        step: 2,
+        docs: CommandDocs {
+            summary: "Counts its arguments.",
+            since: "1.0.0",
+            group: "generic",
+            complexity: "O(N)",
+            arguments: &[CommandArgument {
+                name: "key",
+                kind: ArgumentType::Key,
+                optional: true,
+                multiple: true,
+            }],
+        },
+    };
+
+    struct Count;
+
+    impl CommandHandler for Count {
+        fn call(
+            &self,
+            args: &[Bytes],
+            _ctx: &mut CommandContext,
+        ) -> Result<RESPValues, RedisCommandError> {
+            Ok(RESPValues::Integer(args.len() as i64))
+        }
+    }
 
     #[test]
-    fn parse_command_docs_with_no_string_correctly() {
+    fn dispatch_registered_command_correctly() {
+        let mut registry = CommandRegistry::new();
+        registry.register(COUNT, Count);
         let value = RESPValues::Array(vec![
-            RESPValues::BulkString("COMMAND".to_string()),
-            RESPValues::BulkString("DOCS".to_string()),
+            RESPValues::BulkString(Bytes::from_static(b"COUNT")),
+            RESPValues::BulkString(Bytes::from_static(b"a")),
         ]);
-        let result = RedisCommand::try_from(value);
+        let result = registry.dispatch(value, &mut test_context(&mut test_state()));
 
-        assert!(result.is_ok_and(|r| r == RedisCommand::CommandDocs(None)));
+        assert_eq!(result, RESPValues::Integer(2));
+        assert!(registry.spec(b"Count").is_some_and(|s| s.arity == -1));
     }
 
     #[test]
-    fn parse_command_docs_with_a_string_correctly() {
+    fn dispatch_while_a_function_is_busy_fails() {
+        let shared = Shared::default();
+        shared.config.write().unwrap().busy_script_timeout = 0;
+        shared.functions.start(2);
+        let echo = RESPValues::Array(vec![
+            RESPValues::BulkString(Bytes::from_static(b"ECHO")),
+            RESPValues::BulkString(Bytes::from_static(b"hi")),
+        ]);
+        let kill = RESPValues::Array(vec![
+            RESPValues::BulkString(Bytes::from_static(b"FUNCTION")),
+            RESPValues::BulkString(Bytes::from_static(b"KILL")),
+        ]);
+
+        let result = shared.dispatch(echo.clone(), &mut test_state());
+        assert_eq!(result, RESPValues::from(RedisCommandError::Busy));
+        let result = shared.dispatch(kill, &mut test_state());
+        assert_eq!(result, RESPValues::SimpleString("OK".to_string()));
+
+        // the killed function is stopped at the next command it calls
+        let mut function = test_state();
+        function.id = 2;
+        function.script = Some(ScriptCall { no_writes: false });
+        let result = shared.dispatch(echo.clone(), &mut function);
+        assert_eq!(
+            result,
+            RESPValues::SimpleError("ERR Script killed by user with FUNCTION KILL...".to_string())
+        );
+
+        shared.functions.finish(2);
+        let result = shared.dispatch(echo, &mut test_state());
+        assert_eq!(result, RESPValues::BulkString(Bytes::from_static(b"hi")));
+    }
+
+    #[test]
+    fn dispatch_unknown_command_fails() {
         let value = RESPValues::Array(vec![
-            RESPValues::BulkString("COMMAND".to_string()),
-            RESPValues::BulkString("DOCS".to_string()),
-            RESPValues::BulkString("SET".to_string()),
+            RESPValues::BulkString(Bytes::from_static(b"NOPE")),
+            RESPValues::BulkString(Bytes::from_static(b"a")),
+            RESPValues::BulkString(Bytes::from_static(b"b")),
         ]);
-        let result = RedisCommand::try_from(value);
+        let result = Shared::default().dispatch(value, &mut test_state());
 
-        assert!(result.is_ok_and(|r| r == RedisCommand::CommandDocs(Some("SET".to_string()))));
+        assert_eq!(
+            result,
+            RESPValues::SimpleError(
+                "ERR unknown command 'NOPE', with args beginning with: 'a' 'b' ".to_string()
+            )
+        );
     }
 
     #[test]
-    fn parse_ping_with_no_string_correctly() {
-        let value = RESPValues::Array(vec![RESPValues::BulkString("PING".to_string())]);
-        let result = RedisCommand::try_from(value);
+    fn dispatch_with_wrong_number_of_arguments_fails() {
+        let value = RESPValues::Array(vec![RESPValues::BulkString(Bytes::from_static(b"ECHO"))]);
+        let result = Shared::default().dispatch(value, &mut test_state());
 
-        assert!(result.is_ok_and(|r| r == RedisCommand::Ping(None)));
+        assert_eq!(
+            result,
+            RESPValues::SimpleError("ERR wrong number of arguments for 'echo' command".to_string())
+        );
     }
 
     #[test]
-    fn parse_ping_with_one_string_correctly() {
-        let value = RESPValues::Array(vec![
-            RESPValues::BulkString("PING".to_string()),
-            RESPValues::BulkString("testing".to_string()),
+    fn dispatch_while_subscribed_fails() {
+        let shared = Shared::default();
+        let mut state = test_state();
+        state.channels.insert(Bytes::from_static(b"news"));
+        let echo = RESPValues::Array(vec![
+            RESPValues::BulkString(Bytes::from_static(b"ECHO")),
+            RESPValues::BulkString(Bytes::from_static(b"a")),
         ]);
-        let result = RedisCommand::try_from(value);
+        let result = shared.dispatch(echo.clone(), &mut state);
+
+        assert_eq!(
+            result,
+            RESPValues::SimpleError("ERR Can't execute 'echo': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context".to_string())
+        );
 
-        assert!(result.is_ok_and(|r| r == RedisCommand::Ping(Some("testing".to_string()))));
+        state.protocol = RESPVersion::RESP3;
+        let result = shared.dispatch(echo, &mut state);
+        assert_eq!(result, RESPValues::BulkString(Bytes::from_static(b"a")));
     }
 
     #[test]
-    fn parse_echo_with_string_correctly() {
-        let value = RESPValues::Array(vec![
-            RESPValues::BulkString("ECHO".to_string()),
-            RESPValues::BulkString("testing".to_string()),
-        ]);
-        let result = RedisCommand::try_from(value);
+    fn dispatch_non_array_request_fails() {
+        let value = RESPValues::Integer(1);
+        let result = Shared::default().dispatch(value, &mut test_state());
+
+        assert!(
+            matches!(result, RESPValues::SimpleError(e) if e.starts_with("ERR Protocol error"))
+        );
+    }
+
+    #[test]
+    fn unknown_command_error_is_truncated_correctly() {
+        let error = RedisCommandError::UnknownCommand(
+            "x".repeat(200),
+            vec![Bytes::from("y".repeat(200)); 3],
+        );
+        let result = error.to_string();
+
+        assert_eq!(
+            result,
+            format!(
+                "ERR unknown command '{}', with args beginning with: '{}' ",
+                "x".repeat(128),
+                "y".repeat(128)
+            )
+        );
+    }
+
+    #[test]
+    fn spec_accepts_arity_correctly() {
+        assert!(COUNT.accepts(1) && COUNT.accepts(5));
+        assert!(!COUNT.accepts(0));
+
+        let exact = CommandSpec { arity: 2, ..COUNT };
+        assert!(exact.accepts(2));
+        assert!(!exact.accepts(3));
+    }
+
+    #[test]
+    fn spec_keys_correctly() {
+        let args = ["COUNT", "k1", "v1", "k2", "v2"].map(|a| Bytes::from(a.as_bytes()));
+
+        assert_eq!(COUNT.keys(&args), vec!["k1", "k2"]);
+        assert_eq!(COUNT.keys(&args[..1]), Vec::<&Bytes>::new());
+
+        let first_only = CommandSpec {
+            last_key: 1,
+            step: 1,
+            ..COUNT
+        };
+        assert_eq!(first_only.keys(&args), vec!["k1"]);
+    }
+
+    #[test]
+    fn spec_docs_correctly() {
+        let field = |name: &'static str| RESPValues::BulkString(name.into());
+        let result = COUNT.docs();
 
-        assert!(result.is_ok_and(|r| r == RedisCommand::Echo("testing".to_string())));
+        assert_eq!(
+            result,
+            RESPValues::Map(vec![
+                (field("summary"), field("Counts its arguments.")),
+                (field("since"), field("1.0.0")),
+                (field("group"), field("generic")),
+                (field("complexity"), field("O(N)")),
+                (field("arity"), RESPValues::Integer(-1)),
+                (
+                    field("arguments"),
+                    RESPValues::Array(vec![RESPValues::Map(vec![
+                        (field("name"), field("key")),
+                        (field("type"), field("key")),
+                        (
+                            field("flags"),
+                            RESPValues::Set(vec![
+                                RESPValues::SimpleString("optional".to_string()),
+                                RESPValues::SimpleString("multiple".to_string()),
+                            ])
+                        ),
+                    ])])
+                ),
+            ])
+        );
     }
 }
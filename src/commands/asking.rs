@@ -0,0 +1,74 @@
+use bytes::Bytes;
+
+use super::{
+    CommandContext, CommandDocs, CommandFlag, CommandHandler, CommandSpec, RedisCommandError,
+};
+use crate::resp::RESPValues;
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "asking",
+    arity: 1,
+    flags: &[CommandFlag::Fast],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Signals that a cluster client is following an -ASK redirect.",
+        since: "3.0.0",
+        group: "cluster",
+        complexity: "O(1)",
+        arguments: &[],
+    },
+};
+
+pub struct Asking;
+
+impl CommandHandler for Asking {
+    // Lets the next command use the keys of a slot this node is importing
+    fn call(
+        &self,
+        _args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        if !ctx.server.config.read().unwrap().cluster_enabled {
+            return Err(RedisCommandError::Invalid(
+                "This instance has cluster support disabled".to_string(),
+            ));
+        }
+        ctx.connection.asking = true;
+        Ok(RESPValues::SimpleString("OK".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod asking_tests {
+    use bytes::Bytes;
+
+    use super::Asking;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler},
+        resp::RESPValues,
+    };
+
+    #[test]
+    fn asking_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        ctx.server.config.write().unwrap().cluster_enabled = true;
+        let result = Asking.call(&[Bytes::from_static(b"ASKING")], &mut ctx);
+
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("OK".to_string())));
+        assert!(ctx.connection.asking);
+    }
+
+    #[test]
+    fn asking_while_disabled_fails() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let result = Asking.call(&[Bytes::from_static(b"ASKING")], &mut ctx);
+
+        assert!(result
+            .is_err_and(|e| e.to_string() == "ERR This instance has cluster support disabled"));
+        assert!(!ctx.connection.asking);
+    }
+}
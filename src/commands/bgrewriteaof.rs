@@ -0,0 +1,83 @@
+use bytes::Bytes;
+
+use super::{
+    CommandContext, CommandDocs, CommandFlag, CommandHandler, CommandSpec, RedisCommandError,
+};
+use crate::resp::RESPValues;
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "bgrewriteaof",
+    arity: 1,
+    flags: &[CommandFlag::Admin, CommandFlag::NoScript],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Asynchronously rewrites the append-only file to disk.",
+        since: "1.0.0",
+        group: "server",
+        complexity: "O(1)",
+        arguments: &[],
+    },
+};
+
+pub struct Bgrewriteaof;
+
+impl CommandHandler for Bgrewriteaof {
+    fn call(
+        &self,
+        _args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let started = ctx.server.bgrewriteaof().map_err(|error| {
+            RedisCommandError::Invalid(format!("Can't rewrite append only file: {error}"))
+        })?;
+        if !started {
+            return Err(RedisCommandError::Invalid(
+                "Background append only file rewriting already in progress".to_string(),
+            ));
+        }
+        Ok(RESPValues::SimpleString(
+            "Background append only file rewriting started".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod bgrewriteaof_tests {
+    use bytes::Bytes;
+
+    use super::Bgrewriteaof;
+    use crate::{
+        commands::{test_context, test_dir, test_state, CommandHandler},
+        resp::RESPValues,
+        store::Value,
+    };
+
+    #[test]
+    fn bgrewriteaof_correctly() {
+        let dir = test_dir("bgrewriteaof");
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        {
+            let mut config = ctx.server.config.write().unwrap();
+            config.dir = dir.path().to_path_buf();
+            config.aof_use_rdb_preamble = false;
+        }
+        ctx.server.store.set(
+            Bytes::from_static(b"k"),
+            Value::String(Bytes::from_static(b"v")),
+        );
+        let result = Bgrewriteaof.call(&[Bytes::from_static(b"BGREWRITEAOF")], &mut ctx);
+
+        assert!(result.is_ok_and(|r| r
+            == RESPValues::SimpleString(
+                "Background append only file rewriting started".to_string()
+            )));
+        while ctx.server.aof.rewriting() {
+            std::thread::yield_now();
+        }
+        let base = std::fs::read(dir.join("appendonlydir/appendonly.aof.1.base.aof")).unwrap();
+        assert!(base.starts_with(b"*4\r\n$7\r\nRESTORE\r\n$1\r\nk\r\n$1\r\n0\r\n"));
+    }
+}
@@ -0,0 +1,89 @@
+use bytes::Bytes;
+
+use super::{
+    ArgumentType, CommandArgument, CommandContext, CommandDocs, CommandFlag, CommandHandler,
+    CommandSpec, RedisCommandError,
+};
+use crate::resp::RESPValues;
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "bgsave",
+    arity: -1,
+    flags: &[CommandFlag::Admin, CommandFlag::NoScript],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Asynchronously saves the database(s) to disk.",
+        since: "1.0.0",
+        group: "server",
+        complexity: "O(1)",
+        arguments: &[CommandArgument {
+            name: "schedule",
+            kind: ArgumentType::PureToken,
+            optional: true,
+            multiple: false,
+        }],
+    },
+};
+
+pub struct Bgsave;
+
+impl CommandHandler for Bgsave {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        // SCHEDULE only matters while an AOF rewrite runs, and there is no AOF
+        match &args[1..] {
+            [] => {}
+            [schedule] if schedule.eq_ignore_ascii_case(b"SCHEDULE") => {}
+            _ => return Err(RedisCommandError::Invalid("syntax error".to_string())),
+        }
+
+        if !ctx.server.bgsave() {
+            return Err(RedisCommandError::Invalid(
+                "Background save already in progress".to_string(),
+            ));
+        }
+        Ok(RESPValues::SimpleString(
+            "Background saving started".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod bgsave_tests {
+    use bytes::Bytes;
+
+    use super::Bgsave;
+    use crate::{
+        commands::{test_context, test_dir, test_state, CommandHandler, RedisCommandError},
+        resp::RESPValues,
+    };
+
+    #[test]
+    fn bgsave_correctly() {
+        let dir = test_dir("bgsave");
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        ctx.server.config.write().unwrap().dir = dir.path().to_path_buf();
+        let result = Bgsave.call(&[Bytes::from_static(b"BGSAVE")], &mut ctx);
+
+        assert!(result
+            .is_ok_and(|r| r == RESPValues::SimpleString("Background saving started".to_string())));
+        while ctx.server.snapshots.in_progress() {
+            std::thread::yield_now();
+        }
+        assert!(dir.join("dump.rdb").exists());
+    }
+
+    #[test]
+    fn bgsave_with_unknown_option_fails() {
+        let args = [Bytes::from_static(b"BGSAVE"), Bytes::from_static(b"later")];
+        let result = Bgsave.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_err_and(|e| e == RedisCommandError::Invalid("syntax error".to_string())));
+    }
+}
@@ -0,0 +1,465 @@
+use std::net::IpAddr;
+
+use bytes::Bytes;
+
+use super::{
+    CommandContext, CommandDocs, CommandFlag, CommandHandler, CommandSpec, RedisCommandError,
+};
+use crate::{
+    cluster::{key_slot, ClusterError, Node, SetSlot, SLOTS},
+    resp::RESPValues,
+    server::Shared,
+};
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "cluster",
+    arity: -2,
+    flags: &[CommandFlag::Loading, CommandFlag::Stale],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "A container for Redis Cluster commands.",
+        since: "3.0.0",
+        group: "cluster",
+        complexity: "Depends on subcommand.",
+        arguments: &[],
+    },
+};
+
+pub struct Cluster;
+
+impl CommandHandler for Cluster {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let server = ctx.server;
+        if !server.config.read().unwrap().cluster_enabled {
+            return Err(RedisCommandError::Invalid(
+                "This instance has cluster support disabled".to_string(),
+            ));
+        }
+        let cluster = &server.cluster;
+        let subcommand = args[1].to_ascii_uppercase();
+        let ok = || RESPValues::SimpleString("OK".to_string());
+        match (&subcommand[..], args.len()) {
+            (b"INFO", 2) => Ok(text(info(server))),
+            (b"MYID", 2) => Ok(RESPValues::BulkString(cluster.myself().id.into())),
+            (b"NODES", 2) => Ok(text(cluster.describe())),
+            (b"SLOTS", 2) => Ok(slots(server)),
+            (b"SHARDS", 2) => Ok(shards(server)),
+            (b"KEYSLOT", 3) => Ok(RESPValues::Integer(key_slot(&args[2]) as i64)),
+            // the bus port after the client one is taken, but there's no bus
+            (b"MEET", 4 | 5) => {
+                let (ip, port) = (
+                    String::from_utf8_lossy(&args[2]),
+                    String::from_utf8_lossy(&args[3]),
+                );
+                let port = port.parse().map_err(|_| {
+                    RedisCommandError::Invalid(format!("Invalid base port specified: {port}"))
+                })?;
+                if ip.parse::<IpAddr>().is_err() {
+                    return Err(RedisCommandError::Invalid(format!(
+                        "Invalid node address specified: {ip}:{port}"
+                    )));
+                }
+                cluster.meet(ip.to_string(), port);
+                Ok(ok())
+            }
+            (b"SETSLOT", 4 | 5) => {
+                let slot = parse_slot(&args[2])?;
+                let id = || {
+                    args.get(4)
+                        .map(|id| String::from_utf8_lossy(id).to_string())
+                };
+                let action = match (&args[3].to_ascii_uppercase()[..], id()) {
+                    (b"MIGRATING", Some(id)) => SetSlot::Migrating(id),
+                    (b"IMPORTING", Some(id)) => SetSlot::Importing(id),
+                    (b"NODE", Some(id)) => SetSlot::Node(id),
+                    (b"STABLE", None) => SetSlot::Stable,
+                    _ => return Err(RedisCommandError::Invalid(
+                        "Invalid CLUSTER SETSLOT action or number of arguments. Try CLUSTER HELP"
+                            .to_string(),
+                    )),
+                };
+                cluster
+                    .set_slot(slot, action, || !keys_in_slot(server, slot).is_empty())
+                    .map_err(invalid)?;
+                Ok(ok())
+            }
+            (b"COUNTKEYSINSLOT", 3) => {
+                let slot = parse_slot(&args[2])
+                    .map_err(|_| RedisCommandError::Invalid("Invalid slot".to_string()))?;
+                Ok(RESPValues::Integer(keys_in_slot(server, slot).len() as i64))
+            }
+            (b"GETKEYSINSLOT", 4) => {
+                let invalid =
+                    || RedisCommandError::Invalid("Invalid slot or number of keys".to_string());
+                let slot = parse_slot(&args[2]).map_err(|_| invalid())?;
+                let count: usize = String::from_utf8_lossy(&args[3])
+                    .parse()
+                    .map_err(|_| invalid())?;
+                let keys = keys_in_slot(server, slot).into_iter().take(count);
+                Ok(RESPValues::Array(
+                    keys.map(RESPValues::BulkString).collect(),
+                ))
+            }
+            (b"ADDSLOTS", 3..) => {
+                cluster
+                    .add_slots(&parse_slots(&args[2..])?)
+                    .map_err(invalid)?;
+                Ok(ok())
+            }
+            (b"DELSLOTS", 3..) => {
+                cluster
+                    .del_slots(&parse_slots(&args[2..])?)
+                    .map_err(invalid)?;
+                Ok(ok())
+            }
+            (b"ADDSLOTSRANGE", n) if n > 2 && n.is_multiple_of(2) => {
+                cluster
+                    .add_slots(&parse_ranges(&args[2..])?)
+                    .map_err(invalid)?;
+                Ok(ok())
+            }
+            (b"DELSLOTSRANGE", n) if n > 2 && n.is_multiple_of(2) => {
+                cluster
+                    .del_slots(&parse_ranges(&args[2..])?)
+                    .map_err(invalid)?;
+                Ok(ok())
+            }
+            (b"INFO", _) => Err(RedisCommandError::WrongArity("cluster|info")),
+            (b"MYID", _) => Err(RedisCommandError::WrongArity("cluster|myid")),
+            (b"NODES", _) => Err(RedisCommandError::WrongArity("cluster|nodes")),
+            (b"SLOTS", _) => Err(RedisCommandError::WrongArity("cluster|slots")),
+            (b"SHARDS", _) => Err(RedisCommandError::WrongArity("cluster|shards")),
+            (b"KEYSLOT", _) => Err(RedisCommandError::WrongArity("cluster|keyslot")),
+            (b"MEET", _) => Err(RedisCommandError::WrongArity("cluster|meet")),
+            (b"SETSLOT", _) => Err(RedisCommandError::WrongArity("cluster|setslot")),
+            (b"COUNTKEYSINSLOT", _) => {
+                Err(RedisCommandError::WrongArity("cluster|countkeysinslot"))
+            }
+            (b"GETKEYSINSLOT", _) => Err(RedisCommandError::WrongArity("cluster|getkeysinslot")),
+            (b"ADDSLOTS", _) => Err(RedisCommandError::WrongArity("cluster|addslots")),
+            (b"DELSLOTS", _) => Err(RedisCommandError::WrongArity("cluster|delslots")),
+            (b"ADDSLOTSRANGE", _) => Err(RedisCommandError::WrongArity("cluster|addslotsrange")),
+            (b"DELSLOTSRANGE", _) => Err(RedisCommandError::WrongArity("cluster|delslotsrange")),
+            _ => Err(RedisCommandError::UnknownSubcommand(
+                SPEC.name,
+                String::from_utf8_lossy(&args[1]).to_string(),
+            )),
+        }
+    }
+}
+
+fn invalid(error: ClusterError) -> RedisCommandError {
+    RedisCommandError::Invalid(error.to_string())
+}
+
+fn text(text: String) -> RESPValues {
+    RESPValues::VerbatimString("txt".to_string(), text.into())
+}
+
+fn bulk(s: impl Into<String>) -> RESPValues {
+    RESPValues::BulkString(Bytes::from(s.into()))
+}
+
+// The keys of `slot` in key order, found going over a snapshot of the
+// keyspace as keys aren't indexed by slot
+fn keys_in_slot(server: &Shared, slot: usize) -> Vec<Bytes> {
+    let snapshot = server.store.snapshot();
+    let mut keys: Vec<Bytes> = (snapshot.iter())
+        .map(|(key, _)| key)
+        .filter(|key| key_slot(key) == slot)
+        .cloned()
+        .collect();
+    keys.sort();
+    keys
+}
+
+fn parse_slot(arg: &Bytes) -> Result<usize, RedisCommandError> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|slot| slot.parse().ok())
+        .filter(|&slot| slot < SLOTS)
+        .ok_or_else(|| invalid(ClusterError::OutOfRange))
+}
+
+fn parse_slots(args: &[Bytes]) -> Result<Vec<usize>, RedisCommandError> {
+    args.iter().map(parse_slot).collect()
+}
+
+// start and end pairs, both included
+fn parse_ranges(args: &[Bytes]) -> Result<Vec<usize>, RedisCommandError> {
+    let mut slots = Vec::new();
+    for pair in args.chunks(2) {
+        let (start, end) = (parse_slot(&pair[0])?, parse_slot(&pair[1])?);
+        if start > end {
+            return Err(RedisCommandError::Invalid(format!(
+                "start slot number {start} is greater than end slot number {end}"
+            )));
+        }
+        slots.extend(start..=end);
+    }
+    Ok(slots)
+}
+
+fn info(server: &Shared) -> String {
+    let cluster = &server.cluster;
+    let assigned = cluster.slots_assigned();
+    let nodes = cluster.nodes();
+    let ranges = cluster.ranges();
+    // the masters serving a slot at least
+    let size = nodes
+        .iter()
+        .filter(|node| ranges.iter().any(|(.., owner)| owner.id == node.id))
+        .count();
+    let state = if assigned == SLOTS { "ok" } else { "fail" };
+    let fields = [
+        ("cluster_state", state.to_string()),
+        ("cluster_slots_assigned", assigned.to_string()),
+        ("cluster_slots_ok", assigned.to_string()),
+        ("cluster_slots_pfail", "0".to_string()),
+        ("cluster_slots_fail", "0".to_string()),
+        ("cluster_known_nodes", nodes.len().to_string()),
+        ("cluster_size", size.to_string()),
+        ("cluster_current_epoch", cluster.current_epoch().to_string()),
+        ("cluster_my_epoch", cluster.myself().epoch.to_string()),
+    ];
+    fields
+        .iter()
+        .map(|(field, value)| format!("{field}:{value}\r\n"))
+        .collect()
+}
+
+// Each range of slots with the node serving it
+fn slots(server: &Shared) -> RESPValues {
+    let ranges = server
+        .cluster
+        .ranges()
+        .into_iter()
+        .map(|(start, end, node)| {
+            RESPValues::Array(vec![
+                RESPValues::Integer(start as i64),
+                RESPValues::Integer(end as i64),
+                RESPValues::Array(vec![
+                    bulk(node.ip),
+                    RESPValues::Integer(node.port.into()),
+                    bulk(node.id),
+                    RESPValues::Array(Vec::new()),
+                ]),
+            ])
+        });
+    RESPValues::Array(ranges.collect())
+}
+
+// Each node, a shard of its own as nodes have no replicas, with its slots
+fn shards(server: &Shared) -> RESPValues {
+    let ranges = server.cluster.ranges();
+    let shard = |node: Node| {
+        let slots = ranges
+            .iter()
+            .filter(|(.., owner)| owner.id == node.id)
+            .flat_map(|(start, end, _)| [*start, *end])
+            .map(|slot| RESPValues::Integer(slot as i64))
+            .collect();
+        let description = RESPValues::Map(vec![
+            (bulk("id"), bulk(node.id)),
+            (bulk("port"), RESPValues::Integer(node.port.into())),
+            (bulk("ip"), bulk(node.ip.clone())),
+            (bulk("endpoint"), bulk(node.ip)),
+            (bulk("role"), bulk("master")),
+            (
+                bulk("replication-offset"),
+                RESPValues::Integer(server.replication.offset() as i64),
+            ),
+            (bulk("health"), bulk("online")),
+        ]);
+        RESPValues::Map(vec![
+            (bulk("slots"), RESPValues::Array(slots)),
+            (bulk("nodes"), RESPValues::Array(vec![description])),
+        ])
+    };
+    RESPValues::Array(server.cluster.nodes().into_iter().map(shard).collect())
+}
+
+#[cfg(test)]
+mod cluster_tests {
+    use bytes::Bytes;
+
+    use super::Cluster;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler, RedisCommandError},
+        resp::RESPValues,
+        store::Value,
+    };
+
+    fn args(args: &[&str]) -> Vec<Bytes> {
+        args.iter().map(|a| Bytes::from(a.to_string())).collect()
+    }
+
+    #[test]
+    fn cluster_addslots_and_slots_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        ctx.server.config.write().unwrap().cluster_enabled = true;
+
+        let result = Cluster.call(&args(&["CLUSTER", "ADDSLOTSRANGE", "0", "99"]), &mut ctx);
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("OK".to_string())));
+        let result = Cluster.call(&args(&["CLUSTER", "SLOTS"]), &mut ctx);
+
+        let id = ctx.server.cluster.myself().id;
+        assert!(result.is_ok_and(|r| r
+            == RESPValues::Array(vec![RESPValues::Array(vec![
+                RESPValues::Integer(0),
+                RESPValues::Integer(99),
+                RESPValues::Array(vec![
+                    RESPValues::BulkString(Bytes::new()),
+                    RESPValues::Integer(0),
+                    RESPValues::BulkString(id.into()),
+                    RESPValues::Array(vec![]),
+                ]),
+            ])])));
+    }
+
+    #[test]
+    fn cluster_info_and_nodes_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        ctx.server.config.write().unwrap().cluster_enabled = true;
+        Cluster
+            .call(&args(&["CLUSTER", "ADDSLOTS", "5", "7"]), &mut ctx)
+            .unwrap();
+
+        let result = Cluster.call(&args(&["CLUSTER", "INFO"]), &mut ctx);
+        assert!(
+            result.is_ok_and(|r| matches!(r, RESPValues::VerbatimString(_, v)
+            if v.starts_with(b"cluster_state:fail\r\ncluster_slots_assigned:2\r\n")))
+        );
+        let result = Cluster.call(&args(&["CLUSTER", "NODES"]), &mut ctx);
+        let id = ctx.server.cluster.myself().id;
+        let expected = format!("{id} :0@10000 myself,master - 0 0 0 connected 5 7\n");
+        assert!(result
+            .is_ok_and(|r| r == RESPValues::VerbatimString("txt".to_string(), expected.into())));
+    }
+
+    #[test]
+    fn cluster_addslots_busy_fails() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        ctx.server.config.write().unwrap().cluster_enabled = true;
+        Cluster
+            .call(&args(&["CLUSTER", "ADDSLOTS", "5"]), &mut ctx)
+            .unwrap();
+
+        let result = Cluster.call(&args(&["CLUSTER", "ADDSLOTS", "6", "5"]), &mut ctx);
+        assert!(result
+            .is_err_and(|e| e == RedisCommandError::Invalid("Slot 5 is already busy".to_string())));
+        let result = Cluster.call(&args(&["CLUSTER", "DELSLOTS", "16384"]), &mut ctx);
+        assert!(result.is_err_and(|e| e.to_string() == "ERR Invalid or out of range slot"));
+    }
+
+    #[test]
+    fn cluster_keyslot_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        ctx.server.config.write().unwrap().cluster_enabled = true;
+        let result = Cluster.call(&args(&["CLUSTER", "KEYSLOT", "{foo}bar"]), &mut ctx);
+
+        assert!(result.is_ok_and(|r| r == RESPValues::Integer(12182)));
+    }
+
+    #[test]
+    fn cluster_meet_invalid_address_fails() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        ctx.server.config.write().unwrap().cluster_enabled = true;
+
+        let result = Cluster.call(&args(&["CLUSTER", "MEET", "localhost", "7000"]), &mut ctx);
+        assert!(result
+            .is_err_and(|e| e.to_string() == "ERR Invalid node address specified: localhost:7000"));
+        let result = Cluster.call(&args(&["CLUSTER", "MEET", "127.0.0.1", "x"]), &mut ctx);
+        assert!(result.is_err_and(|e| e.to_string() == "ERR Invalid base port specified: x"));
+    }
+
+    #[test]
+    fn cluster_setslot_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        ctx.server.config.write().unwrap().cluster_enabled = true;
+        let id = ctx.server.cluster.myself().id;
+        Cluster
+            .call(&args(&["CLUSTER", "ADDSLOTS", "5"]), &mut ctx)
+            .unwrap();
+
+        let result = Cluster.call(
+            &args(&["CLUSTER", "SETSLOT", "5", "MIGRATING", &id]),
+            &mut ctx,
+        );
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("OK".to_string())));
+        let result = Cluster.call(&args(&["CLUSTER", "NODES"]), &mut ctx);
+        assert!(
+            result.is_ok_and(|r| matches!(r, RESPValues::VerbatimString(_, v)
+            if v.ends_with(format!("connected 5 [5->-{id}]\n").as_bytes())))
+        );
+        let result = Cluster.call(&args(&["CLUSTER", "SETSLOT", "5", "STABLE"]), &mut ctx);
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("OK".to_string())));
+    }
+
+    #[test]
+    fn cluster_keys_in_slot_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        ctx.server.config.write().unwrap().cluster_enabled = true;
+        for key in ["{foo}b", "{foo}a", "bar"] {
+            let value = Value::String(Bytes::from_static(b"v"));
+            ctx.server.store.set(Bytes::from(key), value);
+        }
+
+        let result = Cluster.call(&args(&["CLUSTER", "COUNTKEYSINSLOT", "12182"]), &mut ctx);
+        assert!(result.is_ok_and(|r| r == RESPValues::Integer(2)));
+        let result = Cluster.call(&args(&["CLUSTER", "GETKEYSINSLOT", "12182", "1"]), &mut ctx);
+        assert!(result.is_ok_and(
+            |r| r == RESPValues::Array(vec![RESPValues::BulkString(Bytes::from("{foo}a"))])
+        ));
+        let result = Cluster.call(
+            &args(&["CLUSTER", "GETKEYSINSLOT", "12182", "-1"]),
+            &mut ctx,
+        );
+        assert!(result.is_err_and(|e| e.to_string() == "ERR Invalid slot or number of keys"));
+    }
+
+    #[test]
+    fn cluster_setslot_fails() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        ctx.server.config.write().unwrap().cluster_enabled = true;
+        let id = ctx.server.cluster.myself().id;
+
+        let result = Cluster.call(
+            &args(&["CLUSTER", "SETSLOT", "5", "MIGRATING", &id]),
+            &mut ctx,
+        );
+        assert!(result.is_err_and(|e| e.to_string() == "ERR I'm not the owner of hash slot 5"));
+        let result = Cluster.call(
+            &args(&["CLUSTER", "SETSLOT", "5", "IMPORTING", "x"]),
+            &mut ctx,
+        );
+        assert!(result.is_err_and(|e| e.to_string() == "ERR I don't know about node x"));
+        let result = Cluster.call(&args(&["CLUSTER", "SETSLOT", "5", "NODE"]), &mut ctx);
+        assert!(result.is_err_and(|e| e.to_string().starts_with("ERR Invalid CLUSTER SETSLOT")));
+    }
+
+    #[test]
+    fn cluster_while_disabled_fails() {
+        let result = Cluster.call(
+            &args(&["CLUSTER", "INFO"]),
+            &mut test_context(&mut test_state()),
+        );
+
+        assert!(result
+            .is_err_and(|e| e.to_string() == "ERR This instance has cluster support disabled"));
+    }
+}
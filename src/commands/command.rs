@@ -0,0 +1,241 @@
+use bytes::Bytes;
+
+use super::{
+    CommandContext, CommandDocs, CommandFlag, CommandHandler, CommandRegistry, CommandSpec,
+    RedisCommandError,
+};
+use crate::resp::RESPValues;
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "command",
+    arity: -1,
+    flags: &[CommandFlag::Loading, CommandFlag::Stale],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Returns detailed information about all commands.",
+        since: "2.8.13",
+        group: "server",
+        complexity: "O(N) where N is the total number of Redis commands",
+        arguments: &[],
+    },
+};
+
+pub struct Command;
+
+impl CommandHandler for Command {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let registry = &ctx.server.commands;
+        let subcommand = match args.get(1) {
+            None => {
+                let mut specs: Vec<_> = registry.specs().collect();
+                specs.sort_by_key(|spec| spec.name);
+                return Ok(RESPValues::Array(
+                    specs.iter().map(|spec| spec.info()).collect(),
+                ));
+            }
+            Some(v) => v.to_ascii_uppercase(),
+        };
+
+        match &subcommand[..] {
+            b"COUNT" => Ok(RESPValues::Integer(registry.len() as i64)),
+            b"INFO" => Ok(RESPValues::Array(
+                args[2..]
+                    .iter()
+                    .map(|name| {
+                        registry
+                            .spec(name)
+                            .map_or(RESPValues::Null, |spec| spec.info())
+                    })
+                    .collect(),
+            )),
+            b"GETKEYS" => get_keys(registry, &args[2..]),
+            b"DOCS" => Ok(docs(registry, &args[2..])),
+            _ => Err(RedisCommandError::UnknownSubcommand(
+                SPEC.name,
+                String::from_utf8_lossy(&args[1]).to_string(),
+            )),
+        }
+    }
+}
+
+// Docs for the given commands, skipping unknown ones, or for every command
+fn docs(registry: &CommandRegistry, names: &[Bytes]) -> RESPValues {
+    let mut specs: Vec<_> = if names.is_empty() {
+        registry.specs().collect()
+    } else {
+        names
+            .iter()
+            .filter_map(|name| registry.spec(name))
+            .collect()
+    };
+    if names.is_empty() {
+        specs.sort_by_key(|spec| spec.name);
+    }
+
+    RESPValues::Map(
+        specs
+            .into_iter()
+            .map(|spec| (RESPValues::BulkString(spec.name.into()), spec.docs()))
+            .collect(),
+    )
+}
+
+fn get_keys(registry: &CommandRegistry, args: &[Bytes]) -> Result<RESPValues, RedisCommandError> {
+    let error = |message: &str| Err(RedisCommandError::Invalid(message.to_string()));
+    let spec = match args.first().and_then(|name| registry.spec(name)) {
+        Some(spec) => spec,
+        None => return error("Invalid command specified"),
+    };
+    if !spec.accepts(args.len()) {
+        return error("Invalid number of arguments specified for command");
+    }
+
+    let keys = spec.keys(args);
+    if keys.is_empty() {
+        return error("The command has no key arguments");
+    }
+    Ok(RESPValues::Array(
+        keys.into_iter()
+            .map(|key| RESPValues::BulkString(key.clone()))
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod command_tests {
+    use bytes::Bytes;
+
+    use super::{get_keys, Command, SPEC};
+    use crate::{
+        commands::{
+            echo, test_context, test_state, CommandHandler, CommandRegistry, CommandSpec,
+            RedisCommandError,
+        },
+        resp::{RESPValues, RESPVersion},
+    };
+
+    #[test]
+    fn command_docs_with_no_string_correctly() {
+        let args = [Bytes::from_static(b"COMMAND"), Bytes::from_static(b"DOCS")];
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let expected = ctx.server.commands.len();
+        let result = Command.call(&args, &mut ctx);
+
+        assert!(result.is_ok_and(|r| matches!(r, RESPValues::Map(v) if v.len() == expected)));
+    }
+
+    #[test]
+    fn command_docs_with_a_string_correctly() {
+        let args = [
+            Bytes::from_static(b"COMMAND"),
+            Bytes::from_static(b"DOCS"),
+            Bytes::from_static(b"echo"),
+            Bytes::from_static(b"SET"),
+        ];
+        let result = Command.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_ok_and(|r| r
+            == RESPValues::Map(vec![(
+                RESPValues::BulkString(Bytes::from_static(b"echo")),
+                echo::SPEC.docs()
+            )])));
+    }
+
+    #[test]
+    fn command_count_correctly() {
+        let args = [Bytes::from_static(b"COMMAND"), Bytes::from_static(b"COUNT")];
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let expected = ctx.server.commands.len() as i64;
+        let result = Command.call(&args, &mut ctx);
+
+        assert!(result.is_ok_and(|r| r == RESPValues::Integer(expected)));
+    }
+
+    #[test]
+    fn command_lists_every_command_correctly() {
+        let args = [Bytes::from_static(b"COMMAND")];
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let expected = ctx.server.commands.len();
+        let result = Command.call(&args, &mut ctx);
+
+        assert!(result.is_ok_and(|r| matches!(r, RESPValues::Array(v) if v.len() == expected)));
+    }
+
+    #[test]
+    fn command_info_correctly() {
+        let args = [
+            Bytes::from_static(b"COMMAND"),
+            Bytes::from_static(b"info"),
+            Bytes::from_static(b"ECHO"),
+            Bytes::from_static(b"NOPE"),
+        ];
+        let result = Command.call(&args, &mut test_context(&mut test_state()));
+
+        let expected = RESPValues::Array(vec![echo::SPEC.info(), RESPValues::Null]);
+        assert!(result.is_ok_and(|r| r == expected));
+        assert!(echo::SPEC
+            .info()
+            .to_protocol(RESPVersion::RESP2)
+            .to_bytes()
+            .starts_with(b"*10\r\n$4\r\necho\r\n:2\r\n*1\r\n+fast\r\n:0\r\n"));
+    }
+
+    #[test]
+    fn command_getkeys_without_keys_fails() {
+        let args = [
+            Bytes::from_static(b"COMMAND"),
+            Bytes::from_static(b"GETKEYS"),
+            Bytes::from_static(b"ECHO"),
+            Bytes::from_static(b"hi"),
+        ];
+        let result = Command.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_err_and(
+            |e| e == RedisCommandError::Invalid("The command has no key arguments".to_string())
+        ));
+    }
+
+    #[test]
+    fn command_getkeys_correctly() {
+        let mut registry = CommandRegistry::new();
+        let mset = CommandSpec {
+            name: "mset",
+            arity: -3,
+            first_key: 1,
+            last_key: -1,
+            step: 2,
+            ..SPEC
+        };
+        registry.register(mset, Command);
+        let args = ["MSET", "a", "1", "b", "2"].map(|a| Bytes::from(a.as_bytes()));
+
+        assert_eq!(
+            get_keys(&registry, &args),
+            Ok(RESPValues::Array(vec![
+                RESPValues::BulkString(Bytes::from_static(b"a")),
+                RESPValues::BulkString(Bytes::from_static(b"b")),
+            ]))
+        );
+        assert!(get_keys(&registry, &args[..2]).is_err_and(
+            |e| e.to_string() == "ERR Invalid number of arguments specified for command"
+        ));
+    }
+
+    #[test]
+    fn command_unknown_subcommand_fails() {
+        let args = [Bytes::from_static(b"COMMAND"), Bytes::from_static(b"NOPE")];
+        let result = Command.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result
+            .is_err_and(|e| e.to_string() == "ERR unknown subcommand 'NOPE'. Try COMMAND HELP."));
+    }
+}
@@ -0,0 +1,239 @@
+use std::{fs, io, path::Path};
+
+use bytes::Bytes;
+
+use super::{
+    CommandContext, CommandDocs, CommandFlag, CommandHandler, CommandSpec, RedisCommandError,
+};
+use crate::{
+    config::{self, ConfigSetError},
+    glob,
+    resp::RESPValues,
+};
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "config",
+    arity: -2,
+    flags: &[
+        CommandFlag::Admin,
+        CommandFlag::NoScript,
+        CommandFlag::Loading,
+        CommandFlag::Stale,
+    ],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "A container for server configuration commands.",
+        since: "2.0.0",
+        group: "server",
+        complexity: "Depends on subcommand.",
+        arguments: &[],
+    },
+};
+
+pub struct Config;
+
+impl CommandHandler for Config {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        match &args[1].to_ascii_uppercase()[..] {
+            b"GET" if args.len() > 2 => Ok(get(&ctx.server.config.read().unwrap(), &args[2..])),
+            b"GET" => Err(RedisCommandError::WrongArity("config|get")),
+            b"SET" if args.len() > 2 && args.len().is_multiple_of(2) => {
+                let mut config = ctx.server.config.write().unwrap();
+                // every pair is applied or none is
+                let mut updated = config.clone();
+                set(&mut updated, &args[2..])?;
+                *config = updated;
+                Ok(RESPValues::SimpleString("OK".to_string()))
+            }
+            b"SET" => Err(RedisCommandError::WrongArity("config|set")),
+            b"REWRITE" => {
+                let config = ctx.server.config.read().unwrap();
+                let path = config.config_file.as_ref().ok_or_else(|| {
+                    RedisCommandError::Invalid(
+                        "The server is running without a config file".to_string(),
+                    )
+                })?;
+                rewrite(&config, path).map_err(|e| {
+                    RedisCommandError::Invalid(format!("Rewriting config file: {e}"))
+                })?;
+                Ok(RESPValues::SimpleString("OK".to_string()))
+            }
+            _ => Err(RedisCommandError::UnknownSubcommand(
+                SPEC.name,
+                String::from_utf8_lossy(&args[1]).to_string(),
+            )),
+        }
+    }
+}
+
+// Every parameter matching any of the glob `patterns`, each one listed once
+fn get(config: &config::Config, patterns: &[Bytes]) -> RESPValues {
+    RESPValues::Map(
+        config::PARAMETERS
+            .iter()
+            .filter(|parameter| {
+                patterns
+                    .iter()
+                    .any(|pattern| glob::string_match(pattern, parameter.name.as_bytes(), true))
+            })
+            .map(|parameter| {
+                let value = config.get(parameter.name).unwrap_or_default();
+                (
+                    RESPValues::BulkString(parameter.name.into()),
+                    RESPValues::BulkString(value.into()),
+                )
+            })
+            .collect(),
+    )
+}
+
+fn set(config: &mut config::Config, pairs: &[Bytes]) -> Result<(), RedisCommandError> {
+    for pair in pairs.chunks(2) {
+        let name = String::from_utf8_lossy(&pair[0]);
+        let value = String::from_utf8_lossy(&pair[1]);
+
+        config.set(&name, &value).map_err(|e| {
+            RedisCommandError::Invalid(match e {
+                ConfigSetError::UnknownOption(name) => {
+                    format!("Unknown option or number of arguments for CONFIG SET - '{name}'")
+                }
+                ConfigSetError::Immutable(name) => format!(
+                    "CONFIG SET failed (possibly related to argument '{name}') - can't set immutable config"
+                ),
+                ConfigSetError::InvalidValue(name, reason) => format!(
+                    "CONFIG SET failed (possibly related to argument '{name}') - {reason}"
+                ),
+            })
+        })?;
+    }
+
+    Ok(())
+}
+
+// Writes the rewritten file next to the original first, so a failure never leaves it half written
+fn rewrite(config: &config::Config, path: &Path) -> io::Result<()> {
+    let original = match fs::read_to_string(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        result => result?,
+    };
+
+    let temporary = path.with_extension("rewrite.tmp");
+    fs::write(&temporary, config.rewrite(&original))?;
+    fs::rename(&temporary, path)
+}
+
+#[cfg(test)]
+mod config_tests {
+    use std::fs;
+
+    use bytes::Bytes;
+
+    use super::Config;
+    use crate::{
+        commands::{test_context, test_dir, test_state, CommandHandler, RedisCommandError},
+        resp::RESPValues,
+    };
+
+    fn args(args: &[&str]) -> Vec<Bytes> {
+        args.iter().map(|a| Bytes::from(a.to_string())).collect()
+    }
+
+    #[test]
+    fn config_get_correctly() {
+        let result = Config.call(
+            &args(&["CONFIG", "GET", "PORT", "db*", "dbfilename"]),
+            &mut test_context(&mut test_state()),
+        );
+
+        assert!(result.is_ok_and(|r| r
+            == RESPValues::Map(vec![
+                (
+                    RESPValues::BulkString(Bytes::from_static(b"port")),
+                    RESPValues::BulkString(Bytes::from_static(b"6379"))
+                ),
+                (
+                    RESPValues::BulkString(Bytes::from_static(b"dbfilename")),
+                    RESPValues::BulkString(Bytes::from_static(b"dump.rdb"))
+                ),
+            ])));
+    }
+
+    #[test]
+    fn config_set_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let result = Config.call(
+            &args(&["CONFIG", "SET", "maxmemory", "1kb", "loglevel", "debug"]),
+            &mut ctx,
+        );
+
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("OK".to_string())));
+        assert_eq!(ctx.server.config.read().unwrap().maxmemory, 1024);
+    }
+
+    #[test]
+    fn config_set_immutable_fails() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let result = Config.call(
+            &args(&["CONFIG", "SET", "maxmemory", "1kb", "port", "7000"]),
+            &mut ctx,
+        );
+
+        assert!(result.is_err_and(|e| e.to_string()
+            == "ERR CONFIG SET failed (possibly related to argument 'port') - can't set immutable config"));
+        assert_eq!(ctx.server.config.read().unwrap().maxmemory, 0);
+    }
+
+    #[test]
+    fn config_set_unknown_option_fails() {
+        let result = Config.call(
+            &args(&["CONFIG", "SET", "nope", "1"]),
+            &mut test_context(&mut test_state()),
+        );
+
+        assert!(result.is_err_and(|e| e.to_string()
+            == "ERR Unknown option or number of arguments for CONFIG SET - 'nope'"));
+    }
+
+    #[test]
+    fn config_rewrite_without_file_fails() {
+        let result = Config.call(
+            &args(&["CONFIG", "REWRITE"]),
+            &mut test_context(&mut test_state()),
+        );
+
+        assert!(result.is_err_and(|e| e
+            == RedisCommandError::Invalid(
+                "The server is running without a config file".to_string()
+            )));
+    }
+
+    #[test]
+    fn config_rewrite_correctly() {
+        let dir = test_dir("config-rewrite");
+        let path = dir.join("redis.conf");
+        fs::write(&path, "# server\nport 7000\n").unwrap();
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        ctx.server.config.write().unwrap().config_file = Some(path.clone());
+
+        Config
+            .call(&args(&["CONFIG", "SET", "loglevel", "warning"]), &mut ctx)
+            .unwrap();
+        let result = Config.call(&args(&["CONFIG", "REWRITE"]), &mut ctx);
+        let written = fs::read_to_string(&path).unwrap();
+
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("OK".to_string())));
+        assert_eq!(
+            written,
+            "# server\nport 6379\n# Generated by CONFIG REWRITE\nloglevel warning\n"
+        );
+    }
+}
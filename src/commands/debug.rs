@@ -0,0 +1,87 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+
+use super::{
+    CommandContext, CommandDocs, CommandFlag, CommandHandler, CommandSpec, RedisCommandError,
+};
+use crate::{glob, resp::RESPValues};
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "debug",
+    arity: -2,
+    flags: &[
+        CommandFlag::Admin,
+        CommandFlag::NoScript,
+        CommandFlag::Loading,
+        CommandFlag::Stale,
+    ],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "A container for debugging commands.",
+        since: "1.0.0",
+        group: "server",
+        complexity: "Depends on subcommand.",
+        arguments: &[],
+    },
+};
+
+pub struct Debug;
+
+impl CommandHandler for Debug {
+    fn call(
+        &self,
+        args: &[Bytes],
+        _ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        match args.get(1) {
+            Some(v) if v.eq_ignore_ascii_case(b"STRINGMATCH-LEN") => {
+                let seed = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_nanos() as u64);
+                glob::fuzz_string_match(1_000_000, seed);
+                Ok(RESPValues::SimpleString(
+                    "Apparently the server did not crash: test passed".to_string(),
+                ))
+            }
+            _ => Err(RedisCommandError::UnknownSubcommand(
+                SPEC.name,
+                String::from_utf8_lossy(&args[1]).to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod debug_tests {
+    use bytes::Bytes;
+
+    use super::Debug;
+    use crate::commands::{test_context, test_state, CommandHandler, RedisCommandError};
+
+    #[test]
+    fn debug_stringmatch_len_correctly() {
+        let args = [
+            Bytes::from_static(b"DEBUG"),
+            Bytes::from_static(b"STRINGMATCH-LEN"),
+        ];
+        let result = Debug.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn debug_unknown_subcommand_fails() {
+        let args = [
+            Bytes::from_static(b"DEBUG"),
+            Bytes::from_static(b"SEGFAULT"),
+        ];
+        let result = Debug.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_err_and(
+            |e| e == RedisCommandError::UnknownSubcommand("debug", "SEGFAULT".to_string())
+        ));
+    }
+}
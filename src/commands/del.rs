@@ -0,0 +1,73 @@
+use bytes::Bytes;
+
+use super::{
+    ArgumentType, CommandArgument, CommandContext, CommandDocs, CommandFlag, CommandHandler,
+    CommandSpec, RedisCommandError,
+};
+use crate::{config::KeyspaceEvents, resp::RESPValues};
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "del",
+    arity: -2,
+    flags: &[CommandFlag::Write],
+    first_key: 1,
+    last_key: -1,
+    step: 1,
+    docs: CommandDocs {
+        summary: "Deletes one or more keys.",
+        since: "1.0.0",
+        group: "generic",
+        complexity: "O(N) where N is the number of keys that will be removed. When a key to remove holds a value other than a string, the individual complexity for this key is O(M) where M is the number of elements in the list, set, sorted set or hash. Removing a single key that holds a string value is O(1).",
+        arguments: &[CommandArgument {
+            name: "key",
+            kind: ArgumentType::Key,
+            optional: false,
+            multiple: true,
+        }],
+    },
+};
+
+pub struct Del;
+
+impl CommandHandler for Del {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let mut removed = 0;
+        for key in &args[1..] {
+            if ctx.server.store.remove(key).is_some() {
+                ctx.server
+                    .notify_keyspace_event(KeyspaceEvents::GENERIC, "del", key);
+                removed += 1;
+            }
+        }
+        Ok(RESPValues::Integer(removed))
+    }
+}
+
+#[cfg(test)]
+mod del_tests {
+    use bytes::Bytes;
+
+    use super::Del;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler},
+        resp::RESPValues,
+        store::Value,
+    };
+
+    #[test]
+    fn del_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let value = Value::String(Bytes::from_static(b"bar"));
+        ctx.server.store.set(Bytes::from_static(b"foo"), value);
+        let args = ["DEL", "foo", "missing", "foo"].map(Bytes::from);
+        let result = Del.call(&args, &mut ctx);
+
+        assert!(result.is_ok_and(|r| r == RESPValues::Integer(1)));
+        assert_eq!(ctx.server.store.get(b"foo"), None);
+    }
+}
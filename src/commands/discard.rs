@@ -0,0 +1,80 @@
+use bytes::Bytes;
+
+use super::{
+    CommandContext, CommandDocs, CommandFlag, CommandHandler, CommandSpec, RedisCommandError,
+};
+use crate::resp::RESPValues;
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "discard",
+    arity: 1,
+    flags: &[
+        CommandFlag::NoScript,
+        CommandFlag::Loading,
+        CommandFlag::Stale,
+        CommandFlag::Fast,
+    ],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Discards a transaction.",
+        since: "2.0.0",
+        group: "transactions",
+        complexity: "O(N), when N is the number of queued commands",
+        arguments: &[],
+    },
+};
+
+pub struct Discard;
+
+impl CommandHandler for Discard {
+    fn call(
+        &self,
+        _args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        match ctx.connection.transaction.take() {
+            Some(_) => {
+                ctx.connection.watched.clear();
+                Ok(RESPValues::SimpleString("OK".to_string()))
+            }
+            None => Err(RedisCommandError::Invalid(
+                "DISCARD without MULTI".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod discard_tests {
+    use bytes::Bytes;
+
+    use super::Discard;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler, Transaction},
+        resp::RESPValues,
+    };
+
+    #[test]
+    fn discard_clears_the_transaction_correctly() {
+        let args = [Bytes::from_static(b"DISCARD")];
+        let mut state = test_state();
+        state.transaction = Some(Transaction {
+            queued: vec![vec![Bytes::from_static(b"PING")]],
+            ..Default::default()
+        });
+        let result = Discard.call(&args, &mut test_context(&mut state));
+
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("OK".to_string())));
+        assert!(state.transaction.is_none());
+    }
+
+    #[test]
+    fn discard_without_multi_fails() {
+        let args = [Bytes::from_static(b"DISCARD")];
+        let result = Discard.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_err_and(|e| e.to_string() == "ERR DISCARD without MULTI"));
+    }
+}
@@ -0,0 +1,86 @@
+use bytes::Bytes;
+
+use super::{
+    ArgumentType, CommandArgument, CommandContext, CommandDocs, CommandFlag, CommandHandler,
+    CommandSpec, RedisCommandError,
+};
+use crate::{rdb, resp::RESPValues};
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "dump",
+    arity: 2,
+    flags: &[CommandFlag::ReadOnly],
+    first_key: 1,
+    last_key: 1,
+    step: 1,
+    docs: CommandDocs {
+        summary: "Returns a serialized representation of the value stored at a key.",
+        since: "2.6.0",
+        group: "generic",
+        complexity: "O(1) to access the key and additional O(N*M) to serialize it, where N is the number of Redis objects composing the value and M their average size. For small string values the time complexity is thus O(1)+O(1*M) where M is small, so simply O(1).",
+        arguments: &[CommandArgument {
+            name: "key",
+            kind: ArgumentType::Key,
+            optional: false,
+            multiple: false,
+        }],
+    },
+};
+
+pub struct Dump;
+
+impl CommandHandler for Dump {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let Some(value) = ctx.server.store.get(&args[1]) else {
+            return Ok(RESPValues::NullBulkString);
+        };
+        let payload = rdb::dump(&value, ctx.server.rdb_options());
+        Ok(RESPValues::BulkString(payload.into()))
+    }
+}
+
+#[cfg(test)]
+mod dump_tests {
+    use bytes::Bytes;
+
+    use super::Dump;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler},
+        rdb,
+        resp::RESPValues,
+        store::Value,
+    };
+
+    #[test]
+    fn dump_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let value = Value::String(Bytes::from_static(b"bar"));
+        ctx.server
+            .store
+            .set(Bytes::from_static(b"foo"), value.clone());
+        let result = Dump.call(
+            &[Bytes::from_static(b"DUMP"), Bytes::from_static(b"foo")],
+            &mut ctx,
+        );
+
+        assert!(
+            result.is_ok_and(|r| matches!(r, RESPValues::BulkString(payload)
+            if rdb::undump(&payload).is_ok_and(|v| v == value)))
+        );
+    }
+
+    #[test]
+    fn dump_missing_key_correctly() {
+        let result = Dump.call(
+            &[Bytes::from_static(b"DUMP"), Bytes::from_static(b"foo")],
+            &mut test_context(&mut test_state()),
+        );
+
+        assert!(result.is_ok_and(|r| r == RESPValues::NullBulkString));
+    }
+}
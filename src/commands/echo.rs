@@ -0,0 +1,70 @@
+use bytes::Bytes;
+
+use super::{
+    ArgumentType, CommandArgument, CommandContext, CommandDocs, CommandFlag, CommandHandler,
+    CommandSpec, RedisCommandError,
+};
+use crate::resp::RESPValues;
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "echo",
+    arity: 2,
+    flags: &[CommandFlag::Fast],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Returns the given string.",
+        since: "1.0.0",
+        group: "connection",
+        complexity: "O(1)",
+        arguments: &[CommandArgument {
+            name: "message",
+            kind: ArgumentType::String,
+            optional: false,
+            multiple: false,
+        }],
+    },
+};
+
+pub struct Echo;
+
+impl CommandHandler for Echo {
+    fn call(
+        &self,
+        args: &[Bytes],
+        _ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        Ok(RESPValues::BulkString(args[1].clone()))
+    }
+}
+
+#[cfg(test)]
+mod echo_tests {
+    use bytes::Bytes;
+
+    use super::Echo;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler},
+        resp::RESPValues,
+    };
+
+    #[test]
+    fn echo_with_string_correctly() {
+        let args = [Bytes::from_static(b"ECHO"), Bytes::from_static(b"testing")];
+        let result = Echo.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_ok_and(|r| r == RESPValues::BulkString(Bytes::from_static(b"testing"))));
+    }
+
+    #[test]
+    fn echo_binary_string_correctly() {
+        let args = [
+            Bytes::from_static(b"ECHO"),
+            Bytes::from_static(b"\r\n\x00\""),
+        ];
+        let result = Echo.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_ok_and(|r| r.to_bytes() == b"$4\r\n\r\n\x00\"\r\n"));
+    }
+}
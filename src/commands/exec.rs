@@ -0,0 +1,183 @@
+use bytes::Bytes;
+
+use super::{
+    CommandContext, CommandDocs, CommandFlag, CommandHandler, CommandSpec, RedisCommandError,
+};
+use crate::resp::RESPValues;
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "exec",
+    arity: 1,
+    flags: &[
+        CommandFlag::NoScript,
+        CommandFlag::Loading,
+        CommandFlag::Stale,
+    ],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Executes all commands in a transaction.",
+        since: "1.2.0",
+        group: "transactions",
+        complexity: "Depends on commands in the transaction",
+        arguments: &[],
+    },
+};
+
+pub struct Exec;
+
+impl CommandHandler for Exec {
+    // CommandRegistry::dispatch holds every other command off while this runs
+    fn call(
+        &self,
+        _args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let transaction = ctx
+            .connection
+            .transaction
+            .take()
+            .ok_or_else(|| RedisCommandError::Invalid("EXEC without MULTI".to_string()))?;
+
+        let watched = std::mem::take(&mut ctx.connection.watched);
+        if transaction.failed {
+            return Err(RedisCommandError::ExecAbort);
+        }
+
+        // a watched key changed since WATCH, the client is expected to retry
+        let store = &ctx.server.store;
+        if watched
+            .iter()
+            .any(|(key, version)| store.version(key) != *version)
+        {
+            return Ok(RESPValues::NullArray);
+        }
+
+        let commands = &ctx.server.commands;
+        ctx.connection.exec_writes = Some(Vec::new());
+        let replies = transaction
+            .queued
+            .iter()
+            .map(|args| commands.call(args, ctx).unwrap_or_else(RESPValues::from))
+            .collect();
+        let writes = ctx.connection.exec_writes.take().unwrap_or_default();
+        ctx.server.propagate_transaction(&writes);
+        Ok(RESPValues::Array(replies))
+    }
+}
+
+#[cfg(test)]
+mod exec_tests {
+    use bytes::Bytes;
+
+    use super::Exec;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler},
+        resp::RESPValues,
+        store::Value,
+    };
+
+    fn request(args: &[&'static str]) -> RESPValues {
+        RESPValues::Array(
+            args.iter()
+                .map(|arg| RESPValues::BulkString(Bytes::from_static(arg.as_bytes())))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn exec_runs_queued_commands_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let commands = &ctx.server.commands;
+
+        let ok = RESPValues::SimpleString("OK".to_string());
+        let queued = RESPValues::SimpleString("QUEUED".to_string());
+        assert_eq!(commands.dispatch(request(&["MULTI"]), &mut ctx), ok);
+        assert_eq!(commands.dispatch(request(&["PING"]), &mut ctx), queued);
+        assert_eq!(
+            commands.dispatch(request(&["ECHO", "hi"]), &mut ctx),
+            queued
+        );
+
+        let result = commands.dispatch(request(&["EXEC"]), &mut ctx);
+        assert_eq!(
+            result,
+            RESPValues::Array(vec![
+                RESPValues::SimpleString("PONG".to_string()),
+                RESPValues::BulkString(Bytes::from_static(b"hi")),
+            ])
+        );
+        assert!(ctx.connection.transaction.is_none());
+    }
+
+    #[test]
+    fn exec_after_a_watched_key_changed_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let commands = &ctx.server.commands;
+
+        commands.dispatch(request(&["WATCH", "k"]), &mut ctx);
+        commands.dispatch(request(&["MULTI"]), &mut ctx);
+        commands.dispatch(request(&["PING"]), &mut ctx);
+        ctx.server.store.set(
+            Bytes::from_static(b"k"),
+            Value::String(Bytes::from_static(b"v")),
+        );
+
+        let result = commands.dispatch(request(&["EXEC"]), &mut ctx);
+        assert_eq!(result, RESPValues::NullArray);
+        assert!(ctx.connection.watched.is_empty());
+    }
+
+    #[test]
+    fn exec_replies_execution_errors_inline_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let commands = &ctx.server.commands;
+
+        commands.dispatch(request(&["MULTI"]), &mut ctx);
+        commands.dispatch(request(&["PING", "a", "b"]), &mut ctx);
+        commands.dispatch(request(&["ECHO", "hi"]), &mut ctx);
+
+        let result = commands.dispatch(request(&["EXEC"]), &mut ctx);
+        assert_eq!(
+            result,
+            RESPValues::Array(vec![
+                RESPValues::SimpleError("ERR wrong number of arguments for 'ping' command".into()),
+                RESPValues::BulkString(Bytes::from_static(b"hi")),
+            ])
+        );
+    }
+
+    #[test]
+    fn exec_after_a_queueing_error_fails() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let commands = &ctx.server.commands;
+
+        commands.dispatch(request(&["MULTI"]), &mut ctx);
+        let rejected = commands.dispatch(request(&["NOPE"]), &mut ctx);
+        commands.dispatch(request(&["ECHO"]), &mut ctx);
+        commands.dispatch(request(&["PING"]), &mut ctx);
+
+        assert!(matches!(rejected, RESPValues::SimpleError(_)));
+        let result = commands.dispatch(request(&["EXEC"]), &mut ctx);
+        assert_eq!(
+            result,
+            RESPValues::SimpleError(
+                "EXECABORT Transaction discarded because of previous errors.".into()
+            )
+        );
+        assert!(ctx.connection.transaction.is_none());
+    }
+
+    #[test]
+    fn exec_without_multi_fails() {
+        let args = [Bytes::from_static(b"EXEC")];
+        let result = Exec.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_err_and(|e| e.to_string() == "ERR EXEC without MULTI"));
+    }
+}
@@ -0,0 +1,218 @@
+use bytes::Bytes;
+
+use super::{
+    ArgumentType, CommandArgument, CommandContext, CommandDocs, CommandFlag, CommandHandler,
+    CommandSpec, RedisCommandError,
+};
+use crate::{functions::ScriptCall, resp::RESPValues};
+
+const ARGUMENTS: &[CommandArgument] = &[
+    CommandArgument {
+        name: "function",
+        kind: ArgumentType::String,
+        optional: false,
+        multiple: false,
+    },
+    CommandArgument {
+        name: "numkeys",
+        kind: ArgumentType::Integer,
+        optional: false,
+        multiple: false,
+    },
+    CommandArgument {
+        name: "key",
+        kind: ArgumentType::Key,
+        optional: true,
+        multiple: true,
+    },
+    CommandArgument {
+        name: "arg",
+        kind: ArgumentType::String,
+        optional: true,
+        multiple: true,
+    },
+];
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "fcall",
+    arity: -3,
+    flags: &[
+        CommandFlag::NoScript,
+        CommandFlag::Stale,
+        CommandFlag::SkipMonitor,
+        CommandFlag::NoMandatoryKeys,
+    ],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Invokes a function.",
+        since: "7.0.0",
+        group: "scripting",
+        complexity: "Depends on the function that is executed.",
+        arguments: ARGUMENTS,
+    },
+};
+
+pub const READ_ONLY_SPEC: CommandSpec = CommandSpec {
+    name: "fcall_ro",
+    flags: &[
+        CommandFlag::NoScript,
+        CommandFlag::Stale,
+        CommandFlag::SkipMonitor,
+        CommandFlag::NoMandatoryKeys,
+        CommandFlag::ReadOnly,
+    ],
+    docs: CommandDocs {
+        summary: "Invokes a read-only function.",
+        ..SPEC.docs
+    },
+    ..SPEC
+};
+
+// Neither is flagged write, a function that writes is checked as a write here
+// instead, and what it writes is propagated by the commands it calls
+pub struct Fcall;
+
+impl CommandHandler for Fcall {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let invalid = |message: &str| Err(RedisCommandError::Invalid(message.to_string()));
+        let numkeys = String::from_utf8_lossy(&args[2]);
+        let numkeys = match numkeys.parse::<i64>() {
+            Err(_) => return invalid("value is not an integer or out of range"),
+            Ok(n) if n < 0 => return invalid("Number of keys can't be negative"),
+            Ok(n) if n as usize > args.len() - 3 => {
+                return invalid("Number of keys can't be greater than number of args")
+            }
+            Ok(n) => n as usize,
+        };
+        let Some(function) = ctx
+            .server
+            .functions
+            .function(&String::from_utf8_lossy(&args[1]))
+        else {
+            return invalid("Function not found");
+        };
+
+        let read_only = args[0].eq_ignore_ascii_case(b"fcall_ro");
+        if !function.no_writes {
+            if read_only {
+                return invalid("Can not execute a script with write flag using *_ro command.");
+            }
+            if !ctx.connection.master && ctx.server.read_only_replica() {
+                return Err(RedisCommandError::ReadOnly);
+            }
+            if ctx.server.too_few_replicas() {
+                return Err(RedisCommandError::NoReplicas);
+            }
+        }
+
+        let (keys, rest) = args[3..].split_at(numkeys);
+        let call = ScriptCall {
+            no_writes: function.no_writes || read_only,
+        };
+        let outer = ctx.connection.script.replace(call);
+        ctx.server.functions.start(ctx.connection.id);
+        let reply = (function.handler)(keys, rest, ctx);
+        ctx.server.functions.finish(ctx.connection.id);
+        ctx.connection.script = outer;
+        reply
+    }
+}
+
+#[cfg(test)]
+mod fcall_tests {
+    use bytes::Bytes;
+
+    use super::Fcall;
+    use crate::{
+        commands::{
+            function::Function, test_context, test_functions_context, test_state, CommandHandler,
+        },
+        resp::RESPValues,
+        store::Value,
+    };
+
+    #[test]
+    fn fcall_unknown_function_fails() {
+        let args = [
+            Bytes::from_static(b"FCALL"),
+            Bytes::from_static(b"f"),
+            Bytes::from_static(b"1"),
+            Bytes::from_static(b"k"),
+        ];
+        let result = Fcall.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_err_and(|e| e.to_string() == "ERR Function not found"));
+    }
+
+    #[test]
+    fn fcall_with_too_many_keys_fails() {
+        let args = [
+            Bytes::from_static(b"FCALL_RO"),
+            Bytes::from_static(b"f"),
+            Bytes::from_static(b"2"),
+            Bytes::from_static(b"k"),
+        ];
+        let result = Fcall.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_err_and(
+            |e| e.to_string() == "ERR Number of keys can't be greater than number of args"
+        ));
+    }
+
+    #[test]
+    fn fcall_runs_a_function_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_functions_context(&mut state);
+        let library = Bytes::from("#!test name=lib\nremove");
+        let value = Value::String(Bytes::from_static(b"bar"));
+        ctx.server.store.set(Bytes::from_static(b"foo"), value);
+        Function
+            .call(
+                &[Bytes::from("FUNCTION"), Bytes::from("LOAD"), library],
+                &mut ctx,
+            )
+            .unwrap();
+        let args = ["FCALL", "remove", "1", "foo", "DEL"].map(Bytes::from);
+        let result = Fcall.call(&args, &mut ctx);
+
+        assert!(result.is_ok_and(|r| r == RESPValues::Integer(1)));
+        assert_eq!(ctx.server.store.get(b"foo"), None);
+        assert_eq!(ctx.connection.script, None);
+    }
+
+    #[test]
+    fn fcall_ro_with_writes_fails() {
+        let mut state = test_state();
+        let mut ctx = test_functions_context(&mut state);
+        let library = Bytes::from("#!test name=lib\nremove\nreader no-writes");
+        Function
+            .call(
+                &[Bytes::from("FUNCTION"), Bytes::from("LOAD"), library],
+                &mut ctx,
+            )
+            .unwrap();
+
+        let args = ["FCALL_RO", "remove", "1", "foo", "DEL"].map(Bytes::from);
+        let result = Fcall.call(&args, &mut ctx);
+        assert!(result
+            .is_err_and(|e| e.to_string()
+                == "ERR Can not execute a script with write flag using *_ro command."));
+
+        let args = ["FCALL", "reader", "1", "foo", "DEL"].map(Bytes::from);
+        let result = Fcall.call(&args, &mut ctx);
+        assert!(result.is_err_and(
+            |e| e.to_string() == "ERR Write commands are not allowed from read-only scripts."
+        ));
+
+        let args = ["FCALL", "remove", "0", "FCALL", "remove", "0"].map(Bytes::from);
+        let result = Fcall.call(&args, &mut ctx);
+        assert!(result
+            .is_err_and(|e| e.to_string() == "ERR This Redis command is not allowed from script"));
+    }
+}
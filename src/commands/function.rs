@@ -0,0 +1,310 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+use super::{
+    CommandContext, CommandDocs, CommandFlag, CommandHandler, CommandSpec, RedisCommandError,
+};
+use crate::{
+    functions::{valid_name, Library},
+    glob::string_match,
+    resp::RESPValues,
+};
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "function",
+    arity: -2,
+    flags: &[CommandFlag::NoScript],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "A container for function commands.",
+        since: "7.0.0",
+        group: "scripting",
+        complexity: "Depends on subcommand.",
+        arguments: &[],
+    },
+};
+
+// Libraries are loaded by the engines modules register, see
+// CommandRegistry::register_engine. They are propagated as written, but not
+// kept in snapshots or AOF rewrites, like the script cache
+pub struct Function;
+
+impl CommandHandler for Function {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let invalid = |message: String| Err(RedisCommandError::Invalid(message));
+        let subcommand = args[1].to_ascii_uppercase();
+        // LOAD, DELETE and FLUSH write, only LIST and KILL are left to read only
+        // replicas
+        if !matches!(&subcommand[..], b"LIST" | b"KILL")
+            && !ctx.connection.master
+            && ctx.server.read_only_replica()
+        {
+            return Err(RedisCommandError::ReadOnly);
+        }
+        let libraries = &ctx.server.functions;
+        match &subcommand[..] {
+            b"LOAD" => {
+                let (replace, code) = match &args[2..] {
+                    [code] => (false, code),
+                    [replace, code] if replace.eq_ignore_ascii_case(b"REPLACE") => (true, code),
+                    [_, _] => return invalid("Unknown option given".to_string()),
+                    _ => return Err(RedisCommandError::WrongArity("function|load")),
+                };
+                let (engine, name) = metadata(code).map_err(RedisCommandError::Invalid)?;
+                let Some(loader) = ctx.server.commands.engine(&engine) else {
+                    return invalid(format!("Engine '{engine}' not found"));
+                };
+                let body = code.splitn(2, |&c| c == b'\n').nth(1).unwrap_or_default();
+                let functions = loader
+                    .load(&String::from_utf8_lossy(body))
+                    .map_err(RedisCommandError::Invalid)?;
+                let library = Library {
+                    name: name.clone(),
+                    engine,
+                    code: code.clone(),
+                    functions: functions.into_iter().map(Arc::new).collect(),
+                };
+                libraries
+                    .load(library, replace)
+                    .map_err(RedisCommandError::Invalid)?;
+                ctx.propagate(args);
+                Ok(RESPValues::BulkString(name.into()))
+            }
+            b"LIST" => {
+                let mut pattern = None;
+                let mut with_code = false;
+                let mut options = args[2..].iter();
+                while let Some(option) = options.next() {
+                    match &option.to_ascii_uppercase()[..] {
+                        b"WITHCODE" => with_code = true,
+                        b"LIBRARYNAME" => match options.next() {
+                            Some(name) if pattern.is_none() => pattern = Some(name),
+                            Some(_) => {
+                                return invalid(
+                                    "library name argument was already given".to_string(),
+                                )
+                            }
+                            None => {
+                                return invalid("library name argument was not given".to_string())
+                            }
+                        },
+                        _ => {
+                            return invalid(format!(
+                                "Unknown argument {}",
+                                String::from_utf8_lossy(option)
+                            ))
+                        }
+                    }
+                }
+                let listed = libraries
+                    .libraries()
+                    .into_iter()
+                    .filter(|library| {
+                        pattern.is_none_or(|pattern| {
+                            string_match(pattern, library.name.as_bytes(), false)
+                        })
+                    })
+                    .map(|library| list_entry(&library, with_code))
+                    .collect();
+                Ok(RESPValues::Array(listed))
+            }
+            b"DELETE" if args.len() == 3 => {
+                if !libraries.delete(&String::from_utf8_lossy(&args[2])) {
+                    return invalid("Library not found".to_string());
+                }
+                ctx.propagate(args);
+                Ok(RESPValues::SimpleString("OK".to_string()))
+            }
+            b"DELETE" => Err(RedisCommandError::WrongArity("function|delete")),
+            b"KILL" if args.len() == 2 => libraries
+                .kill()
+                .map(|()| RESPValues::SimpleString("OK".to_string())),
+            b"KILL" => Err(RedisCommandError::WrongArity("function|kill")),
+            b"FLUSH" => {
+                let mode = args.get(2).map(|mode| mode.to_ascii_uppercase());
+                if args.len() > 3 || !matches!(mode.as_deref(), None | Some(b"ASYNC" | b"SYNC")) {
+                    return invalid("FUNCTION FLUSH only supports SYNC|ASYNC option".to_string());
+                }
+                libraries.flush();
+                ctx.propagate(args);
+                Ok(RESPValues::SimpleString("OK".to_string()))
+            }
+            _ => Err(RedisCommandError::UnknownSubcommand(
+                SPEC.name,
+                String::from_utf8_lossy(&args[1]).to_string(),
+            )),
+        }
+    }
+}
+
+// A library as FUNCTION LIST shows it, a map in RESP3
+fn list_entry(library: &Library, with_code: bool) -> RESPValues {
+    let bulk = |value: &str| RESPValues::BulkString(Bytes::copy_from_slice(value.as_bytes()));
+    let functions = library
+        .functions
+        .iter()
+        .map(|function| {
+            let flags = match function.no_writes {
+                true => vec![bulk("no-writes")],
+                false => vec![],
+            };
+            RESPValues::Map(vec![
+                (bulk("name"), bulk(&function.name)),
+                (
+                    bulk("description"),
+                    function
+                        .description
+                        .as_deref()
+                        .map_or(RESPValues::Null, bulk),
+                ),
+                (bulk("flags"), RESPValues::Set(flags)),
+            ])
+        })
+        .collect();
+    let mut entry = vec![
+        (bulk("library_name"), bulk(&library.name)),
+        (bulk("engine"), bulk(&library.engine)),
+        (bulk("functions"), RESPValues::Array(functions)),
+    ];
+    if with_code {
+        entry.push((
+            bulk("library_code"),
+            RESPValues::BulkString(library.code.clone()),
+        ));
+    }
+    RESPValues::Map(entry)
+}
+
+// The engine and library name in a `#!<engine> name=<library>` first line
+fn metadata(code: &[u8]) -> Result<(String, String), String> {
+    let line = code.split(|&c| c == b'\n').next().unwrap_or_default();
+    let line = String::from_utf8_lossy(line);
+    let mut fields = line
+        .strip_prefix("#!")
+        .ok_or("Missing library metadata")?
+        .split_whitespace();
+    let engine = fields.next().ok_or("Missing library metadata")?.to_string();
+
+    let mut name = None;
+    for field in fields {
+        match field.strip_prefix("name=") {
+            Some(value) => name = Some(value.to_string()),
+            None => return Err(format!("Invalid metadata value given: {field}")),
+        }
+    }
+
+    let name = name.ok_or("Library name was not given")?;
+    if !valid_name(&name) {
+        return Err("Library names can only contain letters, numbers, or underscores(_) and must be at least one character long".to_string());
+    }
+    Ok((engine, name))
+}
+
+#[cfg(test)]
+mod function_tests {
+    use bytes::Bytes;
+
+    use super::{metadata, Function};
+    use crate::{
+        commands::{test_context, test_functions_context, test_state, CommandHandler},
+        resp::RESPValues,
+    };
+
+    #[test]
+    fn parse_library_metadata_correctly() {
+        let code = b"#!lua name=mylib\nredis.register_function('f', function() return 1 end)";
+
+        assert_eq!(metadata(code), Ok(("lua".to_string(), "mylib".to_string())));
+    }
+
+    #[test]
+    fn parse_invalid_library_metadata_fails() {
+        assert_eq!(
+            metadata(b"return 1"),
+            Err("Missing library metadata".to_string())
+        );
+        assert_eq!(
+            metadata(b"#!lua"),
+            Err("Library name was not given".to_string())
+        );
+        assert_eq!(
+            metadata(b"#!lua name=lib other=1"),
+            Err("Invalid metadata value given: other=1".to_string())
+        );
+        assert!(metadata(b"#!lua name=my-lib").is_err());
+    }
+
+    #[test]
+    fn function_load_without_an_engine_fails() {
+        let args = [
+            Bytes::from_static(b"FUNCTION"),
+            Bytes::from_static(b"LOAD"),
+            Bytes::from_static(b"REPLACE"),
+            Bytes::from_static(b"#!lua name=mylib\n"),
+        ];
+        let result = Function.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_err_and(|e| e.to_string() == "ERR Engine 'lua' not found"));
+    }
+
+    #[test]
+    fn function_load_list_and_delete_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_functions_context(&mut state);
+        let load = [
+            "FUNCTION",
+            "LOAD",
+            "#!test name=mylib\nremove\ncount no-writes",
+        ];
+        let result = Function.call(&load.map(Bytes::from), &mut ctx);
+
+        assert!(result.is_ok_and(|r| r == RESPValues::BulkString(Bytes::from("mylib"))));
+        assert!(ctx.server.functions.function("count").is_some());
+
+        let list = ["FUNCTION", "LIST", "LIBRARYNAME", "my*"].map(Bytes::from);
+        let Ok(RESPValues::Array(listed)) = Function.call(&list, &mut ctx) else {
+            panic!("FUNCTION LIST didn't reply with an array");
+        };
+        let RESPValues::Map(library) = &listed[0] else {
+            panic!("libraries are listed as maps");
+        };
+        assert_eq!(listed.len(), 1);
+        assert_eq!(
+            library[0],
+            (
+                RESPValues::BulkString(Bytes::from("library_name")),
+                RESPValues::BulkString(Bytes::from("mylib"))
+            )
+        );
+
+        let list = ["FUNCTION", "LIST", "LIBRARYNAME", "other*"].map(Bytes::from);
+        let result = Function.call(&list, &mut ctx);
+        assert!(result.is_ok_and(|r| r == RESPValues::Array(vec![])));
+
+        let delete = ["FUNCTION", "DELETE", "mylib"].map(Bytes::from);
+        assert!(Function.call(&delete, &mut ctx).is_ok());
+        assert!(ctx.server.functions.function("count").is_none());
+        let result = Function.call(&delete, &mut ctx);
+        assert!(result.is_err_and(|e| e.to_string() == "ERR Library not found"));
+    }
+
+    #[test]
+    fn function_load_an_existing_library_fails() {
+        let mut state = test_state();
+        let mut ctx = test_functions_context(&mut state);
+        let load = ["FUNCTION", "LOAD", "#!test name=mylib\nremove"].map(Bytes::from);
+        let replace = ["FUNCTION", "LOAD", "REPLACE", "#!test name=mylib\nremove"];
+
+        assert!(Function.call(&load, &mut ctx).is_ok());
+        let result = Function.call(&load, &mut ctx);
+        assert!(result.is_err_and(|e| e.to_string() == "ERR Library 'mylib' already exists"));
+        assert!(Function.call(&replace.map(Bytes::from), &mut ctx).is_ok());
+    }
+}
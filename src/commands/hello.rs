@@ -0,0 +1,258 @@
+use bytes::Bytes;
+
+use super::{
+    ArgumentType, CommandArgument, CommandContext, CommandDocs, CommandFlag, CommandHandler,
+    CommandSpec, ConnectionState, RedisCommandError,
+};
+use crate::{
+    resp::{RESPValues, RESPVersion},
+    server::Shared,
+};
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "hello",
+    arity: -1,
+    flags: &[
+        CommandFlag::NoScript,
+        CommandFlag::Loading,
+        CommandFlag::Stale,
+        CommandFlag::Fast,
+    ],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Handshakes with the Redis server.",
+        since: "6.0.0",
+        group: "connection",
+        complexity: "O(1)",
+        arguments: &[
+            CommandArgument {
+                name: "protover",
+                kind: ArgumentType::Integer,
+                optional: true,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "username",
+                kind: ArgumentType::String,
+                optional: true,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "password",
+                kind: ArgumentType::String,
+                optional: true,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "clientname",
+                kind: ArgumentType::String,
+                optional: true,
+                multiple: false,
+            },
+        ],
+    },
+};
+
+pub struct Hello;
+
+impl CommandHandler for Hello {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let protocol_version = match args.get(1) {
+            None => None,
+            Some(v) => Some(
+                std::str::from_utf8(v)
+                    .ok()
+                    .and_then(|v| v.parse::<i64>().ok())
+                    .ok_or(RedisCommandError::InvalidProtocolVersion)?,
+            ),
+        };
+
+        let mut auth = None;
+        let mut name = None;
+        let mut options = args.iter().skip(2);
+        while let Some(option) = options.next() {
+            match &option.to_ascii_uppercase()[..] {
+                b"AUTH" if options.len() >= 2 => auth = options.next().zip(options.next()),
+                b"SETNAME" if options.len() >= 1 => name = options.next(),
+                _ => {
+                    return Err(RedisCommandError::Invalid(format!(
+                        "Syntax error in HELLO option '{}'",
+                        String::from_utf8_lossy(option)
+                    )))
+                }
+            }
+        }
+
+        let protocol = match protocol_version {
+            None => ctx.connection.protocol,
+            Some(2) => RESPVersion::RESP2,
+            Some(3) if !ctx.server.config.read().unwrap().resp2_only => RESPVersion::RESP3,
+            Some(_) => return Err(RedisCommandError::NoProto),
+        };
+        // there are no passwords, the default user takes any
+        if auth.is_some_and(|(username, _)| username != "default") {
+            return Err(RedisCommandError::WrongPass);
+        }
+        if let Some(name) = name {
+            if name.iter().any(|&c| !(b'!'..=b'~').contains(&c)) {
+                return Err(RedisCommandError::Invalid(
+                    "Client names cannot contain spaces, newlines or special characters."
+                        .to_string(),
+                ));
+            }
+            ctx.connection.name = Some(name.clone());
+        }
+        ctx.connection.protocol = protocol;
+        Ok(server_metadata(ctx.connection, ctx.server))
+    }
+}
+
+fn server_metadata(state: &ConnectionState, shared: &Shared) -> RESPValues {
+    let field = |name: &'static str| RESPValues::BulkString(name.into());
+    let protocol = match state.protocol {
+        RESPVersion::RESP2 => 2,
+        RESPVersion::RESP3 => 3,
+    };
+    let (mode, role) = {
+        let config = shared.config.read().unwrap();
+        let mode = if config.cluster_enabled {
+            "cluster"
+        } else {
+            "standalone"
+        };
+        let role = match config.replicaof {
+            Some(_) => "replica",
+            None => "master",
+        };
+        (mode, role)
+    };
+
+    RESPValues::Map(vec![
+        (field("server"), field("redis")),
+        (field("version"), field(env!("CARGO_PKG_VERSION"))),
+        (field("proto"), RESPValues::Integer(protocol)),
+        (field("id"), RESPValues::Integer(state.id as i64)),
+        (field("mode"), field(mode)),
+        (field("role"), field(role)),
+        (field("modules"), RESPValues::Array(vec![])),
+    ])
+}
+
+#[cfg(test)]
+mod hello_tests {
+    use bytes::Bytes;
+
+    use super::Hello;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler, RedisCommandError},
+        resp::{RESPValues, RESPVersion},
+    };
+
+    #[test]
+    fn hello_with_no_version_correctly() {
+        let args = [Bytes::from_static(b"HELLO")];
+        let mut state = test_state();
+        let result = Hello.call(&args, &mut test_context(&mut state));
+
+        assert!(result.is_ok_and(|r| matches!(r, RESPValues::Map(_))));
+        assert_eq!(state.protocol, RESPVersion::RESP2);
+    }
+
+    #[test]
+    fn hello_with_version_correctly() {
+        let args = [Bytes::from_static(b"HELLO"), Bytes::from_static(b"3")];
+        let mut state = test_state();
+        let result = Hello.call(&args, &mut test_context(&mut state));
+
+        assert!(result.is_ok());
+        assert_eq!(state.protocol, RESPVersion::RESP3);
+    }
+
+    #[test]
+    fn hello_with_unsupported_version_fails() {
+        let args = [Bytes::from_static(b"HELLO"), Bytes::from_static(b"4")];
+        let mut state = test_state();
+        let result = Hello.call(&args, &mut test_context(&mut state));
+
+        assert!(result.is_err_and(|e| e == RedisCommandError::NoProto));
+        assert_eq!(state.protocol, RESPVersion::RESP2);
+    }
+
+    #[test]
+    fn hello_reports_mode_and_role_correctly() {
+        let args = [Bytes::from_static(b"HELLO")];
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        {
+            let mut config = ctx.server.config.write().unwrap();
+            config.cluster_enabled = true;
+            config.replicaof = Some(("10.0.0.1".to_string(), 6379));
+        }
+        let result = Hello.call(&args, &mut ctx);
+
+        let field = |name: &'static str| RESPValues::BulkString(name.into());
+        assert!(result.is_ok_and(|r| matches!(r, RESPValues::Map(fields)
+            if fields.contains(&(field("mode"), field("cluster")))
+                && fields.contains(&(field("role"), field("replica"))))));
+    }
+
+    #[test]
+    fn hello_with_auth_and_setname_correctly() {
+        let args = ["HELLO", "3", "AUTH", "default", "secret", "SETNAME", "app"].map(Bytes::from);
+        let mut state = test_state();
+        let result = Hello.call(&args, &mut test_context(&mut state));
+
+        assert!(result.is_ok());
+        assert_eq!(state.protocol, RESPVersion::RESP3);
+        assert_eq!(state.name, Some(Bytes::from_static(b"app")));
+    }
+
+    #[test]
+    fn hello_with_bad_options_fails() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let unknown = ["HELLO", "3", "SETNAME"].map(Bytes::from);
+        let result = Hello.call(&unknown, &mut ctx);
+        assert!(
+            result.is_err_and(|e| e.to_string() == "ERR Syntax error in HELLO option 'SETNAME'")
+        );
+
+        let user = ["HELLO", "3", "AUTH", "someone", "secret"].map(Bytes::from);
+        let result = Hello.call(&user, &mut ctx);
+        assert!(result.is_err_and(|e| e == RedisCommandError::WrongPass));
+
+        let name = ["HELLO", "3", "SETNAME", "my app"].map(Bytes::from);
+        let result = Hello.call(&name, &mut ctx);
+        assert!(result.is_err_and(|e| e.to_string()
+            == "ERR Client names cannot contain spaces, newlines or special characters."));
+        assert_eq!(ctx.connection.protocol, RESPVersion::RESP2);
+    }
+
+    #[test]
+    fn hello_3_in_resp2_only_mode_fails() {
+        let args = [Bytes::from_static(b"HELLO"), Bytes::from_static(b"3")];
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        ctx.server.config.write().unwrap().resp2_only = true;
+        let result = Hello.call(&args, &mut ctx);
+        assert!(result.is_err_and(|e| e == RedisCommandError::NoProto));
+
+        let args = [Bytes::from_static(b"HELLO"), Bytes::from_static(b"2")];
+        assert!(Hello.call(&args, &mut ctx).is_ok());
+        assert_eq!(state.protocol, RESPVersion::RESP2);
+    }
+
+    #[test]
+    fn hello_with_non_integer_version_fails() {
+        let args = [Bytes::from_static(b"HELLO"), Bytes::from_static(b"three")];
+        let result = Hello.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_err_and(|e| e == RedisCommandError::InvalidProtocolVersion));
+    }
+}
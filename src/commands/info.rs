@@ -0,0 +1,238 @@
+use bytes::Bytes;
+
+use super::{
+    ArgumentType, CommandArgument, CommandContext, CommandDocs, CommandFlag, CommandHandler,
+    CommandSpec, RedisCommandError,
+};
+use crate::{replication::LinkState, resp::RESPValues, server::Shared};
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "info",
+    arity: -1,
+    flags: &[CommandFlag::Loading, CommandFlag::Stale],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Returns information and statistics about the server.",
+        since: "1.0.0",
+        group: "server",
+        complexity: "O(1)",
+        arguments: &[CommandArgument {
+            name: "section",
+            kind: ArgumentType::String,
+            optional: true,
+            multiple: true,
+        }],
+    },
+};
+
+type Fields = Vec<(String, String)>;
+
+type Section = (&'static str, fn(&Shared) -> Fields);
+
+// In the order INFO lists them
+const SECTIONS: &[Section] = &[
+    ("server", server),
+    ("clients", clients),
+    ("replication", replication),
+    ("cluster", cluster),
+];
+
+pub struct Info;
+
+impl CommandHandler for Info {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let every = args.len() == 1
+            || args[1..].iter().any(|name| {
+                [&b"default"[..], b"all", b"everything"]
+                    .iter()
+                    .any(|every| name.eq_ignore_ascii_case(every))
+            });
+
+        let mut text = String::new();
+        for (name, fields) in SECTIONS {
+            if !every
+                && !args[1..]
+                    .iter()
+                    .any(|v| v.eq_ignore_ascii_case(name.as_bytes()))
+            {
+                continue;
+            }
+            if !text.is_empty() {
+                text.push_str("\r\n");
+            }
+
+            let mut title = name.to_string();
+            title[..1].make_ascii_uppercase();
+            text.push_str(&format!("# {title}\r\n"));
+            for (field, value) in fields(ctx.server) {
+                text.push_str(&format!("{field}:{value}\r\n"));
+            }
+        }
+
+        Ok(RESPValues::VerbatimString("txt".to_string(), text.into()))
+    }
+}
+
+fn field(name: &str, value: impl ToString) -> (String, String) {
+    (name.to_string(), value.to_string())
+}
+
+fn server(shared: &Shared) -> Fields {
+    let config = shared.config.read().unwrap();
+    let mode = if config.cluster_enabled {
+        "cluster"
+    } else {
+        "standalone"
+    };
+    vec![
+        field("redis_version", env!("CARGO_PKG_VERSION")),
+        field("redis_mode", mode),
+        field("process_id", std::process::id()),
+        field("tcp_port", config.port),
+        field("uptime_in_seconds", shared.started_at.elapsed().as_secs()),
+    ]
+}
+
+fn clients(shared: &Shared) -> Fields {
+    vec![
+        field("connected_clients", shared.clients.len()),
+        field("maxclients", shared.config.read().unwrap().maxclients),
+    ]
+}
+
+fn replication(shared: &Shared) -> Fields {
+    let replication = &shared.replication;
+    let config = shared.config.read().unwrap();
+    let mut fields = Vec::new();
+    match &config.replicaof {
+        None => fields.push(field("role", "master")),
+        Some((host, port)) => {
+            let link = replication.link_state();
+            let up = if link == LinkState::Connected {
+                "up"
+            } else {
+                "down"
+            };
+            fields.extend([
+                field("role", "slave"),
+                field("master_host", host),
+                field("master_port", port),
+                field("master_link_status", up),
+                field("master_sync_in_progress", u8::from(link == LinkState::Sync)),
+                field("slave_repl_offset", replication.offset()),
+                field("slave_read_only", u8::from(config.replica_read_only)),
+            ]);
+        }
+    }
+
+    let replicas = replication.replicas();
+    fields.push(field("connected_slaves", replicas.len()));
+    for (i, replica) in replicas.iter().enumerate() {
+        let value = format!(
+            "ip={},port={},state={},offset={},lag={}",
+            replica.ip,
+            replica.port,
+            replica.state.name(),
+            replica.offset,
+            replica.lag
+        );
+        fields.push((format!("slave{i}"), value));
+    }
+
+    let (id2, second_offset) = replication.second_id();
+    let second_offset = second_offset.map_or(-1, |offset| offset as i64);
+    let (first_byte, length) = replication.backlog().unwrap_or((0, 0));
+    fields.extend([
+        field("master_replid", replication.id()),
+        field("master_replid2", id2),
+        field("master_repl_offset", replication.offset()),
+        field("second_repl_offset", second_offset),
+        field(
+            "repl_backlog_active",
+            u8::from(replication.backlog().is_some()),
+        ),
+        field("repl_backlog_size", config.repl_backlog_size),
+        field("repl_backlog_first_byte_offset", first_byte),
+        field("repl_backlog_histlen", length),
+    ]);
+    fields
+}
+
+fn cluster(shared: &Shared) -> Fields {
+    let enabled = shared.config.read().unwrap().cluster_enabled;
+    vec![field("cluster_enabled", u8::from(enabled))]
+}
+
+#[cfg(test)]
+mod info_tests {
+    use bytes::Bytes;
+
+    use super::Info;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler},
+        resp::RESPValues,
+        server::ClientAddr,
+    };
+
+    #[test]
+    fn info_clients_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        ctx.server
+            .clients
+            .connect(ClientAddr::Tcp("127.0.0.1:1234".parse().unwrap()));
+        let args = [Bytes::from_static(b"INFO"), Bytes::from_static(b"Clients")];
+        let result = Info.call(&args, &mut ctx);
+
+        assert!(result.is_ok_and(|r| r
+            == RESPValues::VerbatimString(
+                "txt".to_string(),
+                Bytes::from_static(b"# Clients\r\nconnected_clients:1\r\nmaxclients:10000\r\n")
+            )));
+    }
+
+    #[test]
+    fn info_every_section_correctly() {
+        let args = [Bytes::from_static(b"INFO")];
+        let result = Info.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(
+            result.is_ok_and(|r| matches!(r, RESPValues::VerbatimString(_, v)
+            if v.starts_with(b"# Server\r\nredis_version:")
+                && v.windows(11).any(|w| w == b"# Clients\r\n")))
+        );
+    }
+
+    #[test]
+    fn info_replication_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let sender = ctx.connection.messages.clone();
+        let address = ("127.0.0.1".to_string(), 6380);
+        ctx.server.replication.wait_for_dump(7, address, sender);
+        let args = [
+            Bytes::from_static(b"INFO"),
+            Bytes::from_static(b"replication"),
+        ];
+        let result = Info.call(&args, &mut ctx);
+
+        let id = ctx.server.replication.id();
+        let expected = format!(
+            "# Replication\r\nrole:master\r\nconnected_slaves:1\r\n\
+             slave0:ip=127.0.0.1,port=6380,state=wait_bgsave,offset=0,lag=0\r\n\
+             master_replid:{id}\r\nmaster_replid2:0000000000000000000000000000000000000000\r\n\
+             master_repl_offset:0\r\nsecond_repl_offset:-1\r\nrepl_backlog_active:1\r\n\
+             repl_backlog_size:1048576\r\nrepl_backlog_first_byte_offset:1\r\n\
+             repl_backlog_histlen:0\r\n"
+        );
+        assert!(result.is_ok_and(
+            |r| r == RESPValues::VerbatimString("txt".to_string(), Bytes::from(expected))
+        ));
+    }
+}
@@ -0,0 +1,55 @@
+use bytes::Bytes;
+
+use super::{
+    CommandContext, CommandDocs, CommandFlag, CommandHandler, CommandSpec, RedisCommandError,
+};
+use crate::resp::RESPValues;
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "lastsave",
+    arity: 1,
+    flags: &[CommandFlag::Loading, CommandFlag::Stale, CommandFlag::Fast],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Returns the Unix timestamp of the last successful save to disk.",
+        since: "1.0.0",
+        group: "server",
+        complexity: "O(1)",
+        arguments: &[],
+    },
+};
+
+pub struct Lastsave;
+
+impl CommandHandler for Lastsave {
+    fn call(
+        &self,
+        _args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        Ok(RESPValues::Integer(ctx.server.snapshots.last_save() as i64))
+    }
+}
+
+#[cfg(test)]
+mod lastsave_tests {
+    use bytes::Bytes;
+
+    use super::Lastsave;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler},
+        resp::RESPValues,
+    };
+
+    #[test]
+    fn lastsave_since_startup_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let result = Lastsave.call(&[Bytes::from_static(b"LASTSAVE")], &mut ctx);
+
+        let started = ctx.server.snapshots.last_save() as i64;
+        assert!(result.is_ok_and(|r| matches!(r, RESPValues::Integer(t) if t == started && t > 0)));
+    }
+}
@@ -0,0 +1,328 @@
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use bytes::{Bytes, BytesMut};
+
+use super::{
+    ArgumentType, CommandArgument, CommandContext, CommandDocs, CommandFlag, CommandHandler,
+    CommandSpec, RedisCommandError,
+};
+use crate::{
+    config::KeyspaceEvents,
+    rdb,
+    resp::{decode_frame, RESPDecodeError, RESPLimits, RESPValues},
+};
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "migrate",
+    arity: -6,
+    flags: &[CommandFlag::Write],
+    // the keys come last with KEYS, and MIGRATE only moves keys it has, so
+    // it names none for a cluster to route it by
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Atomically transfers a key from one Redis instance to another.",
+        since: "2.6.0",
+        group: "generic",
+        complexity: "This command actually executes a DUMP+DEL in the source instance, and a RESTORE in the target instance. See the pages of these commands for time complexity. Also an O(N) data transfer between the two instances is performed.",
+        arguments: &[
+            CommandArgument {
+                name: "host",
+                kind: ArgumentType::String,
+                optional: false,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "port",
+                kind: ArgumentType::Integer,
+                optional: false,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "key",
+                kind: ArgumentType::Key,
+                optional: false,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "destination-db",
+                kind: ArgumentType::Integer,
+                optional: false,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "timeout",
+                kind: ArgumentType::Integer,
+                optional: false,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "copy",
+                kind: ArgumentType::PureToken,
+                optional: true,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "replace",
+                kind: ArgumentType::PureToken,
+                optional: true,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "authentication",
+                kind: ArgumentType::String,
+                optional: true,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "keys",
+                kind: ArgumentType::Key,
+                optional: true,
+                multiple: true,
+            },
+        ],
+    },
+};
+
+pub struct Migrate;
+
+impl CommandHandler for Migrate {
+    // RESTOREs the DUMPs of the keys on the target and deletes the ones it
+    // took, unless COPY. The target is waited on right here, as Redis does
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        // only the keys deleted are propagated, and not MIGRATE itself
+        ctx.connection.propagate_as = Some(Vec::new());
+        let invalid = |message: &str| RedisCommandError::Invalid(message.to_string());
+        let integer = |arg: &Bytes| {
+            String::from_utf8_lossy(arg)
+                .parse::<i64>()
+                .map_err(|_| invalid("value is not an integer or out of range"))
+        };
+        let host = String::from_utf8_lossy(&args[1]).to_string();
+        let port = u16::try_from(integer(&args[2])?)
+            .map_err(|_| invalid("value is not an integer or out of range"))?;
+        if integer(&args[4])? != 0 {
+            return Err(invalid("DB index is out of range"));
+        }
+        let timeout = match integer(&args[5])? {
+            ..=0 => 1000,
+            timeout => timeout as u64,
+        };
+
+        let (mut copy, mut replace) = (false, false);
+        let mut auth = Vec::new();
+        let mut keys = vec![args[3].clone()];
+        let mut options = args[6..].iter();
+        while let Some(option) = options.next() {
+            match &option.to_ascii_uppercase()[..] {
+                b"COPY" => copy = true,
+                b"REPLACE" => replace = true,
+                b"AUTH" | b"AUTH2" => {
+                    let count = if option.eq_ignore_ascii_case(b"AUTH") {
+                        1
+                    } else {
+                        2
+                    };
+                    auth = vec![Bytes::from_static(b"AUTH")];
+                    auth.extend(options.by_ref().take(count).cloned());
+                    if auth.len() != count + 1 {
+                        return Err(invalid("syntax error"));
+                    }
+                }
+                b"KEYS" => {
+                    if !args[3].is_empty() {
+                        return Err(invalid("When using MIGRATE KEYS option, the key argument must be set to the empty string"));
+                    }
+                    keys = options.by_ref().cloned().collect();
+                }
+                _ => return Err(invalid("syntax error")),
+            }
+        }
+
+        let store = &ctx.server.store;
+        let entries: Vec<_> = keys
+            .into_iter()
+            .filter_map(|key| store.get(&key).map(|value| (key, value)))
+            .collect();
+        if entries.is_empty() {
+            return Ok(RESPValues::SimpleString("NOKEY".to_string()));
+        }
+        // in a cluster the target is importing the slot, which RESTORE-ASKING gets through
+        let restore: &'static [u8] = if ctx.server.config.read().unwrap().cluster_enabled {
+            b"RESTORE-ASKING"
+        } else {
+            b"RESTORE"
+        };
+        let options = ctx.server.rdb_options();
+        let mut requests: Vec<Vec<Bytes>> = Vec::new();
+        if !auth.is_empty() {
+            requests.push(auth.clone());
+        }
+        for (key, value) in &entries {
+            let payload = rdb::dump(value, options);
+            let mut request = vec![
+                Bytes::from_static(restore),
+                key.clone(),
+                Bytes::from_static(b"0"),
+                Bytes::from(payload),
+            ];
+            if replace {
+                request.push(Bytes::from_static(b"REPLACE"));
+            }
+            requests.push(request);
+        }
+
+        let mut replies = exchange(&host, port, Duration::from_millis(timeout), &requests)?;
+        let target_error = |error: &str| {
+            RedisCommandError::Invalid(format!("Target instance replied with error: {error}"))
+        };
+        if !auth.is_empty() {
+            if let RESPValues::SimpleError(error) = replies.remove(0) {
+                return Err(target_error(&error));
+            }
+        }
+        let mut error = None;
+        let mut deleted = vec![Bytes::from_static(b"DEL")];
+        for ((key, _), reply) in entries.iter().zip(replies) {
+            match reply {
+                RESPValues::SimpleError(e) => {
+                    error.get_or_insert(e);
+                }
+                _ if copy => {}
+                _ => {
+                    store.remove(key);
+                    ctx.server
+                        .notify_keyspace_event(KeyspaceEvents::GENERIC, "del", key);
+                    deleted.push(key.clone());
+                }
+            }
+        }
+        if deleted.len() > 1 {
+            ctx.connection.propagate_as = Some(deleted);
+        }
+        match error {
+            Some(error) => Err(target_error(&error)),
+            None => Ok(RESPValues::SimpleString("OK".to_string())),
+        }
+    }
+}
+
+// Sends all of `requests` to the target at once, then reads a reply to each
+fn exchange(
+    host: &str,
+    port: u16,
+    timeout: Duration,
+    requests: &[Vec<Bytes>],
+) -> Result<Vec<RESPValues>, RedisCommandError> {
+    let connecting =
+        |_: io::Error| RedisCommandError::IoError("error or timeout connecting to the client");
+    let address = (host, port).to_socket_addrs().map_err(connecting)?.next();
+    let address = address.ok_or_else(|| connecting(io::ErrorKind::NotFound.into()))?;
+    let mut stream = TcpStream::connect_timeout(&address, timeout).map_err(connecting)?;
+
+    let writing =
+        |_: io::Error| RedisCommandError::IoError("error or timeout writing to target instance");
+    let mut bytes = Vec::new();
+    for request in requests {
+        let args = request
+            .iter()
+            .cloned()
+            .map(RESPValues::BulkString)
+            .collect();
+        RESPValues::Array(args).encode(&mut bytes);
+    }
+    stream.set_write_timeout(Some(timeout)).map_err(writing)?;
+    stream.write_all(&bytes).map_err(writing)?;
+
+    let reading =
+        |_: io::Error| RedisCommandError::IoError("error or timeout reading to target instance");
+    stream.set_read_timeout(Some(timeout)).map_err(reading)?;
+    let mut buffer = BytesMut::new();
+    let mut chunk = [0; 4096];
+    let mut replies = Vec::new();
+    while replies.len() < requests.len() {
+        match decode_frame(&mut buffer, &RESPLimits::default()) {
+            Ok(reply) => replies.push(reply),
+            Err(RESPDecodeError::NeedMoreData) => match stream.read(&mut chunk) {
+                Ok(0) => return Err(reading(io::Error::from(io::ErrorKind::UnexpectedEof))),
+                Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+                Err(error) => return Err(reading(error)),
+            },
+            Err(RESPDecodeError::Invalid(_)) => {
+                return Err(reading(io::Error::from(io::ErrorKind::InvalidData)))
+            }
+        }
+    }
+    Ok(replies)
+}
+
+#[cfg(test)]
+mod migrate_tests {
+    use bytes::Bytes;
+
+    use super::Migrate;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler, RedisCommandError},
+        resp::RESPValues,
+        store::Value,
+    };
+
+    fn args(args: &[&str]) -> Vec<Bytes> {
+        args.iter().map(|a| Bytes::from(a.to_string())).collect()
+    }
+
+    #[test]
+    fn migrate_missing_keys_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let result = Migrate.call(
+            &args(&["MIGRATE", "127.0.0.1", "1", "", "0", "10", "KEYS", "a", "b"]),
+            &mut ctx,
+        );
+
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("NOKEY".to_string())));
+        assert_eq!(ctx.connection.propagate_as, Some(Vec::new()));
+    }
+
+    #[test]
+    fn migrate_to_an_unreachable_target_fails() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .unwrap()
+            .port();
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let value = Value::String(Bytes::from_static(b"v"));
+        ctx.server.store.set(Bytes::from_static(b"a"), value);
+        let result = Migrate.call(
+            &args(&["MIGRATE", "127.0.0.1", &port.to_string(), "a", "0", "100"]),
+            &mut ctx,
+        );
+
+        assert!(result.is_err_and(
+            |e| e == RedisCommandError::IoError("error or timeout connecting to the client")
+        ));
+        assert!(ctx.server.store.contains(b"a"));
+    }
+
+    #[test]
+    fn migrate_key_with_keys_fails() {
+        let result = Migrate.call(
+            &args(&["MIGRATE", "127.0.0.1", "1", "a", "0", "10", "KEYS", "b"]),
+            &mut test_context(&mut test_state()),
+        );
+
+        assert!(result.is_err_and(|e| e.to_string().starts_with(
+            "ERR When using MIGRATE KEYS option, the key argument must be set to the empty string"
+        )));
+    }
+}
@@ -0,0 +1,78 @@
+use bytes::Bytes;
+
+use super::{
+    CommandContext, CommandDocs, CommandFlag, CommandHandler, CommandSpec, RedisCommandError,
+    Transaction,
+};
+use crate::resp::RESPValues;
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "multi",
+    arity: 1,
+    flags: &[
+        CommandFlag::NoScript,
+        CommandFlag::Loading,
+        CommandFlag::Stale,
+        CommandFlag::Fast,
+    ],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Starts a transaction.",
+        since: "1.2.0",
+        group: "transactions",
+        complexity: "O(1)",
+        arguments: &[],
+    },
+};
+
+pub struct Multi;
+
+impl CommandHandler for Multi {
+    fn call(
+        &self,
+        _args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        if ctx.connection.transaction.is_some() {
+            return Err(RedisCommandError::Invalid(
+                "MULTI calls can not be nested".to_string(),
+            ));
+        }
+
+        ctx.connection.transaction = Some(Transaction::default());
+        Ok(RESPValues::SimpleString("OK".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod multi_tests {
+    use bytes::Bytes;
+
+    use super::Multi;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler, Transaction},
+        resp::RESPValues,
+    };
+
+    #[test]
+    fn multi_starts_a_transaction_correctly() {
+        let args = [Bytes::from_static(b"MULTI")];
+        let mut state = test_state();
+        let result = Multi.call(&args, &mut test_context(&mut state));
+
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("OK".to_string())));
+        assert_eq!(state.transaction, Some(Transaction::default()));
+    }
+
+    #[test]
+    fn multi_inside_a_transaction_fails() {
+        let args = [Bytes::from_static(b"MULTI")];
+        let mut state = test_state();
+        state.transaction = Some(Transaction::default());
+        let result = Multi.call(&args, &mut test_context(&mut state));
+
+        assert!(result.is_err_and(|e| e.to_string() == "ERR MULTI calls can not be nested"));
+    }
+}
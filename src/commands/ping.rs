@@ -0,0 +1,105 @@
+use bytes::Bytes;
+
+use super::{
+    ArgumentType, CommandArgument, CommandContext, CommandDocs, CommandFlag, CommandHandler,
+    CommandSpec, RedisCommandError,
+};
+use crate::resp::RESPValues;
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "ping",
+    arity: -1,
+    flags: &[CommandFlag::Fast, CommandFlag::Stale],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Returns the server's liveliness response.",
+        since: "1.0.0",
+        group: "connection",
+        complexity: "O(1)",
+        arguments: &[CommandArgument {
+            name: "message",
+            kind: ArgumentType::String,
+            optional: true,
+            multiple: false,
+        }],
+    },
+};
+
+pub struct Ping;
+
+impl CommandHandler for Ping {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        // a subscribed RESP2 client expects every reply shaped like a message
+        if ctx.connection.subscriber_mode() && args.len() <= 2 {
+            let message = args.get(1).cloned().unwrap_or_default();
+            return Ok(RESPValues::Array(vec![
+                RESPValues::BulkString(Bytes::from_static(b"pong")),
+                RESPValues::BulkString(message),
+            ]));
+        }
+        Ok(match args {
+            [_] => RESPValues::SimpleString("PONG".to_string()),
+            [_, message] => RESPValues::BulkString(message.clone()),
+            _ => return Err(RedisCommandError::WrongArity(SPEC.name)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod ping_tests {
+    use bytes::Bytes;
+
+    use super::Ping;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler, RedisCommandError},
+        resp::RESPValues,
+    };
+
+    #[test]
+    fn ping_with_no_string_correctly() {
+        let args = [Bytes::from_static(b"PING")];
+        let result = Ping.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("PONG".to_string())));
+    }
+
+    #[test]
+    fn ping_with_one_string_correctly() {
+        let args = [Bytes::from_static(b"PING"), Bytes::from_static(b"testing")];
+        let result = Ping.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_ok_and(|r| r == RESPValues::BulkString(Bytes::from_static(b"testing"))));
+    }
+
+    #[test]
+    fn ping_while_subscribed_correctly() {
+        let mut state = test_state();
+        state.channels.insert(Bytes::from_static(b"news"));
+        let args = [Bytes::from_static(b"PING")];
+        let result = Ping.call(&args, &mut test_context(&mut state));
+
+        assert!(result.is_ok_and(|r| r
+            == RESPValues::Array(vec![
+                RESPValues::BulkString(Bytes::from_static(b"pong")),
+                RESPValues::BulkString(Bytes::new()),
+            ])));
+    }
+
+    #[test]
+    fn ping_with_two_strings_fails() {
+        let args = [
+            Bytes::from_static(b"PING"),
+            Bytes::from_static(b"a"),
+            Bytes::from_static(b"b"),
+        ];
+        let result = Ping.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_err_and(|e| e == RedisCommandError::WrongArity("ping")));
+    }
+}
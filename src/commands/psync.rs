@@ -0,0 +1,224 @@
+use bytes::Bytes;
+
+use super::{
+    ArgumentType, CommandArgument, CommandContext, CommandDocs, CommandFlag, CommandHandler,
+    CommandSpec, RedisCommandError,
+};
+use crate::{
+    config::{ClientClass, LogLevel},
+    replication::LinkState,
+    resp::RESPValues,
+    server::ClientAddr,
+};
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "psync",
+    arity: -3,
+    flags: &[CommandFlag::Admin, CommandFlag::NoScript],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "An internal command used in replication.",
+        since: "2.8.0",
+        group: "server",
+        complexity: "",
+        arguments: &[
+            CommandArgument {
+                name: "replicationid",
+                kind: ArgumentType::String,
+                optional: false,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "offset",
+                kind: ArgumentType::Integer,
+                optional: false,
+                multiple: false,
+            },
+        ],
+    },
+};
+
+pub struct Psync;
+
+impl CommandHandler for Psync {
+    // +CONTINUE when the backlog still has the writes from the offset asked
+    // for on, a full resynchronization otherwise: +FULLRESYNC, then the dump
+    // and the writes after it as they come. These go out with the stream, a
+    // replica gets no reply of its own. It runs with every other command
+    // held off, so no write lands between the snapshot and the replica joining
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let server = ctx.server;
+        let (replicating, notice, diskless, delay) = {
+            let config = server.config.read().unwrap();
+            (
+                config.replicaof.is_some(),
+                config.loglevel <= LogLevel::Notice,
+                config.repl_diskless_sync && ctx.connection.replica_eof,
+                config.repl_diskless_sync_delay,
+            )
+        };
+        if replicating && server.replication.link_state() != LinkState::Connected {
+            return Err(RedisCommandError::NoMasterLink);
+        }
+
+        let ip = match server
+            .clients
+            .get(ctx.connection.id)
+            .map(|client| client.addr)
+        {
+            Some(ClientAddr::Tcp(addr)) => addr.ip().to_string(),
+            _ => String::new(),
+        };
+        let port = ctx.connection.replica_port.unwrap_or(0);
+        if notice {
+            eprintln!("Replica {ip}:{port} asks for synchronization");
+        }
+        ctx.connection.class = ClientClass::Replica;
+
+        // offset isn't a number for a replica with nothing to continue
+        let offset = std::str::from_utf8(&args[2])
+            .ok()
+            .and_then(|offset| offset.parse().ok());
+        let position = std::str::from_utf8(&args[1]).ok().zip(offset);
+        let sender = ctx.connection.messages.clone();
+        let address = (ip.clone(), port);
+        let continued = position.is_some_and(|position| {
+            let (id, sender) = (ctx.connection.id, sender.clone());
+            server
+                .replication
+                .partial_sync(id, address.clone(), sender, position)
+        });
+        if continued {
+            if notice {
+                eprintln!("Partial resynchronization request from {ip}:{port} accepted.");
+            }
+        } else if diskless {
+            server
+                .replication
+                .wait_for_dump(ctx.connection.id, address, sender);
+            // otherwise the cron starts it once more replicas had the time to join
+            if delay == 0 {
+                server
+                    .replication
+                    .start_diskless(server.store.snapshot(), server.rdb_options());
+            } else if notice {
+                eprintln!("Delay next BGSAVE for diskless SYNC");
+            }
+        } else {
+            server.replication.full_sync(
+                ctx.connection.id,
+                address,
+                sender,
+                server.store.snapshot(),
+                server.rdb_options(),
+            );
+        }
+        Ok(RESPValues::NullBulkString)
+    }
+}
+
+#[cfg(test)]
+mod psync_tests {
+    use bytes::Bytes;
+    use tokio::sync::mpsc;
+
+    use super::Psync;
+    use crate::{
+        commands::{test_context, CommandContext, CommandHandler, ConnectionState},
+        config::ClientClass,
+        resp::RESPValues,
+        store::Value,
+    };
+
+    #[test]
+    fn psync_full_resync_correctly() {
+        let (sender, mut messages) = mpsc::unbounded_channel();
+        let mut state = ConnectionState::new(1, sender);
+        let mut ctx = test_context(&mut state);
+        ctx.server.store.set(
+            Bytes::from_static(b"k"),
+            Value::String(Bytes::from_static(b"v")),
+        );
+        let args = [
+            Bytes::from_static(b"PSYNC"),
+            Bytes::from_static(b"?"),
+            Bytes::from_static(b"-1"),
+        ];
+        let result = Psync.call(&args, &mut ctx);
+
+        // the history starts over with the backlog
+        let id = ctx.server.replication.id();
+        assert!(result.is_ok());
+        let fullresync = RESPValues::SimpleString(format!("FULLRESYNC {id} 0"));
+        assert_eq!(messages.blocking_recv(), Some(fullresync));
+        assert_eq!(state.class, ClientClass::Replica);
+        let Some(RESPValues::BulkString(dump)) = messages.blocking_recv() else {
+            panic!("no dump sent");
+        };
+        let (entries, _) = crate::rdb::read(&dump, Default::default()).unwrap();
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[test]
+    fn psync_continue_correctly() {
+        let (sender, _messages) = mpsc::unbounded_channel();
+        let mut state = ConnectionState::new(1, sender);
+        let mut ctx = test_context(&mut state);
+        let args = [
+            Bytes::from_static(b"PSYNC"),
+            Bytes::from_static(b"?"),
+            Bytes::from_static(b"-1"),
+        ];
+        let _ = Psync.call(&args, &mut ctx);
+        ctx.server.replication.detach(1);
+        let id = ctx.server.replication.id();
+        ctx.server
+            .propagate(&[Bytes::from_static(b"DEL"), Bytes::from_static(b"k")]);
+
+        let args = [
+            Bytes::from_static(b"PSYNC"),
+            Bytes::copy_from_slice(id.as_bytes()),
+            Bytes::from_static(b"1"),
+        ];
+        let (sender, mut messages) = mpsc::unbounded_channel();
+        let mut state = ConnectionState::new(2, sender);
+        let mut ctx = CommandContext {
+            connection: &mut state,
+            server: ctx.server,
+        };
+        let result = Psync.call(&args, &mut ctx);
+
+        assert!(result.is_ok());
+        let reply = RESPValues::SimpleString(format!("CONTINUE {id}"));
+        assert_eq!(messages.blocking_recv(), Some(reply));
+        assert!(messages
+            .blocking_recv()
+            .is_some_and(|m| m.to_bytes().starts_with(b"*2\r\n$3\r\nDEL")));
+    }
+
+    #[test]
+    fn psync_waits_for_a_diskless_dump_correctly() {
+        let (sender, mut messages) = mpsc::unbounded_channel();
+        let mut state = ConnectionState::new(1, sender);
+        state.replica_eof = true;
+        let mut ctx = test_context(&mut state);
+        let args = [
+            Bytes::from_static(b"PSYNC"),
+            Bytes::from_static(b"?"),
+            Bytes::from_static(b"-1"),
+        ];
+        let result = Psync.call(&args, &mut ctx);
+
+        // for repl-diskless-sync-delay, for other replicas to join
+        assert!(result.is_ok());
+        assert!(messages.try_recv().is_err());
+        assert!(!ctx.server.replication.dump_due(5));
+        assert!(ctx.server.replication.dump_due(0));
+    }
+}
@@ -0,0 +1,112 @@
+use bytes::Bytes;
+
+use super::{
+    ArgumentType, CommandArgument, CommandContext, CommandDocs, CommandFlag, CommandHandler,
+    CommandSpec, RedisCommandError,
+};
+use crate::resp::RESPValues;
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "publish",
+    arity: 3,
+    flags: &[
+        CommandFlag::PubSub,
+        CommandFlag::Loading,
+        CommandFlag::Stale,
+        CommandFlag::Fast,
+    ],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Posts a message to a channel.",
+        since: "2.0.0",
+        group: "pubsub",
+        complexity: "O(N+M) where N is the number of clients subscribed to the receiving channel and M is the total number of subscribed patterns (by any client).",
+        arguments: &[
+            CommandArgument {
+                name: "channel",
+                kind: ArgumentType::String,
+                optional: false,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "message",
+                kind: ArgumentType::String,
+                optional: false,
+                multiple: false,
+            },
+        ],
+    },
+};
+
+pub const SHARD_SPEC: CommandSpec = CommandSpec {
+    name: "spublish",
+    first_key: 1,
+    last_key: 1,
+    step: 1,
+    docs: CommandDocs {
+        summary: "Posts a message to a shard channel.",
+        since: "7.0.0",
+        complexity:
+            "O(N) where N is the number of clients subscribed to the receiving shard channel.",
+        arguments: &[
+            CommandArgument {
+                name: "shardchannel",
+                kind: ArgumentType::String,
+                optional: false,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "message",
+                kind: ArgumentType::String,
+                optional: false,
+                multiple: false,
+            },
+        ],
+        ..SPEC.docs
+    },
+    ..SPEC
+};
+
+// PUBLISH, and SPUBLISH for shard channels
+pub struct Publish;
+
+impl CommandHandler for Publish {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let pubsub = &ctx.server.pubsub;
+        let receivers = if args[0].eq_ignore_ascii_case(SHARD_SPEC.name.as_bytes()) {
+            pubsub.publish_shard(&args[1], &args[2])
+        } else {
+            pubsub.publish(&args[1], &args[2])
+        };
+        Ok(RESPValues::Integer(receivers as i64))
+    }
+}
+
+#[cfg(test)]
+mod publish_tests {
+    use bytes::Bytes;
+
+    use super::Publish;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler},
+        resp::RESPValues,
+    };
+
+    #[test]
+    fn publish_without_subscribers_correctly() {
+        let args = [
+            Bytes::from_static(b"PUBLISH"),
+            Bytes::from_static(b"news"),
+            Bytes::from_static(b"hi"),
+        ];
+        let result = Publish.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_ok_and(|r| r == RESPValues::Integer(0)));
+    }
+}
@@ -0,0 +1,167 @@
+use bytes::Bytes;
+
+use super::{CommandContext, CommandDocs, CommandHandler, CommandSpec, RedisCommandError};
+use crate::{
+    pubsub::{SubscriptionKind, Subscriptions},
+    resp::RESPValues,
+};
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "pubsub",
+    arity: -2,
+    flags: &[],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "A container for Pub/Sub commands.",
+        since: "2.8.0",
+        group: "pubsub",
+        complexity: "Depends on subcommand.",
+        arguments: &[],
+    },
+};
+
+pub struct PubSub;
+
+impl CommandHandler for PubSub {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let pubsub = &ctx.server.pubsub;
+        match &args[1].to_ascii_uppercase()[..] {
+            b"CHANNELS" => channels(pubsub.subscriptions(SubscriptionKind::Channel), args)
+                .ok_or(RedisCommandError::WrongArity("pubsub|channels")),
+            b"SHARDCHANNELS" => {
+                channels(pubsub.subscriptions(SubscriptionKind::ShardChannel), args)
+                    .ok_or(RedisCommandError::WrongArity("pubsub|shardchannels"))
+            }
+            b"NUMSUB" => Ok(numsub(
+                pubsub.subscriptions(SubscriptionKind::Channel),
+                args,
+            )),
+            b"SHARDNUMSUB" => Ok(numsub(
+                pubsub.subscriptions(SubscriptionKind::ShardChannel),
+                args,
+            )),
+            b"NUMPAT" if args.len() == 2 => Ok(RESPValues::Integer(
+                pubsub.subscriptions(SubscriptionKind::Pattern).len() as i64,
+            )),
+            b"NUMPAT" => Err(RedisCommandError::WrongArity("pubsub|numpat")),
+            _ => Err(RedisCommandError::UnknownSubcommand(
+                SPEC.name,
+                String::from_utf8_lossy(&args[1]).to_string(),
+            )),
+        }
+    }
+}
+
+// Takes an optional pattern, anything more is a wrong arity
+fn channels(subscriptions: &Subscriptions, args: &[Bytes]) -> Option<RESPValues> {
+    let pattern = match &args[2..] {
+        [] => None,
+        [pattern] => Some(&pattern[..]),
+        _ => return None,
+    };
+    let names = subscriptions.names(pattern);
+    Some(RESPValues::Array(
+        names.into_iter().map(RESPValues::BulkString).collect(),
+    ))
+}
+
+fn numsub(subscriptions: &Subscriptions, args: &[Bytes]) -> RESPValues {
+    RESPValues::Map(
+        args[2..]
+            .iter()
+            .map(|channel| {
+                (
+                    RESPValues::BulkString(channel.clone()),
+                    RESPValues::Integer(subscriptions.count(channel) as i64),
+                )
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod pubsub_tests {
+    use bytes::Bytes;
+    use tokio::sync::mpsc;
+
+    use super::PubSub;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler, RedisCommandError},
+        resp::RESPValues,
+    };
+
+    #[test]
+    fn pubsub_channels_and_numsub_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let (subscriber, _messages) = mpsc::unbounded_channel();
+        let news = Bytes::from_static(b"news");
+        ctx.server
+            .pubsub
+            .channels
+            .subscribe(news.clone(), 1, subscriber);
+
+        let args = [
+            Bytes::from_static(b"PUBSUB"),
+            Bytes::from_static(b"channels"),
+            Bytes::from_static(b"n*"),
+        ];
+        let result = PubSub.call(&args, &mut ctx);
+        assert!(result
+            .is_ok_and(|r| r == RESPValues::Array(vec![RESPValues::BulkString(news.clone())])));
+
+        let args = [
+            Bytes::from_static(b"PUBSUB"),
+            Bytes::from_static(b"NUMSUB"),
+            news.clone(),
+            Bytes::from_static(b"sport"),
+        ];
+        let result = PubSub.call(&args, &mut ctx);
+        assert!(result.is_ok_and(|r| r
+            == RESPValues::Map(vec![
+                (RESPValues::BulkString(news), RESPValues::Integer(1)),
+                (
+                    RESPValues::BulkString(Bytes::from_static(b"sport")),
+                    RESPValues::Integer(0)
+                ),
+            ])));
+    }
+
+    #[test]
+    fn pubsub_numpat_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let (subscriber, _messages) = mpsc::unbounded_channel();
+        ctx.server
+            .pubsub
+            .patterns
+            .subscribe(Bytes::from_static(b"a*"), 1, subscriber.clone());
+        ctx.server
+            .pubsub
+            .patterns
+            .subscribe(Bytes::from_static(b"a*"), 2, subscriber);
+
+        let args = [Bytes::from_static(b"PUBSUB"), Bytes::from_static(b"NUMPAT")];
+        let result = PubSub.call(&args, &mut ctx);
+        assert!(result.is_ok_and(|r| r == RESPValues::Integer(1)));
+    }
+
+    #[test]
+    fn pubsub_shardchannels_with_extra_arguments_fails() {
+        let args = [
+            Bytes::from_static(b"PUBSUB"),
+            Bytes::from_static(b"SHARDCHANNELS"),
+            Bytes::from_static(b"a"),
+            Bytes::from_static(b"b"),
+        ];
+        let result = PubSub.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_err_and(|e| e == RedisCommandError::WrongArity("pubsub|shardchannels")));
+    }
+}
@@ -0,0 +1,64 @@
+use bytes::Bytes;
+
+use super::{
+    CommandContext, CommandDocs, CommandFlag, CommandHandler, CommandSpec, RedisCommandError,
+};
+use crate::resp::RESPValues;
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "quit",
+    arity: -1,
+    flags: &[
+        CommandFlag::NoScript,
+        CommandFlag::Loading,
+        CommandFlag::Stale,
+        CommandFlag::Fast,
+    ],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Closes the connection.",
+        since: "1.0.0",
+        group: "connection",
+        complexity: "O(1)",
+        arguments: &[],
+    },
+};
+
+pub struct Quit;
+
+impl CommandHandler for Quit {
+    fn call(
+        &self,
+        _args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        ctx.connection.quit = true;
+        Ok(RESPValues::SimpleString("OK".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod quit_tests {
+    use bytes::Bytes;
+
+    use super::Quit;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler},
+        resp::RESPValues,
+    };
+
+    #[test]
+    fn quit_correctly() {
+        let mut state = test_state();
+        let result = Quit.call(
+            &[Bytes::from_static(b"QUIT")],
+            &mut test_context(&mut state),
+        );
+
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("OK".to_string())));
+        assert!(state.quit);
+        assert!(!state.closing);
+    }
+}
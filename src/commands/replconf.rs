@@ -0,0 +1,151 @@
+use std::str::FromStr;
+
+use bytes::Bytes;
+
+use super::{
+    CommandContext, CommandDocs, CommandFlag, CommandHandler, CommandSpec, RedisCommandError,
+};
+use crate::{replication, resp::RESPValues};
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "replconf",
+    arity: -1,
+    flags: &[
+        CommandFlag::Admin,
+        CommandFlag::NoScript,
+        CommandFlag::Loading,
+        CommandFlag::Stale,
+    ],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "An internal command for configuring the replication stream.",
+        since: "3.0.0",
+        group: "server",
+        complexity: "O(1)",
+        arguments: &[],
+    },
+};
+
+pub struct Replconf;
+
+impl CommandHandler for Replconf {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        // options come in pairs of a name and its value
+        if args.len().is_multiple_of(2) {
+            return Err(RedisCommandError::Invalid("syntax error".to_string()));
+        }
+        for option in args[1..].chunks(2) {
+            match &option[0].to_ascii_lowercase()[..] {
+                b"listening-port" => ctx.connection.replica_port = Some(integer(&option[1])?),
+                // replicas get no reply to these, see server::execute_buffered
+                b"ack" => {
+                    let offset = integer(&option[1])?;
+                    ctx.server.replication.ack(ctx.connection.id, offset);
+                }
+                // from the master, answered on the link, see replication::sync
+                b"getack" => {
+                    if ctx.connection.master {
+                        let offset = ctx.server.replication.offset();
+                        let _ = ctx.connection.messages.send(replication::ack(offset));
+                    }
+                }
+                b"capa" => {
+                    if option[1].eq_ignore_ascii_case(b"eof") {
+                        ctx.connection.replica_eof = true;
+                    }
+                }
+                // everything else this server streams each replica understands
+                b"ip-address" => {}
+                _ => {
+                    return Err(RedisCommandError::Invalid(format!(
+                        "Unrecognized REPLCONF option: {}",
+                        String::from_utf8_lossy(&option[0])
+                    )))
+                }
+            }
+        }
+        Ok(RESPValues::SimpleString("OK".to_string()))
+    }
+}
+
+fn integer<T: FromStr>(value: &Bytes) -> Result<T, RedisCommandError> {
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| {
+            RedisCommandError::Invalid("value is not an integer or out of range".to_string())
+        })
+}
+
+#[cfg(test)]
+mod replconf_tests {
+    use bytes::Bytes;
+
+    use super::Replconf;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler, RedisCommandError},
+        resp::RESPValues,
+    };
+
+    #[test]
+    fn replconf_listening_port_correctly() {
+        let mut state = test_state();
+        let args = [
+            Bytes::from_static(b"REPLCONF"),
+            Bytes::from_static(b"listening-port"),
+            Bytes::from_static(b"6380"),
+            Bytes::from_static(b"capa"),
+            Bytes::from_static(b"psync2"),
+        ];
+        let result = Replconf.call(&args, &mut test_context(&mut state));
+
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("OK".to_string())));
+        assert_eq!(state.replica_port, Some(6380));
+    }
+
+    #[test]
+    fn replconf_ack_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let address = ("127.0.0.1".to_string(), 6380);
+        let snapshot = ctx.server.store.snapshot();
+        let sender = ctx.connection.messages.clone();
+        ctx.server.replication.full_sync(
+            ctx.connection.id,
+            address,
+            sender,
+            snapshot,
+            Default::default(),
+        );
+        let args = [
+            Bytes::from_static(b"REPLCONF"),
+            Bytes::from_static(b"ACK"),
+            Bytes::from_static(b"42"),
+        ];
+        let result = Replconf.call(&args, &mut ctx);
+
+        assert!(result.is_ok());
+        let replicas = ctx.server.replication.replicas();
+        assert_eq!((replicas[0].offset, replicas[0].lag), (42, 0));
+    }
+
+    #[test]
+    fn replconf_unknown_option_fails() {
+        let args = [
+            Bytes::from_static(b"REPLCONF"),
+            Bytes::from_static(b"color"),
+            Bytes::from_static(b"red"),
+        ];
+        let result = Replconf.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result
+            .is_err_and(|e| e
+                == RedisCommandError::Invalid("Unrecognized REPLCONF option: color".to_string())));
+    }
+}
@@ -0,0 +1,153 @@
+use bytes::Bytes;
+
+use super::{
+    ArgumentType, CommandArgument, CommandContext, CommandDocs, CommandFlag, CommandHandler,
+    CommandSpec, RedisCommandError,
+};
+use crate::{config::LogLevel, resp::RESPValues};
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "replicaof",
+    arity: 3,
+    flags: &[
+        CommandFlag::Admin,
+        CommandFlag::NoScript,
+        CommandFlag::Stale,
+    ],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Configures a server as replica of another, or promotes it to a master.",
+        since: "5.0.0",
+        group: "server",
+        complexity: "O(1)",
+        arguments: &[
+            CommandArgument {
+                name: "host",
+                kind: ArgumentType::String,
+                optional: false,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "port",
+                kind: ArgumentType::Integer,
+                optional: false,
+                multiple: false,
+            },
+        ],
+    },
+};
+
+pub const SLAVE_SPEC: CommandSpec = CommandSpec {
+    name: "slaveof",
+    docs: CommandDocs {
+        summary: "Sets a Redis server as a replica of another, or promotes it to being a master.",
+        since: "1.0.0",
+        ..SPEC.docs
+    },
+    ..SPEC
+};
+
+pub struct Replicaof;
+
+impl CommandHandler for Replicaof {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let master = if args[1].eq_ignore_ascii_case(b"no") && args[2].eq_ignore_ascii_case(b"one")
+        {
+            None
+        } else {
+            let port = std::str::from_utf8(&args[2])
+                .ok()
+                .and_then(|port| port.parse().ok())
+                .ok_or_else(|| RedisCommandError::Invalid("Invalid master port".to_string()))?;
+            Some((String::from_utf8_lossy(&args[1]).to_string(), port))
+        };
+
+        let mut config = ctx.server.config.write().unwrap();
+        if config.replicaof == master {
+            let reply = match master {
+                Some(_) => "OK Already connected to specified master",
+                None => "OK",
+            };
+            return Ok(RESPValues::SimpleString(reply.to_string()));
+        }
+        let notice = config.loglevel <= LogLevel::Notice;
+        config.replicaof = master.clone();
+        drop(config);
+
+        let id = ctx.connection.id;
+        match master {
+            Some((host, port)) if notice => {
+                eprintln!("REPLICAOF {host}:{port} enabled (user request from 'id={id}')")
+            }
+            Some(_) => {}
+            None => {
+                // whatever the replicas of this server go on from is its own history now
+                ctx.server.replication.shift_id();
+                if notice {
+                    eprintln!("MASTER MODE enabled (user request from 'id={id}')");
+                }
+            }
+        }
+        ctx.server.replication.master_changed();
+        Ok(RESPValues::SimpleString("OK".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod replicaof_tests {
+    use bytes::Bytes;
+
+    use super::Replicaof;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler, RedisCommandError},
+        resp::RESPValues,
+    };
+
+    #[test]
+    fn replicaof_and_back_to_master_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let id = ctx.server.replication.id();
+
+        let args = [
+            Bytes::from_static(b"REPLICAOF"),
+            Bytes::from_static(b"127.0.0.1"),
+            Bytes::from_static(b"6380"),
+        ];
+        let result = Replicaof.call(&args, &mut ctx);
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("OK".to_string())));
+        assert_eq!(
+            ctx.server.config.read().unwrap().replicaof,
+            Some(("127.0.0.1".to_string(), 6380))
+        );
+
+        let args = [
+            Bytes::from_static(b"REPLICAOF"),
+            Bytes::from_static(b"no"),
+            Bytes::from_static(b"ONE"),
+        ];
+        let result = Replicaof.call(&args, &mut ctx);
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("OK".to_string())));
+        assert_eq!(ctx.server.config.read().unwrap().replicaof, None);
+        assert_ne!(ctx.server.replication.id(), id);
+    }
+
+    #[test]
+    fn replicaof_invalid_port_fails() {
+        let args = [
+            Bytes::from_static(b"REPLICAOF"),
+            Bytes::from_static(b"127.0.0.1"),
+            Bytes::from_static(b"70000"),
+        ];
+        let result = Replicaof.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result
+            .is_err_and(|e| e == RedisCommandError::Invalid("Invalid master port".to_string())));
+    }
+}
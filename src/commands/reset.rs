@@ -0,0 +1,87 @@
+use bytes::Bytes;
+
+use super::{
+    CommandContext, CommandDocs, CommandFlag, CommandHandler, CommandSpec, RedisCommandError,
+};
+use crate::resp::{RESPValues, RESPVersion};
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "reset",
+    arity: 1,
+    flags: &[
+        CommandFlag::NoScript,
+        CommandFlag::Loading,
+        CommandFlag::Stale,
+        CommandFlag::Fast,
+    ],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Resets the connection.",
+        since: "6.2.0",
+        group: "connection",
+        complexity: "O(1)",
+        arguments: &[],
+    },
+};
+
+pub struct Reset;
+
+impl CommandHandler for Reset {
+    fn call(
+        &self,
+        _args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let state = &mut *ctx.connection;
+        ctx.server.pubsub.disconnect(state);
+        state.channels.clear();
+        state.patterns.clear();
+        state.shard_channels.clear();
+        state.transaction = None;
+        state.watched.clear();
+        state.protocol = RESPVersion::RESP2;
+        state.name = None;
+        state.asking = false;
+        Ok(RESPValues::SimpleString("RESET".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod reset_tests {
+    use bytes::Bytes;
+
+    use super::Reset;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler, Transaction},
+        pubsub::SubscriptionKind,
+        resp::{RESPValues, RESPVersion},
+    };
+
+    #[test]
+    fn reset_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let channel = Bytes::from_static(b"news");
+        ctx.server
+            .pubsub
+            .subscriptions(SubscriptionKind::Channel)
+            .subscribe(
+                channel.clone(),
+                ctx.connection.id,
+                ctx.connection.messages.clone(),
+            );
+        ctx.connection.channels.insert(channel.clone());
+        ctx.connection.transaction = Some(Transaction::default());
+        ctx.connection.protocol = RESPVersion::RESP3;
+        let result = Reset.call(&[Bytes::from_static(b"RESET")], &mut ctx);
+
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("RESET".to_string())));
+        assert_eq!(ctx.connection.subscriptions(), 0);
+        let subscriptions = ctx.server.pubsub.subscriptions(SubscriptionKind::Channel);
+        assert_eq!(subscriptions.count(&channel), 0);
+        assert!(ctx.connection.transaction.is_none());
+        assert_eq!(ctx.connection.protocol, RESPVersion::RESP2);
+    }
+}
@@ -0,0 +1,191 @@
+use bytes::Bytes;
+
+use super::{
+    ArgumentType, CommandArgument, CommandContext, CommandDocs, CommandFlag, CommandHandler,
+    CommandSpec, RedisCommandError,
+};
+use crate::{config::KeyspaceEvents, rdb, resp::RESPValues};
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "restore",
+    arity: -4,
+    flags: &[CommandFlag::Write, CommandFlag::DenyOom],
+    first_key: 1,
+    last_key: 1,
+    step: 1,
+    docs: CommandDocs {
+        summary: "Creates a key from the serialized representation of a value.",
+        since: "2.6.0",
+        group: "generic",
+        complexity: "O(1) to create the new key and additional O(N*M) to reconstruct the serialized value, where N is the number of Redis objects composing the value and M their average size. For small string values the time complexity is thus O(1)+O(1*M) where M is small, so simply O(1). However for sorted set values the complexity is O(N*M*log(N)) because inserting values into sorted sets is O(log(N)).",
+        arguments: &[
+            CommandArgument {
+                name: "key",
+                kind: ArgumentType::Key,
+                optional: false,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "ttl",
+                kind: ArgumentType::Integer,
+                optional: false,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "serialized-value",
+                kind: ArgumentType::String,
+                optional: false,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "replace",
+                kind: ArgumentType::PureToken,
+                optional: true,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "absttl",
+                kind: ArgumentType::PureToken,
+                optional: true,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "seconds",
+                kind: ArgumentType::Integer,
+                optional: true,
+                multiple: false,
+            },
+            CommandArgument {
+                name: "frequency",
+                kind: ArgumentType::Integer,
+                optional: true,
+                multiple: false,
+            },
+        ],
+    },
+};
+
+// what MIGRATE sends, served in a slot being imported without ASKING
+pub const ASKING_SPEC: CommandSpec = CommandSpec {
+    name: "restore-asking",
+    docs: CommandDocs {
+        summary: "An internal command for migrating keys in a cluster.",
+        since: "3.0.0",
+        ..SPEC.docs
+    },
+    ..SPEC
+};
+
+// RESTORE, and RESTORE-ASKING
+pub struct Restore;
+
+impl CommandHandler for Restore {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let invalid = |message: &str| RedisCommandError::Invalid(message.to_string());
+        let mut replace = false;
+        let mut options = args[4..].iter();
+        while let Some(option) = options.next() {
+            let mut value = |message: &str| {
+                let value = options.next().ok_or_else(|| invalid("syntax error"))?;
+                String::from_utf8_lossy(value)
+                    .parse::<i64>()
+                    .map_err(|_| invalid(message))
+            };
+            // keys don't age or count accesses, so these are checked but unused
+            match &option.to_ascii_uppercase()[..] {
+                b"REPLACE" => replace = true,
+                b"ABSTTL" => {}
+                b"IDLETIME" => {
+                    let message = "Invalid IDLETIME value, must be >= 0";
+                    if value(message)? < 0 {
+                        return Err(invalid(message));
+                    }
+                }
+                b"FREQ" => {
+                    let message = "Invalid FREQ value, must be >= 0 and <= 255";
+                    let frequency = value(message)?;
+                    if !(0..=255).contains(&frequency) {
+                        return Err(invalid(message));
+                    }
+                }
+                _ => return Err(invalid("syntax error")),
+            }
+        }
+
+        let ttl = String::from_utf8_lossy(&args[2]).parse::<i64>();
+        match ttl {
+            Ok(0) => {}
+            Ok(1..) => {
+                return Err(invalid(
+                    "RESTORE with a TTL isn't supported, keys can't expire",
+                ))
+            }
+            _ => return Err(invalid("Invalid TTL value, must be >= 0")),
+        }
+        let key = &args[1];
+        if !replace && ctx.server.store.contains(key) {
+            return Err(RedisCommandError::BusyKey);
+        }
+        let value = rdb::undump(&args[3]).map_err(|e| invalid(&e.to_string()))?;
+        ctx.server.store.set(key.clone(), value);
+        ctx.server
+            .notify_keyspace_event(KeyspaceEvents::GENERIC, "restore", key);
+        Ok(RESPValues::SimpleString("OK".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod restore_tests {
+    use bytes::Bytes;
+
+    use super::Restore;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler, RedisCommandError},
+        rdb,
+        resp::RESPValues,
+        store::Value,
+    };
+
+    fn args(payload: &[u8], options: &[&'static str]) -> Vec<Bytes> {
+        let mut args = ["RESTORE", "foo", "0"].map(Bytes::from).to_vec();
+        args.push(Bytes::copy_from_slice(payload));
+        args.extend(options.iter().map(|option| Bytes::from(*option)));
+        args
+    }
+
+    #[test]
+    fn restore_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let value = Value::String(Bytes::from_static(b"bar"));
+        let payload = rdb::dump(&value, rdb::Options::default());
+
+        let result = Restore.call(&args(&payload, &[]), &mut ctx);
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("OK".to_string())));
+        assert_eq!(ctx.server.store.get(b"foo"), Some(value));
+
+        let result = Restore.call(&args(&payload, &["REPLACE", "IDLETIME", "10"]), &mut ctx);
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("OK".to_string())));
+    }
+
+    #[test]
+    fn restore_busy_key_fails() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let value = Value::String(Bytes::from_static(b"bar"));
+        ctx.server
+            .store
+            .set(Bytes::from_static(b"foo"), value.clone());
+        let payload = rdb::dump(&value, rdb::Options::default());
+
+        let result = Restore.call(&args(&payload, &[]), &mut ctx);
+        assert!(result.is_err_and(|e| e == RedisCommandError::BusyKey));
+        let result = Restore.call(&args(b"garbage", &["REPLACE"]), &mut ctx);
+        assert!(result
+            .is_err_and(|e| e.to_string() == "ERR DUMP payload version or checksum are wrong"));
+    }
+}
@@ -0,0 +1,124 @@
+use bytes::Bytes;
+
+use super::{
+    CommandContext, CommandDocs, CommandFlag, CommandHandler, CommandSpec, RedisCommandError,
+};
+use crate::resp::RESPValues;
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "role",
+    arity: 1,
+    flags: &[
+        CommandFlag::NoScript,
+        CommandFlag::Loading,
+        CommandFlag::Stale,
+        CommandFlag::Fast,
+    ],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Returns the replication role.",
+        since: "2.8.12",
+        group: "server",
+        complexity: "O(1)",
+        arguments: &[],
+    },
+};
+
+pub struct Role;
+
+impl CommandHandler for Role {
+    // A master with its offset and each replica as ip, port and the offset it
+    // acknowledged, or a replica with its master, link state and offset
+    fn call(
+        &self,
+        _args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let replication = &ctx.server.replication;
+        let offset = RESPValues::Integer(replication.offset() as i64);
+        let bulk = |s: String| RESPValues::BulkString(Bytes::from(s));
+        let role = match ctx.server.config.read().unwrap().replicaof.clone() {
+            None => {
+                let replicas = replication
+                    .replicas()
+                    .into_iter()
+                    .map(|replica| {
+                        RESPValues::Array(vec![
+                            bulk(replica.ip),
+                            bulk(replica.port.to_string()),
+                            bulk(replica.offset.to_string()),
+                        ])
+                    })
+                    .collect();
+                vec![
+                    bulk("master".to_string()),
+                    offset,
+                    RESPValues::Array(replicas),
+                ]
+            }
+            Some((host, port)) => vec![
+                bulk("slave".to_string()),
+                bulk(host),
+                RESPValues::Integer(port.into()),
+                bulk(replication.link_state().name().to_string()),
+                offset,
+            ],
+        };
+        Ok(RESPValues::Array(role))
+    }
+}
+
+#[cfg(test)]
+mod role_tests {
+    use bytes::Bytes;
+
+    use super::Role;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler},
+        resp::RESPValues,
+    };
+
+    fn bulk(s: &'static str) -> RESPValues {
+        RESPValues::BulkString(Bytes::from_static(s.as_bytes()))
+    }
+
+    #[test]
+    fn role_of_a_master_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let sender = ctx.connection.messages.clone();
+        let address = ("127.0.0.1".to_string(), 6380);
+        ctx.server.replication.wait_for_dump(7, address, sender);
+        let result = Role.call(&[Bytes::from_static(b"ROLE")], &mut ctx);
+
+        assert!(result.is_ok_and(|r| r
+            == RESPValues::Array(vec![
+                bulk("master"),
+                RESPValues::Integer(0),
+                RESPValues::Array(vec![RESPValues::Array(vec![
+                    bulk("127.0.0.1"),
+                    bulk("6380"),
+                    bulk("0"),
+                ])]),
+            ])));
+    }
+
+    #[test]
+    fn role_of_a_replica_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        ctx.server.config.write().unwrap().replicaof = Some(("10.0.0.1".to_string(), 6379));
+        let result = Role.call(&[Bytes::from_static(b"ROLE")], &mut ctx);
+
+        assert!(result.is_ok_and(|r| r
+            == RESPValues::Array(vec![
+                bulk("slave"),
+                bulk("10.0.0.1"),
+                RESPValues::Integer(6379),
+                bulk("connect"),
+                RESPValues::Integer(0),
+            ])));
+    }
+}
@@ -0,0 +1,67 @@
+use bytes::Bytes;
+
+use super::{
+    CommandContext, CommandDocs, CommandFlag, CommandHandler, CommandSpec, RedisCommandError,
+};
+use crate::resp::RESPValues;
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "save",
+    arity: 1,
+    flags: &[CommandFlag::Admin, CommandFlag::NoScript],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Synchronously saves the database(s) to disk.",
+        since: "1.0.0",
+        group: "server",
+        complexity: "O(N) where N is the total number of keys in all databases",
+        arguments: &[],
+    },
+};
+
+pub struct Save;
+
+impl CommandHandler for Save {
+    fn call(
+        &self,
+        _args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        // fails with a background save in progress
+        ctx.server
+            .save()
+            .map_err(|error| RedisCommandError::Invalid(error.to_string()))?;
+        Ok(RESPValues::SimpleString("OK".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod save_tests {
+    use bytes::Bytes;
+
+    use super::Save;
+    use crate::{
+        commands::{test_context, test_dir, test_state, CommandHandler},
+        resp::RESPValues,
+        store::Value,
+    };
+
+    #[test]
+    fn save_to_dbfilename_correctly() {
+        let dir = test_dir("save");
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        ctx.server.config.write().unwrap().dir = dir.path().to_path_buf();
+        ctx.server.store.set(
+            Bytes::from_static(b"k"),
+            Value::String(Bytes::from_static(b"v")),
+        );
+        let result = Save.call(&[Bytes::from_static(b"SAVE")], &mut ctx);
+
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("OK".to_string())));
+        let dump = std::fs::read(dir.join("dump.rdb")).unwrap();
+        assert!(dump.starts_with(b"REDIS0011"));
+    }
+}
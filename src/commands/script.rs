@@ -0,0 +1,142 @@
+use bytes::Bytes;
+
+use super::{
+    CommandContext, CommandDocs, CommandFlag, CommandHandler, CommandSpec, RedisCommandError,
+};
+use crate::resp::RESPValues;
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "script",
+    arity: -2,
+    flags: &[CommandFlag::NoScript],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "A container for Lua scripts management commands.",
+        since: "2.6.0",
+        group: "scripting",
+        complexity: "Depends on subcommand.",
+        arguments: &[],
+    },
+};
+
+pub struct Script;
+
+impl CommandHandler for Script {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let scripts = &ctx.server.scripts;
+        match &args[1].to_ascii_uppercase()[..] {
+            b"LOAD" => match args {
+                [_, _, body] => Ok(RESPValues::BulkString(scripts.load(body.clone()).into())),
+                _ => Err(RedisCommandError::WrongArity("script|load")),
+            },
+            b"EXISTS" if args.len() > 2 => Ok(RESPValues::Array(
+                args[2..]
+                    .iter()
+                    .map(|sha| {
+                        let sha = String::from_utf8_lossy(sha);
+                        RESPValues::Integer(scripts.contains(&sha).into())
+                    })
+                    .collect(),
+            )),
+            b"EXISTS" => Err(RedisCommandError::WrongArity("script|exists")),
+            b"FLUSH" => {
+                let mode = args.get(2).map(|mode| mode.to_ascii_uppercase());
+                if args.len() > 3 || !matches!(mode.as_deref(), None | Some(b"ASYNC" | b"SYNC")) {
+                    return Err(RedisCommandError::Invalid(
+                        "SCRIPT FLUSH only support SYNC|ASYNC option".to_string(),
+                    ));
+                }
+                scripts.flush();
+                Ok(RESPValues::SimpleString("OK".to_string()))
+            }
+            // scripts are only cached, what runs is a function, for FUNCTION KILL
+            b"KILL" if args.len() == 2 && ctx.server.functions.is_running() => {
+                Err(RedisCommandError::Busy)
+            }
+            b"KILL" if args.len() == 2 => Err(RedisCommandError::NotBusy),
+            b"KILL" => Err(RedisCommandError::WrongArity("script|kill")),
+            _ => Err(RedisCommandError::UnknownSubcommand(
+                SPEC.name,
+                String::from_utf8_lossy(&args[1]).to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod script_tests {
+    use bytes::Bytes;
+
+    use super::Script;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler, RedisCommandError},
+        resp::RESPValues,
+    };
+
+    #[test]
+    fn script_load_and_exists_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let load = [
+            Bytes::from_static(b"SCRIPT"),
+            Bytes::from_static(b"LOAD"),
+            Bytes::from_static(b"return 1"),
+        ];
+        let sha = Bytes::from_static(b"e0e1f9fabfc9d4800c877a703b823ac0578ff8db");
+
+        let result = Script.call(&load, &mut ctx);
+        assert!(result.is_ok_and(|r| r == RESPValues::BulkString(sha.clone())));
+
+        let exists = [
+            Bytes::from_static(b"SCRIPT"),
+            Bytes::from_static(b"exists"),
+            sha,
+            Bytes::from_static(b"nope"),
+        ];
+        let result = Script.call(&exists, &mut ctx);
+        assert!(result.is_ok_and(
+            |r| r == RESPValues::Array(vec![RESPValues::Integer(1), RESPValues::Integer(0)])
+        ));
+    }
+
+    #[test]
+    fn script_flush_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        ctx.server.scripts.load(Bytes::from_static(b"return 1"));
+        let args = [
+            Bytes::from_static(b"SCRIPT"),
+            Bytes::from_static(b"FLUSH"),
+            Bytes::from_static(b"async"),
+        ];
+        let result = Script.call(&args, &mut ctx);
+
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("OK".to_string())));
+        assert!(ctx.server.scripts.is_empty());
+    }
+
+    #[test]
+    fn script_kill_without_a_running_script_fails() {
+        let args = [Bytes::from_static(b"SCRIPT"), Bytes::from_static(b"KILL")];
+        let result = Script.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_err_and(|e| e == RedisCommandError::NotBusy));
+    }
+
+    #[test]
+    fn script_kill_a_running_function_fails() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        ctx.server.functions.start(2);
+        let args = [Bytes::from_static(b"SCRIPT"), Bytes::from_static(b"KILL")];
+        let result = Script.call(&args, &mut ctx);
+
+        assert!(result.is_err_and(|e| e == RedisCommandError::Busy));
+    }
+}
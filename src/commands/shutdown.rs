@@ -0,0 +1,160 @@
+use bytes::Bytes;
+
+use super::{
+    CommandContext, CommandDocs, CommandFlag, CommandHandler, CommandSpec, RedisCommandError,
+};
+use crate::{config::LogLevel, resp::RESPValues};
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "shutdown",
+    arity: -1,
+    flags: &[
+        CommandFlag::Admin,
+        CommandFlag::NoScript,
+        CommandFlag::Loading,
+        CommandFlag::Stale,
+    ],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Synchronously saves the database(s) to disk and shuts down the Redis server.",
+        since: "1.0.0",
+        group: "server",
+        complexity: "O(N) when saving, where N is the total number of keys in all databases when saving data, otherwise O(1)",
+        arguments: &[],
+    },
+};
+
+pub struct Shutdown;
+
+impl CommandHandler for Shutdown {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let syntax_error = || RedisCommandError::Invalid("syntax error".to_string());
+        let mut save = None;
+        let mut force = false;
+        let mut abort = false;
+
+        for arg in &args[1..] {
+            match &arg.to_ascii_uppercase()[..] {
+                b"NOSAVE" if save.is_none() => save = Some(false),
+                b"SAVE" if save.is_none() => save = Some(true),
+                // NOW skips waiting for lagging replicas, which this shutdown never
+                // does, it only closes the connections once their replies are written
+                b"NOW" => {}
+                b"FORCE" => force = true,
+                b"ABORT" => abort = true,
+                _ => return Err(syntax_error()),
+            }
+        }
+
+        if abort {
+            if args.len() > 2 {
+                return Err(syntax_error());
+            }
+            return Err(RedisCommandError::Invalid(
+                "No shutdown in progress.".to_string(),
+            ));
+        }
+
+        // without SAVE or NOSAVE it saves if there are save points, as in Redis
+        let save = save.unwrap_or_else(|| !ctx.server.config.read().unwrap().save.is_empty());
+        // FORCE goes on with the shutdown regardless
+        if let Err(error) = ctx.server.prepare_shutdown(save) {
+            let warn = ctx.server.config.read().unwrap().loglevel <= LogLevel::Warning;
+            if force {
+                if warn {
+                    eprintln!("Error trying to save the DB, exiting anyway: {error}");
+                }
+            } else {
+                if warn {
+                    eprintln!("Error trying to save the DB, can't exit: {error}");
+                }
+                return Err(RedisCommandError::Invalid(
+                    "Errors trying to SHUTDOWN. Check logs.".to_string(),
+                ));
+            }
+        }
+        ctx.server.shutdown.finish();
+        ctx.connection.closing = true;
+        Ok(RESPValues::SimpleString("OK".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod shutdown_tests {
+    use bytes::Bytes;
+
+    use super::Shutdown;
+    use crate::commands::{test_context, test_state, CommandHandler, RedisCommandError};
+
+    #[test]
+    fn shutdown_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let args = [
+            Bytes::from_static(b"SHUTDOWN"),
+            Bytes::from_static(b"nosave"),
+        ];
+        let result = Shutdown.call(&args, &mut ctx);
+
+        assert!(result.is_ok());
+        assert!(ctx.connection.closing);
+        assert!(ctx.server.shutdown.is_shutdown());
+        assert!(ctx.server.shutdown.is_finished());
+    }
+
+    #[test]
+    fn shutdown_with_save_and_nosave_fails() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let args = [
+            Bytes::from_static(b"SHUTDOWN"),
+            Bytes::from_static(b"SAVE"),
+            Bytes::from_static(b"NOSAVE"),
+        ];
+        let result = Shutdown.call(&args, &mut ctx);
+
+        assert!(result.is_err_and(|e| e == RedisCommandError::Invalid("syntax error".to_string())));
+        assert!(!ctx.server.shutdown.is_shutdown());
+    }
+
+    #[test]
+    fn shutdown_abort_without_shutdown_fails() {
+        let args = [
+            Bytes::from_static(b"SHUTDOWN"),
+            Bytes::from_static(b"ABORT"),
+        ];
+        let result = Shutdown.call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_err_and(|e| e.to_string() == "ERR No shutdown in progress."));
+    }
+
+    #[test]
+    fn shutdown_force_with_a_failing_save_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        ctx.server.config.write().unwrap().dir = "/nonexistent/redis-clone".into();
+        let save = [Bytes::from_static(b"SHUTDOWN"), Bytes::from_static(b"SAVE")];
+        let result = Shutdown.call(&save, &mut ctx);
+
+        assert!(
+            result.is_err_and(|e| e.to_string() == "ERR Errors trying to SHUTDOWN. Check logs.")
+        );
+        assert!(!ctx.server.shutdown.is_shutdown());
+
+        let force = [
+            Bytes::from_static(b"SHUTDOWN"),
+            Bytes::from_static(b"SAVE"),
+            Bytes::from_static(b"FORCE"),
+        ];
+        let result = Shutdown.call(&force, &mut ctx);
+
+        assert!(result.is_ok());
+        assert!(ctx.server.shutdown.is_shutdown());
+    }
+}
@@ -0,0 +1,202 @@
+use bytes::Bytes;
+
+use super::{
+    ArgumentType, CommandArgument, CommandContext, CommandDocs, CommandFlag, CommandHandler,
+    CommandSpec, RedisCommandError,
+};
+use crate::{
+    pubsub::{push, SubscriptionKind},
+    resp::RESPValues,
+};
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "subscribe",
+    arity: -2,
+    flags: &[
+        CommandFlag::PubSub,
+        CommandFlag::NoScript,
+        CommandFlag::Loading,
+        CommandFlag::Stale,
+    ],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Listens for messages published to channels.",
+        since: "2.0.0",
+        group: "pubsub",
+        complexity: "O(N) where N is the number of channels to subscribe to.",
+        arguments: &[CommandArgument {
+            name: "channel",
+            kind: ArgumentType::String,
+            optional: false,
+            multiple: true,
+        }],
+    },
+};
+
+pub const PATTERN_SPEC: CommandSpec = CommandSpec {
+    name: "psubscribe",
+    docs: CommandDocs {
+        summary: "Listens for messages published to channels that match one or more patterns.",
+        complexity: "O(N) where N is the number of patterns to subscribe to.",
+        arguments: &[CommandArgument {
+            name: "pattern",
+            kind: ArgumentType::Pattern,
+            optional: false,
+            multiple: true,
+        }],
+        ..SPEC.docs
+    },
+    ..SPEC
+};
+
+pub const SHARD_SPEC: CommandSpec = CommandSpec {
+    name: "ssubscribe",
+    // shard channels are routed like keys in a cluster
+    first_key: 1,
+    last_key: -1,
+    step: 1,
+    docs: CommandDocs {
+        summary: "Listens for messages published to shard channels.",
+        since: "7.0.0",
+        complexity: "O(N) where N is the number of shard channels to subscribe to.",
+        arguments: &[CommandArgument {
+            name: "shardchannel",
+            kind: ArgumentType::String,
+            optional: false,
+            multiple: true,
+        }],
+        ..SPEC.docs
+    },
+    ..SPEC
+};
+
+// SUBSCRIBE for channels, PSUBSCRIBE for patterns and SSUBSCRIBE for shard channels
+pub struct Subscribe(pub SubscriptionKind);
+
+impl CommandHandler for Subscribe {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let connection = &mut *ctx.connection;
+        let subscriptions = ctx.server.pubsub.subscriptions(self.0);
+        let (confirmation, _) = self.0.confirmations();
+
+        let mut replies = Vec::new();
+        for channel in &args[1..] {
+            if connection.subscribed_mut(self.0).insert(channel.clone()) {
+                let subscriber = connection.messages.clone();
+                subscriptions.subscribe(channel.clone(), connection.id, subscriber);
+            }
+            replies.push(push(
+                confirmation,
+                vec![
+                    RESPValues::BulkString(channel.clone()),
+                    RESPValues::Integer(connection.subscription_count(self.0) as i64),
+                ],
+            ));
+        }
+
+        connection.update_class();
+        Ok(connection.replies(replies))
+    }
+}
+
+#[cfg(test)]
+mod subscribe_tests {
+    use bytes::Bytes;
+
+    use super::Subscribe;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler},
+        config::ClientClass,
+        pubsub::{push, SubscriptionKind},
+        resp::RESPValues,
+    };
+
+    #[test]
+    fn subscribe_to_channels_correctly() {
+        let args = [
+            Bytes::from_static(b"SUBSCRIBE"),
+            Bytes::from_static(b"a"),
+            Bytes::from_static(b"b"),
+            Bytes::from_static(b"a"),
+        ];
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        let result = Subscribe(SubscriptionKind::Channel).call(&args, &mut ctx);
+
+        let confirm = |channel: &'static [u8], count| {
+            push(
+                "subscribe",
+                vec![
+                    RESPValues::BulkString(Bytes::from_static(channel)),
+                    RESPValues::Integer(count),
+                ],
+            )
+        };
+        assert!(result.is_ok_and(|r| r == confirm(b"a", 1)));
+        assert_eq!(
+            ctx.connection.extra_replies,
+            [confirm(b"b", 2), confirm(b"a", 2)]
+        );
+        assert_eq!(ctx.connection.class, ClientClass::PubSub);
+        assert_eq!(
+            ctx.server
+                .pubsub
+                .publish(&Bytes::from_static(b"a"), &Bytes::new()),
+            1
+        );
+    }
+
+    #[test]
+    fn psubscribe_counts_every_subscription_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        ctx.connection.channels.insert(Bytes::from_static(b"a"));
+        let args = [Bytes::from_static(b"PSUBSCRIBE"), Bytes::from_static(b"a*")];
+        let result = Subscribe(SubscriptionKind::Pattern).call(&args, &mut ctx);
+
+        let expected = push(
+            "psubscribe",
+            vec![
+                RESPValues::BulkString(Bytes::from_static(b"a*")),
+                RESPValues::Integer(2),
+            ],
+        );
+        assert!(result.is_ok_and(|r| r == expected));
+        assert_eq!(
+            ctx.server
+                .pubsub
+                .publish(&Bytes::from_static(b"ab"), &Bytes::new()),
+            1
+        );
+    }
+
+    #[test]
+    fn ssubscribe_counts_shard_channels_apart_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        ctx.connection.channels.insert(Bytes::from_static(b"a"));
+        let args = [Bytes::from_static(b"SSUBSCRIBE"), Bytes::from_static(b"a")];
+        let result = Subscribe(SubscriptionKind::ShardChannel).call(&args, &mut ctx);
+
+        let expected = push(
+            "ssubscribe",
+            vec![
+                RESPValues::BulkString(Bytes::from_static(b"a")),
+                RESPValues::Integer(1),
+            ],
+        );
+        assert!(result.is_ok_and(|r| r == expected));
+        assert_eq!(
+            ctx.server
+                .pubsub
+                .publish_shard(&Bytes::from_static(b"a"), &Bytes::new()),
+            1
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use bytes::Bytes;
+
+use super::{
+    ArgumentType, CommandArgument, CommandContext, CommandDocs, CommandFlag, CommandHandler,
+    CommandSpec, RedisCommandError,
+};
+use crate::{
+    pubsub::{push, SubscriptionKind},
+    resp::RESPValues,
+};
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "unsubscribe",
+    arity: -1,
+    flags: &[
+        CommandFlag::PubSub,
+        CommandFlag::NoScript,
+        CommandFlag::Loading,
+        CommandFlag::Stale,
+    ],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Stops listening to messages posted to channels.",
+        since: "2.0.0",
+        group: "pubsub",
+        complexity: "O(N) where N is the number of channels to unsubscribe.",
+        arguments: &[CommandArgument {
+            name: "channel",
+            kind: ArgumentType::String,
+            optional: true,
+            multiple: true,
+        }],
+    },
+};
+
+pub const PATTERN_SPEC: CommandSpec = CommandSpec {
+    name: "punsubscribe",
+    docs: CommandDocs {
+        summary:
+            "Stops listening to messages published to channels that match one or more patterns.",
+        complexity: "O(N) where N is the number of patterns to unsubscribe.",
+        arguments: &[CommandArgument {
+            name: "pattern",
+            kind: ArgumentType::Pattern,
+            optional: true,
+            multiple: true,
+        }],
+        ..SPEC.docs
+    },
+    ..SPEC
+};
+
+pub const SHARD_SPEC: CommandSpec = CommandSpec {
+    name: "sunsubscribe",
+    // shard channels are routed like keys in a cluster
+    first_key: 1,
+    last_key: -1,
+    step: 1,
+    docs: CommandDocs {
+        summary: "Stops listening to messages posted to shard channels.",
+        since: "7.0.0",
+        complexity: "O(N) where N is the number of shard channels to unsubscribe.",
+        arguments: &[CommandArgument {
+            name: "shardchannel",
+            kind: ArgumentType::String,
+            optional: true,
+            multiple: true,
+        }],
+        ..SPEC.docs
+    },
+    ..SPEC
+};
+
+// UNSUBSCRIBE for channels, PUNSUBSCRIBE for patterns and SUNSUBSCRIBE for
+// shard channels
+pub struct Unsubscribe(pub SubscriptionKind);
+
+impl CommandHandler for Unsubscribe {
+    // Without arguments it unsubscribes from everything of its kind
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        let connection = &mut *ctx.connection;
+        let subscriptions = ctx.server.pubsub.subscriptions(self.0);
+        let (_, confirmation) = self.0.confirmations();
+        let channels = match &args[1..] {
+            [] => connection.subscribed(self.0).iter().cloned().collect(),
+            channels => channels.to_vec(),
+        };
+
+        let mut replies = Vec::new();
+        for channel in channels {
+            if connection.subscribed_mut(self.0).remove(&channel) {
+                subscriptions.unsubscribe(&channel, connection.id);
+            }
+            replies.push(push(
+                confirmation,
+                vec![
+                    RESPValues::BulkString(channel),
+                    RESPValues::Integer(connection.subscription_count(self.0) as i64),
+                ],
+            ));
+        }
+        if replies.is_empty() {
+            replies.push(push(
+                confirmation,
+                vec![
+                    RESPValues::Null,
+                    RESPValues::Integer(connection.subscription_count(self.0) as i64),
+                ],
+            ));
+        }
+
+        connection.update_class();
+        Ok(connection.replies(replies))
+    }
+}
+
+#[cfg(test)]
+mod unsubscribe_tests {
+    use bytes::Bytes;
+
+    use super::Unsubscribe;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler},
+        config::ClientClass,
+        pubsub::{push, SubscriptionKind},
+        resp::RESPValues,
+    };
+
+    #[test]
+    fn unsubscribe_from_every_channel_correctly() {
+        let mut state = test_state();
+        let mut ctx = test_context(&mut state);
+        for channel in [&b"a"[..], b"b"] {
+            let channel = Bytes::copy_from_slice(channel);
+            let subscriber = ctx.connection.messages.clone();
+            ctx.connection.channels.insert(channel.clone());
+            ctx.server.pubsub.channels.subscribe(channel, 1, subscriber);
+        }
+        ctx.connection.class = ClientClass::PubSub;
+
+        let result = Unsubscribe(SubscriptionKind::Channel)
+            .call(&[Bytes::from_static(b"UNSUBSCRIBE")], &mut ctx);
+
+        let confirm = |channel: &'static [u8], count| {
+            push(
+                "unsubscribe",
+                vec![
+                    RESPValues::BulkString(Bytes::from_static(channel)),
+                    RESPValues::Integer(count),
+                ],
+            )
+        };
+        assert!(result.is_ok_and(|r| r == confirm(b"a", 1)));
+        assert_eq!(ctx.connection.extra_replies, [confirm(b"b", 0)]);
+        assert_eq!(ctx.connection.class, ClientClass::Normal);
+        assert_eq!(
+            ctx.server
+                .pubsub
+                .publish(&Bytes::from_static(b"a"), &Bytes::new()),
+            0
+        );
+    }
+
+    #[test]
+    fn unsubscribe_without_subscriptions_correctly() {
+        let args = [Bytes::from_static(b"PUNSUBSCRIBE")];
+        let result = Unsubscribe(SubscriptionKind::Pattern)
+            .call(&args, &mut test_context(&mut test_state()));
+
+        assert!(result.is_ok_and(|r| r.to_bytes() == b">3\r\n$12\r\npunsubscribe\r\n_\r\n:0\r\n"));
+    }
+}
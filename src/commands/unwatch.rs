@@ -0,0 +1,62 @@
+use bytes::Bytes;
+
+use super::{
+    CommandContext, CommandDocs, CommandFlag, CommandHandler, CommandSpec, RedisCommandError,
+};
+use crate::resp::RESPValues;
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "unwatch",
+    arity: 1,
+    flags: &[
+        CommandFlag::NoScript,
+        CommandFlag::Loading,
+        CommandFlag::Stale,
+        CommandFlag::Fast,
+    ],
+    first_key: 0,
+    last_key: 0,
+    step: 0,
+    docs: CommandDocs {
+        summary: "Forgets about watched keys of a transaction.",
+        since: "2.2.0",
+        group: "transactions",
+        complexity: "O(1)",
+        arguments: &[],
+    },
+};
+
+pub struct Unwatch;
+
+impl CommandHandler for Unwatch {
+    fn call(
+        &self,
+        _args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        ctx.connection.watched.clear();
+        Ok(RESPValues::SimpleString("OK".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod unwatch_tests {
+    use bytes::Bytes;
+
+    use super::Unwatch;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler},
+        resp::RESPValues,
+    };
+
+    #[test]
+    fn unwatch_forgets_watched_keys_correctly() {
+        let args = [Bytes::from_static(b"UNWATCH")];
+        let mut state = test_state();
+        state.watched.push((Bytes::from_static(b"a"), 0));
+        let result = Unwatch.call(&args, &mut test_context(&mut state));
+
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("OK".to_string())));
+        assert!(state.watched.is_empty());
+    }
+}
@@ -0,0 +1,96 @@
+use bytes::Bytes;
+
+use super::{
+    ArgumentType, CommandArgument, CommandContext, CommandDocs, CommandFlag, CommandHandler,
+    CommandSpec, RedisCommandError,
+};
+use crate::resp::RESPValues;
+
+pub const SPEC: CommandSpec = CommandSpec {
+    name: "watch",
+    arity: -2,
+    flags: &[
+        CommandFlag::NoScript,
+        CommandFlag::Loading,
+        CommandFlag::Stale,
+        CommandFlag::Fast,
+    ],
+    first_key: 1,
+    last_key: -1,
+    step: 1,
+    docs: CommandDocs {
+        summary: "Monitors changes to keys to determine the execution of a transaction.",
+        since: "2.2.0",
+        group: "transactions",
+        complexity: "O(1) for every key.",
+        arguments: &[CommandArgument {
+            name: "key",
+            kind: ArgumentType::Key,
+            optional: false,
+            multiple: true,
+        }],
+    },
+};
+
+pub struct Watch;
+
+impl CommandHandler for Watch {
+    fn call(
+        &self,
+        args: &[Bytes],
+        ctx: &mut CommandContext,
+    ) -> Result<RESPValues, RedisCommandError> {
+        if ctx.connection.transaction.is_some() {
+            return Err(RedisCommandError::Invalid(
+                "WATCH inside MULTI is not allowed".to_string(),
+            ));
+        }
+
+        for key in &args[1..] {
+            // watching a key twice keeps the version it was first watched at
+            if ctx.connection.watched.iter().any(|(k, _)| k == key) {
+                continue;
+            }
+            let version = ctx.server.store.version(key);
+            ctx.connection.watched.push((key.clone(), version));
+        }
+        Ok(RESPValues::SimpleString("OK".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod watch_tests {
+    use bytes::Bytes;
+
+    use super::Watch;
+    use crate::{
+        commands::{test_context, test_state, CommandHandler, Transaction},
+        resp::RESPValues,
+    };
+
+    #[test]
+    fn watch_keys_correctly() {
+        let args = [
+            Bytes::from_static(b"WATCH"),
+            Bytes::from_static(b"a"),
+            Bytes::from_static(b"b"),
+            Bytes::from_static(b"a"),
+        ];
+        let mut state = test_state();
+        let result = Watch.call(&args, &mut test_context(&mut state));
+
+        assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("OK".to_string())));
+        let keys: Vec<_> = state.watched.iter().map(|(key, _)| &key[..]).collect();
+        assert_eq!(keys, [&b"a"[..], &b"b"[..]]);
+    }
+
+    #[test]
+    fn watch_inside_multi_fails() {
+        let args = [Bytes::from_static(b"WATCH"), Bytes::from_static(b"a")];
+        let mut state = test_state();
+        state.transaction = Some(Transaction::default());
+        let result = Watch.call(&args, &mut test_context(&mut state));
+
+        assert!(result.is_err_and(|e| e.to_string() == "ERR WATCH inside MULTI is not allowed"));
+    }
+}
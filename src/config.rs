@@ -0,0 +1,1427 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    net::{IpAddr, Ipv4Addr},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::resp::{split_inline_arguments, RESPLimits};
+
+// Settings the server is started with
+#[derive(PartialEq, Debug, Clone)]
+pub struct Config {
+    pub bind: IpAddr,
+    pub port: u16,
+    pub unixsocket: Option<PathBuf>,
+    // mode the unix socket is created with, such as 0o700, 0 leaving it to the umask
+    pub unixsocketperm: u32,
+    // working directory the RDB file is written to
+    pub dir: PathBuf,
+    pub dbfilename: String,
+    // BGSAVE runs once any of these has as many changes within its seconds
+    pub save: Vec<SavePoint>,
+    // LZF compresses the strings in RDB files
+    pub rdbcompression: bool,
+    // a CRC64 ends RDB files and is checked when they are loaded
+    pub rdbchecksum: bool,
+    // logs every write command to the AOF, the files named after
+    // appendfilename in appenddirname under dir
+    pub appendonly: bool,
+    pub appendfilename: String,
+    pub appenddirname: String,
+    pub appendfsync: AppendFsync,
+    // AOF rewrites write the dataset as an RDB dump rather than commands
+    pub aof_use_rdb_preamble: bool,
+    // an AOF ending mid command is loaded up to there rather than refused
+    pub aof_load_truncated: bool,
+    // the AOF is rewritten once it grew this many percent over its size after
+    // the last rewrite, 0 disabling it, and is at least auto_aof_rewrite_min_size
+    pub auto_aof_rewrite_percentage: u64,
+    pub auto_aof_rewrite_min_size: u64,
+    // the master this server replicates, as host and port, which REPLICAOF changes
+    pub replicaof: Option<(String, u16)>,
+    // a replica refuses writes from anyone but its master
+    pub replica_read_only: bool,
+    // seconds between the PINGs a master sends its replicas
+    pub repl_ping_replica_period: u64,
+    // in bytes, how much of the writes streamed to replicas is kept for one
+    // that reconnects to continue from
+    pub repl_backlog_size: usize,
+    // replicas get the dump streamed as it's written, the ones that ask for
+    // one within repl_diskless_sync_delay seconds of each other the same
+    pub repl_diskless_sync: bool,
+    pub repl_diskless_sync_delay: u64,
+    // a master refuses writes with fewer than min_replicas_to_write replicas
+    // that acknowledged within min_replicas_max_lag seconds, 0 for either
+    // disabling it
+    pub min_replicas_to_write: usize,
+    pub min_replicas_max_lag: u64,
+    // in milliseconds, how long a function runs before other clients are
+    // told the server is busy and the function may be killed
+    pub busy_script_timeout: u64,
+    // runs the node in Redis Cluster mode, what it knows of the cluster kept
+    // in cluster_config_file under dir
+    pub cluster_enabled: bool,
+    pub cluster_config_file: String,
+    // in bytes, 0 meaning no limit
+    pub maxmemory: u64,
+    // seconds between keepalive probes on idle client sockets, 0 disabling them
+    pub tcp_keepalive: u64,
+    // disables Nagle's algorithm on client sockets, so small replies go out at once
+    pub tcp_nodelay: bool,
+    // clients whose pending replies outgrow these are disconnected
+    pub client_output_buffer_limit: OutputBufferLimits,
+    // threads serving connections, each with its own runtime and shards of
+    // the keyspace; 1 leaves them all to tokio's scheduler
+    pub io_threads: usize,
+    // connections past this many are refused
+    pub maxclients: usize,
+    pub loglevel: LogLevel,
+    pub limits: RESPLimits,
+    // HELLO refuses RESP3, for testing how clients fall back to RESP2
+    pub resp2_only: bool,
+    // TLS connections are only accepted when a port is set
+    pub tls_port: Option<u16>,
+    pub tls_cert_file: Option<PathBuf>,
+    pub tls_key_file: Option<PathBuf>,
+    // clients are verified against this CA as tls_auth_clients asks
+    pub tls_ca_cert_file: Option<PathBuf>,
+    pub tls_auth_clients: TlsAuthClients,
+    // the classes of key changes published to __keyspace@0__ and __keyevent@0__
+    pub notify_keyspace_events: KeyspaceEvents,
+    // the file the config was loaded from, CONFIG REWRITE writes back to it
+    pub config_file: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 6379,
+            unixsocket: None,
+            unixsocketperm: 0,
+            dir: PathBuf::from("."),
+            dbfilename: "dump.rdb".to_string(),
+            // the save points Redis has without a config file
+            save: vec![
+                SavePoint {
+                    seconds: 3600,
+                    changes: 1,
+                },
+                SavePoint {
+                    seconds: 300,
+                    changes: 100,
+                },
+                SavePoint {
+                    seconds: 60,
+                    changes: 10000,
+                },
+            ],
+            rdbcompression: true,
+            rdbchecksum: true,
+            appendonly: false,
+            appendfilename: "appendonly.aof".to_string(),
+            appenddirname: "appendonlydir".to_string(),
+            appendfsync: AppendFsync::default(),
+            aof_use_rdb_preamble: true,
+            aof_load_truncated: true,
+            auto_aof_rewrite_percentage: 100,
+            auto_aof_rewrite_min_size: 64 * 1024 * 1024,
+            replicaof: None,
+            replica_read_only: true,
+            repl_ping_replica_period: 10,
+            repl_backlog_size: 1024 * 1024,
+            repl_diskless_sync: true,
+            repl_diskless_sync_delay: 5,
+            min_replicas_to_write: 0,
+            min_replicas_max_lag: 10,
+            busy_script_timeout: 5000,
+            cluster_enabled: false,
+            cluster_config_file: "nodes.conf".to_string(),
+            maxmemory: 0,
+            tcp_keepalive: 300,
+            tcp_nodelay: true,
+            client_output_buffer_limit: OutputBufferLimits::default(),
+            io_threads: 1,
+            maxclients: 10000,
+            loglevel: LogLevel::default(),
+            limits: RESPLimits::default(),
+            resp2_only: false,
+            tls_port: None,
+            tls_cert_file: None,
+            tls_key_file: None,
+            tls_ca_cert_file: None,
+            tls_auth_clients: TlsAuthClients::default(),
+            notify_keyspace_events: KeyspaceEvents::default(),
+            config_file: None,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum ConfigSetError {
+    UnknownOption(String),
+    Immutable(&'static str),
+    InvalidValue(&'static str, String),
+}
+
+impl std::fmt::Display for ConfigSetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownOption(name) => write!(f, "unknown option '{name}'"),
+            Self::Immutable(name) => write!(f, "can't set immutable config '{name}'"),
+            Self::InvalidValue(name, reason) => write!(f, "invalid value for '{name}': {reason}"),
+        }
+    }
+}
+
+impl Config {
+    // Reads a redis.conf style file on top of the defaults, see `apply_file`
+    pub fn load(path: impl AsRef<Path>) -> io::Result<(Self, Vec<String>)> {
+        let path = fs::canonicalize(path)?;
+        let mut config = Config {
+            config_file: Some(path.clone()),
+            ..Config::default()
+        };
+        let ignored = config
+            .apply_file(&fs::read_to_string(&path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        Ok((config, ignored))
+    }
+
+    // Re-reads `config_file` on top of the current parameters. Static
+    // parameters can't change while running, so the ones the file changed
+    // keep their current value and are returned along with the unsupported
+    // directives
+    pub fn reload(&self) -> io::Result<(Self, Vec<String>)> {
+        let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+        let path = self.config_file.as_ref().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "the server is running without a config file",
+            )
+        })?;
+
+        let mut reloaded = self.clone();
+        let mut skipped = reloaded
+            .apply_file(&fs::read_to_string(path)?)
+            .map_err(invalid)?;
+        for parameter in PARAMETERS.iter().filter(|parameter| !parameter.mutable) {
+            let current = (parameter.get)(self);
+            if (parameter.get)(&reloaded) != current {
+                (parameter.set)(&mut reloaded, &current).map_err(invalid)?;
+                skipped.push(parameter.name.to_string());
+            }
+        }
+
+        Ok((reloaded, skipped))
+    }
+
+    // Applies every directive in `text`, one per line with `#` comments and
+    // arguments quoted as in inline commands. Directives this server doesn't
+    // support are skipped and returned so they can be reported
+    pub fn apply_file(&mut self, text: &str) -> Result<Vec<String>, String> {
+        let mut ignored = Vec::new();
+        // save lines add up, the first one replacing the points there were
+        let mut save_points: Option<String> = None;
+
+        for (number, line) in text.lines().enumerate() {
+            let args = match directive(line) {
+                Some(Ok(args)) => args,
+                Some(Err(e)) => return Err(format!("line {}: {e}", number + 1)),
+                None => continue,
+            };
+            let name = args[0].to_ascii_lowercase();
+            let mut value = args[1..].join(" ");
+            if name == "save" {
+                if let Some(previous) = &save_points {
+                    value = format!("{previous} {value}");
+                }
+                save_points = Some(value.clone());
+            }
+            match parameter(&name) {
+                Some(parameter) => (parameter.set)(self, &value)
+                    .map_err(|e| format!("line {}: '{name}' {e}", number + 1))?,
+                None => ignored.push(name),
+            }
+        }
+
+        Ok(ignored)
+    }
+
+    pub fn get(&self, name: &str) -> Option<String> {
+        parameter(&name.to_ascii_lowercase()).map(|parameter| (parameter.get)(self))
+    }
+
+    // Changes a parameter at runtime, as CONFIG SET does
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), ConfigSetError> {
+        let parameter = parameter(&name.to_ascii_lowercase())
+            .ok_or_else(|| ConfigSetError::UnknownOption(name.to_string()))?;
+        if !parameter.mutable {
+            return Err(ConfigSetError::Immutable(parameter.name));
+        }
+        (parameter.set)(self, value).map_err(|e| ConfigSetError::InvalidValue(parameter.name, e))
+    }
+
+    // Returns `original` with every supported directive set to its current
+    // value, keeping comments and unknown directives in place and appending
+    // the parameters that differ from their default but weren't in the file
+    pub fn rewrite(&self, original: &str) -> String {
+        let mut written = HashSet::new();
+        let mut lines = Vec::new();
+
+        for line in original.lines() {
+            let name = match directive(line) {
+                Some(Ok(args)) => args[0].to_ascii_lowercase(),
+                _ => {
+                    lines.push(line.to_string());
+                    continue;
+                }
+            };
+            match parameter(&name) {
+                // later duplicates of a directive are dropped, the first one holds the value
+                Some(parameter) if !written.insert(parameter.name) => {}
+                Some(parameter) => lines.push(parameter.line(self)),
+                None => lines.push(line.to_string()),
+            }
+        }
+
+        let defaults = Config::default();
+        let missing: Vec<_> = PARAMETERS
+            .iter()
+            .filter(|p| !written.contains(p.name) && (p.get)(self) != (p.get)(&defaults))
+            .collect();
+        if !missing.is_empty() {
+            lines.push("# Generated by CONFIG REWRITE".to_string());
+            lines.extend(missing.iter().map(|parameter| parameter.line(self)));
+        }
+
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+// A parameter readable through CONFIG GET and settable from redis.conf
+pub struct Parameter {
+    pub name: &'static str,
+    // whether CONFIG SET may change it while the server runs
+    pub mutable: bool,
+    get: fn(&Config) -> String,
+    set: fn(&mut Config, &str) -> Result<(), String>,
+}
+
+impl Parameter {
+    fn line(&self, config: &Config) -> String {
+        let value = (self.get)(config);
+        // a line for every save point, as Redis writes them
+        if self.name == "save" && !value.is_empty() {
+            let words: Vec<_> = value.split(' ').collect();
+            let lines: Vec<_> = words
+                .chunks(2)
+                .map(|point| format!("save {}", point.join(" ")))
+                .collect();
+            return lines.join("\n");
+        }
+        format!("{} {}", self.name, quote(&value))
+    }
+}
+
+pub const PARAMETERS: &[Parameter] = &[
+    Parameter {
+        name: "bind",
+        mutable: false,
+        get: |c| c.bind.to_string(),
+        // only the first address is listened on, a leading `-` marks it optional in Redis
+        set: |c, v| {
+            let first = v.split_whitespace().next().unwrap_or_default();
+            c.bind = first
+                .trim_start_matches('-')
+                .parse()
+                .map_err(|_| format!("invalid address '{first}'"))?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "port",
+        mutable: false,
+        get: |c| c.port.to_string(),
+        set: |c, v| {
+            c.port = v.parse().map_err(|_| format!("invalid port '{v}'"))?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "unixsocket",
+        mutable: false,
+        get: |c| display_path(&c.unixsocket),
+        set: |c, v| {
+            c.unixsocket = optional_path(v);
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "unixsocketperm",
+        mutable: false,
+        get: |c| format!("{:o}", c.unixsocketperm),
+        set: |c, v| {
+            c.unixsocketperm = parse_permissions(v)?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "dir",
+        mutable: true,
+        get: |c| c.dir.display().to_string(),
+        set: |c, v| {
+            c.dir = PathBuf::from(v);
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "dbfilename",
+        mutable: true,
+        get: |c| c.dbfilename.clone(),
+        set: |c, v| {
+            if v.is_empty() || v.contains('/') {
+                return Err("dbfilename can't be a path, just a filename".to_string());
+            }
+            c.dbfilename = v.to_string();
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "save",
+        mutable: true,
+        get: |c| {
+            let points: Vec<_> = c
+                .save
+                .iter()
+                .map(|p| format!("{} {}", p.seconds, p.changes))
+                .collect();
+            points.join(" ")
+        },
+        set: |c, v| {
+            c.save = parse_save_points(v)?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "rdbcompression",
+        mutable: true,
+        get: |c| yes_no(c.rdbcompression),
+        set: |c, v| {
+            c.rdbcompression = parse_yes_no(v)?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "rdbchecksum",
+        mutable: false,
+        get: |c| yes_no(c.rdbchecksum),
+        set: |c, v| {
+            c.rdbchecksum = parse_yes_no(v)?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "cluster-enabled",
+        mutable: false,
+        get: |c| yes_no(c.cluster_enabled),
+        set: |c, v| {
+            c.cluster_enabled = parse_yes_no(v)?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "cluster-config-file",
+        mutable: false,
+        get: |c| c.cluster_config_file.clone(),
+        set: |c, v| {
+            if v.is_empty() {
+                return Err("cluster-config-file can't be empty".to_string());
+            }
+            c.cluster_config_file = v.to_string();
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "appendonly",
+        mutable: true,
+        get: |c| yes_no(c.appendonly),
+        set: |c, v| {
+            c.appendonly = parse_yes_no(v)?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "appendfilename",
+        mutable: false,
+        get: |c| c.appendfilename.clone(),
+        set: |c, v| {
+            if v.is_empty() || v.contains('/') {
+                return Err("appendfilename can't be a path, just a filename".to_string());
+            }
+            c.appendfilename = v.to_string();
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "appenddirname",
+        mutable: false,
+        get: |c| c.appenddirname.clone(),
+        set: |c, v| {
+            if v.is_empty() || v.contains('/') {
+                return Err("appenddirname can't be a path, just a directory name".to_string());
+            }
+            c.appenddirname = v.to_string();
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "appendfsync",
+        mutable: true,
+        get: |c| c.appendfsync.name().to_string(),
+        set: |c, v| {
+            c.appendfsync = v.parse()?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "aof-use-rdb-preamble",
+        mutable: true,
+        get: |c| yes_no(c.aof_use_rdb_preamble),
+        set: |c, v| {
+            c.aof_use_rdb_preamble = parse_yes_no(v)?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "aof-load-truncated",
+        mutable: true,
+        get: |c| yes_no(c.aof_load_truncated),
+        set: |c, v| {
+            c.aof_load_truncated = parse_yes_no(v)?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "auto-aof-rewrite-percentage",
+        mutable: true,
+        get: |c| c.auto_aof_rewrite_percentage.to_string(),
+        set: |c, v| {
+            c.auto_aof_rewrite_percentage = v
+                .parse()
+                .map_err(|_| format!("invalid auto-aof-rewrite-percentage '{v}'"))?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "auto-aof-rewrite-min-size",
+        mutable: true,
+        get: |c| c.auto_aof_rewrite_min_size.to_string(),
+        set: |c, v| {
+            c.auto_aof_rewrite_min_size = parse_memory(v)?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "replicaof",
+        mutable: false,
+        get: |c| match &c.replicaof {
+            Some((host, port)) => format!("{host} {port}"),
+            None => String::new(),
+        },
+        set: |c, v| {
+            c.replicaof = parse_replicaof(v)?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "replica-read-only",
+        mutable: true,
+        get: |c| yes_no(c.replica_read_only),
+        set: |c, v| {
+            c.replica_read_only = parse_yes_no(v)?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "repl-ping-replica-period",
+        mutable: true,
+        get: |c| c.repl_ping_replica_period.to_string(),
+        set: |c, v| {
+            c.repl_ping_replica_period = v
+                .parse()
+                .ok()
+                .filter(|&period| period > 0)
+                .ok_or_else(|| format!("invalid repl-ping-replica-period '{v}'"))?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "repl-backlog-size",
+        mutable: true,
+        get: |c| c.repl_backlog_size.to_string(),
+        set: |c, v| {
+            c.repl_backlog_size = parse_memory(v)? as usize;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "repl-diskless-sync",
+        mutable: true,
+        get: |c| yes_no(c.repl_diskless_sync),
+        set: |c, v| {
+            c.repl_diskless_sync = parse_yes_no(v)?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "repl-diskless-sync-delay",
+        mutable: true,
+        get: |c| c.repl_diskless_sync_delay.to_string(),
+        set: |c, v| {
+            c.repl_diskless_sync_delay = v
+                .parse()
+                .map_err(|_| format!("invalid repl-diskless-sync-delay '{v}'"))?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "min-replicas-to-write",
+        mutable: true,
+        get: |c| c.min_replicas_to_write.to_string(),
+        set: |c, v| {
+            c.min_replicas_to_write = v
+                .parse()
+                .map_err(|_| format!("invalid min-replicas-to-write '{v}'"))?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "min-replicas-max-lag",
+        mutable: true,
+        get: |c| c.min_replicas_max_lag.to_string(),
+        set: |c, v| {
+            c.min_replicas_max_lag = v
+                .parse()
+                .map_err(|_| format!("invalid min-replicas-max-lag '{v}'"))?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "busy-script-timeout",
+        mutable: true,
+        get: |c| c.busy_script_timeout.to_string(),
+        set: |c, v| {
+            c.busy_script_timeout = v
+                .parse()
+                .map_err(|_| format!("invalid busy-script-timeout '{v}'"))?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "maxmemory",
+        mutable: true,
+        get: |c| c.maxmemory.to_string(),
+        set: |c, v| {
+            c.maxmemory = parse_memory(v)?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "tcp-keepalive",
+        mutable: true,
+        get: |c| c.tcp_keepalive.to_string(),
+        set: |c, v| {
+            c.tcp_keepalive = v
+                .parse()
+                .map_err(|_| format!("invalid tcp-keepalive '{v}'"))?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "tcp-nodelay",
+        mutable: true,
+        get: |c| yes_no(c.tcp_nodelay),
+        set: |c, v| {
+            c.tcp_nodelay = parse_yes_no(v)?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "client-output-buffer-limit",
+        mutable: true,
+        get: |c| c.client_output_buffer_limit.to_string(),
+        set: |c, v| {
+            c.client_output_buffer_limit.apply(v)?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "io-threads",
+        mutable: false,
+        get: |c| c.io_threads.to_string(),
+        set: |c, v| {
+            c.io_threads = v
+                .parse()
+                .ok()
+                .filter(|threads| (1..=128).contains(threads))
+                .ok_or_else(|| format!("invalid io-threads '{v}'"))?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "maxclients",
+        mutable: true,
+        get: |c| c.maxclients.to_string(),
+        set: |c, v| {
+            c.maxclients = v
+                .parse()
+                .ok()
+                .filter(|maxclients| *maxclients > 0)
+                .ok_or_else(|| format!("invalid maxclients '{v}'"))?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "loglevel",
+        mutable: true,
+        get: |c| c.loglevel.name().to_string(),
+        set: |c, v| {
+            c.loglevel = v.parse()?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "proto-max-bulk-len",
+        mutable: true,
+        get: |c| c.limits.max_bulk_length.to_string(),
+        set: |c, v| {
+            c.limits.max_bulk_length = parse_memory(v)? as usize;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "client-query-buffer-limit",
+        mutable: true,
+        get: |c| c.limits.max_query_buffer_length.to_string(),
+        set: |c, v| {
+            c.limits.max_query_buffer_length = parse_memory(v)? as usize;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "resp2-only",
+        mutable: true,
+        get: |c| yes_no(c.resp2_only),
+        set: |c, v| {
+            c.resp2_only = parse_yes_no(v)?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "tls-port",
+        mutable: false,
+        get: |c| c.tls_port.unwrap_or(0).to_string(),
+        set: |c, v| {
+            let port: u16 = v.parse().map_err(|_| format!("invalid port '{v}'"))?;
+            c.tls_port = (port != 0).then_some(port);
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "tls-cert-file",
+        mutable: false,
+        get: |c| display_path(&c.tls_cert_file),
+        set: |c, v| {
+            c.tls_cert_file = optional_path(v);
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "tls-key-file",
+        mutable: false,
+        get: |c| display_path(&c.tls_key_file),
+        set: |c, v| {
+            c.tls_key_file = optional_path(v);
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "tls-ca-cert-file",
+        mutable: false,
+        get: |c| display_path(&c.tls_ca_cert_file),
+        set: |c, v| {
+            c.tls_ca_cert_file = optional_path(v);
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "tls-auth-clients",
+        mutable: false,
+        get: |c| c.tls_auth_clients.name().to_string(),
+        set: |c, v| {
+            c.tls_auth_clients = v.parse()?;
+            Ok(())
+        },
+    },
+    Parameter {
+        name: "notify-keyspace-events",
+        mutable: true,
+        get: |c| c.notify_keyspace_events.to_string(),
+        set: |c, v| {
+            c.notify_keyspace_events = v.parse()?;
+            Ok(())
+        },
+    },
+];
+
+// One snapshotting rule of the save parameter
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct SavePoint {
+    pub seconds: u64,
+    pub changes: u64,
+}
+
+// Pairs of seconds and changes, nothing at all turning snapshotting off
+fn parse_save_points(value: &str) -> Result<Vec<SavePoint>, String> {
+    let words: Vec<_> = value.split_whitespace().collect();
+    if words.len() % 2 != 0 {
+        return Err("invalid save parameters".to_string());
+    }
+    words
+        .chunks(2)
+        .map(|pair| {
+            let number = |word: &str| {
+                word.parse()
+                    .map_err(|_| format!("invalid save parameter '{word}'"))
+            };
+            Ok(SavePoint {
+                seconds: number(pair[0])?,
+                changes: number(pair[1])?,
+            })
+        })
+        .collect()
+}
+
+fn yes_no(value: bool) -> String {
+    if value { "yes" } else { "no" }.to_string()
+}
+
+// `<host> <port>`, or `no one` for none
+fn parse_replicaof(value: &str) -> Result<Option<(String, u16)>, String> {
+    match value.split_whitespace().collect::<Vec<_>>()[..] {
+        [] => Ok(None),
+        [no, one] if no.eq_ignore_ascii_case("no") && one.eq_ignore_ascii_case("one") => Ok(None),
+        [host, port] => match port.parse() {
+            Ok(port) => Ok(Some((host.to_string(), port))),
+            Err(_) => Err(format!("invalid master port '{port}'")),
+        },
+        _ => Err(format!("invalid replicaof '{value}'")),
+    }
+}
+
+pub fn parse_yes_no(value: &str) -> Result<bool, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        _ => Err(format!("argument must be 'yes' or 'no', not '{value}'")),
+    }
+}
+
+// Unset paths read and write as empty strings
+fn display_path(path: &Option<PathBuf>) -> String {
+    path.as_ref()
+        .map_or(String::new(), |p| p.display().to_string())
+}
+
+fn optional_path(value: &str) -> Option<PathBuf> {
+    (!value.is_empty()).then(|| PathBuf::from(value))
+}
+
+fn parameter(name: &str) -> Option<&'static Parameter> {
+    PARAMETERS.iter().find(|parameter| parameter.name == name)
+}
+
+// An octal file mode such as 700 or 0770
+pub fn parse_permissions(value: &str) -> Result<u32, String> {
+    u32::from_str_radix(value, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o777)
+        .ok_or_else(|| format!("invalid permissions '{value}'"))
+}
+
+// The arguments of a config file line, or None for blank lines and comments
+fn directive(line: &str) -> Option<Result<Vec<String>, String>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    Some(
+        split_inline_arguments(line.as_bytes())
+            .map(|args| {
+                args.into_iter()
+                    .map(|a| String::from_utf8_lossy(&a).to_string())
+                    .collect()
+            })
+            .map_err(|e| e.to_string()),
+    )
+}
+
+// Quotes `value` when it wouldn't read back as a single argument
+fn quote(value: &str) -> String {
+    if !value.is_empty() && !value.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'') {
+        return value.to_string();
+    }
+
+    let mut quoted = String::from('"');
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Ordered from the most to the least verbose
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy, Default)]
+pub enum LogLevel {
+    Debug,
+    Verbose,
+    #[default]
+    Notice,
+    Warning,
+}
+
+impl LogLevel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Debug => "debug",
+            Self::Verbose => "verbose",
+            Self::Notice => "notice",
+            Self::Warning => "warning",
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "debug" => Ok(Self::Debug),
+            "verbose" => Ok(Self::Verbose),
+            "notice" => Ok(Self::Notice),
+            "warning" => Ok(Self::Warning),
+            _ => Err(format!("invalid log level '{s}'")),
+        }
+    }
+}
+
+// Which output buffer limits a client is held to
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum ClientClass {
+    #[default]
+    Normal,
+    Replica,
+    PubSub,
+}
+
+impl FromStr for ClientClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "normal" => Ok(Self::Normal),
+            // the name Redis used before replica
+            "replica" | "slave" => Ok(Self::Replica),
+            "pubsub" => Ok(Self::PubSub),
+            _ => Err(format!("invalid client class '{s}'")),
+        }
+    }
+}
+
+// In bytes, 0 meaning no limit. A client is disconnected as soon as its
+// pending replies reach `hard`, or once they stayed over `soft` for
+// `soft_seconds`
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct OutputBufferLimit {
+    pub hard: u64,
+    pub soft: u64,
+    pub soft_seconds: u64,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct OutputBufferLimits {
+    pub normal: OutputBufferLimit,
+    pub replica: OutputBufferLimit,
+    pub pubsub: OutputBufferLimit,
+}
+
+impl Default for OutputBufferLimits {
+    // the defaults of redis.conf
+    fn default() -> Self {
+        let limit = |hard, soft, soft_seconds| OutputBufferLimit {
+            hard,
+            soft,
+            soft_seconds,
+        };
+        Self {
+            normal: limit(0, 0, 0),
+            replica: limit(256 * 1024 * 1024, 64 * 1024 * 1024, 60),
+            pubsub: limit(32 * 1024 * 1024, 8 * 1024 * 1024, 60),
+        }
+    }
+}
+
+impl OutputBufferLimits {
+    pub fn get(&self, class: ClientClass) -> OutputBufferLimit {
+        match class {
+            ClientClass::Normal => self.normal,
+            ClientClass::Replica => self.replica,
+            ClientClass::PubSub => self.pubsub,
+        }
+    }
+
+    // Sets the classes in `value`, given as `<class> <hard> <soft> <seconds>`
+    // groups, leaving the others as they are. Nothing changes on errors
+    pub fn apply(&mut self, value: &str) -> Result<(), String> {
+        let words: Vec<_> = value.split_whitespace().collect();
+        if words.is_empty() || words.len() % 4 != 0 {
+            return Err("wrong number of arguments in buffer limit configuration.".to_string());
+        }
+
+        let mut updated = *self;
+        for group in words.chunks(4) {
+            let limit = OutputBufferLimit {
+                hard: parse_memory(group[1])?,
+                soft: parse_memory(group[2])?,
+                soft_seconds: group[3]
+                    .parse()
+                    .map_err(|_| format!("invalid soft limit seconds '{}'", group[3]))?,
+            };
+            match group[0].parse()? {
+                ClientClass::Normal => updated.normal = limit,
+                ClientClass::Replica => updated.replica = limit,
+                ClientClass::PubSub => updated.pubsub = limit,
+            }
+        }
+
+        *self = updated;
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for OutputBufferLimits {
+    // as CONFIG GET shows it, still naming replicas slave
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let classes = [
+            ("normal", self.normal),
+            ("slave", self.replica),
+            ("pubsub", self.pubsub),
+        ];
+        let groups: Vec<_> = classes
+            .iter()
+            .map(|(name, l)| format!("{name} {} {} {}", l.hard, l.soft, l.soft_seconds))
+            .collect();
+        write!(f, "{}", groups.join(" "))
+    }
+}
+
+// When AOF writes are flushed to disk
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum AppendFsync {
+    // after every write command
+    Always,
+    // once a second, losing at most that much on a crash
+    #[default]
+    Everysec,
+    // whenever the operating system gets to it
+    No,
+}
+
+impl AppendFsync {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Always => "always",
+            Self::Everysec => "everysec",
+            Self::No => "no",
+        }
+    }
+}
+
+impl FromStr for AppendFsync {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "always" => Ok(Self::Always),
+            "everysec" => Ok(Self::Everysec),
+            "no" => Ok(Self::No),
+            _ => Err(format!("invalid appendfsync value '{s}'")),
+        }
+    }
+}
+
+// Whether TLS clients must present a certificate signed by tls_ca_cert_file
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum TlsAuthClients {
+    #[default]
+    Yes,
+    No,
+    // verified when presented, but clients without one are accepted too
+    Optional,
+}
+
+impl TlsAuthClients {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Yes => "yes",
+            Self::No => "no",
+            Self::Optional => "optional",
+        }
+    }
+}
+
+impl FromStr for TlsAuthClients {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "yes" => Ok(Self::Yes),
+            "no" => Ok(Self::No),
+            "optional" => Ok(Self::Optional),
+            _ => Err(format!("invalid tls-auth-clients value '{s}'")),
+        }
+    }
+}
+
+// Flags set by the letters of notify-keyspace-events. K and E pick the
+// channels events go to, the others which classes of events are published
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct KeyspaceEvents(u16);
+
+impl KeyspaceEvents {
+    pub const KEYSPACE: Self = Self(1 << 0);
+    pub const KEYEVENT: Self = Self(1 << 1);
+    pub const GENERIC: Self = Self(1 << 2);
+    pub const STRING: Self = Self(1 << 3);
+    pub const LIST: Self = Self(1 << 4);
+    pub const SET: Self = Self(1 << 5);
+    pub const HASH: Self = Self(1 << 6);
+    pub const ZSET: Self = Self(1 << 7);
+    pub const EXPIRED: Self = Self(1 << 8);
+    pub const EVICTED: Self = Self(1 << 9);
+    pub const STREAM: Self = Self(1 << 10);
+    pub const KEY_MISS: Self = Self(1 << 11);
+    pub const MODULE: Self = Self(1 << 12);
+    pub const NEW: Self = Self(1 << 13);
+    // what A stands for, every class but key misses and new keys
+    pub const ALL: Self = Self(0b1_0111_1111_1100);
+
+    // in the order Redis lists them
+    const LETTERS: [(char, Self); 13] = [
+        ('g', Self::GENERIC),
+        ('$', Self::STRING),
+        ('l', Self::LIST),
+        ('s', Self::SET),
+        ('h', Self::HASH),
+        ('z', Self::ZSET),
+        ('x', Self::EXPIRED),
+        ('e', Self::EVICTED),
+        ('t', Self::STREAM),
+        ('d', Self::MODULE),
+        ('K', Self::KEYSPACE),
+        ('E', Self::KEYEVENT),
+        ('m', Self::KEY_MISS),
+    ];
+
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    // Whether an event of `class` gets published at all
+    pub fn publishes(&self, class: Self) -> bool {
+        self.0 & class.0 != 0 && self.0 & (Self::KEYSPACE.0 | Self::KEYEVENT.0) != 0
+    }
+}
+
+impl std::ops::BitOr for KeyspaceEvents {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl FromStr for KeyspaceEvents {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.chars().try_fold(Self::default(), |events, letter| {
+            let flag = match letter {
+                'A' => Self::ALL,
+                'n' => Self::NEW,
+                _ => Self::LETTERS
+                    .iter()
+                    .find(|(l, _)| *l == letter)
+                    .map(|(_, flag)| *flag)
+                    .ok_or_else(|| {
+                        "Invalid event class character. Use 'Ag$lshzxeKEtmdn'.".to_string()
+                    })?,
+            };
+            Ok(events | flag)
+        })
+    }
+}
+
+impl std::fmt::Display for KeyspaceEvents {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let all = self.contains(Self::ALL);
+        if all {
+            write!(f, "A")?;
+        }
+        for (letter, flag) in Self::LETTERS {
+            if !(all && Self::ALL.contains(flag)) && self.contains(flag) {
+                write!(f, "{letter}")?;
+            }
+        }
+        if self.contains(Self::NEW) {
+            write!(f, "n")?;
+        }
+        Ok(())
+    }
+}
+
+// Parses a memory amount the way redis.conf does: a plain number of bytes or
+// one with a unit, where k/m/g are powers of 1000 and kb/mb/gb powers of 1024
+pub fn parse_memory(value: &str) -> Result<u64, String> {
+    let lowercase = value.to_ascii_lowercase();
+    let split = lowercase
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(lowercase.len());
+    let (number, unit) = lowercase.split_at(split);
+    let multiplier: u64 = match unit {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return Err(format!("invalid memory unit in '{value}'")),
+    };
+
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid memory amount '{value}'"))
+}
+
+#[cfg(test)]
+mod config_tests {
+    use std::net::Ipv4Addr;
+
+    use super::{
+        parse_memory, parse_permissions, Config, ConfigSetError, KeyspaceEvents, LogLevel,
+        OutputBufferLimit, OutputBufferLimits, SavePoint,
+    };
+    use crate::commands::test_dir;
+
+    #[test]
+    fn parse_memory_correctly() {
+        assert_eq!(parse_memory("100"), Ok(100));
+        assert_eq!(parse_memory("1k"), Ok(1000));
+        assert_eq!(parse_memory("1kb"), Ok(1024));
+        assert_eq!(parse_memory("2GB"), Ok(2 * 1024 * 1024 * 1024));
+    }
+
+    #[test]
+    fn parse_invalid_memory_fails() {
+        assert!(parse_memory("").is_err());
+        assert!(parse_memory("10tb").is_err());
+        assert!(parse_memory("-1").is_err());
+        assert!(parse_memory("99999999999999999999gb").is_err());
+    }
+
+    #[test]
+    fn parse_log_level_correctly() {
+        assert_eq!("WARNING".parse(), Ok(LogLevel::Warning));
+        assert!("loud".parse::<LogLevel>().is_err());
+        assert!(LogLevel::Debug < LogLevel::Notice);
+    }
+
+    #[test]
+    fn apply_config_file_correctly() {
+        let mut config = Config::default();
+        let text = "# example\n\nport 7000\nbind 0.0.0.0 -::1\nmaxmemory 100mb\ndir \"/var/lib/my redis\"\nsave 3600 1\n";
+        let result = config.apply_file(text);
+
+        assert_eq!(result, Ok(vec![]));
+        assert_eq!(config.port, 7000);
+        assert_eq!(
+            config.save,
+            [SavePoint {
+                seconds: 3600,
+                changes: 1
+            }]
+        );
+        assert_eq!(config.bind, Ipv4Addr::UNSPECIFIED);
+        assert_eq!(config.maxmemory, 100 * 1024 * 1024);
+        assert_eq!(config.dir.to_str(), Some("/var/lib/my redis"));
+    }
+
+    #[test]
+    fn apply_config_file_with_invalid_value_fails() {
+        let mut config = Config::default();
+        let result = config.apply_file("port 6380\nport many\n");
+
+        assert_eq!(
+            result,
+            Err("line 2: 'port' invalid port 'many'".to_string())
+        );
+    }
+
+    #[test]
+    fn set_parameters_correctly() {
+        let mut config = Config::default();
+
+        assert_eq!(config.set("LogLevel", "warning"), Ok(()));
+        assert_eq!(config.get("loglevel"), Some("warning".to_string()));
+        assert_eq!(
+            config.set("port", "1"),
+            Err(ConfigSetError::Immutable("port"))
+        );
+        assert_eq!(
+            config.set("nope", "1"),
+            Err(ConfigSetError::UnknownOption("nope".to_string()))
+        );
+        assert!(matches!(
+            config.set("maxmemory", "lots"),
+            Err(ConfigSetError::InvalidValue("maxmemory", _))
+        ));
+    }
+
+    #[test]
+    fn rewrite_config_file_correctly() {
+        let mut config = Config::default();
+        let original = "# keep me\nport 7000\nsave 3600 1\nport 7001\n";
+        config.apply_file(original).unwrap();
+        config.set("loglevel", "warning").unwrap();
+        config.set("dir", "/tmp/with space").unwrap();
+        let result = config.rewrite(original);
+
+        assert_eq!(
+            result,
+            "# keep me\nport 7001\nsave 3600 1\n# Generated by CONFIG REWRITE\ndir \"/tmp/with space\"\nloglevel warning\n"
+        );
+
+        let mut reloaded = Config::default();
+        reloaded.apply_file(&result).unwrap();
+        assert_eq!(reloaded, config);
+    }
+
+    #[test]
+    fn reload_config_file_correctly() {
+        let dir = test_dir("config-reload");
+        let path = dir.join("redis.conf");
+        std::fs::write(&path, "port 7000\nloglevel debug\n").unwrap();
+        let (mut config, _) = Config::load(&path).unwrap();
+        config.set("maxmemory", "1mb").unwrap();
+
+        std::fs::write(&path, "port 7001\nloglevel warning\n").unwrap();
+        let result = config.reload();
+
+        let (reloaded, skipped) = result.unwrap();
+        assert_eq!(skipped, vec!["port".to_string()]);
+        assert_eq!(reloaded.port, 7000);
+        assert_eq!(reloaded.loglevel, LogLevel::Warning);
+        assert_eq!(reloaded.maxmemory, 1024 * 1024);
+    }
+
+    #[test]
+    fn parse_permissions_correctly() {
+        assert_eq!(parse_permissions("700"), Ok(0o700));
+        assert_eq!(parse_permissions("0770"), Ok(0o770));
+        assert!(parse_permissions("800").is_err());
+        assert!(parse_permissions("7777").is_err());
+    }
+
+    #[test]
+    fn apply_output_buffer_limits_correctly() {
+        let mut limits = OutputBufferLimits::default();
+        let result = limits.apply("normal 1mb 512kb 10 slave 0 0 0");
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            limits.normal,
+            OutputBufferLimit {
+                hard: 1024 * 1024,
+                soft: 512 * 1024,
+                soft_seconds: 10
+            }
+        );
+        assert_eq!(
+            limits.to_string(),
+            "normal 1048576 524288 10 slave 0 0 0 pubsub 33554432 8388608 60"
+        );
+    }
+
+    #[test]
+    fn apply_invalid_output_buffer_limits_fails() {
+        let mut limits = OutputBufferLimits::default();
+
+        assert!(limits.apply("normal 1mb 512kb").is_err());
+        assert!(limits.apply("normal 1mb 512kb 10 nope 0 0 0").is_err());
+        assert_eq!(limits, OutputBufferLimits::default());
+    }
+
+    #[test]
+    fn parse_keyspace_events_correctly() {
+        let events: KeyspaceEvents = "Kx$".parse().unwrap();
+        assert!(events.publishes(KeyspaceEvents::EXPIRED));
+        assert!(!events.publishes(KeyspaceEvents::GENERIC));
+        assert_eq!(events.to_string(), "$xK");
+
+        let all: KeyspaceEvents = "EAm".parse().unwrap();
+        assert_eq!(all.to_string(), "AEm");
+        assert!(!""
+            .parse::<KeyspaceEvents>()
+            .unwrap()
+            .publishes(KeyspaceEvents::ALL));
+    }
+
+    #[test]
+    fn parse_invalid_keyspace_events_fails() {
+        assert!("KQ".parse::<KeyspaceEvents>().is_err());
+    }
+
+    #[test]
+    fn apply_save_points_correctly() {
+        let mut config = Config::default();
+        config.apply_file("save 900 1\nsave 300 10\n").unwrap();
+
+        assert_eq!(config.get("save"), Some("900 1 300 10".to_string()));
+        assert_eq!(
+            config.rewrite(""),
+            "# Generated by CONFIG REWRITE\nsave 900 1\nsave 300 10\n"
+        );
+
+        config.apply_file("save \"\"\n").unwrap();
+        assert!(config.save.is_empty());
+        assert!(config.set("save", "900").is_err());
+    }
+}
@@ -0,0 +1,42 @@
+// CRC-16 CCITT in its XMODEM variant, as Redis Cluster hashes keys to
+// slots with it
+
+const POLYNOMIAL: u16 = 0x1021;
+
+const TABLE: [u16; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ POLYNOMIAL
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+pub fn checksum(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0, |crc, byte| {
+        (crc << 8) ^ TABLE[((crc >> 8) ^ *byte as u16) as usize]
+    })
+}
+
+#[cfg(test)]
+mod crc16_tests {
+    use super::checksum;
+
+    #[test]
+    fn checksum_correctly() {
+        // the check value in the Redis Cluster specification
+        assert_eq!(checksum(b"123456789"), 0x31c3);
+        assert_eq!(checksum(b""), 0);
+    }
+}
@@ -0,0 +1,43 @@
+// CRC-64 with the Jones coefficients, reflected, as Redis checksums RDB
+// files with it
+
+const POLYNOMIAL: u64 = 0x95ac_9329_ac4b_c9b5;
+
+const TABLE: [u64; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u64;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ POLYNOMIAL
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+// Continues `crc` over `bytes`, starting from 0 for a new checksum
+pub fn update(crc: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(crc, |crc, byte| {
+        TABLE[((crc ^ *byte as u64) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod crc64_tests {
+    use super::update;
+
+    #[test]
+    fn checksum_correctly() {
+        // the check value in Redis' crc64 tests
+        assert_eq!(update(0, b"123456789"), 0xe9c6_d914_c4b8_d9ca);
+        assert_eq!(update(update(0, b"1234"), b"56789"), 0xe9c6_d914_c4b8_d9ca);
+    }
+}
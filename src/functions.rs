@@ -0,0 +1,337 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+
+use crate::{
+    commands::{CommandContext, RedisCommandError},
+    resp::RESPValues,
+};
+
+// Runs a function with the keys and the other arguments FCALL passed it
+pub type FunctionHandler = Box<
+    dyn Fn(&[Bytes], &[Bytes], &mut CommandContext) -> Result<RESPValues, RedisCommandError>
+        + Send
+        + Sync,
+>;
+
+// A function a library registers, see Engine::load
+pub struct Function {
+    pub name: String,
+    pub description: Option<String>,
+    // declared with the no-writes flag, so FCALL_RO may call it and it may
+    // only call commands that don't write
+    pub no_writes: bool,
+    pub handler: FunctionHandler,
+}
+
+// Turns the code of a library, after its `#!<engine> name=<library>` line,
+// into the functions it registers. Engines are added by modules, see
+// CommandRegistry::register_engine
+pub trait Engine: Send + Sync {
+    fn load(&self, code: &str) -> Result<Vec<Function>, String>;
+}
+
+pub struct Library {
+    pub name: String,
+    pub engine: String,
+    pub code: Bytes,
+    pub functions: Vec<Arc<Function>>,
+}
+
+// What a connection's running function may do, see CommandRegistry::call
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct ScriptCall {
+    pub no_writes: bool,
+}
+
+// The libraries FUNCTION LOAD registered, with their functions by name, and
+// the functions FCALL is running by client
+#[derive(Default)]
+pub struct Libraries {
+    state: Mutex<State>,
+    runs: Mutex<HashMap<u64, Run>>,
+    // how many runs there are, so commands needn't lock them to check
+    running: AtomicUsize,
+}
+
+struct Run {
+    since: Instant,
+    // a function that wrote can't be killed, it would leave its writes half done
+    wrote: bool,
+    // asked to stop by FUNCTION KILL, which the next command it calls does
+    killed: bool,
+}
+
+#[derive(Default)]
+struct State {
+    libraries: BTreeMap<String, Arc<Library>>,
+    functions: HashMap<String, Arc<Function>>,
+}
+
+impl Libraries {
+    // Adds `library`, or with `replace` puts it in the place of the one of
+    // the same name. Its functions can't be named like another library's
+    pub fn load(&self, library: Library, replace: bool) -> Result<(), String> {
+        if library.functions.is_empty() {
+            return Err("No functions registered".to_string());
+        }
+        let mut state = self.state.lock().unwrap();
+        if !replace && state.libraries.contains_key(&library.name) {
+            return Err(format!("Library '{}' already exists", library.name));
+        }
+        // the library it replaces may have functions of the same names
+        let replaced = state.libraries.get(&library.name);
+        for (i, function) in library.functions.iter().enumerate() {
+            if !valid_name(&function.name) {
+                return Err("Function names can only contain letters, numbers, or underscores(_) and must be at least one character long".to_string());
+            }
+            if library.functions[..i]
+                .iter()
+                .any(|other| other.name == function.name)
+            {
+                return Err("Function already exists in the library".to_string());
+            }
+            let taken = state.functions.get(&function.name).is_some_and(|other| {
+                !replaced.is_some_and(|replaced| {
+                    replaced.functions.iter().any(|own| Arc::ptr_eq(own, other))
+                })
+            });
+            if taken {
+                return Err(format!("Function {} already exists", function.name));
+            }
+        }
+
+        let library = Arc::new(library);
+        if let Some(old) = state
+            .libraries
+            .insert(library.name.clone(), library.clone())
+        {
+            for function in &old.functions {
+                state.functions.remove(&function.name);
+            }
+        }
+        for function in &library.functions {
+            state
+                .functions
+                .insert(function.name.clone(), function.clone());
+        }
+        Ok(())
+    }
+
+    pub fn function(&self, name: &str) -> Option<Arc<Function>> {
+        self.state.lock().unwrap().functions.get(name).cloned()
+    }
+
+    // The libraries in name order
+    pub fn libraries(&self) -> Vec<Arc<Library>> {
+        let state = self.state.lock().unwrap();
+        state.libraries.values().cloned().collect()
+    }
+
+    // Whether there was a library by that name
+    pub fn delete(&self, name: &str) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let Some(library) = state.libraries.remove(name) else {
+            return false;
+        };
+        for function in &library.functions {
+            state.functions.remove(&function.name);
+        }
+        true
+    }
+
+    pub fn flush(&self) {
+        *self.state.lock().unwrap() = State::default();
+    }
+
+    // Around the function FCALL runs for `client`
+    pub fn start(&self, client: u64) {
+        let run = Run {
+            since: Instant::now(),
+            wrote: false,
+            killed: false,
+        };
+        if self.runs.lock().unwrap().insert(client, run).is_none() {
+            self.running.fetch_add(1, Ordering::AcqRel);
+        }
+    }
+
+    pub fn finish(&self, client: u64) {
+        if self.runs.lock().unwrap().remove(&client).is_some() {
+            self.running.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+
+    pub fn wrote(&self, client: u64) {
+        if let Some(run) = self.runs.lock().unwrap().get_mut(&client) {
+            run.wrote = true;
+        }
+    }
+
+    pub fn killed(&self, client: u64) -> bool {
+        let runs = self.runs.lock().unwrap();
+        runs.get(&client).is_some_and(|run| run.killed)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Acquire) > 0
+    }
+
+    // Whether a function of another client has run for longer than `timeout`
+    pub fn busy(&self, client: u64, timeout: Duration) -> bool {
+        self.is_running()
+            && self
+                .runs
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|(&id, run)| id != client && run.since.elapsed() >= timeout)
+    }
+
+    // Asks the running functions to stop, unless one of them already wrote
+    pub fn kill(&self) -> Result<(), RedisCommandError> {
+        let mut runs = self.runs.lock().unwrap();
+        if runs.is_empty() {
+            return Err(RedisCommandError::NotBusy);
+        }
+        if runs.values().any(|run| run.wrote) {
+            return Err(RedisCommandError::Unkillable);
+        }
+        for run in runs.values_mut() {
+            run.killed = true;
+        }
+        Ok(())
+    }
+}
+
+// Library and function names, as Redis allows them
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Registers a function for each line of the code, `<name> [no-writes]`, that
+// runs its arguments, then its keys, as a command
+#[cfg(test)]
+pub(crate) struct TestEngine;
+
+#[cfg(test)]
+impl Engine for TestEngine {
+    fn load(&self, code: &str) -> Result<Vec<Function>, String> {
+        code.lines()
+            .map(
+                |line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                    [name] | [name, "no-writes"] => Ok(Function {
+                        name: name.to_string(),
+                        description: None,
+                        no_writes: line.ends_with("no-writes"),
+                        handler: Box::new(|keys, args, ctx| {
+                            let command = [args, keys].concat();
+                            ctx.server.commands.call(&command, ctx)
+                        }),
+                    }),
+                    _ => Err(format!("Invalid function: {line}")),
+                },
+            )
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod libraries_tests {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+
+    use super::{Function, Libraries, Library};
+    use crate::{commands::RedisCommandError, resp::RESPValues};
+
+    fn library(name: &str, functions: &[&str]) -> Library {
+        Library {
+            name: name.to_string(),
+            engine: "test".to_string(),
+            code: Bytes::new(),
+            functions: functions
+                .iter()
+                .map(|name| {
+                    Function {
+                        name: name.to_string(),
+                        description: None,
+                        no_writes: false,
+                        handler: Box::new(|_, _, _| Ok(RESPValues::Integer(1))),
+                    }
+                    .into()
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn load_and_replace_libraries_correctly() {
+        let libraries = Libraries::default();
+        libraries.load(library("lib", &["f", "g"]), false).unwrap();
+        assert!(libraries.function("g").is_some());
+
+        libraries.load(library("lib", &["f"]), true).unwrap();
+        assert!(libraries.function("f").is_some());
+        assert!(libraries.function("g").is_none());
+        assert_eq!(libraries.libraries().len(), 1);
+
+        assert!(libraries.delete("lib"));
+        assert!(libraries.function("f").is_none());
+        assert!(!libraries.delete("lib"));
+    }
+
+    #[test]
+    fn load_conflicting_libraries_fails() {
+        let libraries = Libraries::default();
+        libraries.load(library("lib", &["f"]), false).unwrap();
+
+        assert_eq!(
+            libraries.load(library("lib", &["g"]), false),
+            Err("Library 'lib' already exists".to_string())
+        );
+        assert_eq!(
+            libraries.load(library("other", &["f"]), false),
+            Err("Function f already exists".to_string())
+        );
+        assert_eq!(
+            libraries.load(library("empty", &[]), false),
+            Err("No functions registered".to_string())
+        );
+        assert!(libraries.load(library("bad", &["no-dash"]), false).is_err());
+    }
+
+    #[test]
+    fn busy_and_kill_a_running_function_correctly() {
+        let libraries = Libraries::default();
+        assert_eq!(libraries.kill(), Err(RedisCommandError::NotBusy));
+
+        libraries.start(1);
+        assert!(libraries.busy(2, Duration::ZERO));
+        assert!(!libraries.busy(1, Duration::ZERO));
+        assert!(!libraries.busy(2, Duration::from_secs(60)));
+
+        assert_eq!(libraries.kill(), Ok(()));
+        assert!(libraries.killed(1));
+        libraries.finish(1);
+        assert!(!libraries.is_running());
+        assert!(!libraries.busy(2, Duration::ZERO));
+    }
+
+    #[test]
+    fn kill_a_function_that_wrote_fails() {
+        let libraries = Libraries::default();
+        libraries.start(1);
+        libraries.wrote(1);
+
+        assert_eq!(libraries.kill(), Err(RedisCommandError::Unkillable));
+        assert!(!libraries.killed(1));
+    }
+}
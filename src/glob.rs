@@ -0,0 +1,193 @@
+// Glob-style pattern matching with the same syntax as Redis' stringmatchlen:
+// `*`, `?`, `[abc]`, `[^abc]`, `[a-z]` and `\` to escape the next character.
+//
+// Unlike the recursive matcher in Redis this one keeps a single backtrack
+// point for the last `*` seen, so matching is O(pattern * string) even for
+// patterns with many nested wildcards.
+pub fn string_match(pattern: &[u8], string: &[u8], nocase: bool) -> bool {
+    let mut p = 0;
+    let mut s = 0;
+    // pattern position right after the last `*` and the string position it is tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while s < string.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            p += 1;
+            backtrack = Some((p, s));
+            continue;
+        }
+
+        if p < pattern.len() {
+            if let Some(next) = match_one(pattern, p, string[s], nocase) {
+                p = next;
+                s += 1;
+                continue;
+            }
+        }
+
+        match backtrack {
+            Some((star_p, star_s)) => {
+                p = star_p;
+                s = star_s + 1;
+                backtrack = Some((star_p, s));
+            }
+            None => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == b'*')
+}
+
+// Runs the matcher over random patterns and strings built from the glob
+// metacharacters, returning how many of them matched. Used by
+// DEBUG STRINGMATCH-LEN and meant to be driven by external fuzzers as well.
+pub fn fuzz_string_match(iterations: usize, seed: u64) -> usize {
+    const ALPHABET: &[u8] = b"ab*?[]^-\\";
+    let mut rng = XorShift(seed | 1);
+    let mut matches = 0;
+
+    for _ in 0..iterations {
+        let pattern: Vec<u8> = (0..rng.next() % 32)
+            .map(|_| ALPHABET[(rng.next() % ALPHABET.len() as u64) as usize])
+            .collect();
+        let string: Vec<u8> = (0..rng.next() % 32)
+            .map(|_| ALPHABET[(rng.next() % ALPHABET.len() as u64) as usize])
+            .collect();
+
+        if string_match(&pattern, &string, rng.next() & 1 == 0) {
+            matches += 1;
+        }
+    }
+
+    matches
+}
+
+// Returns the position after the pattern token at `p` if it matches `c`
+fn match_one(pattern: &[u8], p: usize, c: u8, nocase: bool) -> Option<usize> {
+    match pattern[p] {
+        b'?' => Some(p + 1),
+        b'[' => {
+            let (matched, next) = match_class(pattern, p + 1, c, nocase);
+            matched.then_some(next)
+        }
+        b'\\' if p + 1 < pattern.len() => eq(pattern[p + 1], c, nocase).then_some(p + 2),
+        literal => eq(literal, c, nocase).then_some(p + 1),
+    }
+}
+
+// Matches `c` against the class starting right after `[`. An unterminated
+// class extends to the end of the pattern, as it does in Redis.
+fn match_class(pattern: &[u8], mut i: usize, c: u8, nocase: bool) -> (bool, usize) {
+    let negate = i < pattern.len() && pattern[i] == b'^';
+    if negate {
+        i += 1;
+    }
+
+    let mut matched = false;
+    while i < pattern.len() && pattern[i] != b']' {
+        if pattern[i] == b'\\' && i + 1 < pattern.len() {
+            matched |= eq(pattern[i + 1], c, nocase);
+            i += 2;
+        } else if i + 2 < pattern.len() && pattern[i + 1] == b'-' {
+            let (mut start, mut end) = (pattern[i], pattern[i + 2]);
+            let mut c = c;
+            if start > end {
+                std::mem::swap(&mut start, &mut end);
+            }
+            if nocase {
+                start = start.to_ascii_lowercase();
+                end = end.to_ascii_lowercase();
+                c = c.to_ascii_lowercase();
+            }
+            matched |= start <= c && c <= end;
+            i += 3;
+        } else {
+            matched |= eq(pattern[i], c, nocase);
+            i += 1;
+        }
+    }
+
+    // skip the closing bracket
+    if i < pattern.len() {
+        i += 1;
+    }
+
+    (matched != negate, i)
+}
+
+fn eq(a: u8, b: u8, nocase: bool) -> bool {
+    if nocase {
+        a.eq_ignore_ascii_case(&b)
+    } else {
+        a == b
+    }
+}
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod string_match_tests {
+    use std::time::{Duration, Instant};
+
+    use super::{fuzz_string_match, string_match};
+
+    #[test]
+    fn match_literal_correctly() {
+        assert!(string_match(b"hello", b"hello", false));
+        assert!(!string_match(b"hello", b"hellO", false));
+        assert!(string_match(b"hello", b"hellO", true));
+    }
+
+    #[test]
+    fn match_wildcards_correctly() {
+        assert!(string_match(b"*", b"", false));
+        assert!(string_match(b"h*o", b"hello", false));
+        assert!(string_match(b"h?llo", b"hallo", false));
+        assert!(!string_match(b"h?llo", b"hllo", false));
+        assert!(string_match(b"user:*:name", b"user:1000:name", false));
+        assert!(!string_match(b"user:*:name", b"user:1000:email", false));
+    }
+
+    #[test]
+    fn match_classes_correctly() {
+        assert!(string_match(b"h[ae]llo", b"hello", false));
+        assert!(!string_match(b"h[ae]llo", b"hillo", false));
+        assert!(string_match(b"h[^e]llo", b"hallo", false));
+        assert!(!string_match(b"h[^e]llo", b"hello", false));
+        assert!(string_match(b"h[a-b]llo", b"hbllo", false));
+        assert!(string_match(b"h[b-a]llo", b"hbllo", false));
+        assert!(string_match(b"h[A-B]llo", b"hbllo", true));
+    }
+
+    #[test]
+    fn match_escaped_characters_correctly() {
+        assert!(string_match(b"h\\*llo", b"h*llo", false));
+        assert!(!string_match(b"h\\*llo", b"hello", false));
+        assert!(string_match(b"[\\]]", b"]", false));
+        assert!(string_match(b"a\\", b"a\\", false));
+    }
+
+    #[test]
+    fn match_nested_wildcards_in_linear_time() {
+        let pattern = b"a*a*a*a*a*a*a*a*a*a*a*a*a*a*a*a*a*a*a*a*b";
+        let string = vec![b'a'; 10_000];
+        let start = Instant::now();
+
+        assert!(!string_match(pattern, &string, false));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn fuzz_string_match_does_not_panic() {
+        fuzz_string_match(10_000, 42);
+    }
+}
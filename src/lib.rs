@@ -1,2 +1,22 @@
+pub mod aof;
+pub mod cluster;
+pub mod codec;
 pub mod commands;
+pub mod config;
+pub mod crc16;
+pub mod crc64;
+pub mod functions;
+pub mod glob;
+pub mod lzf;
+pub mod pool;
+pub mod pubsub;
+#[cfg(any(test, feature = "arbitrary", feature = "proptest"))]
+pub mod random;
+pub mod rdb;
+pub mod replay;
+pub mod replication;
 pub mod resp;
+pub mod scripts;
+pub mod server;
+pub mod store;
+pub mod tls;
@@ -0,0 +1,165 @@
+// LZF compression as liblzf does it, which is how Redis compresses strings
+// in RDB files. The output is a sequence of literal runs, a control byte
+// below 32 followed by that many plus one bytes, and back references to the
+// last 8kb, whose control byte holds the length in its top 3 bits.
+
+const HASH_LOG: u32 = 14;
+const MAX_LITERAL: usize = 1 << 5;
+const MAX_OFFSET: usize = 1 << 13;
+const MAX_REFERENCE: usize = (1 << 8) + (1 << 3);
+// a back reference of 3 bytes expands to at most MAX_REFERENCE of them
+const MAX_EXPANSION: usize = MAX_REFERENCE / 3;
+
+// None when `input` doesn't compress to at most `max_length` bytes
+pub fn compress(input: &[u8], max_length: usize) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(max_length + MAX_LITERAL);
+    let mut table = vec![usize::MAX; 1 << HASH_LOG];
+    let mut literals = 0;
+    out.push(0);
+
+    let mut i = 0;
+    while i < input.len() {
+        let candidate = (i + 2 < input.len())
+            .then(|| {
+                let slot = hash(&input[i..i + 3]);
+                std::mem::replace(&mut table[slot], i)
+            })
+            .filter(|&c| c != usize::MAX && i - c <= MAX_OFFSET)
+            .filter(|&c| input[c..c + 3] == input[i..i + 3]);
+
+        match candidate {
+            Some(start) => {
+                let longest = (input.len() - i).min(MAX_REFERENCE);
+                let mut length = 3;
+                while length < longest && input[start + length] == input[i + length] {
+                    length += 1;
+                }
+                end_literals(&mut out, literals);
+
+                let offset = i - start - 1;
+                let encoded = length - 2;
+                let top = (offset >> 8) as u8;
+                if encoded < 7 {
+                    out.push(top | (encoded as u8) << 5);
+                } else {
+                    out.push(top | 7 << 5);
+                    out.push((encoded - 7) as u8);
+                }
+                out.push(offset as u8);
+
+                out.push(0);
+                literals = 0;
+                i += length;
+            }
+            None => {
+                out.push(input[i]);
+                literals += 1;
+                i += 1;
+                if literals == MAX_LITERAL {
+                    end_literals(&mut out, literals);
+                    out.push(0);
+                    literals = 0;
+                }
+            }
+        }
+        if out.len() > max_length + 1 {
+            return None;
+        }
+    }
+
+    end_literals(&mut out, literals);
+    (out.len() <= max_length).then_some(out)
+}
+
+// Fills in the control byte of the run of `count` literals out ends with,
+// dropping it if the run is empty
+fn end_literals(out: &mut Vec<u8>, count: usize) {
+    if count == 0 {
+        out.pop();
+    } else {
+        let control = out.len() - count - 1;
+        out[control] = (count - 1) as u8;
+    }
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let value = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+    (value.wrapping_mul(2654435761) >> (32 - HASH_LOG)) as usize
+}
+
+// None when `input` isn't valid LZF or doesn't expand to `length` bytes.
+// The length comes with the input, so it's checked against what the input
+// could expand to before anything is allocated for it
+pub fn decompress(input: &[u8], length: usize) -> Option<Vec<u8>> {
+    if length > input.len().saturating_mul(MAX_EXPANSION) {
+        return None;
+    }
+    let mut out = Vec::with_capacity(length);
+    let mut i = 0;
+
+    while i < input.len() {
+        let control = input[i] as usize;
+        i += 1;
+        if control < MAX_LITERAL {
+            let run = input.get(i..i + control + 1)?;
+            out.extend_from_slice(run);
+            i += run.len();
+        } else {
+            let mut count = control >> 5;
+            if count == 7 {
+                count += *input.get(i)? as usize;
+                i += 1;
+            }
+            let back = ((control & 0x1f) << 8 | *input.get(i)? as usize) + 1;
+            i += 1;
+            let start = out.len().checked_sub(back)?;
+            // the reference may overlap what it is copying, so byte by byte
+            for k in 0..count + 2 {
+                out.push(out[start + k]);
+            }
+        }
+        if out.len() > length {
+            return None;
+        }
+    }
+
+    (out.len() == length).then_some(out)
+}
+
+#[cfg(test)]
+mod lzf_tests {
+    use super::{compress, decompress};
+
+    #[test]
+    fn compress_and_decompress_correctly() {
+        let input = b"abcabcabcabcabcabcabcabc hello hello hello hello".repeat(50);
+        let compressed = compress(&input, input.len()).unwrap();
+
+        assert!(compressed.len() < input.len() / 4);
+        assert_eq!(decompress(&compressed, input.len()), Some(input));
+    }
+
+    #[test]
+    fn compress_long_literal_runs_correctly() {
+        let input: Vec<u8> = (0..=255).cycle().take(300).collect();
+        let compressed = compress(&input, input.len() + 16).unwrap();
+
+        assert_eq!(decompress(&compressed, input.len()), Some(input));
+    }
+
+    #[test]
+    fn compress_incompressible_input_fails() {
+        let input: Vec<u8> = (0..=255).collect();
+
+        assert_eq!(compress(&input, input.len() - 4), None);
+    }
+
+    #[test]
+    fn decompress_invalid_input_fails() {
+        // a back reference before the start of the output
+        assert_eq!(decompress(&[0x20, 0x05], 3), None);
+        assert_eq!(decompress(&[0x02, b'a'], 3), None);
+        // far more than two bytes could expand to
+        assert_eq!(decompress(&[0x00, b'a'], 1 << 40), None);
+    }
+}
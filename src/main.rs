@@ -1,72 +1,240 @@
-use std::io;
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+};
 
+use clap::Parser;
 use redis_clone::{
-    commands::{RedisCommand, RedisCommandError},
-    resp::RESPValues,
+    config::{self, Config, LogLevel, TlsAuthClients},
+    replay::{self, Recorder},
+    server::{RedisServer, ShutdownHandle},
+};
+#[cfg(unix)]
+use {
+    redis_clone::server::Shared,
+    std::sync::Arc,
+    tokio::signal::unix::{signal, SignalKind},
 };
-use tokio::net::{TcpListener, TcpStream};
 
-#[tokio::main]
-async fn main() -> io::Result<()> {
-    let port = 6379;
-    let server = TcpListener::bind(("127.0.0.1", port)).await?;
+// Server flags left out fall back to the defaults in Config
+#[derive(Parser)]
+struct Args {
+    /// redis.conf style file loaded before the flags below are applied
+    config_file: Option<PathBuf>,
+    #[arg(long)]
+    port: Option<u16>,
+    /// Address to listen on
+    #[arg(long)]
+    bind: Option<IpAddr>,
+    /// Also listen on this unix socket
+    #[arg(long)]
+    unixsocket: Option<PathBuf>,
+    /// Octal mode the unix socket is created with, such as 700
+    #[arg(long, value_parser = config::parse_permissions)]
+    unixsocketperm: Option<u32>,
+    /// Working directory the database file is written to
+    #[arg(long)]
+    dir: Option<PathBuf>,
+    #[arg(long)]
+    dbfilename: Option<String>,
+    /// Memory limit such as 100mb or 2gb, 0 for none
+    #[arg(long, value_parser = config::parse_memory)]
+    maxmemory: Option<u64>,
+    /// Seconds between keepalive probes on idle clients, 0 for none
+    #[arg(long)]
+    tcp_keepalive: Option<u64>,
+    /// Disable Nagle's algorithm on client sockets, yes or no
+    #[arg(long, value_parser = config::parse_yes_no)]
+    tcp_nodelay: Option<bool>,
+    /// Threads serving connections, each running its own runtime
+    #[arg(long)]
+    io_threads: Option<usize>,
+    /// Most clients connected at once
+    #[arg(long)]
+    maxclients: Option<usize>,
+    /// One of debug, verbose, notice or warning
+    #[arg(long)]
+    loglevel: Option<LogLevel>,
+    /// Also accept TLS connections on this port
+    #[arg(long)]
+    tls_port: Option<u16>,
+    #[arg(long)]
+    tls_cert_file: Option<PathBuf>,
+    #[arg(long)]
+    tls_key_file: Option<PathBuf>,
+    /// CA that client certificates are verified against
+    #[arg(long)]
+    tls_ca_cert_file: Option<PathBuf>,
+    /// One of yes, no or optional
+    #[arg(long)]
+    tls_auth_clients: Option<TlsAuthClients>,
+    /// Record every inbound command to this file
+    #[arg(long)]
+    record: Option<PathBuf>,
+    /// Replay a recording against --replay-target instead of serving
+    #[arg(long, conflicts_with = "record")]
+    replay: Option<PathBuf>,
+    #[arg(long, default_value = "127.0.0.1:6379")]
+    replay_target: SocketAddr,
+    /// Replay speed multiplier, 2.0 replays twice as fast as recorded
+    #[arg(long, default_value_t = 1.0)]
+    replay_speed: f64,
+    /// Largest bulk string accepted from clients, such as 512mb
+    #[arg(long, value_parser = config::parse_memory)]
+    proto_max_bulk_len: Option<u64>,
+    /// Deepest nesting of aggregate types accepted from clients
+    #[arg(long)]
+    proto_max_nesting: Option<usize>,
+    /// Largest incomplete request buffered per client, such as 1gb
+    #[arg(long, value_parser = config::parse_memory)]
+    client_query_buffer_limit: Option<u64>,
+}
 
-    loop {
-        match server.accept().await {
-            Err(_) => eprintln!("Error at accepting connection"),
-            Ok((stream, _)) => {
-                tokio::spawn(accept_connection(stream));
-            }
+impl Args {
+    fn apply(&self, config: &mut Config) {
+        if let Some(port) = self.port {
+            config.port = port;
+        }
+        if let Some(bind) = self.bind {
+            config.bind = bind;
+        }
+        if let Some(unixsocket) = &self.unixsocket {
+            config.unixsocket = Some(unixsocket.clone());
+        }
+        if let Some(mode) = self.unixsocketperm {
+            config.unixsocketperm = mode;
+        }
+        if let Some(dir) = &self.dir {
+            config.dir = dir.clone();
+        }
+        if let Some(dbfilename) = &self.dbfilename {
+            config.dbfilename = dbfilename.clone();
+        }
+        if let Some(maxmemory) = self.maxmemory {
+            config.maxmemory = maxmemory;
+        }
+        if let Some(seconds) = self.tcp_keepalive {
+            config.tcp_keepalive = seconds;
+        }
+        if let Some(nodelay) = self.tcp_nodelay {
+            config.tcp_nodelay = nodelay;
+        }
+        if let Some(threads) = self.io_threads {
+            config.io_threads = threads;
+        }
+        if let Some(maxclients) = self.maxclients {
+            config.maxclients = maxclients;
+        }
+        if let Some(loglevel) = self.loglevel {
+            config.loglevel = loglevel;
+        }
+        if let Some(port) = self.tls_port {
+            config.tls_port = Some(port);
+        }
+        if let Some(path) = &self.tls_cert_file {
+            config.tls_cert_file = Some(path.clone());
+        }
+        if let Some(path) = &self.tls_key_file {
+            config.tls_key_file = Some(path.clone());
+        }
+        if let Some(path) = &self.tls_ca_cert_file {
+            config.tls_ca_cert_file = Some(path.clone());
+        }
+        if let Some(auth) = self.tls_auth_clients {
+            config.tls_auth_clients = auth;
+        }
+        if let Some(length) = self.proto_max_bulk_len {
+            config.limits.max_bulk_length = length as usize;
+        }
+        if let Some(depth) = self.proto_max_nesting {
+            config.limits.max_nesting_depth = depth;
+        }
+        if let Some(length) = self.client_query_buffer_limit {
+            config.limits.max_query_buffer_length = length as usize;
         }
     }
 }
 
-async fn accept_connection(conn: TcpStream) -> io::Result<()> {
-    loop {
-        let mut buf = [0; 512];
-        let command = match conn.try_read(&mut buf) {
-            Ok(0) => break,
-            Ok(_) => {
-                let command = String::from_utf8_lossy(&buf).to_string();
-                let parsed_command = parse_command(command);
-                parsed_command
-            }
-            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => continue,
-            Err(e) => return Err(e),
-        };
+#[tokio::main]
+async fn main() -> io::Result<()> {
+    let args = Args::parse();
+
+    if let Some(path) = args.replay {
+        let commands = replay::load(path)?;
+        return replay::replay(&commands, args.replay_target, args.replay_speed).await;
+    }
 
-        if let Err(error) = command {
-            reply_error_to_client(error, &conn).expect("couldn't reply to client");
-        } else {
-            reply_command_to_client(command.ok().unwrap(), &conn)
-                .expect("couldn't respond to client");
+    let mut config = match &args.config_file {
+        Some(path) => {
+            let (config, ignored) = Config::load(path)?;
+            if config.loglevel <= LogLevel::Warning {
+                for name in ignored {
+                    eprintln!("Ignoring unsupported config directive '{name}'");
+                }
+            }
+            config
         }
+        None => Config::default(),
+    };
+    args.apply(&mut config);
 
-        // responds_to_client(command, &conn).expect("couldn't respond to client");
+    let mut builder = RedisServer::builder().config(config);
+    if let Some(path) = args.record {
+        builder = builder.recorder(Recorder::create(path)?);
     }
 
-    Ok(())
-}
+    let server = builder.build().await?;
+    #[cfg(unix)]
+    tokio::spawn(reload_on_hangup(server.shared().clone()));
+    tokio::spawn(shutdown_on_termination(server.shutdown_handle()));
 
-fn parse_command(command: String) -> Result<RedisCommand, RedisCommandError> {
-    let client_input = RESPValues::try_from(command.as_str()).expect("couldn't parse client input");
-    RedisCommand::try_from(client_input.clone())
+    // what io_uring can't serve falls back to tokio's backend
+    #[cfg(all(feature = "uring", target_os = "linux"))]
+    if server.uring_supported() {
+        return server.run_uring().await;
+    } else if server.shared().config.read().unwrap().loglevel <= LogLevel::Notice {
+        eprintln!("TLS, unix sockets and io-threads aren't served on io_uring, using tokio");
+    }
+    server.run().await
 }
 
-fn reply_command_to_client(command: RedisCommand, conn: &TcpStream) -> io::Result<usize> {
-    match command {
-        RedisCommand::Ping(Some(v)) => conn.try_write(format!("+\"{v}\"\r\n").as_bytes()),
-        RedisCommand::Ping(_) => conn.try_write("+PONG\r\n".as_bytes()),
-        RedisCommand::Echo(v) => conn.try_write(format!("+\"{v}\"\r\n").as_bytes()),
-        _ => unimplemented!(),
+// Shuts the server down gracefully on SIGTERM or Ctrl-C
+async fn shutdown_on_termination(shutdown: ShutdownHandle) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            _ = terminate.recv() => {}
+            interrupted = tokio::signal::ctrl_c() => interrupted?,
+        }
     }
-    // conn.try_write("+PONG\r\n".as_bytes())
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await?;
+
+    shutdown.shutdown();
+    Ok(())
 }
 
-fn reply_error_to_client(command_error: RedisCommandError, conn: &TcpStream) -> io::Result<usize> {
-    match command_error {
-        RedisCommandError::NotImplemented => {
-            conn.try_write("+Command not implemented\r\n".as_bytes())
+// Re-reads the config file every time the process gets SIGHUP
+#[cfg(unix)]
+async fn reload_on_hangup(shared: Arc<Shared>) -> io::Result<()> {
+    let mut hangup = signal(SignalKind::hangup())?;
+
+    while hangup.recv().await.is_some() {
+        let result = shared.reload_config();
+        if shared.config.read().unwrap().loglevel > LogLevel::Warning {
+            continue;
+        }
+        match result {
+            Ok(skipped) => {
+                for name in skipped {
+                    eprintln!("Config reload skipped '{name}'");
+                }
+            }
+            Err(error) => eprintln!("Error at reloading config: {error}"),
         }
     }
+
+    Ok(())
 }
@@ -0,0 +1,104 @@
+use std::sync::Mutex;
+
+use bytes::BytesMut;
+
+// Buffers connections read into and write replies from, handed back when
+// they close so a busy server isn't allocating them with every connection
+pub struct BufferPool {
+    buffers: Mutex<Vec<BytesMut>>,
+    // every buffer comes out with at least this much room
+    capacity: usize,
+    max_buffers: usize,
+}
+
+impl BufferPool {
+    pub fn new(capacity: usize, max_buffers: usize) -> Self {
+        Self {
+            buffers: Mutex::new(Vec::new()),
+            capacity,
+            max_buffers,
+        }
+    }
+
+    pub fn take(&self) -> BytesMut {
+        let pooled = self.buffers.lock().unwrap().pop();
+        pooled.unwrap_or_else(|| BytesMut::with_capacity(self.capacity))
+    }
+
+    // Keeps `buffer` for a later `take` unless the pool is full or the buffer
+    // grew far past the usual capacity. Room given up by writes that advanced
+    // it is reclaimed first, which only allocates if frames still borrow it
+    pub fn put(&self, mut buffer: BytesMut) {
+        buffer.clear();
+        buffer.reserve(self.capacity);
+        if buffer.capacity() > self.capacity * 4 {
+            return;
+        }
+
+        let mut buffers = self.buffers.lock().unwrap();
+        if buffers.len() < self.max_buffers {
+            buffers.push(buffer);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffers.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod buffer_pool_tests {
+    use bytes::{Buf, BufMut, BytesMut};
+
+    use super::BufferPool;
+
+    #[test]
+    fn reuse_buffers_correctly() {
+        let pool = BufferPool::new(64, 2);
+        let mut buffer = pool.take();
+        buffer.put_slice(b"PING\r\n");
+        let pointer = buffer.as_ptr();
+        pool.put(buffer);
+
+        let reused = pool.take();
+        assert!(reused.is_empty());
+        assert_eq!(reused.as_ptr(), pointer);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn keep_at_most_max_buffers_correctly() {
+        let pool = BufferPool::new(64, 2);
+        for _ in 0..3 {
+            pool.put(BytesMut::with_capacity(64));
+        }
+
+        assert_eq!(pool.len(), 2);
+    }
+
+    #[test]
+    fn reclaim_advanced_buffers_correctly() {
+        let pool = BufferPool::new(64, 2);
+        let mut buffer = pool.take();
+        buffer.put_slice(&[b'a'; 64]);
+        buffer.advance(48);
+        let pointer = buffer.as_ptr();
+        pool.put(buffer);
+
+        let reused = pool.take();
+        assert!(reused.capacity() >= 64);
+        assert_eq!(reused.as_ptr(), pointer.wrapping_sub(48));
+    }
+
+    #[test]
+    fn drop_oversized_buffers_correctly() {
+        let pool = BufferPool::new(64, 2);
+        pool.put(BytesMut::with_capacity(1024));
+
+        assert!(pool.is_empty());
+    }
+}
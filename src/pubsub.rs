@@ -0,0 +1,338 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use bytes::Bytes;
+use tokio::sync::mpsc;
+
+use crate::{commands::ConnectionState, config::KeyspaceEvents, glob, resp::RESPValues};
+
+// Where a connection gets the messages published to it, as push frames it
+// writes out between replies
+pub type Subscriber = mpsc::UnboundedSender<RESPValues>;
+
+// Subscribers by channel, keyed by client id
+#[derive(Default)]
+pub struct Subscriptions {
+    subscribers: Mutex<HashMap<Bytes, HashMap<u64, Subscriber>>>,
+}
+
+impl Subscriptions {
+    pub fn subscribe(&self, name: Bytes, id: u64, subscriber: Subscriber) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.entry(name).or_default().insert(id, subscriber);
+    }
+
+    pub fn unsubscribe(&self, name: &[u8], id: u64) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        if let Some(clients) = subscribers.get_mut(name) {
+            clients.remove(&id);
+            if clients.is_empty() {
+                subscribers.remove(name);
+            }
+        }
+    }
+
+    // Names with at least one subscriber, only those matching `pattern` if any
+    pub fn names(&self, pattern: Option<&[u8]>) -> Vec<Bytes> {
+        let subscribers = self.subscribers.lock().unwrap();
+        subscribers
+            .keys()
+            .filter(|name| pattern.is_none_or(|pattern| glob::string_match(pattern, name, false)))
+            .cloned()
+            .collect()
+    }
+
+    pub fn count(&self, name: &[u8]) -> usize {
+        let subscribers = self.subscribers.lock().unwrap();
+        subscribers.get(name).map_or(0, HashMap::len)
+    }
+
+    pub fn len(&self) -> usize {
+        self.subscribers.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Sends `message()` to everyone subscribed to `name`, returning how many
+    // subscribers it reached
+    pub fn send(&self, name: &[u8], message: impl Fn() -> RESPValues) -> usize {
+        let subscribers = self.subscribers.lock().unwrap();
+        subscribers
+            .get(name)
+            .map_or(0, |clients| send_all(clients, message()))
+    }
+
+    // Sends `message(pattern)` to everyone subscribed to a pattern `channel` matches
+    pub fn send_matching(&self, channel: &[u8], message: impl Fn(&Bytes) -> RESPValues) -> usize {
+        let subscribers = self.subscribers.lock().unwrap();
+        subscribers
+            .iter()
+            .filter(|(pattern, _)| glob::string_match(pattern, channel, false))
+            .map(|(pattern, clients)| send_all(clients, message(pattern)))
+            .sum()
+    }
+}
+
+// a client that has just disconnected still counts, as in Redis
+fn send_all(clients: &HashMap<u64, Subscriber>, message: RESPValues) -> usize {
+    for subscriber in clients.values() {
+        let _ = subscriber.send(message.clone());
+    }
+    clients.len()
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum SubscriptionKind {
+    Channel,
+    Pattern,
+    // the cluster flavour of channels, counted and published to separately
+    ShardChannel,
+}
+
+impl SubscriptionKind {
+    // the first element of the frames confirming a subscription or its end
+    pub fn confirmations(&self) -> (&'static str, &'static str) {
+        match self {
+            Self::Channel => ("subscribe", "unsubscribe"),
+            Self::Pattern => ("psubscribe", "punsubscribe"),
+            Self::ShardChannel => ("ssubscribe", "sunsubscribe"),
+        }
+    }
+}
+
+// Every pub/sub subscription across connections
+#[derive(Default)]
+pub struct PubSub {
+    pub channels: Subscriptions,
+    pub patterns: Subscriptions,
+    pub shard_channels: Subscriptions,
+}
+
+impl PubSub {
+    // Drops every subscription of a connection that is going away
+    pub fn disconnect(&self, connection: &ConnectionState) {
+        for kind in [
+            SubscriptionKind::Channel,
+            SubscriptionKind::Pattern,
+            SubscriptionKind::ShardChannel,
+        ] {
+            let subscriptions = self.subscriptions(kind);
+            for name in connection.subscribed(kind) {
+                subscriptions.unsubscribe(name, connection.id);
+            }
+        }
+    }
+
+    pub fn subscriptions(&self, kind: SubscriptionKind) -> &Subscriptions {
+        match kind {
+            SubscriptionKind::Channel => &self.channels,
+            SubscriptionKind::Pattern => &self.patterns,
+            SubscriptionKind::ShardChannel => &self.shard_channels,
+        }
+    }
+
+    // Delivers `message` to the subscribers of `channel` and of every pattern
+    // matching it, a client subscribed to both gets it twice
+    pub fn publish(&self, channel: &Bytes, message: &Bytes) -> usize {
+        let channel_value = RESPValues::BulkString(channel.clone());
+        let message_value = RESPValues::BulkString(message.clone());
+
+        let direct = self.channels.send(channel, || {
+            push(
+                "message",
+                vec![channel_value.clone(), message_value.clone()],
+            )
+        });
+        let matched = self.patterns.send_matching(channel, |pattern| {
+            let pattern = RESPValues::BulkString(pattern.clone());
+            push(
+                "pmessage",
+                vec![pattern, channel_value.clone(), message_value.clone()],
+            )
+        });
+        direct + matched
+    }
+
+    // Tells __keyspace@0__:<key> subscribers which `event` happened to `key`,
+    // and __keyevent@0__:<event> ones which key it happened to, as far as
+    // `events` enables them for `class`
+    pub fn notify(&self, events: KeyspaceEvents, class: KeyspaceEvents, event: &str, key: &Bytes) {
+        if !events.publishes(class) {
+            return;
+        }
+        if events.contains(KeyspaceEvents::KEYSPACE) {
+            let mut channel = b"__keyspace@0__:".to_vec();
+            channel.extend_from_slice(key);
+            self.publish(&channel.into(), &Bytes::copy_from_slice(event.as_bytes()));
+        }
+        if events.contains(KeyspaceEvents::KEYEVENT) {
+            let channel = format!("__keyevent@0__:{event}");
+            self.publish(&channel.into(), key);
+        }
+    }
+
+    // Shard channels never match patterns
+    pub fn publish_shard(&self, channel: &Bytes, message: &Bytes) -> usize {
+        self.shard_channels.send(channel, || {
+            push(
+                "smessage",
+                vec![
+                    RESPValues::BulkString(channel.clone()),
+                    RESPValues::BulkString(message.clone()),
+                ],
+            )
+        })
+    }
+}
+
+// A `kind` push frame followed by `values`, the shape of every pub/sub message
+pub fn push(kind: &'static str, values: Vec<RESPValues>) -> RESPValues {
+    let mut frame = vec![RESPValues::BulkString(Bytes::from_static(kind.as_bytes()))];
+    frame.extend(values);
+    RESPValues::Push(frame)
+}
+
+#[cfg(test)]
+mod pubsub_tests {
+    use bytes::Bytes;
+    use tokio::sync::mpsc;
+
+    use super::{push, PubSub};
+    use crate::{config::KeyspaceEvents, resp::RESPValues};
+
+    #[test]
+    fn publish_to_subscribers_correctly() {
+        let pubsub = PubSub::default();
+        let (first, mut first_messages) = mpsc::unbounded_channel();
+        let (second, mut second_messages) = mpsc::unbounded_channel();
+        let channel = Bytes::from_static(b"news");
+        pubsub.channels.subscribe(channel.clone(), 1, first);
+        pubsub.channels.subscribe(channel.clone(), 2, second);
+
+        assert_eq!(pubsub.publish(&channel, &Bytes::from_static(b"hi")), 2);
+        let expected = push(
+            "message",
+            vec![
+                RESPValues::BulkString(channel.clone()),
+                RESPValues::BulkString(Bytes::from_static(b"hi")),
+            ],
+        );
+        assert_eq!(first_messages.try_recv(), Ok(expected.clone()));
+        assert_eq!(second_messages.try_recv(), Ok(expected));
+    }
+
+    #[test]
+    fn publish_to_pattern_subscribers_correctly() {
+        let pubsub = PubSub::default();
+        let (subscriber, mut messages) = mpsc::unbounded_channel();
+        pubsub
+            .patterns
+            .subscribe(Bytes::from_static(b"news.*"), 1, subscriber.clone());
+        pubsub
+            .patterns
+            .subscribe(Bytes::from_static(b"sport.*"), 1, subscriber);
+
+        let channel = Bytes::from_static(b"news.tech");
+        assert_eq!(pubsub.publish(&channel, &Bytes::from_static(b"hi")), 1);
+        let expected = push(
+            "pmessage",
+            vec![
+                RESPValues::BulkString(Bytes::from_static(b"news.*")),
+                RESPValues::BulkString(channel),
+                RESPValues::BulkString(Bytes::from_static(b"hi")),
+            ],
+        );
+        assert_eq!(messages.try_recv(), Ok(expected));
+        assert!(messages.try_recv().is_err());
+    }
+
+    #[test]
+    fn publish_to_shard_channels_apart_correctly() {
+        let pubsub = PubSub::default();
+        let (subscriber, mut messages) = mpsc::unbounded_channel();
+        let channel = Bytes::from_static(b"news");
+        pubsub
+            .shard_channels
+            .subscribe(channel.clone(), 1, subscriber.clone());
+        pubsub
+            .patterns
+            .subscribe(Bytes::from_static(b"*"), 1, subscriber);
+
+        assert_eq!(
+            pubsub.publish_shard(&channel, &Bytes::from_static(b"hi")),
+            1
+        );
+        let expected = push(
+            "smessage",
+            vec![
+                RESPValues::BulkString(channel.clone()),
+                RESPValues::BulkString(Bytes::from_static(b"hi")),
+            ],
+        );
+        assert_eq!(messages.try_recv(), Ok(expected));
+        assert!(messages.try_recv().is_err());
+    }
+
+    #[test]
+    fn list_subscribed_names_correctly() {
+        let pubsub = PubSub::default();
+        let (subscriber, _messages) = mpsc::unbounded_channel();
+        let news = Bytes::from_static(b"news");
+        pubsub
+            .channels
+            .subscribe(news.clone(), 1, subscriber.clone());
+        pubsub
+            .channels
+            .subscribe(news.clone(), 2, subscriber.clone());
+        pubsub
+            .channels
+            .subscribe(Bytes::from_static(b"sport"), 1, subscriber);
+
+        assert_eq!(
+            pubsub.channels.names(Some(b"n*")),
+            std::slice::from_ref(&news)
+        );
+        assert_eq!(pubsub.channels.names(None).len(), 2);
+        assert_eq!(pubsub.channels.count(&news), 2);
+        assert_eq!(pubsub.channels.count(b"weather"), 0);
+    }
+
+    #[test]
+    fn notify_keyspace_events_correctly() {
+        let pubsub = PubSub::default();
+        let (subscriber, mut messages) = mpsc::unbounded_channel();
+        pubsub
+            .patterns
+            .subscribe(Bytes::from_static(b"__key*__:*"), 1, subscriber);
+        let key = Bytes::from_static(b"k");
+
+        let events = KeyspaceEvents::KEYEVENT | KeyspaceEvents::STRING;
+        pubsub.notify(events, KeyspaceEvents::GENERIC, "del", &key);
+        assert!(messages.try_recv().is_err());
+
+        pubsub.notify(events, KeyspaceEvents::STRING, "set", &key);
+        let expected = push(
+            "pmessage",
+            vec![
+                RESPValues::BulkString(Bytes::from_static(b"__key*__:*")),
+                RESPValues::BulkString(Bytes::from_static(b"__keyevent@0__:set")),
+                RESPValues::BulkString(key),
+            ],
+        );
+        assert_eq!(messages.try_recv(), Ok(expected));
+        assert!(messages.try_recv().is_err());
+    }
+
+    #[test]
+    fn stop_publishing_after_unsubscribe_correctly() {
+        let pubsub = PubSub::default();
+        let (subscriber, mut messages) = mpsc::unbounded_channel();
+        let channel = Bytes::from_static(b"news");
+        pubsub.channels.subscribe(channel.clone(), 1, subscriber);
+        pubsub.channels.unsubscribe(&channel, 1);
+
+        assert_eq!(pubsub.publish(&channel, &Bytes::from_static(b"hi")), 0);
+        assert!(messages.try_recv().is_err());
+    }
+}
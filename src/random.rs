@@ -0,0 +1,151 @@
+// Random RESP trees for property tests and fuzzing. Every generated value
+// survives `RESPValues::decode(&value.to_bytes())` unchanged, so simple strings
+// never contain CR or LF, doubles are never NaN and nesting stays well below
+// the decoder's default depth limit.
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impl {
+    use arbitrary::{Arbitrary, Result, Unstructured};
+
+    use crate::resp::RESPValues;
+
+    const MAX_DEPTH: usize = 4;
+
+    impl<'a> Arbitrary<'a> for RESPValues {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            arbitrary_value(u, MAX_DEPTH)
+        }
+    }
+
+    fn arbitrary_value(u: &mut Unstructured, depth: usize) -> Result<RESPValues> {
+        let kinds = if depth == 0 { 12 } else { 16 };
+
+        Ok(match u.choose_index(kinds)? {
+            0 => RESPValues::SimpleString(line(u)?),
+            1 => RESPValues::SimpleError(line(u)?),
+            2 => RESPValues::Integer(u.arbitrary()?),
+            3 => RESPValues::BulkString(Vec::<u8>::arbitrary(u)?.into()),
+            4 => RESPValues::NullBulkString,
+            5 => RESPValues::NullArray,
+            6 => RESPValues::Null,
+            7 => RESPValues::Boolean(u.arbitrary()?),
+            8 => {
+                let value: f64 = u.arbitrary()?;
+                RESPValues::Double(if value.is_nan() { 0.0 } else { value })
+            }
+            9 => RESPValues::BigNumber(u.arbitrary::<i128>()?.to_string()),
+            10 => RESPValues::BulkError(Vec::<u8>::arbitrary(u)?.into()),
+            11 => {
+                let encoding = (0..3)
+                    .map(|_| u.int_in_range(b'a'..=b'z').map(char::from))
+                    .collect::<Result<String>>()?;
+                RESPValues::VerbatimString(encoding, Vec::<u8>::arbitrary(u)?.into())
+            }
+            12 => RESPValues::Array(elements(u, depth)?),
+            13 => RESPValues::Set(elements(u, depth)?),
+            14 => RESPValues::Push(elements(u, depth)?),
+            _ => {
+                let len = u.int_in_range(0..=4)?;
+                let pairs = (0..len)
+                    .map(|_| {
+                        Ok((
+                            arbitrary_value(u, depth - 1)?,
+                            arbitrary_value(u, depth - 1)?,
+                        ))
+                    })
+                    .collect::<Result<_>>()?;
+                RESPValues::Map(pairs)
+            }
+        })
+    }
+
+    fn elements(u: &mut Unstructured, depth: usize) -> Result<Vec<RESPValues>> {
+        let len = u.int_in_range(0..=4)?;
+        (0..len).map(|_| arbitrary_value(u, depth - 1)).collect()
+    }
+
+    fn line(u: &mut Unstructured) -> Result<String> {
+        Ok(String::arbitrary(u)?.replace(['\r', '\n'], ""))
+    }
+}
+
+#[cfg(any(test, feature = "proptest"))]
+pub use proptest_impl::resp_values;
+
+#[cfg(any(test, feature = "proptest"))]
+mod proptest_impl {
+    use bytes::Bytes;
+    use proptest::{collection::vec, prelude::*};
+
+    use crate::resp::RESPValues;
+
+    pub fn resp_values() -> impl Strategy<Value = RESPValues> {
+        let line = || "[^\r\n]*";
+        let bytes = || vec(any::<u8>(), 0..32).prop_map(Bytes::from);
+        let leaf = prop_oneof![
+            line().prop_map(RESPValues::SimpleString),
+            line().prop_map(RESPValues::SimpleError),
+            any::<i64>().prop_map(RESPValues::Integer),
+            bytes().prop_map(RESPValues::BulkString),
+            Just(RESPValues::NullBulkString),
+            Just(RESPValues::NullArray),
+            Just(RESPValues::Null),
+            any::<bool>().prop_map(RESPValues::Boolean),
+            any::<f64>()
+                .prop_filter("NaN never equals itself", |v| !v.is_nan())
+                .prop_map(RESPValues::Double),
+            "[+-]?[0-9]{1,40}".prop_map(RESPValues::BigNumber),
+            bytes().prop_map(RESPValues::BulkError),
+            ("[a-z]{3}", bytes()).prop_map(|(e, v)| RESPValues::VerbatimString(e, v)),
+        ];
+
+        leaf.prop_recursive(4, 64, 8, |inner| {
+            prop_oneof![
+                vec(inner.clone(), 0..8).prop_map(RESPValues::Array),
+                vec(inner.clone(), 0..8).prop_map(RESPValues::Set),
+                vec(inner.clone(), 0..8).prop_map(RESPValues::Push),
+                vec((inner.clone(), inner), 0..8).prop_map(RESPValues::Map),
+            ]
+        })
+    }
+}
+
+#[cfg(test)]
+mod round_trip_tests {
+    use bytes::Bytes;
+    use proptest::prelude::*;
+
+    use super::resp_values;
+    use crate::resp::{RESPDecoder, RESPValues, RESPVersion};
+
+    proptest! {
+        #[test]
+        fn decode_encoded_value_correctly(value in resp_values()) {
+            let encoded = Bytes::from(value.to_bytes());
+            let result = RESPValues::decode(&encoded);
+
+            prop_assert_eq!(result, Ok((value, encoded.len())));
+        }
+
+        #[test]
+        fn decode_encoded_value_in_chunks_correctly(value in resp_values(), chunk in 1..16usize) {
+            let mut decoder = RESPDecoder::new();
+            let mut result = decoder.decode();
+
+            for part in value.to_bytes().chunks(chunk) {
+                decoder.feed(part);
+                result = decoder.decode();
+            }
+
+            prop_assert_eq!(result, Ok(value));
+        }
+
+        #[test]
+        fn decode_value_downgraded_to_resp2_correctly(value in resp_values()) {
+            let value = value.to_protocol(RESPVersion::RESP2);
+            let encoded = Bytes::from(value.to_bytes());
+            let result = RESPValues::decode(&encoded);
+
+            prop_assert_eq!(result, Ok((value, encoded.len())));
+        }
+    }
+}
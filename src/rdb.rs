@@ -0,0 +1,721 @@
+use std::{
+    fs,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bytes::Bytes;
+
+use crate::{
+    config::{LogLevel, SavePoint},
+    crc64, lzf,
+    store::{Store, Value},
+};
+
+// the version Redis 7.2 writes, older tooling reads it as long as only the
+// types it knows appear
+pub const RDB_VERSION: u16 = 11;
+
+const OPCODE_FUNCTION: u8 = 0xf5;
+const OPCODE_MODULE_AUX: u8 = 0xf7;
+const OPCODE_IDLE: u8 = 0xf8;
+const OPCODE_FREQ: u8 = 0xf9;
+const OPCODE_AUX: u8 = 0xfa;
+const OPCODE_RESIZEDB: u8 = 0xfb;
+const OPCODE_EXPIRETIME_MS: u8 = 0xfc;
+const OPCODE_EXPIRETIME: u8 = 0xfd;
+const OPCODE_SELECTDB: u8 = 0xfe;
+const OPCODE_EOF: u8 = 0xff;
+
+const TYPE_STRING: u8 = 0;
+
+// the first two bits of a length marking a specially encoded string
+const ENCODED: u8 = 0xc0;
+const ENCODED_INT8: u8 = 0;
+const ENCODED_INT16: u8 = 1;
+const ENCODED_INT32: u8 = 2;
+const ENCODED_LZF: u8 = 3;
+
+// the first version ending in a checksum
+const CHECKSUM_VERSION: u16 = 5;
+
+// How dumps are written and checked, from rdbcompression and rdbchecksum
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Options {
+    pub compression: bool,
+    pub checksum: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            compression: true,
+            checksum: true,
+        }
+    }
+}
+
+// Writes `entries` as database 0 of an RDB file
+pub fn write(entries: &[(Bytes, Value)], options: Options, out: &mut impl Write) -> io::Result<()> {
+    let mut out = Checksummed { inner: out, crc: 0 };
+    let out = &mut out;
+    out.write_all(format!("REDIS{RDB_VERSION:04}").as_bytes())?;
+    let ctime = unix_time();
+    for (name, value) in [
+        ("redis-ver", env!("CARGO_PKG_VERSION").to_string()),
+        ("redis-bits", (usize::BITS).to_string()),
+        ("ctime", ctime.to_string()),
+    ] {
+        out.write_all(&[OPCODE_AUX])?;
+        write_string(out, name.as_bytes(), options)?;
+        write_string(out, value.as_bytes(), options)?;
+    }
+
+    out.write_all(&[OPCODE_SELECTDB])?;
+    write_length(out, 0)?;
+    out.write_all(&[OPCODE_RESIZEDB])?;
+    write_length(out, entries.len() as u64)?;
+    // there are no expiring keys yet, so the expires table is always empty
+    write_length(out, 0)?;
+
+    for (key, value) in entries {
+        match value {
+            Value::String(bytes) => {
+                out.write_all(&[TYPE_STRING])?;
+                write_string(out, key, options)?;
+                write_string(out, bytes, options)?;
+            }
+        }
+    }
+
+    out.write_all(&[OPCODE_EOF])?;
+    // a zero checksum tells readers it wasn't computed
+    let crc = if options.checksum { out.crc } else { 0 };
+    out.inner.write_all(&crc.to_le_bytes())
+}
+
+// Keeps the CRC64 of everything written through it
+struct Checksummed<W> {
+    inner: W,
+    crc: u64,
+}
+
+impl<W: Write> Write for Checksummed<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.crc = crc64::update(self.crc, &buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// 6, 14, 32 or 64 bits depending on how large `length` is, the first two
+// bits of the first byte saying which
+fn write_length(out: &mut impl Write, length: u64) -> io::Result<()> {
+    match length {
+        0..0x40 => out.write_all(&[length as u8]),
+        0x40..0x4000 => out.write_all(&(0x4000 | length as u16).to_be_bytes()),
+        0x4000..=0xffff_ffff => {
+            out.write_all(&[0x80])?;
+            out.write_all(&(length as u32).to_be_bytes())
+        }
+        _ => {
+            out.write_all(&[0x81])?;
+            out.write_all(&length.to_be_bytes())
+        }
+    }
+}
+
+// Redis leaves strings of 20 bytes or less alone, and only keeps the
+// compressed form when it saves at least 4 bytes
+fn write_string(out: &mut impl Write, bytes: &[u8], options: Options) -> io::Result<()> {
+    let compressed = (options.compression && bytes.len() > 20)
+        .then(|| lzf::compress(bytes, bytes.len() - 4))
+        .flatten();
+    if let Some(compressed) = compressed {
+        out.write_all(&[ENCODED | ENCODED_LZF])?;
+        write_length(out, compressed.len() as u64)?;
+        write_length(out, bytes.len() as u64)?;
+        return out.write_all(&compressed);
+    }
+    write_length(out, bytes.len() as u64)?;
+    out.write_all(bytes)
+}
+
+// Reads the database 0 entries of an RDB file, dropping keys that already
+// expired. Other databases are skipped as the server only has the one. Keys
+// can't expire yet, so the ones that would later are loaded without their
+// expire time, how many is returned alongside for the caller to warn about
+pub fn read(input: &[u8], options: Options) -> io::Result<(Vec<(Bytes, Value)>, usize)> {
+    let mut reader = Reader { input, position: 0 };
+    let header = reader.take(9)?;
+    let version = header
+        .strip_prefix(b"REDIS")
+        .and_then(|v| std::str::from_utf8(v).ok())
+        .and_then(|v| v.parse::<u16>().ok())
+        .ok_or_else(|| invalid_rdb("wrong signature"))?;
+    if version > RDB_VERSION {
+        return Err(invalid_rdb(&format!(
+            "can't handle RDB format version {version}"
+        )));
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let mut entries = Vec::new();
+    let mut database = 0;
+    let mut expires_at = None;
+    let mut persisted = 0;
+    loop {
+        match reader.byte()? {
+            OPCODE_EOF if version < CHECKSUM_VERSION => break,
+            OPCODE_EOF => {
+                let end = reader.position;
+                let expected = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+                let crc = crc64::update(0, &input[..end]);
+                if options.checksum && expected != 0 && expected != crc {
+                    return Err(invalid_rdb(&format!(
+                        "wrong checksum, expected {expected:x} got {crc:x}"
+                    )));
+                }
+                break;
+            }
+            OPCODE_SELECTDB => database = reader.length()?,
+            OPCODE_RESIZEDB => {
+                reader.length()?;
+                reader.length()?;
+            }
+            OPCODE_AUX => {
+                reader.string()?;
+                reader.string()?;
+            }
+            OPCODE_EXPIRETIME_MS => {
+                let bytes = reader.take(8)?.try_into().unwrap();
+                expires_at = Some(u64::from_le_bytes(bytes));
+            }
+            OPCODE_EXPIRETIME => {
+                let bytes = reader.take(4)?.try_into().unwrap();
+                expires_at = Some(u32::from_le_bytes(bytes) as u64 * 1000);
+            }
+            OPCODE_IDLE => {
+                reader.length()?;
+            }
+            OPCODE_FREQ => {
+                reader.byte()?;
+            }
+            OPCODE_FUNCTION | OPCODE_MODULE_AUX => {
+                return Err(invalid_rdb("functions and modules aren't supported"))
+            }
+            TYPE_STRING => {
+                let key = reader.string()?;
+                let value = Value::String(reader.string()?);
+                let expires_at = expires_at.take();
+                if database != 0 || expires_at.is_some_and(|at| at <= now) {
+                    continue;
+                }
+                if expires_at.is_some() {
+                    persisted += 1;
+                }
+                entries.push((key, value));
+            }
+            kind => return Err(invalid_rdb(&format!("unsupported value type {kind}"))),
+        }
+    }
+    Ok((entries, persisted))
+}
+
+pub fn load(path: &Path, options: Options) -> io::Result<(Vec<(Bytes, Value)>, usize)> {
+    read(&fs::read(path)?, options)
+}
+
+// The warning for the keys `read` loaded without their expire time
+pub fn warn_persisted(persisted: usize, loglevel: LogLevel) {
+    if persisted > 0 && loglevel <= LogLevel::Warning {
+        eprintln!(
+            "Warning: {persisted} keys with an expire time loaded without one, keys can't expire"
+        );
+    }
+}
+
+struct Reader<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        let end = self.position.saturating_add(count);
+        let bytes = self
+            .input
+            .get(self.position..end)
+            .ok_or_else(|| invalid_rdb("unexpected end of file"))?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn length(&mut self) -> io::Result<u64> {
+        match self.encoded_length()? {
+            Ok(length) => Ok(length),
+            Err(_) => Err(invalid_rdb("unexpected string encoding")),
+        }
+    }
+
+    // A plain length, or the encoding of a string stored in the bits left
+    fn encoded_length(&mut self) -> io::Result<Result<u64, u8>> {
+        let first = self.byte()?;
+        Ok(match first >> 6 {
+            0 => Ok((first & 0x3f) as u64),
+            1 => Ok(((first as u64 & 0x3f) << 8) | self.byte()? as u64),
+            2 if first == 0x80 => Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()) as u64),
+            2 if first == 0x81 => Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap())),
+            2 => return Err(invalid_rdb("unknown length encoding")),
+            _ => Err(first & !ENCODED),
+        })
+    }
+
+    fn string(&mut self) -> io::Result<Bytes> {
+        let integer = match self.encoded_length()? {
+            Ok(length) => {
+                let length = usize::try_from(length).map_err(|_| invalid_rdb("string too long"))?;
+                return Ok(Bytes::copy_from_slice(self.take(length)?));
+            }
+            Err(ENCODED_INT8) => self.byte()? as i8 as i64,
+            Err(ENCODED_INT16) => i16::from_le_bytes(self.take(2)?.try_into().unwrap()) as i64,
+            Err(ENCODED_INT32) => i32::from_le_bytes(self.take(4)?.try_into().unwrap()) as i64,
+            Err(ENCODED_LZF) => {
+                let compressed = self.length()?;
+                let length = self.length()?;
+                let compressed =
+                    usize::try_from(compressed).map_err(|_| invalid_rdb("string too long"))?;
+                let length = usize::try_from(length).map_err(|_| invalid_rdb("string too long"))?;
+                let string = lzf::decompress(self.take(compressed)?, length)
+                    .ok_or_else(|| invalid_rdb("invalid LZF compressed string"))?;
+                return Ok(Bytes::from(string));
+            }
+            Err(encoding) => {
+                return Err(invalid_rdb(&format!("unknown string encoding {encoding}")))
+            }
+        };
+        Ok(Bytes::from(integer.to_string()))
+    }
+}
+
+fn invalid_rdb(reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid RDB file: {reason}"),
+    )
+}
+
+// The DUMP payload of `value`: its type and encoding as in an RDB file,
+// then the RDB version and the CRC64 of all that
+pub fn dump(value: &Value, options: Options) -> Vec<u8> {
+    let mut out = Vec::new();
+    match value {
+        Value::String(bytes) => {
+            out.push(TYPE_STRING);
+            // writing to a Vec doesn't fail
+            write_string(&mut out, bytes, options).unwrap();
+        }
+    }
+    out.extend_from_slice(&RDB_VERSION.to_le_bytes());
+    let crc = crc64::update(0, &out);
+    out.extend_from_slice(&crc.to_le_bytes());
+    out
+}
+
+// The value of a DUMP payload, which RESTORE takes only from the same or
+// an older RDB version and with its checksum right
+pub fn undump(payload: &[u8]) -> io::Result<Value> {
+    let wrong = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "DUMP payload version or checksum are wrong",
+        )
+    };
+    let (body, footer) = payload
+        .len()
+        .checked_sub(10)
+        .map(|end| payload.split_at(end))
+        .ok_or_else(wrong)?;
+    let version = u16::from_le_bytes([footer[0], footer[1]]);
+    let crc = u64::from_le_bytes(footer[2..].try_into().unwrap());
+    if version > RDB_VERSION || crc != crc64::update(0, &payload[..payload.len() - 8]) {
+        return Err(wrong());
+    }
+    let mut reader = Reader {
+        input: body,
+        position: 0,
+    };
+    let value = match reader.byte() {
+        Ok(TYPE_STRING) => reader.string().map(Value::String).ok(),
+        _ => None,
+    };
+    value
+        .filter(|_| reader.position == body.len())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Bad data format"))
+}
+
+// Writes `entries` to `path` through a temporary file in the same directory,
+// so a crash halfway never leaves a truncated dump behind
+pub fn save(path: &Path, entries: &[(Bytes, Value)], options: Options) -> io::Result<()> {
+    save_through(path, &temp_path(path, "temp"), entries, options)
+}
+
+fn save_through(
+    path: &Path,
+    temp: &Path,
+    entries: &[(Bytes, Value)],
+    options: Options,
+) -> io::Result<()> {
+    let result = (|| {
+        let file = fs::File::create(temp)?;
+        let mut out = BufWriter::new(file);
+        write(entries, options, &mut out)?;
+        out.into_inner()?.sync_all()?;
+        fs::rename(temp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(temp);
+    }
+    result
+}
+
+// temp-<pid>.rdb for SAVE, temp-bg-<pid>.rdb for BGSAVE, as in Redis
+fn temp_path(path: &Path, prefix: &str) -> PathBuf {
+    path.with_file_name(format!("{prefix}-{}.rdb", std::process::id()))
+}
+
+// Tracks the saves of the keyspace, only one background save runs at a time
+pub struct Snapshots {
+    state: Arc<SaveState>,
+}
+
+struct SaveState {
+    in_progress: AtomicBool,
+    // unix time of the last successful save, startup until there's one
+    last_save: AtomicU64,
+    // Store::changes as of the last successful save
+    saved_changes: AtomicU64,
+    // unix time of the last background save that failed, 0 if the last one didn't
+    failed_at: AtomicU64,
+}
+
+impl Default for Snapshots {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(SaveState {
+                in_progress: AtomicBool::new(false),
+                last_save: AtomicU64::new(unix_time()),
+                saved_changes: AtomicU64::new(0),
+                failed_at: AtomicU64::new(0),
+            }),
+        }
+    }
+}
+
+impl Snapshots {
+    pub fn in_progress(&self) -> bool {
+        self.state.in_progress.load(Ordering::Acquire)
+    }
+
+    pub fn last_save(&self) -> u64 {
+        self.state.last_save.load(Ordering::Acquire)
+    }
+
+    // Writes made to `store` that no save holds yet
+    pub fn unsaved_changes(&self, store: &Store) -> u64 {
+        store.changes() - self.state.saved_changes.load(Ordering::Acquire)
+    }
+
+    // Saves a snapshot of `store` before returning. It counts as a save in
+    // progress, so the save points don't start one alongside, and fails
+    // while a background save runs rather than waiting on it
+    pub fn save(&self, store: &Store, path: &Path, options: Options) -> io::Result<()> {
+        if self.state.in_progress.swap(true, Ordering::AcqRel) {
+            return Err(io::Error::other("Background save already in progress"));
+        }
+        let snapshot = store.snapshot();
+        let result = save(path, &snapshot.entries(), options);
+        if result.is_ok() {
+            self.state.saved(snapshot.changes());
+        }
+        self.state.in_progress.store(false, Ordering::Release);
+        result
+    }
+
+    // Saves a snapshot of `store` on a thread of its own, false if a save is
+    // still running. A failure is logged at `loglevel`
+    pub fn background(
+        &self,
+        store: &Store,
+        path: PathBuf,
+        options: Options,
+        loglevel: LogLevel,
+    ) -> bool {
+        if self.state.in_progress.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+        // the snapshot is cheap to take, it's reading it that waits for the thread
+        let snapshot = store.snapshot();
+        let state = self.state.clone();
+        thread::spawn(move || {
+            let temp = temp_path(&path, "temp-bg");
+            match save_through(&path, &temp, &snapshot.entries(), options) {
+                Ok(()) => state.saved(snapshot.changes()),
+                Err(error) => {
+                    if loglevel <= LogLevel::Warning {
+                        eprintln!("Background saving error: {error}");
+                    }
+                    state.failed_at.store(unix_time(), Ordering::Release);
+                }
+            }
+            state.in_progress.store(false, Ordering::Release);
+        });
+        true
+    }
+
+    // Whether a save point asks for a background save now. After a failed
+    // one the next waits a few seconds, as Redis does
+    pub fn due(&self, points: &[SavePoint], store: &Store) -> bool {
+        let now = unix_time();
+        let failed_at = self.state.failed_at.load(Ordering::Acquire);
+        if self.in_progress() || failed_at + RETRY_SECONDS > now {
+            return false;
+        }
+        let changes = self.unsaved_changes(store);
+        let elapsed = now.saturating_sub(self.last_save());
+        points
+            .iter()
+            .any(|point| changes >= point.changes && elapsed >= point.seconds)
+    }
+}
+
+impl SaveState {
+    fn saved(&self, changes: u64) {
+        self.last_save.store(unix_time(), Ordering::Release);
+        self.saved_changes.store(changes, Ordering::Release);
+        self.failed_at.store(0, Ordering::Release);
+    }
+}
+
+// how long a failed background save holds off the save points
+const RETRY_SECONDS: u64 = 5;
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod rdb_tests {
+    use std::sync::atomic::Ordering;
+
+    use bytes::Bytes;
+
+    use super::{dump, read, undump, write, write_length, Options, Snapshots};
+    use crate::{
+        commands::test_dir,
+        config::SavePoint,
+        store::{Store, Value},
+    };
+
+    #[test]
+    fn write_lengths_correctly() {
+        let mut out = Vec::new();
+        for length in [10, 700, 70000, 1 << 40] {
+            write_length(&mut out, length).unwrap();
+        }
+
+        assert_eq!(
+            out,
+            [
+                &[0x0a][..],
+                &[0x42, 0xbc],
+                &[0x80, 0x00, 0x01, 0x11, 0x70],
+                &[0x81, 0, 0, 0x01, 0, 0, 0, 0, 0],
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn write_string_entries_correctly() {
+        let entries = [(
+            Bytes::from_static(b"k"),
+            Value::String(Bytes::from_static(b"v")),
+        )];
+        let options = Options {
+            checksum: false,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        write(&entries, options, &mut out).unwrap();
+
+        assert!(out.starts_with(b"REDIS0011\xfa\x09redis-ver"));
+        assert!(out.ends_with(b"\xfe\x00\xfb\x01\x00\x00\x01k\x01v\xff\0\0\0\0\0\0\0\0"));
+    }
+
+    #[test]
+    fn read_written_entries_correctly() {
+        let entries = vec![
+            (
+                Bytes::from_static(b"k"),
+                Value::String(Bytes::from(vec![7; 20000])),
+            ),
+            (Bytes::from_static(b"empty"), Value::String(Bytes::new())),
+        ];
+        let mut out = Vec::new();
+        write(&entries, Options::default(), &mut out).unwrap();
+
+        assert!(out.len() < 1000);
+        assert_eq!(read(&out, Options::default()).unwrap(), (entries, 0));
+    }
+
+    #[test]
+    fn read_redis_encodings_correctly() {
+        // an integer encoded value, an expired key, one expiring in the
+        // future (loaded nevertheless) and a key of database 1
+        let dump = b"REDIS0009\xfe\x00\x00\x01a\xc1\x39\x30\
+            \xfc\x01\0\0\0\0\0\0\0\x00\x01b\x01v\
+            \xfc\0\0\0\0\0\0\0\x7f\x00\x01d\x01v\
+            \xfe\x01\x00\x01c\x01v\xff\0\0\0\0\0\0\0\0";
+        let (entries, persisted) = read(dump, Options::default()).unwrap();
+
+        assert_eq!(persisted, 1);
+        assert_eq!(
+            entries,
+            [
+                (
+                    Bytes::from_static(b"a"),
+                    Value::String(Bytes::from_static(b"12345"))
+                ),
+                (
+                    Bytes::from_static(b"d"),
+                    Value::String(Bytes::from_static(b"v"))
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn read_truncated_rdb_fails() {
+        let mut out = Vec::new();
+        write(&[], Options::default(), &mut out).unwrap();
+
+        assert!(read(&out[..out.len() - 10], Options::default()).is_err());
+        assert!(read(b"RADIS0011\xff", Options::default()).is_err());
+    }
+
+    #[test]
+    fn read_rdb_with_wrong_checksum_fails() {
+        let entries = [(
+            Bytes::from_static(b"k"),
+            Value::String(Bytes::from_static(b"v")),
+        )];
+        let mut out = Vec::new();
+        write(&entries, Options::default(), &mut out).unwrap();
+        let last = out.len() - 1;
+        out[last] ^= 1;
+
+        assert!(read(&out, Options::default()).is_err());
+        let unchecked = Options {
+            checksum: false,
+            ..Default::default()
+        };
+        assert!(read(&out, unchecked).is_ok());
+    }
+
+    #[test]
+    fn dump_and_undump_correctly() {
+        let value = Value::String(Bytes::from_static(b"bar"));
+        let payload = dump(&value, Options::default());
+
+        assert_eq!(&payload[..7], b"\x00\x03bar\x0b\x00");
+        assert_eq!(payload.len(), 15);
+        assert_eq!(undump(&payload).unwrap(), value);
+    }
+
+    #[test]
+    fn undump_wrong_payload_fails() {
+        let mut payload = dump(
+            &Value::String(Bytes::from_static(b"bar")),
+            Options::default(),
+        );
+        payload[2] = b'c';
+
+        let error = undump(&payload).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "DUMP payload version or checksum are wrong"
+        );
+        assert!(undump(b"short").is_err());
+    }
+
+    #[test]
+    fn undump_oversized_lzf_string_fails() {
+        // a 2 byte LZF string claiming to expand to 2^40 bytes
+        let mut payload = vec![0x00, 0xc3, 0x02, 0x81];
+        payload.extend_from_slice(&(1u64 << 40).to_be_bytes());
+        payload.extend_from_slice(&[0x00, b'a']);
+        payload.extend_from_slice(&super::RDB_VERSION.to_le_bytes());
+        let crc = crate::crc64::update(0, &payload);
+        payload.extend_from_slice(&crc.to_le_bytes());
+
+        let error = undump(&payload).unwrap_err();
+        assert_eq!(error.to_string(), "Bad data format");
+    }
+
+    #[test]
+    fn save_during_a_background_save_fails() {
+        let dir = test_dir("rdb-save");
+        let snapshots = Snapshots::default();
+        let store = Store::default();
+        // as if a BGSAVE were running
+        snapshots.state.in_progress.store(true, Ordering::Release);
+        let result = snapshots.save(&store, &dir.join("dump.rdb"), Default::default());
+
+        assert!(result.is_err_and(|e| e.to_string() == "Background save already in progress"));
+        assert!(!dir.join("dump.rdb").exists());
+        // still the background save's
+        assert!(snapshots.in_progress());
+    }
+
+    #[test]
+    fn save_points_come_due_correctly() {
+        let snapshots = Snapshots::default();
+        let store = Store::default();
+        let points = [SavePoint {
+            seconds: 0,
+            changes: 2,
+        }];
+        store.set(
+            Bytes::from_static(b"k"),
+            Value::String(Bytes::from_static(b"v")),
+        );
+        assert!(!snapshots.due(&points, &store));
+
+        store.remove(b"k");
+        assert!(snapshots.due(&points, &store));
+        snapshots.state.saved(store.changes());
+        assert!(!snapshots.due(&points, &store));
+        assert_eq!(snapshots.unsaved_changes(&store), 0);
+    }
+}
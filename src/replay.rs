@@ -0,0 +1,192 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    net::SocketAddr,
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{tcp::OwnedWriteHalf, TcpStream},
+    time::sleep_until,
+};
+
+use crate::resp::{RESPDecodeError, RESPDecoder, RESPValues};
+
+// A recording is a sequence of RESP arrays of the form
+// [microseconds since recording started, connection id, inbound frame]
+#[derive(PartialEq, Debug, Clone)]
+pub struct RecordedCommand {
+    pub timestamp: Duration,
+    pub connection_id: u64,
+    pub frame: RESPValues,
+}
+
+impl From<&RecordedCommand> for RESPValues {
+    fn from(value: &RecordedCommand) -> Self {
+        RESPValues::Array(vec![
+            RESPValues::Integer(value.timestamp.as_micros() as i64),
+            RESPValues::Integer(value.connection_id as i64),
+            value.frame.clone(),
+        ])
+    }
+}
+
+impl TryFrom<RESPValues> for RecordedCommand {
+    type Error = io::Error;
+
+    fn try_from(value: RESPValues) -> Result<Self, Self::Error> {
+        match value {
+            RESPValues::Array(v) => match <[RESPValues; 3]>::try_from(v) {
+                Ok([RESPValues::Integer(timestamp), RESPValues::Integer(connection_id), frame]) => {
+                    Ok(Self {
+                        timestamp: Duration::from_micros(timestamp as u64),
+                        connection_id: connection_id as u64,
+                        frame,
+                    })
+                }
+                _ => Err(invalid_recording("malformed entry")),
+            },
+            _ => Err(invalid_recording("entry is not an array")),
+        }
+    }
+}
+
+pub struct Recorder {
+    started_at: Instant,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl Recorder {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            started_at: Instant::now(),
+            writer: Mutex::new(BufWriter::new(File::create(path)?)),
+        })
+    }
+
+    pub fn record(&self, connection_id: u64, frame: &RESPValues) -> io::Result<()> {
+        let entry = RecordedCommand {
+            timestamp: self.started_at.elapsed(),
+            connection_id,
+            frame: frame.clone(),
+        };
+
+        let mut writer = self.writer.lock().unwrap();
+        writer.write_all(&RESPValues::from(&entry).to_bytes())?;
+        writer.flush()
+    }
+}
+
+pub fn load(path: impl AsRef<Path>) -> io::Result<Vec<RecordedCommand>> {
+    let mut decoder = RESPDecoder::new();
+    decoder.feed(&std::fs::read(path)?);
+
+    let mut commands = Vec::new();
+    loop {
+        match decoder.decode() {
+            Ok(entry) => commands.push(RecordedCommand::try_from(entry)?),
+            Err(RESPDecodeError::NeedMoreData) => break,
+            Err(RESPDecodeError::Invalid(e)) => return Err(invalid_recording(&e.to_string())),
+        }
+    }
+
+    Ok(commands)
+}
+
+// Sends the recorded commands to `target` over one connection per recorded
+// connection id, keeping the original spacing between them divided by `speed`.
+// Replies are read and discarded.
+pub async fn replay(
+    commands: &[RecordedCommand],
+    target: SocketAddr,
+    speed: f64,
+) -> io::Result<()> {
+    let started_at = tokio::time::Instant::now();
+    let mut connections: HashMap<u64, OwnedWriteHalf> = HashMap::new();
+
+    for command in commands {
+        sleep_until(started_at + command.timestamp.div_f64(speed)).await;
+
+        let conn = match connections.get_mut(&command.connection_id) {
+            Some(conn) => conn,
+            None => {
+                let (mut reader, writer) = TcpStream::connect(target).await?.into_split();
+                tokio::spawn(async move {
+                    let mut buf = [0; 512];
+                    while let Ok(n) = reader.read(&mut buf).await {
+                        if n == 0 {
+                            break;
+                        }
+                    }
+                });
+                connections.entry(command.connection_id).or_insert(writer)
+            }
+        };
+
+        conn.write_all(&command.frame.to_bytes()).await?;
+    }
+
+    Ok(())
+}
+
+fn invalid_recording(reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid recording: {reason}"),
+    )
+}
+
+#[cfg(test)]
+mod recording_tests {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+
+    use super::{load, RecordedCommand, Recorder};
+    use crate::{commands::test_dir, resp::RESPValues};
+
+    #[test]
+    fn recorded_command_round_trip() {
+        let command = RecordedCommand {
+            timestamp: Duration::from_micros(1500),
+            connection_id: 3,
+            frame: RESPValues::Array(vec![RESPValues::BulkString(Bytes::from_static(b"PING"))]),
+        };
+        let result = RecordedCommand::try_from(RESPValues::from(&command));
+
+        assert!(result.is_ok_and(|r| r == command));
+    }
+
+    #[test]
+    fn parse_malformed_recorded_command_fails() {
+        let value = RESPValues::Array(vec![RESPValues::Integer(1)]);
+        let result = RecordedCommand::try_from(value);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn load_recorded_commands_in_order() {
+        let dir = test_dir("recording");
+        let path = dir.join("recording.resp");
+        let recorder = Recorder::create(&path).unwrap();
+        let ping = RESPValues::Array(vec![RESPValues::BulkString(Bytes::from_static(b"PING"))]);
+        let echo = RESPValues::Array(vec![
+            RESPValues::BulkString(Bytes::from_static(b"ECHO")),
+            RESPValues::BulkString(Bytes::from_static(b"\r\nbinary\x00")),
+        ]);
+
+        recorder.record(1, &ping).unwrap();
+        recorder.record(2, &echo).unwrap();
+        let result = load(&path).unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!((result[0].connection_id, &result[0].frame), (1, &ping));
+        assert_eq!((result[1].connection_id, &result[1].frame), (2, &echo));
+        assert!(result[0].timestamp <= result[1].timestamp);
+    }
+}
@@ -0,0 +1,955 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    future,
+    hash::{BuildHasher, RandomState},
+    io::{self, Write},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use bytes::{Bytes, BytesMut};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    sync::{mpsc, Notify},
+};
+
+use crate::{
+    commands::ConnectionState,
+    config::LogLevel,
+    pubsub::Subscriber,
+    rdb,
+    resp::{decode_frame, RESPDecodeError, RESPValues},
+    server::Shared,
+    store::Snapshot,
+};
+
+// Both ends of replication: the replicas this server streams its writes to,
+// and the link to its own master when it is a replica of one
+pub struct Replication {
+    state: Arc<Mutex<ReplicationState>>,
+    link: Mutex<LinkState>,
+    // wakes the link up when REPLICAOF changes the master
+    master_changed: Notify,
+}
+
+impl Default for Replication {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ReplicationState {
+                id: random_id(),
+                id2: NO_ID.to_string(),
+                second_offset: None,
+                offset: 0,
+                backlog: None,
+                replicas: BTreeMap::new(),
+                streaming: false,
+            })),
+            link: Mutex::new(LinkState::Connect),
+            master_changed: Notify::new(),
+        }
+    }
+}
+
+// what the second replication id is while there's none
+const NO_ID: &str = "0000000000000000000000000000000000000000";
+
+struct ReplicationState {
+    // names the history of the dataset, offset being how far into it this
+    // server is, in bytes of the stream of writes
+    id: String,
+    // the history this one branched off, which a replica can still continue
+    // up to second_offset, as when this server was promoted from replica
+    id2: String,
+    second_offset: Option<u64>,
+    offset: u64,
+    // none until the first replica asks for one, as in Redis
+    backlog: Option<Backlog>,
+    // keyed by client id
+    replicas: BTreeMap<u64, Replica>,
+    // a diskless dump is being streamed
+    streaming: bool,
+}
+
+impl ReplicationState {
+    // whatever history came before the backlog can't be continued
+    fn start_backlog(&mut self) {
+        if self.backlog.is_none() {
+            self.id = random_id();
+            self.id2 = NO_ID.to_string();
+            self.second_offset = None;
+            self.backlog = Some(Backlog::new());
+        }
+    }
+
+    // where the dump taken now is in the history
+    fn fullresync(&self) -> RESPValues {
+        RESPValues::SimpleString(format!("FULLRESYNC {} {}", self.id, self.offset))
+    }
+
+    // Streams a write to the replicas and keeps it in the backlog
+    fn stream(&mut self, frame: &RESPValues, backlog_size: usize) {
+        let length = frame.to_bytes().len();
+        if let Some(backlog) = &mut self.backlog {
+            backlog.push(self.offset + 1, length, frame.clone(), backlog_size);
+        }
+        self.offset += length as u64;
+        for replica in self.replicas.values_mut() {
+            replica.send(frame.clone());
+        }
+    }
+}
+
+// The last writes streamed, each with the offset it starts at, so a
+// replica that lost its link can go on from where it was instead of loading
+// a dump all over again. Replicas only ask for offsets a write starts at,
+// so whole writes are kept rather than bytes
+struct Backlog {
+    frames: VecDeque<(u64, usize, RESPValues)>,
+    // of the writes kept, as encoded
+    length: usize,
+}
+
+impl Backlog {
+    fn new() -> Self {
+        Self {
+            frames: VecDeque::new(),
+            length: 0,
+        }
+    }
+
+    // Drops the oldest writes past `size` bytes, but for the last one
+    fn push(&mut self, start: u64, length: usize, frame: RESPValues, size: usize) {
+        self.frames.push_back((start, length, frame));
+        self.length += length;
+        while self.length > size && self.frames.len() > 1 {
+            let (_, dropped, _) = self.frames.pop_front().unwrap();
+            self.length -= dropped;
+        }
+    }
+
+    // The writes from `offset` on, None if the backlog starts after it.
+    // `end` is the offset of the first write yet to come
+    fn since(&self, offset: u64, end: u64) -> Option<Vec<RESPValues>> {
+        if offset == end {
+            return Some(Vec::new());
+        }
+        let first = self
+            .frames
+            .iter()
+            .position(|(start, ..)| *start == offset)?;
+        Some(
+            self.frames
+                .range(first..)
+                .map(|(.., frame)| frame.clone())
+                .collect(),
+        )
+    }
+}
+
+pub struct Replica {
+    pub ip: String,
+    // where the replica listens, as it told with REPLCONF listening-port
+    pub port: u16,
+    pub state: ReplicaState,
+    sender: Subscriber,
+    // writes fed while its dump is written, which go out right after it
+    pending: Vec<RESPValues>,
+    // when it asked for a dump, for the ones waiting on a diskless one
+    since: Instant,
+    // the offset the replica last acknowledged with REPLCONF ACK, and when
+    acked: u64,
+    acked_at: Instant,
+}
+
+// A replica as INFO and ROLE show it
+#[derive(PartialEq, Debug, Clone)]
+pub struct ReplicaInfo {
+    pub ip: String,
+    pub port: u16,
+    pub state: ReplicaState,
+    pub offset: u64,
+    // seconds since the replica last acknowledged an offset
+    pub lag: u64,
+}
+
+impl Replica {
+    fn new((ip, port): (String, u16), state: ReplicaState, sender: Subscriber) -> Self {
+        Self {
+            ip,
+            port,
+            state,
+            sender,
+            pending: Vec::new(),
+            since: Instant::now(),
+            acked: 0,
+            acked_at: Instant::now(),
+        }
+    }
+
+    fn send(&mut self, frame: RESPValues) {
+        match self.state {
+            // the dump still to be taken has it
+            ReplicaState::WaitBgsave => {}
+            ReplicaState::SendBulk => self.pending.push(frame),
+            // a replica gone is detached when its connection closes
+            ReplicaState::Online => {
+                let _ = self.sender.send(frame);
+            }
+        }
+    }
+
+    // Once its dump went out, with the writes since right after it
+    fn online(&mut self) {
+        self.state = ReplicaState::Online;
+        // lag counts from the replica having a dataset to acknowledge
+        self.acked_at = Instant::now();
+        for frame in std::mem::take(&mut self.pending) {
+            self.send(frame);
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ReplicaState {
+    // for a diskless dump to start, see Replication::start_diskless
+    WaitBgsave,
+    SendBulk,
+    Online,
+}
+
+impl ReplicaState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::WaitBgsave => "wait_bgsave",
+            Self::SendBulk => "send_bulk",
+            Self::Online => "online",
+        }
+    }
+}
+
+// Sends what rdb::write writes to each of the replicas of a diskless dump,
+// in chunks as it comes
+struct Fanout {
+    senders: Vec<Subscriber>,
+    buffer: Vec<u8>,
+}
+
+const FANOUT_CHUNK_SIZE: usize = 16 * 1024;
+
+impl Write for Fanout {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(bytes);
+        if self.buffer.len() >= FANOUT_CHUNK_SIZE {
+            self.flush()?;
+        }
+        Ok(bytes.len())
+    }
+
+    // replicas gone are detached when their connections close
+    fn flush(&mut self) -> io::Result<()> {
+        let chunk = Bytes::from(std::mem::take(&mut self.buffer));
+        for sender in &self.senders {
+            let _ = sender.send(RESPValues::Raw(chunk.clone()));
+        }
+        Ok(())
+    }
+}
+
+// How far a replica got with its master, as ROLE shows it
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum LinkState {
+    Connect,
+    Connecting,
+    Sync,
+    Connected,
+}
+
+impl LinkState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Connect => "connect",
+            Self::Connecting => "connecting",
+            Self::Sync => "sync",
+            Self::Connected => "connected",
+        }
+    }
+}
+
+impl Replication {
+    pub fn id(&self) -> String {
+        self.state.lock().unwrap().id.clone()
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.state.lock().unwrap().offset
+    }
+
+    // The history this one branched off and the offset it can be continued
+    // up to, as INFO shows them
+    pub fn second_id(&self) -> (String, Option<u64>) {
+        let state = self.state.lock().unwrap();
+        (state.id2.clone(), state.second_offset)
+    }
+
+    // The offset of the first byte in the backlog and how many it holds,
+    // None until there's a backlog
+    pub fn backlog(&self) -> Option<(u64, usize)> {
+        let state = self.state.lock().unwrap();
+        let backlog = state.backlog.as_ref()?;
+        let first = backlog
+            .frames
+            .front()
+            .map_or(state.offset + 1, |(start, ..)| *start);
+        Some((first, backlog.length))
+    }
+
+    pub fn link_state(&self) -> LinkState {
+        *self.link.lock().unwrap()
+    }
+
+    // For a replica that became a master: its replicas, which followed the
+    // same master, can still continue the old history from the backlog
+    pub fn shift_id(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.id2 = std::mem::replace(&mut state.id, random_id());
+        state.second_offset = Some(state.offset + 1);
+    }
+
+    // For REPLICAOF, which already changed replicaof
+    pub fn master_changed(&self) {
+        self.master_changed.notify_one();
+    }
+
+    // Streams a write to the replicas. Without a backlog there is no stream
+    // to move along, as in Redis
+    pub fn feed(&self, frame: &RESPValues, backlog_size: usize) {
+        let mut state = self.state.lock().unwrap();
+        if state.backlog.is_some() || !state.replicas.is_empty() {
+            state.stream(frame, backlog_size);
+        }
+    }
+
+    // Makes client `id` a replica that loads a dump before it gets the
+    // writes, the one of `snapshot` written for it alone on a thread of its
+    // own. The snapshot must be taken with no write since, see the psync command
+    pub fn full_sync(
+        &self,
+        id: u64,
+        address: (String, u16),
+        sender: Subscriber,
+        snapshot: Snapshot,
+        options: rdb::Options,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        state.start_backlog();
+        let replica = Replica::new(address, ReplicaState::SendBulk, sender);
+        let _ = replica.sender.send(state.fullresync());
+        state.replicas.insert(id, replica);
+        drop(state);
+
+        let state = self.state.clone();
+        thread::spawn(move || {
+            let mut dump = Vec::new();
+            // writing to memory doesn't fail
+            let _ = rdb::write(&snapshot.entries(), options, &mut dump);
+            let mut state = state.lock().unwrap();
+            if let Some(replica) = state.replicas.get_mut(&id) {
+                let _ = replica.sender.send(RESPValues::BulkString(dump.into()));
+                replica.online();
+            }
+        });
+    }
+
+    // Makes client `id` a replica waiting for the next diskless dump
+    pub fn wait_for_dump(&self, id: u64, address: (String, u16), sender: Subscriber) {
+        let mut state = self.state.lock().unwrap();
+        state.start_backlog();
+        let replica = Replica::new(address, ReplicaState::WaitBgsave, sender);
+        state.replicas.insert(id, replica);
+    }
+
+    // Whether a replica waited `delay` seconds for a diskless dump, for more
+    // to join it, with none being streamed
+    pub fn dump_due(&self, delay: u64) -> bool {
+        let state = self.state.lock().unwrap();
+        !state.streaming
+            && state.replicas.values().any(|replica| {
+                replica.state == ReplicaState::WaitBgsave
+                    && replica.since.elapsed().as_secs() >= delay
+            })
+    }
+
+    // Streams the dump of `snapshot` to all the replicas waiting for one, as
+    // it's written, with no file in between. The length isn't known up front,
+    // so it goes out as `$EOF:<mark>`, the dump and the mark again. Callers
+    // hold exec_lock exclusively, so the snapshot is at the offset the
+    // replicas are told
+    pub fn start_diskless(&self, snapshot: Snapshot, options: rdb::Options) {
+        let mut state = self.state.lock().unwrap();
+        let reply = state.fullresync();
+        let mut ids = Vec::new();
+        let mut senders = Vec::new();
+        for (id, replica) in &mut state.replicas {
+            if replica.state == ReplicaState::WaitBgsave {
+                let _ = replica.sender.send(reply.clone());
+                replica.state = ReplicaState::SendBulk;
+                ids.push(*id);
+                senders.push(replica.sender.clone());
+            }
+        }
+        if ids.is_empty() {
+            return;
+        }
+        state.streaming = true;
+        drop(state);
+
+        let state = self.state.clone();
+        thread::spawn(move || {
+            let mark = random_id();
+            let mut out = Fanout {
+                senders,
+                buffer: format!("$EOF:{mark}\r\n").into_bytes(),
+            };
+            // writing to the channels doesn't fail
+            let _ = rdb::write(&snapshot.entries(), options, &mut out);
+            out.buffer.extend_from_slice(mark.as_bytes());
+            let _ = out.flush();
+
+            let mut state = state.lock().unwrap();
+            state.streaming = false;
+            for id in ids {
+                if let Some(replica) = state.replicas.get_mut(&id) {
+                    replica.online();
+                }
+            }
+        });
+    }
+
+    // Makes client `id` a replica going on from `offset` of history
+    // `replication_id`, sending it what it missed from the backlog. False if
+    // it has to resync from a dump instead
+    pub fn partial_sync(
+        &self,
+        id: u64,
+        address: (String, u16),
+        sender: Subscriber,
+        (replication_id, offset): (&str, u64),
+    ) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let known = replication_id == state.id
+            || (replication_id == state.id2
+                && state.second_offset.is_some_and(|second| offset <= second));
+        let missed = state
+            .backlog
+            .as_ref()
+            .and_then(|backlog| backlog.since(offset, state.offset + 1));
+        let Some(missed) = missed.filter(|_| known) else {
+            return false;
+        };
+
+        let mut replica = Replica::new(address, ReplicaState::Online, sender);
+        let _ = replica
+            .sender
+            .send(RESPValues::SimpleString(format!("CONTINUE {}", state.id)));
+        for frame in missed {
+            replica.send(frame);
+        }
+        state.replicas.insert(id, replica);
+        true
+    }
+
+    // REPLCONF ACK from client `id`, when it is a replica
+    pub fn ack(&self, id: u64, offset: u64) {
+        if let Some(replica) = self.state.lock().unwrap().replicas.get_mut(&id) {
+            replica.acked = replica.acked.max(offset);
+            replica.acked_at = Instant::now();
+        }
+    }
+
+    pub fn replicas(&self) -> Vec<ReplicaInfo> {
+        let state = self.state.lock().unwrap();
+        state
+            .replicas
+            .values()
+            .map(|replica| ReplicaInfo {
+                ip: replica.ip.clone(),
+                port: replica.port,
+                state: replica.state,
+                offset: replica.acked,
+                lag: replica.acked_at.elapsed().as_secs(),
+            })
+            .collect()
+    }
+
+    pub fn detach(&self, id: u64) {
+        self.state.lock().unwrap().replicas.remove(&id);
+    }
+
+    // What the master streamed, passed on to this server's own replicas.
+    // The offset follows the master's either way
+    fn forward(&self, frame: &RESPValues, backlog_size: usize) {
+        self.state.lock().unwrap().stream(frame, backlog_size);
+    }
+
+    // Takes on the history of the master after loading its dump, with a
+    // backlog of its own for replicas of this server to continue from. The
+    // replicas attached follow the history left behind, they're detached and
+    // their clients returned to be disconnected, so they sync again
+    fn resynced(&self, id: String, offset: u64) -> Vec<u64> {
+        let mut state = self.state.lock().unwrap();
+        state.id = id;
+        state.id2 = NO_ID.to_string();
+        state.second_offset = None;
+        state.offset = offset;
+        state.backlog = Some(Backlog::new());
+        std::mem::take(&mut state.replicas).into_keys().collect()
+    }
+
+    // The master went on with a history of its own, which the one before
+    // leads into, as when a replica of the same master was promoted
+    fn continued(&self, id: Option<String>) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(id) = id.filter(|id| *id != state.id) {
+            state.id2 = std::mem::replace(&mut state.id, id);
+            state.second_offset = Some(state.offset + 1);
+        }
+        state.backlog.get_or_insert_with(Backlog::new);
+    }
+
+    fn set_link(&self, state: LinkState) {
+        *self.link.lock().unwrap() = state;
+    }
+}
+
+// 40 hex characters, as Redis' replication ids and cluster node ids
+pub fn random_id() -> String {
+    let seed = format!(
+        "{:?} {} {}",
+        SystemTime::now(),
+        std::process::id(),
+        RandomState::new().hash_one(0u8)
+    );
+    sha1_smol::Sha1::from(seed).digest().to_string()
+}
+
+// Keeps this server in sync with the master in replicaof, reconnecting a
+// second after the link drops, until the server shuts down
+pub async fn link(shared: Arc<Shared>) {
+    let replication = &shared.replication;
+    loop {
+        let master = shared.config.read().unwrap().replicaof.clone();
+        let synced = async {
+            match &master {
+                Some((host, port)) => sync(&shared, host, *port).await,
+                None => future::pending().await,
+            }
+        };
+        let result = tokio::select! {
+            result = synced => Some(result),
+            _ = replication.master_changed.notified() => None,
+            _ = shared.shutdown.wait() => return,
+        };
+        replication.set_link(LinkState::Connect);
+        let Some(result) = result else {
+            continue;
+        };
+        if let Err(error) = result {
+            if shared.config.read().unwrap().loglevel <= LogLevel::Warning {
+                eprintln!("Error condition on socket for SYNC: {error}");
+            }
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+            _ = replication.master_changed.notified() => {}
+            _ = shared.shutdown.wait() => return,
+        }
+    }
+}
+
+// Runs the handshake with the master, loads the dump it sends and applies
+// the writes it streams after it, for as long as the connection lasts
+async fn sync(shared: &Shared, host: &str, port: u16) -> io::Result<()> {
+    let replication = &shared.replication;
+    let (listening_port, limits, loglevel) = {
+        let config = shared.config.read().unwrap();
+        (config.port, config.limits, config.loglevel)
+    };
+    replication.set_link(LinkState::Connecting);
+    let mut stream = TcpStream::connect((host, port)).await?;
+    let mut buffer = BytesMut::new();
+
+    let pong = request(&mut stream, &mut buffer, &["PING"]).await?;
+    if pong.starts_with('-') {
+        return Err(io::Error::other(format!(
+            "Error reply to PING from master: '{pong}'"
+        )));
+    }
+    // masters that don't know these options sync all the same
+    let listening_port = listening_port.to_string();
+    let options: [&[&str]; 2] = [
+        &["REPLCONF", "listening-port", &listening_port],
+        &["REPLCONF", "capa", "eof", "capa", "psync2"],
+    ];
+    for args in options {
+        request(&mut stream, &mut buffer, args).await?;
+    }
+
+    // a server goes on from its own history, which a master it branched
+    // off, or one that branched off the same, can continue
+    replication.set_link(LinkState::Sync);
+    let (id, offset) = {
+        let state = replication.state.lock().unwrap();
+        (state.id.clone(), state.offset)
+    };
+    let next = (offset + 1).to_string();
+    let reply = request(&mut stream, &mut buffer, &["PSYNC", &id, &next]).await?;
+    if let Some(continued) = reply.strip_prefix("+CONTINUE") {
+        // a master that kept its id doesn't name it
+        let continued = continued.trim();
+        replication.continued((!continued.is_empty()).then(|| continued.to_string()));
+        replication.set_link(LinkState::Connected);
+        if loglevel <= LogLevel::Notice {
+            eprintln!("MASTER <-> REPLICA sync: Master accepted a Partial Resynchronization.");
+        }
+    } else {
+        let (id, offset) = reply
+            .strip_prefix("+FULLRESYNC ")
+            .and_then(|position| position.split_once(' '))
+            .and_then(|(id, offset)| Some((id.to_string(), offset.parse().ok()?)))
+            .ok_or_else(|| {
+                io::Error::other(format!("Unexpected reply to PSYNC from master: {reply}"))
+            })?;
+        if loglevel <= LogLevel::Notice {
+            eprintln!("Full resync from master: {id}:{offset}");
+        }
+
+        let dump = read_bulk(&mut stream, &mut buffer).await?;
+        let (entries, persisted) = rdb::read(&dump, shared.rdb_options())?;
+        rdb::warn_persisted(persisted, loglevel);
+        // no command sees the dataset half loaded, as EXEC has it to itself
+        let replicas = {
+            let _exclusive = shared.exec_lock.write().unwrap();
+            shared.store.clear();
+            for (key, value) in entries {
+                shared.store.set(key, value);
+            }
+            replication.resynced(id, offset)
+        };
+        if !replicas.is_empty() && loglevel <= LogLevel::Notice {
+            eprintln!(
+                "Disconnecting {} replicas after the full resync",
+                replicas.len()
+            );
+        }
+        for replica in replicas {
+            shared.clients.kill(replica);
+        }
+        replication.set_link(LinkState::Connected);
+        if loglevel <= LogLevel::Notice {
+            eprintln!("MASTER <-> REPLICA sync: Finished with success");
+        }
+    }
+
+    // REPLCONF GETACK replies through the connection's messages
+    let (sender, mut acks) = mpsc::unbounded_channel();
+    let mut connection = ConnectionState::new(0, sender);
+    connection.master = true;
+    // the master learns how far this replica got from an ACK every second
+    let mut ticks = tokio::time::interval(Duration::from_secs(1));
+    let (mut reader, mut writer) = stream.split();
+    loop {
+        loop {
+            // the dump may be followed by the CRLF a bulk string ends with
+            let newlines = buffer.iter().take_while(|b| matches!(b, b'\r' | b'\n'));
+            let _ = buffer.split_to(newlines.count());
+            match decode_frame(&mut buffer, &limits) {
+                Ok(frame) => {
+                    shared.dispatch(frame.clone(), &mut connection);
+                    let backlog_size = shared.config.read().unwrap().repl_backlog_size;
+                    replication.forward(&frame, backlog_size);
+                }
+                Err(RESPDecodeError::NeedMoreData) => break,
+                Err(RESPDecodeError::Invalid(error)) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Protocol error from master: {error}"),
+                    ))
+                }
+            }
+        }
+        while let Ok(ack) = acks.try_recv() {
+            writer.write_all(&ack.to_bytes()).await?;
+        }
+        tokio::select! {
+            read = reader.read_buf(&mut buffer) => {
+                if read? == 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Connection with master lost",
+                    ));
+                }
+            }
+            _ = ticks.tick() => {
+                writer.write_all(&ack(replication.offset()).to_bytes()).await?;
+            }
+        }
+    }
+}
+
+// REPLCONF ACK, telling the master how far into its stream this replica is
+pub fn ack(offset: u64) -> RESPValues {
+    let args = ["REPLCONF", "ACK", &offset.to_string()]
+        .map(|arg| RESPValues::BulkString(Bytes::copy_from_slice(arg.as_bytes())));
+    RESPValues::Array(args.to_vec())
+}
+
+// Sends a command to the master and returns the line it replies with
+async fn request(
+    stream: &mut TcpStream,
+    buffer: &mut BytesMut,
+    args: &[&str],
+) -> io::Result<String> {
+    let args = args
+        .iter()
+        .map(|arg| RESPValues::BulkString(Bytes::copy_from_slice(arg.as_bytes())))
+        .collect();
+    stream
+        .write_all(&RESPValues::Array(args).to_bytes())
+        .await?;
+    read_line(stream, buffer).await
+}
+
+async fn read_line(stream: &mut TcpStream, buffer: &mut BytesMut) -> io::Result<String> {
+    loop {
+        if let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+            let line = buffer.split_to(end + 1);
+            return Ok(String::from_utf8_lossy(&line).trim_end().to_string());
+        }
+        if stream.read_buf(buffer).await? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Connection with master lost",
+            ));
+        }
+    }
+}
+
+// The dump after +FULLRESYNC, `$<length>\r\n` and that many bytes, which
+// masters may precede with newlines to keep the link alive while they write
+// it, or with diskless replication `$EOF:<mark>\r\n`, the dump and the mark
+async fn read_bulk(stream: &mut TcpStream, buffer: &mut BytesMut) -> io::Result<Bytes> {
+    let header = loop {
+        let line = read_line(stream, buffer).await?;
+        if !line.is_empty() {
+            break line;
+        }
+    };
+    let bad = || {
+        io::Error::other(format!(
+            "Bad protocol from master, the first byte is not '$': {header}"
+        ))
+    };
+    let length = header.strip_prefix('$').ok_or_else(bad)?;
+    let mark = length.strip_prefix("EOF:").map(str::as_bytes);
+    let length: usize = match mark {
+        Some(_) => 0,
+        None => length.parse().map_err(|_| bad())?,
+    };
+    // where the dump ends in the buffer, and the mark after it
+    let end = |buffer: &BytesMut| match mark {
+        Some(mark) => buffer
+            .windows(mark.len())
+            .position(|window| window == mark)
+            .map(|position| (position, mark.len())),
+        None => (buffer.len() >= length).then_some((length, 0)),
+    };
+    loop {
+        if let Some((length, mark)) = end(buffer) {
+            let dump = buffer.split_to(length).freeze();
+            let _ = buffer.split_to(mark);
+            return Ok(dump);
+        }
+        if stream.read_buf(buffer).await? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Connection with master lost while reading the dump",
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod replication_tests {
+    use bytes::Bytes;
+    use tokio::sync::mpsc;
+
+    use super::{ReplicaState, Replication};
+    use crate::{
+        resp::RESPValues,
+        store::{Store, Value},
+    };
+
+    #[test]
+    fn send_writes_after_the_dump_correctly() {
+        let replication = Replication::default();
+        let store = Store::default();
+        let (sender, mut messages) = mpsc::unbounded_channel();
+        let write = RESPValues::Array(vec![RESPValues::BulkString(Bytes::from_static(b"DEL"))]);
+
+        replication.full_sync(
+            7,
+            ("127.0.0.1".to_string(), 6380),
+            sender,
+            store.snapshot(),
+            Default::default(),
+        );
+        replication.feed(&write, 1024);
+
+        let id = replication.id();
+        let fullresync = RESPValues::SimpleString(format!("FULLRESYNC {id} 0"));
+        assert_eq!(messages.blocking_recv(), Some(fullresync));
+        assert!(matches!(
+            messages.blocking_recv(),
+            Some(RESPValues::BulkString(dump)) if dump.starts_with(b"REDIS")
+        ));
+        assert_eq!(messages.blocking_recv(), Some(write.clone()));
+        assert_eq!(replication.offset(), write.to_bytes().len() as u64);
+        let state = replication.state.lock().unwrap();
+        assert_eq!(state.replicas[&7].state, ReplicaState::Online);
+    }
+
+    #[test]
+    fn detach_the_replicas_after_a_full_resync_correctly() {
+        let replication = Replication::default();
+        let store = Store::default();
+        let (sender, _messages) = mpsc::unbounded_channel();
+        replication.full_sync(
+            7,
+            ("127.0.0.1".to_string(), 6380),
+            sender,
+            store.snapshot(),
+            Default::default(),
+        );
+
+        let detached = replication.resynced("a".repeat(40), 100);
+
+        assert_eq!(detached, vec![7]);
+        assert!(replication.replicas().is_empty());
+        assert_eq!(replication.offset(), 100);
+    }
+
+    #[test]
+    fn feed_without_replicas_correctly() {
+        let replication = Replication::default();
+        let id = replication.id();
+        replication.feed(&RESPValues::Array(vec![]), 1024);
+
+        assert_eq!(replication.offset(), 0);
+        replication.shift_id();
+        assert_ne!(replication.id(), id);
+        assert_eq!(replication.state.lock().unwrap().id2, id);
+    }
+
+    fn write(key: &'static [u8]) -> RESPValues {
+        RESPValues::Array(vec![
+            RESPValues::BulkString(Bytes::from_static(b"DEL")),
+            RESPValues::BulkString(Bytes::from_static(key)),
+        ])
+    }
+
+    #[test]
+    fn continue_from_the_backlog_correctly() {
+        let replication = Replication::default();
+        let (sender, _messages) = mpsc::unbounded_channel();
+        let address = ("127.0.0.1".to_string(), 6380);
+        let snapshot = Store::default().snapshot();
+        replication.full_sync(7, address.clone(), sender, snapshot, Default::default());
+        let id = replication.id();
+        replication.detach(7);
+        let length = write(b"a").to_bytes().len() as u64;
+        replication.feed(&write(b"a"), 1024);
+        replication.feed(&write(b"b"), 1024);
+
+        let (sender, mut messages) = mpsc::unbounded_channel();
+        assert!(replication.partial_sync(8, address.clone(), sender, (&id, length + 1)));
+        let reply = RESPValues::SimpleString(format!("CONTINUE {id}"));
+        assert_eq!(messages.blocking_recv(), Some(reply));
+        assert_eq!(messages.blocking_recv(), Some(write(b"b")));
+
+        // still continuing the history it was promoted from
+        replication.shift_id();
+        let (sender, mut messages) = mpsc::unbounded_channel();
+        assert!(replication.partial_sync(9, address, sender, (&id, 2 * length + 1)));
+        let reply = RESPValues::SimpleString(format!("CONTINUE {}", replication.id()));
+        assert_eq!(messages.blocking_recv(), Some(reply));
+    }
+
+    #[test]
+    fn continue_from_a_dropped_offset_fails() {
+        let replication = Replication::default();
+        let (sender, _messages) = mpsc::unbounded_channel();
+        let address = ("127.0.0.1".to_string(), 6380);
+        let snapshot = Store::default().snapshot();
+        replication.full_sync(7, address.clone(), sender, snapshot, Default::default());
+        let id = replication.id();
+        let length = write(b"a").to_bytes().len();
+        replication.feed(&write(b"a"), length);
+        replication.feed(&write(b"b"), length);
+
+        let (sender, _messages) = mpsc::unbounded_channel();
+        assert!(!replication.partial_sync(8, address.clone(), sender, (&id, 1)));
+        let (sender, _messages) = mpsc::unbounded_channel();
+        let other = super::random_id();
+        let offset = length as u64 + 1;
+        assert!(!replication.partial_sync(8, address, sender, (&other, offset)));
+    }
+
+    #[test]
+    fn stream_a_diskless_dump_to_the_replicas_waiting_correctly() {
+        let replication = Replication::default();
+        let store = Store::default();
+        store.set(
+            Bytes::from_static(b"k"),
+            Value::String(Bytes::from_static(b"v")),
+        );
+        let address = ("127.0.0.1".to_string(), 6380);
+        let (first, mut first_messages) = mpsc::unbounded_channel();
+        let (second, mut second_messages) = mpsc::unbounded_channel();
+        replication.wait_for_dump(7, address.clone(), first);
+        replication.wait_for_dump(8, address, second);
+        // in the dump to come
+        replication.feed(&write(b"a"), 1024);
+
+        assert!(replication.dump_due(0));
+        assert!(!replication.dump_due(60));
+        replication.start_diskless(store.snapshot(), Default::default());
+        replication.feed(&write(b"b"), 1024);
+
+        let offset = write(b"a").to_bytes().len();
+        let fullresync = format!("FULLRESYNC {} {offset}", replication.id());
+        for messages in [&mut first_messages, &mut second_messages] {
+            assert_eq!(
+                messages.blocking_recv(),
+                Some(RESPValues::SimpleString(fullresync.clone()))
+            );
+            let mut stream = Vec::new();
+            while let Some(RESPValues::Raw(chunk)) = messages.blocking_recv() {
+                stream.extend_from_slice(&chunk);
+                if stream.len() >= 87 && stream.ends_with(&stream[5..45]) {
+                    break;
+                }
+            }
+            assert!(stream.starts_with(b"$EOF:"));
+            let dump = &stream[47..stream.len() - 40];
+            assert_eq!(
+                crate::rdb::read(dump, Default::default()).unwrap().0.len(),
+                1
+            );
+            assert_eq!(messages.blocking_recv(), Some(write(b"b")));
+        }
+        assert!(!replication.dump_due(0));
+    }
+}
@@ -1,4 +1,6 @@
-use regex::Regex;
+use std::io::Write;
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum RESPValues {
@@ -6,122 +8,725 @@ pub enum RESPValues {
     SimpleString(String),
     SimpleError(String),
     Integer(i64),
-    BulkString(String),
+    BulkString(Bytes),
     Array(Vec<RESPValues>),
+    NullBulkString,
+    NullArray,
     // RESP3
     Null,
-    Boolean,
-    Double,
-    BigNumber,
-    BulkError,
-    VerbatimString,
-    Map,
-    Set,
-    Push,
-}
-
-impl TryFrom<&str> for RESPValues {
-    type Error = ();
-
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        if value.len() == 0 {
-            todo!("Returns error if len of value is 0");
-        }
+    Boolean(bool),
+    Double(f64),
+    BigNumber(String),
+    BulkError(Bytes),
+    VerbatimString(String, Bytes),
+    Map(Vec<(RESPValues, RESPValues)>),
+    Set(Vec<RESPValues>),
+    Push(Vec<RESPValues>),
+    // bytes that go out as they are, for the dumps of diskless replication
+    Raw(Bytes),
+}
 
-        let (first_element, rest_elements) = match value.split_once("\r\n") {
-            Some((first, rest)) => (first, rest),
-            None => todo!("Handle split once \\r\\n"),
-        };
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum RESPVersion {
+    #[default]
+    RESP2,
+    RESP3,
+}
 
-        // Match all single line elements
-        // Match simple strings
-        if let Some(captures) = Regex::new(r"^\+(?<value>.+)$")
-            .unwrap()
-            .captures(&first_element)
-        {
-            return Ok(Self::SimpleString(captures["value"].to_string()));
+// Bounds enforced while decoding so a hostile client can't trigger deep
+// recursion or make the server buffer arbitrarily large frames
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct RESPLimits {
+    pub max_nesting_depth: usize,
+    // proto-max-bulk-len
+    pub max_bulk_length: usize,
+    // longest line accepted without a CRLF, e.g. an inline command or a type header
+    pub max_inline_length: usize,
+    // client-query-buffer-limit, bounding incomplete frames such as huge arrays of small elements
+    pub max_query_buffer_length: usize,
+}
+
+impl Default for RESPLimits {
+    fn default() -> Self {
+        Self {
+            max_nesting_depth: 32,
+            max_bulk_length: 512 * 1024 * 1024,
+            max_inline_length: 64 * 1024,
+            max_query_buffer_length: 1024 * 1024 * 1024,
         }
-        // Match simple errors
-        if let Some(captures) = Regex::new("^-(?<value>.+)$")
-            .unwrap()
-            .captures(&first_element)
-        {
-            return Ok(Self::SimpleError(captures["value"].to_string()));
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum RESPParseError {
+    EmptyInput,
+    MissingCRLF,
+    UnknownType(u8),
+    InvalidInteger,
+    InvalidBulkLength,
+    BulkLengthMismatch,
+    InvalidMultibulkLength,
+    InvalidBoolean,
+    InvalidDouble,
+    InvalidBigNumber,
+    InvalidVerbatimString,
+    UnbalancedQuotes,
+    NestingTooDeep,
+    BulkTooLong,
+    LineTooLong,
+    QueryBufferTooLong,
+}
+
+impl std::fmt::Display for RESPParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EmptyInput => write!(f, "empty input"),
+            Self::MissingCRLF => write!(f, "missing CRLF terminator"),
+            Self::UnknownType(c) => write!(f, "unknown type byte '{}'", c.escape_ascii()),
+            Self::InvalidInteger => write!(f, "invalid integer"),
+            Self::InvalidBulkLength => write!(f, "invalid bulk length"),
+            Self::BulkLengthMismatch => write!(f, "bulk length does not match its payload"),
+            Self::InvalidMultibulkLength => write!(f, "invalid multibulk length"),
+            Self::InvalidBoolean => write!(f, "invalid boolean"),
+            Self::InvalidDouble => write!(f, "invalid double"),
+            Self::InvalidBigNumber => write!(f, "invalid big number"),
+            Self::InvalidVerbatimString => write!(f, "invalid verbatim string"),
+            Self::UnbalancedQuotes => write!(f, "unbalanced quotes in request"),
+            Self::NestingTooDeep => write!(f, "nesting depth exceeds the maximum"),
+            Self::BulkTooLong => write!(f, "bulk length exceeds proto-max-bulk-len"),
+            Self::LineTooLong => write!(f, "too big inline request"),
+            Self::QueryBufferTooLong => {
+                write!(f, "query buffer exceeds client-query-buffer-limit")
+            }
         }
-        // Match 64bit integers
-        if let Some(captures) = Regex::new(r"^:(?<value>(\+|-)?\d+)$")
-            .unwrap()
-            .captures(&first_element)
-        {
-            return match &captures["value"].parse::<i64>() {
-                Ok(v) => Ok(Self::Integer(*v)),
-                Err(_) => todo!("Resolve Error in integer match"),
-            };
+    }
+}
+
+impl TryFrom<&[u8]> for RESPValues {
+    type Error = RESPParseError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        RESPValues::try_from(Bytes::copy_from_slice(value))
+    }
+}
+
+impl TryFrom<Bytes> for RESPValues {
+    type Error = RESPParseError;
+
+    fn try_from(value: Bytes) -> Result<Self, Self::Error> {
+        parse_value(&value, &RESPLimits::default(), 0).map(|(v, _)| v)
+    }
+}
+
+// Parses the first value in `value`, returning it along with the number of bytes it spans.
+// Bulk payloads are slices of `value`, so they share its buffer instead of being copied
+fn parse_value(
+    value: &Bytes,
+    limits: &RESPLimits,
+    depth: usize,
+) -> Result<(RESPValues, usize), RESPParseError> {
+    if value.is_empty() {
+        return Err(RESPParseError::EmptyInput);
+    }
+
+    let (first_element, rest_elements) =
+        split_once_crlf(value).ok_or(RESPParseError::MissingCRLF)?;
+    let header_length = first_element.len() + 2;
+    let (kind, line) = first_element
+        .split_first()
+        .map(|(kind, line)| (*kind, line))
+        .ok_or(RESPParseError::UnknownType(value[0]))?;
+
+    let value = match kind {
+        // Match all single line elements
+        b'+' => RESPValues::SimpleString(String::from_utf8_lossy(line).to_string()),
+        b'-' => RESPValues::SimpleError(String::from_utf8_lossy(line).to_string()),
+        b':' => RESPValues::Integer(parse_number(line).ok_or(RESPParseError::InvalidInteger)?),
+        b'#' => match line {
+            b"t" => RESPValues::Boolean(true),
+            b"f" => RESPValues::Boolean(false),
+            _ => return Err(RESPParseError::InvalidBoolean),
+        },
+        b',' => RESPValues::Double(parse_number(line).ok_or(RESPParseError::InvalidDouble)?),
+        b'(' => {
+            let digits = line
+                .strip_prefix(b"+")
+                .or(line.strip_prefix(b"-"))
+                .unwrap_or(line);
+            if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+                return Err(RESPParseError::InvalidBigNumber);
+            }
+            RESPValues::BigNumber(String::from_utf8_lossy(line).to_string())
         }
+        b'_' if line.is_empty() => RESPValues::Null,
+        b'$' if line == b"-1" => RESPValues::NullBulkString,
+        b'*' if line == b"-1" => RESPValues::NullArray,
 
         // Match all 2+ lines elements
-        // Match bulk string
-        if let Some(_) = Regex::new(r"^\$\d+").unwrap().captures(&first_element) {
-            return match rest_elements.split("\r\n").next() {
-                None => todo!("Handle none in match bulk string"),
-                Some(v) => Ok(Self::BulkString(v.to_string())),
+        b'$' | b'!' | b'=' => {
+            let length = parse_number::<usize>(line).ok_or(RESPParseError::InvalidBulkLength)?;
+            if length > limits.max_bulk_length {
+                return Err(RESPParseError::BulkTooLong);
+            }
+            if rest_elements.len() < length.saturating_add(2) {
+                return Err(RESPParseError::MissingCRLF);
+            }
+            if &rest_elements[length..length + 2] != b"\r\n" {
+                return Err(RESPParseError::BulkLengthMismatch);
+            }
+
+            let data = value.slice(header_length..header_length + length);
+            let value = match kind {
+                b'$' => RESPValues::BulkString(data),
+                b'!' => RESPValues::BulkError(data),
+                _ => {
+                    if data.len() < 4 || data[3] != b':' {
+                        return Err(RESPParseError::InvalidVerbatimString);
+                    }
+                    let encoding = String::from_utf8_lossy(&data[..3]).to_string();
+                    RESPValues::VerbatimString(encoding, data.slice(4..))
+                }
             };
+            return Ok((value, header_length + length + 2));
         }
+        b'*' | b'~' | b'>' | b'%' => {
+            let n = parse_number::<usize>(line).ok_or(RESPParseError::InvalidMultibulkLength)?;
+            if depth >= limits.max_nesting_depth {
+                return Err(RESPParseError::NestingTooDeep);
+            }
+            let count = match kind {
+                b'%' => n
+                    .checked_mul(2)
+                    .ok_or(RESPParseError::InvalidMultibulkLength)?,
+                _ => n,
+            };
+            // every element takes at least 3 bytes, so don't trust the header any further than that
+            let mut elements = Vec::with_capacity(count.min(value.len() / 3));
+            let mut length = header_length;
+
+            for _ in 0..count {
+                let (element, element_length) =
+                    parse_value(&value.slice(length..), limits, depth + 1)?;
 
-        // Match arrays
-        if let Some(captures) = Regex::new(r"^\*(?<array_length>\d+)$")
-            .unwrap()
-            .captures(&first_element)
-        {
-            let n = match captures["array_length"].parse::<usize>() {
-                Ok(v) => v,
-                Err(_) => todo!("Array size not usize parseable"),
+                length += element_length;
+                elements.push(element);
+            }
+
+            let value = match kind {
+                b'*' => RESPValues::Array(elements),
+                b'~' => RESPValues::Set(elements),
+                b'>' => RESPValues::Push(elements),
+                _ => {
+                    let mut elements = elements.into_iter();
+                    let mut pairs = Vec::with_capacity(n);
+                    while let (Some(k), Some(v)) = (elements.next(), elements.next()) {
+                        pairs.push((k, v));
+                    }
+                    RESPValues::Map(pairs)
+                }
             };
-            let mut array = Vec::with_capacity(n);
-            let mut remaining_elements = rest_elements.to_string();
+            return Ok((value, length));
+        }
+        _ => return Err(RESPParseError::UnknownType(kind)),
+    };
 
-            for _ in 0..n {
-                let result = match RESPValues::try_from(remaining_elements.as_str()) {
-                    Ok(v) => v,
-                    Err(_) => todo!("Handle recursive array try from"),
-                };
+    Ok((value, header_length))
+}
+
+impl RESPValues {
+    // Decodes the first value in `input`, returning it along with the number of
+    // bytes it took, so `decode(&encoded)` gives back what `encode` wrote
+    pub fn decode(input: &Bytes) -> Result<(RESPValues, usize), RESPParseError> {
+        parse_value(input, &RESPLimits::default(), 0)
+    }
 
-                remaining_elements = remaining_elements.replacen(&result.to_string(), "", 1);
-                array.push(result);
+    // Writes the encoded value into `dst`, e.g. a connection's reusable output
+    // buffer, so several replies can be batched without intermediate allocations
+    pub fn encode(&self, dst: &mut impl BufMut) {
+        match self {
+            Self::SimpleString(v) => put_line(dst, b'+', v.as_bytes()),
+            Self::SimpleError(v) => put_line(dst, b'-', v.as_bytes()),
+            Self::Integer(v) => put_header(dst, b':', *v),
+            Self::BulkString(v) => put_length_prefixed(dst, b'$', &[v]),
+            Self::Array(v) => put_aggregate(dst, b'*', v),
+            Self::NullBulkString => dst.put_slice(b"$-1\r\n"),
+            Self::NullArray => dst.put_slice(b"*-1\r\n"),
+            Self::Null => dst.put_slice(b"_\r\n"),
+            Self::Boolean(v) => dst.put_slice(if *v { b"#t\r\n" } else { b"#f\r\n" }),
+            Self::Double(v) => {
+                dst.put_u8(b',');
+                put_double(dst, *v);
+                dst.put_slice(b"\r\n");
             }
+            Self::BigNumber(v) => put_line(dst, b'(', v.as_bytes()),
+            Self::BulkError(v) => put_length_prefixed(dst, b'!', &[v]),
+            Self::VerbatimString(encoding, v) => {
+                put_length_prefixed(dst, b'=', &[encoding.as_bytes(), b":", v])
+            }
+            Self::Map(v) => {
+                put_header(dst, b'%', v.len() as i64);
+                for (key, value) in v {
+                    key.encode(dst);
+                    value.encode(dst);
+                }
+            }
+            Self::Set(v) => put_aggregate(dst, b'~', v),
+            Self::Push(v) => put_aggregate(dst, b'>', v),
+            Self::Raw(v) => dst.put_slice(v),
+        }
+    }
 
-            return Ok(Self::Array(array));
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.encode(&mut bytes);
+        bytes
+    }
+
+    // Maps the value to its closest representation in `version`, so replies can
+    // be built once and sent to both RESP2 and RESP3 clients
+    pub fn to_protocol(self, version: RESPVersion) -> RESPValues {
+        match version {
+            RESPVersion::RESP2 => match self {
+                Self::Array(v) | Self::Set(v) | Self::Push(v) => {
+                    Self::Array(v.into_iter().map(|e| e.to_protocol(version)).collect())
+                }
+                Self::Map(v) => Self::Array(
+                    v.into_iter()
+                        .flat_map(|(k, v)| [k.to_protocol(version), v.to_protocol(version)])
+                        .collect(),
+                ),
+                Self::Null => Self::NullBulkString,
+                Self::Boolean(v) => Self::Integer(v.into()),
+                Self::Double(v) => Self::BulkString(format_double(v).into()),
+                Self::BigNumber(v) => Self::BulkString(v.into()),
+                // a simple error ends at the first CRLF, Redis blanks them out too
+                Self::BulkError(v) => {
+                    Self::SimpleError(String::from_utf8_lossy(&v).replace(['\r', '\n'], " "))
+                }
+                Self::VerbatimString(_, v) => Self::BulkString(v),
+                v => v,
+            },
+            RESPVersion::RESP3 => match self {
+                Self::Array(v) => {
+                    Self::Array(v.into_iter().map(|e| e.to_protocol(version)).collect())
+                }
+                Self::Set(v) => Self::Set(v.into_iter().map(|e| e.to_protocol(version)).collect()),
+                Self::Push(v) => {
+                    Self::Push(v.into_iter().map(|e| e.to_protocol(version)).collect())
+                }
+                Self::Map(v) => Self::Map(
+                    v.into_iter()
+                        .map(|(k, v)| (k.to_protocol(version), v.to_protocol(version)))
+                        .collect(),
+                ),
+                Self::NullBulkString | Self::NullArray => Self::Null,
+                v => v,
+            },
         }
+    }
+}
 
-        Ok(Self::BigNumber)
+fn format_double(value: f64) -> String {
+    let mut bytes = Vec::new();
+    put_double(&mut bytes, value);
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+fn put_double(dst: &mut impl BufMut, value: f64) {
+    if value.is_nan() {
+        dst.put_slice(b"nan");
+    } else if value.is_infinite() && value > 0.0 {
+        dst.put_slice(b"inf");
+    } else if value.is_infinite() {
+        dst.put_slice(b"-inf");
+    } else {
+        let _ = write!(dst.writer(), "{value}");
     }
 }
 
-impl ToString for RESPValues {
-    fn to_string(&self) -> String {
-        match self {
-            Self::SimpleString(v) => format!("+{v}\r\n"),
-            Self::SimpleError(v) => format!("-{v}\r\n"),
-            Self::Integer(v) => format!(":{v}\r\n"),
-            Self::BulkString(v) => format!("${}\r\n{}\r\n", v.len(), v),
-            Self::Array(v) => {
-                let length = v.len();
-                let elements_repr: Vec<_> = v.iter().map(|e| e.to_string()).collect();
-                let elements_repr = elements_repr.join("");
-                format!("*{length}\r\n{elements_repr}")
+fn put_line(dst: &mut impl BufMut, kind: u8, value: &[u8]) {
+    dst.put_u8(kind);
+    dst.put_slice(value);
+    dst.put_slice(b"\r\n");
+}
+
+// Writes `kind` followed by `value` in decimal and CRLF, without going through `format!`
+pub(crate) fn put_header(dst: &mut impl BufMut, kind: u8, value: i64) {
+    let mut digits = [0; 20];
+    let mut remaining = value.unsigned_abs();
+    let mut start = digits.len();
+    loop {
+        start -= 1;
+        digits[start] = b'0' + (remaining % 10) as u8;
+        remaining /= 10;
+        if remaining == 0 {
+            break;
+        }
+    }
+
+    dst.put_u8(kind);
+    if value < 0 {
+        dst.put_u8(b'-');
+    }
+    dst.put_slice(&digits[start..]);
+    dst.put_slice(b"\r\n");
+}
+
+fn put_length_prefixed(dst: &mut impl BufMut, kind: u8, parts: &[&[u8]]) {
+    put_header(
+        dst,
+        kind,
+        parts.iter().map(|p| p.len()).sum::<usize>() as i64,
+    );
+    for part in parts {
+        dst.put_slice(part);
+    }
+    dst.put_slice(b"\r\n");
+}
+
+fn put_aggregate(dst: &mut impl BufMut, kind: u8, values: &[RESPValues]) {
+    put_header(dst, kind, values.len() as i64);
+    for element in values {
+        element.encode(dst);
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum RESPDecodeError {
+    NeedMoreData,
+    Invalid(RESPParseError),
+}
+
+impl From<RESPParseError> for RESPDecodeError {
+    fn from(value: RESPParseError) -> Self {
+        Self::Invalid(value)
+    }
+}
+
+// Buffers bytes read from a connection and yields complete frames from it,
+// so a frame split across several reads is only parsed once it has fully arrived
+#[derive(Default)]
+pub struct RESPDecoder {
+    buffer: BytesMut,
+    limits: RESPLimits,
+    scan: FrameScan,
+}
+
+impl RESPDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_limits(limits: RESPLimits) -> Self {
+        Self {
+            buffer: BytesMut::new(),
+            limits,
+            scan: FrameScan::default(),
+        }
+    }
+
+    // Decodes out of `buffer`, e.g. one taken from a BufferPool
+    pub fn with_buffer(limits: RESPLimits, buffer: BytesMut) -> Self {
+        Self {
+            buffer,
+            limits,
+            scan: FrameScan::default(),
+        }
+    }
+
+    // Gives the buffer back, with any input not decoded yet
+    pub fn into_buffer(self) -> BytesMut {
+        self.buffer
+    }
+
+    pub fn feed(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    // The buffer pending input accumulates in, for reading from a socket
+    // straight into it instead of going through `feed`. Input is only ever
+    // appended to it, the decoder resumes where it left off
+    pub fn buffer_mut(&mut self) -> &mut BytesMut {
+        &mut self.buffer
+    }
+
+    pub fn decode(&mut self) -> Result<RESPValues, RESPDecodeError> {
+        let result = resume_frame(&mut self.buffer, &self.limits, &mut self.scan);
+        if let Err(RESPDecodeError::Invalid(_)) = result {
+            self.scan = FrameScan::default();
+        }
+        result
+    }
+}
+
+// How much of a frame that hasn't fully arrived frame_length went through,
+// so the next read picks up from there instead of scanning it all again
+#[derive(Default, Debug, Clone)]
+pub(crate) struct FrameScan {
+    // the length of the frame's elements that are complete
+    scanned: usize,
+    // how many elements each aggregate the scan is in still expects,
+    // outermost first. Empty when there's no frame under way
+    pending: Vec<u64>,
+}
+
+// Decodes the first complete frame in `buffer` and removes it from the buffer.
+// The frame is split off without copying, so bulk payloads keep pointing at it
+pub fn decode_frame(
+    buffer: &mut BytesMut,
+    limits: &RESPLimits,
+) -> Result<RESPValues, RESPDecodeError> {
+    resume_frame(buffer, limits, &mut FrameScan::default())
+}
+
+// decode_frame, going on from where `scan` stopped the last time
+pub(crate) fn resume_frame(
+    buffer: &mut BytesMut,
+    limits: &RESPLimits,
+    scan: &mut FrameScan,
+) -> Result<RESPValues, RESPDecodeError> {
+    // anything not starting with a type byte is an inline command, e.g. `PING\r\n` from telnet
+    while buffer.first().is_some_and(|c| !is_type_byte(*c)) {
+        let (value, length) = decode_inline(buffer, limits)?;
+        buffer.advance(length);
+
+        // empty lines are skipped rather than treated as a command
+        if let Some(value) = value {
+            return Ok(value);
+        }
+    }
+
+    let length = match frame_length(buffer, limits, scan) {
+        Err(RESPDecodeError::NeedMoreData) if buffer.len() > limits.max_query_buffer_length => {
+            return Err(RESPParseError::QueryBufferTooLong.into())
+        }
+        length => length?,
+    };
+    let frame = buffer.split_to(length).freeze();
+    let (value, _) = parse_value(&frame, limits, 0)?;
+
+    Ok(value)
+}
+
+fn decode_inline(
+    buffer: &[u8],
+    limits: &RESPLimits,
+) -> Result<(Option<RESPValues>, usize), RESPDecodeError> {
+    let end = match buffer.iter().position(|c| *c == b'\n') {
+        Some(end) => end,
+        None if buffer.len() > limits.max_inline_length => {
+            return Err(RESPParseError::LineTooLong.into())
+        }
+        None => return Err(RESPDecodeError::NeedMoreData),
+    };
+    let arguments = split_inline_arguments(&buffer[..end])?;
+
+    if arguments.is_empty() {
+        return Ok((None, end + 1));
+    }
+
+    let value = RESPValues::Array(
+        arguments
+            .into_iter()
+            .map(|a| RESPValues::BulkString(a.into()))
+            .collect(),
+    );
+    Ok((Some(value), end + 1))
+}
+
+fn is_type_byte(c: u8) -> bool {
+    matches!(
+        c,
+        b'+' | b'-'
+            | b':'
+            | b'$'
+            | b'*'
+            | b'_'
+            | b'#'
+            | b','
+            | b'('
+            | b'!'
+            | b'='
+            | b'%'
+            | b'~'
+            | b'>'
+    )
+}
+
+// Splits an inline command line into arguments the way redis-cli and Redis'
+// sdssplitargs do: whitespace separated, with "double quotes" supporting
+// escapes like \n and \x41, and 'single quotes' supporting only \'
+pub(crate) fn split_inline_arguments(line: &[u8]) -> Result<Vec<Vec<u8>>, RESPParseError> {
+    let mut arguments = Vec::new();
+    let mut i = 0;
+
+    loop {
+        while i < line.len() && line[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i == line.len() {
+            return Ok(arguments);
+        }
+
+        let mut argument = Vec::new();
+        match line[i] {
+            quote @ (b'"' | b'\'') => {
+                i += 1;
+                loop {
+                    match line.get(i) {
+                        None => return Err(RESPParseError::UnbalancedQuotes),
+                        Some(c) if *c == quote => {
+                            i += 1;
+                            break;
+                        }
+                        Some(b'\\') if quote == b'"' => {
+                            let hex = line
+                                .get(i + 2..i + 4)
+                                .filter(|_| line.get(i + 1) == Some(&b'x'))
+                                .and_then(|h| std::str::from_utf8(h).ok())
+                                .and_then(|h| u8::from_str_radix(h, 16).ok());
+                            if let Some(c) = hex {
+                                argument.push(c);
+                                i += 4;
+                                continue;
+                            }
+
+                            let c = match line.get(i + 1) {
+                                Some(b'n') => b'\n',
+                                Some(b'r') => b'\r',
+                                Some(b't') => b'\t',
+                                Some(b'b') => 0x08,
+                                Some(b'a') => 0x07,
+                                Some(c) => *c,
+                                None => return Err(RESPParseError::UnbalancedQuotes),
+                            };
+                            argument.push(c);
+                            i += 2;
+                        }
+                        Some(b'\\') if line.get(i + 1) == Some(&b'\'') => {
+                            argument.push(b'\'');
+                            i += 2;
+                        }
+                        Some(c) => {
+                            argument.push(*c);
+                            i += 1;
+                        }
+                    }
+                }
+
+                // a closing quote must be followed by a space or the end of the line
+                if i < line.len() && !line[i].is_ascii_whitespace() {
+                    return Err(RESPParseError::UnbalancedQuotes);
+                }
+            }
+            _ => {
+                while i < line.len() && !line[i].is_ascii_whitespace() {
+                    argument.push(line[i]);
+                    i += 1;
+                }
+            }
+        }
+
+        arguments.push(argument);
+    }
+}
+
+// Returns the length of the first complete frame in `value`, failing early when
+// the frame is known to exceed `limits` before all of it has been received.
+// Elements already scanned by an earlier call that needed more data are
+// skipped, `scan` is reset once the frame is complete
+fn frame_length(
+    value: &[u8],
+    limits: &RESPLimits,
+    scan: &mut FrameScan,
+) -> Result<usize, RESPDecodeError> {
+    if scan.pending.is_empty() {
+        scan.pending.push(1);
+    }
+    loop {
+        match scan.pending.last() {
+            None => return Ok(std::mem::take(scan).scanned),
+            Some(0) => {
+                scan.pending.pop();
+                continue;
+            }
+            Some(_) => {}
+        }
+
+        let rest = &value[scan.scanned..];
+        let (header, _) = match split_once_crlf(rest) {
+            Some(v) => v,
+            None if rest.len() > limits.max_inline_length => {
+                return Err(RESPParseError::LineTooLong.into())
+            }
+            None => return Err(RESPDecodeError::NeedMoreData),
+        };
+        let header_length = header.len() + 2;
+
+        // the element's own length, and for an aggregate how many it holds
+        let (length, elements) = match header.first() {
+            Some(b'$' | b'!' | b'=') => {
+                let length =
+                    parse_number::<i64>(&header[1..]).ok_or(RESPParseError::InvalidBulkLength)?;
+                let length = if length < 0 {
+                    header_length
+                } else if length as u64 > limits.max_bulk_length as u64 {
+                    return Err(RESPParseError::BulkTooLong.into());
+                } else {
+                    header_length
+                        .saturating_add(length as usize)
+                        .saturating_add(2)
+                };
+                if rest.len() < length {
+                    return Err(RESPDecodeError::NeedMoreData);
+                }
+                (length, None)
+            }
+            Some(kind @ (b'*' | b'~' | b'>' | b'%')) => {
+                let length = parse_number::<i64>(&header[1..])
+                    .ok_or(RESPParseError::InvalidMultibulkLength)?;
+                if scan.pending.len() > limits.max_nesting_depth {
+                    return Err(RESPParseError::NestingTooDeep.into());
+                }
+                let elements = if *kind == b'%' {
+                    length.saturating_mul(2)
+                } else {
+                    length
+                };
+                (header_length, Some(elements.max(0) as u64))
             }
-            _ => unimplemented!(),
+            Some(_) => (header_length, None),
+            None => return Err(RESPParseError::EmptyInput.into()),
+        };
+
+        scan.scanned += length;
+        if let Some(remaining) = scan.pending.last_mut() {
+            *remaining -= 1;
         }
+        scan.pending.extend(elements);
     }
 }
 
+fn parse_number<T: std::str::FromStr>(value: &[u8]) -> Option<T> {
+    std::str::from_utf8(value).ok()?.parse().ok()
+}
+
+fn split_once_crlf(value: &[u8]) -> Option<(&[u8], &[u8])> {
+    value
+        .windows(2)
+        .position(|w| w == b"\r\n")
+        .map(|i| (&value[..i], &value[i + 2..]))
+}
+
 #[cfg(test)]
 mod impl_try_from_for_resp {
+    use bytes::Bytes;
+
     use super::RESPValues;
 
     #[test]
     fn parse_simple_string_correctly() {
-        let value = "+PING\r\n";
+        let value: &[u8] = b"+PING\r\n";
         let result = RESPValues::try_from(value);
 
         assert!(result.is_ok_and(|r| r == RESPValues::SimpleString("PING".to_string())))
@@ -129,7 +734,7 @@ mod impl_try_from_for_resp {
 
     #[test]
     fn parse_simple_error_correctly() {
-        let value = "-TEST ERROR\r\n";
+        let value: &[u8] = b"-TEST ERROR\r\n";
         let result = RESPValues::try_from(value);
 
         assert!(result.is_ok_and(|r| r == RESPValues::SimpleError("TEST ERROR".to_string())));
@@ -137,7 +742,7 @@ mod impl_try_from_for_resp {
 
     #[test]
     fn parse_integer_correctly() {
-        let value = ":2\r\n";
+        let value: &[u8] = b":2\r\n";
         let result = RESPValues::try_from(value);
 
         assert!(result.is_ok_and(|r| r == RESPValues::Integer(2)));
@@ -145,7 +750,7 @@ mod impl_try_from_for_resp {
 
     #[test]
     fn parse_negative_integer_correctly() {
-        let value = ":-2\r\n";
+        let value: &[u8] = b":-2\r\n";
         let result = RESPValues::try_from(value);
 
         assert!(result.is_ok_and(|r| r == RESPValues::Integer(-2)));
@@ -153,23 +758,90 @@ mod impl_try_from_for_resp {
 
     #[test]
     fn parse_bulk_string_correctly() {
-        let value = "$4\r\nBulk\r\n";
+        let value: &[u8] = b"$4\r\nBulk\r\n";
         let result = RESPValues::try_from(value);
 
-        assert!(result.is_ok_and(|r| r == RESPValues::BulkString("Bulk".to_string())));
+        assert!(result.is_ok_and(|r| r == RESPValues::BulkString(Bytes::from_static(b"Bulk"))));
     }
 
     #[test]
     fn parse_empty_bulk_string_correctly() {
-        let value = "$0\r\n\r\n";
+        let value: &[u8] = b"$0\r\n\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert!(result.is_ok_and(|r| r == RESPValues::BulkString(Bytes::new())));
+    }
+
+    #[test]
+    fn parse_binary_bulk_string_correctly() {
+        let value: &[u8] = b"$4\r\n\x00\xff\xfe\x01\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert!(result.is_ok_and(
+            |r| r == RESPValues::BulkString(Bytes::from_static(&[0x00, 0xff, 0xfe, 0x01]))
+        ));
+    }
+
+    #[test]
+    fn parse_bulk_string_containing_crlf_correctly() {
+        let value: &[u8] = b"$6\r\nab\r\ncd\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert!(result.is_ok_and(|r| r == RESPValues::BulkString(Bytes::from_static(b"ab\r\ncd"))));
+    }
+
+    #[test]
+    fn parse_array_with_bulk_string_containing_crlf_correctly() {
+        let value: &[u8] = b"*2\r\n$4\r\n\r\n\r\n\r\n:1\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert!(result.is_ok_and(|r| r
+            == RESPValues::Array(vec![
+                RESPValues::BulkString(Bytes::from_static(b"\r\n\r\n")),
+                RESPValues::Integer(1)
+            ])));
+    }
+
+    #[test]
+    fn parse_null_bulk_string_correctly() {
+        let value: &[u8] = b"$-1\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert!(result.is_ok_and(|r| r == RESPValues::NullBulkString));
+    }
+
+    #[test]
+    fn parse_null_array_correctly() {
+        let value: &[u8] = b"*-1\r\n";
         let result = RESPValues::try_from(value);
 
-        assert!(result.is_ok_and(|r| r == RESPValues::BulkString(String::new())));
+        assert!(result.is_ok_and(|r| r == RESPValues::NullArray));
+    }
+
+    #[test]
+    fn parse_null_correctly() {
+        let value: &[u8] = b"_\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert!(result.is_ok_and(|r| r == RESPValues::Null));
+    }
+
+    #[test]
+    fn parse_array_with_nulls_correctly() {
+        let value: &[u8] = b"*3\r\n$-1\r\n*-1\r\n_\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert!(result.is_ok_and(|r| r
+            == RESPValues::Array(vec![
+                RESPValues::NullBulkString,
+                RESPValues::NullArray,
+                RESPValues::Null
+            ])));
     }
 
     #[test]
     fn parse_array_with_zero_items_correctly() {
-        let value = "*0\r\n";
+        let value: &[u8] = b"*0\r\n";
         let result = RESPValues::try_from(value);
 
         assert!(result.is_ok_and(|r| r == RESPValues::Array(vec![])));
@@ -177,7 +849,7 @@ mod impl_try_from_for_resp {
 
     #[test]
     fn parse_array_with_one_item_correctly() {
-        let value = "*1\r\n:1\r\n";
+        let value: &[u8] = b"*1\r\n:1\r\n";
         let result = RESPValues::try_from(value);
 
         assert!(result.is_ok_and(|r| r == RESPValues::Array(vec![RESPValues::Integer(1)])));
@@ -185,77 +857,894 @@ mod impl_try_from_for_resp {
 
     #[test]
     fn parse_nested_array_correctly() {
-        let value = "*2\r\n*1\r\n+PING\r\n$4\r\nPONG\r\n";
+        let value: &[u8] = b"*2\r\n*1\r\n+PING\r\n$4\r\nPONG\r\n";
         let result = RESPValues::try_from(value);
 
         assert!(result.is_ok_and(|r| r
             == RESPValues::Array(vec![
                 RESPValues::Array(vec![RESPValues::SimpleString("PING".to_string())]),
-                RESPValues::BulkString("PONG".to_string())
+                RESPValues::BulkString(Bytes::from_static(b"PONG"))
             ])));
     }
 }
 
 #[cfg(test)]
-mod impl_to_string_for_resp {
-    use super::RESPValues;
+mod resp_parse_errors {
+    use super::{RESPParseError, RESPValues};
 
     #[test]
-    fn simple_string_to_string() {
-        let value = RESPValues::SimpleString(String::from("PING"));
-        let result = value.to_string();
-        assert_eq!(&result, "+PING\r\n");
+    fn parse_empty_input_fails() {
+        let value: &[u8] = b"";
+        let result = RESPValues::try_from(value);
+
+        assert_eq!(result, Err(RESPParseError::EmptyInput));
     }
 
     #[test]
-    fn simple_error_to_string() {
-        let value = RESPValues::SimpleError(String::from("TEST ERROR"));
-        let result = value.to_string();
-        assert_eq!(&result, "-TEST ERROR\r\n");
+    fn parse_missing_crlf_fails() {
+        let value: &[u8] = b"+PING";
+        let result = RESPValues::try_from(value);
+
+        assert_eq!(result, Err(RESPParseError::MissingCRLF));
     }
 
     #[test]
-    fn integer_to_string() {
-        let value = RESPValues::Integer(10);
-        let result = value.to_string();
-        assert_eq!(&result, ":10\r\n");
+    fn parse_unknown_type_fails() {
+        let value: &[u8] = b"PING\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert_eq!(result, Err(RESPParseError::UnknownType(b'P')));
     }
 
     #[test]
-    fn negative_integer_to_string() {
-        let value = RESPValues::Integer(-10);
-        let result = value.to_string();
-        assert_eq!(&result, ":-10\r\n");
+    fn parse_malformed_integer_fails() {
+        let value: &[u8] = b":12a\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert_eq!(result, Err(RESPParseError::InvalidInteger));
     }
 
     #[test]
-    fn bulk_string_to_string() {
-        let value = RESPValues::BulkString(String::from("testing"));
-        let result = value.to_string();
-        assert_eq!(&result, "$7\r\ntesting\r\n");
+    fn parse_overflowing_integer_fails() {
+        let value: &[u8] = b":99999999999999999999\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert_eq!(result, Err(RESPParseError::InvalidInteger));
     }
 
     #[test]
-    fn empty_array_to_string() {
-        let value = RESPValues::Array(vec![]);
-        let result = value.to_string();
-        assert_eq!(&result, "*0\r\n");
+    fn parse_malformed_bulk_length_fails() {
+        let value: &[u8] = b"$x\r\nBulk\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert_eq!(result, Err(RESPParseError::InvalidBulkLength));
     }
 
     #[test]
-    fn one_item_array_to_string() {
-        let value = RESPValues::Array(vec![RESPValues::Integer(2)]);
-        let result = value.to_string();
-        assert_eq!(&result, "*1\r\n:2\r\n");
+    fn parse_bulk_string_shorter_than_declared_fails() {
+        let value: &[u8] = b"$10\r\nBulk\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert_eq!(result, Err(RESPParseError::MissingCRLF));
     }
 
     #[test]
-    fn nested_items_array_to_string() {
-        let value = RESPValues::Array(vec![
-            RESPValues::Integer(2),
-            RESPValues::Array(vec![RESPValues::BulkString(String::from("PONG"))]),
-        ]);
-        let result = value.to_string();
-        assert_eq!(&result, "*2\r\n:2\r\n*1\r\n$4\r\nPONG\r\n");
+    fn parse_bulk_string_longer_than_declared_fails() {
+        let value: &[u8] = b"$2\r\nBulk\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert_eq!(result, Err(RESPParseError::BulkLengthMismatch));
+    }
+
+    #[test]
+    fn parse_invalid_boolean_fails() {
+        let value: &[u8] = b"#x\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert_eq!(result, Err(RESPParseError::InvalidBoolean));
+    }
+
+    #[test]
+    fn parse_invalid_double_fails() {
+        let value: &[u8] = b",1.2.3\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert_eq!(result, Err(RESPParseError::InvalidDouble));
+    }
+
+    #[test]
+    fn parse_invalid_big_number_fails() {
+        let value: &[u8] = b"(12a\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert_eq!(result, Err(RESPParseError::InvalidBigNumber));
+    }
+
+    #[test]
+    fn parse_verbatim_string_without_encoding_fails() {
+        let value: &[u8] = b"=2\r\nab\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert_eq!(result, Err(RESPParseError::InvalidVerbatimString));
+    }
+
+    #[test]
+    fn parse_malformed_array_length_fails() {
+        let value: &[u8] = b"*-3\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert_eq!(result, Err(RESPParseError::InvalidMultibulkLength));
+    }
+
+    #[test]
+    fn parse_truncated_array_fails() {
+        let value: &[u8] = b"*2\r\n:1\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert_eq!(result, Err(RESPParseError::EmptyInput));
+    }
+}
+
+#[cfg(test)]
+mod impl_to_bytes_for_resp {
+    use bytes::{Bytes, BytesMut};
+
+    use super::RESPValues;
+
+    #[test]
+    fn simple_string_to_bytes() {
+        let value = RESPValues::SimpleString(String::from("PING"));
+        let result = value.to_bytes();
+        assert_eq!(&result, b"+PING\r\n");
+    }
+
+    #[test]
+    fn simple_error_to_bytes() {
+        let value = RESPValues::SimpleError(String::from("TEST ERROR"));
+        let result = value.to_bytes();
+        assert_eq!(&result, b"-TEST ERROR\r\n");
+    }
+
+    #[test]
+    fn integer_to_bytes() {
+        let value = RESPValues::Integer(10);
+        let result = value.to_bytes();
+        assert_eq!(&result, b":10\r\n");
+    }
+
+    #[test]
+    fn negative_integer_to_bytes() {
+        let value = RESPValues::Integer(-10);
+        let result = value.to_bytes();
+        assert_eq!(&result, b":-10\r\n");
+    }
+
+    #[test]
+    fn bulk_string_to_bytes() {
+        let value = RESPValues::BulkString(Bytes::from_static(b"testing"));
+        let result = value.to_bytes();
+        assert_eq!(&result, b"$7\r\ntesting\r\n");
+    }
+
+    #[test]
+    fn binary_bulk_string_to_bytes() {
+        let value = RESPValues::BulkString(Bytes::from_static(&[0x00, 0xff, 0xfe, 0x01]));
+        let result = value.to_bytes();
+        assert_eq!(&result, b"$4\r\n\x00\xff\xfe\x01\r\n");
+    }
+
+    #[test]
+    fn null_bulk_string_to_bytes() {
+        let value = RESPValues::NullBulkString;
+        let result = value.to_bytes();
+        assert_eq!(&result, b"$-1\r\n");
+    }
+
+    #[test]
+    fn null_array_to_bytes() {
+        let value = RESPValues::NullArray;
+        let result = value.to_bytes();
+        assert_eq!(&result, b"*-1\r\n");
+    }
+
+    #[test]
+    fn null_to_bytes() {
+        let value = RESPValues::Null;
+        let result = value.to_bytes();
+        assert_eq!(&result, b"_\r\n");
+    }
+
+    #[test]
+    fn min_integer_to_bytes() {
+        let value = RESPValues::Integer(i64::MIN);
+        let result = value.to_bytes();
+        assert_eq!(&result, b":-9223372036854775808\r\n");
+    }
+
+    #[test]
+    fn zero_integer_to_bytes() {
+        let value = RESPValues::Integer(0);
+        let result = value.to_bytes();
+        assert_eq!(&result, b":0\r\n");
+    }
+
+    #[test]
+    fn encode_several_values_into_one_buffer() {
+        let mut buffer = BytesMut::new();
+        RESPValues::SimpleString(String::from("OK")).encode(&mut buffer);
+        RESPValues::Integer(42).encode(&mut buffer);
+        RESPValues::BulkString(Bytes::from_static(b"PONG")).encode(&mut buffer);
+        assert_eq!(&buffer[..], b"+OK\r\n:42\r\n$4\r\nPONG\r\n");
+    }
+
+    #[test]
+    fn empty_array_to_bytes() {
+        let value = RESPValues::Array(vec![]);
+        let result = value.to_bytes();
+        assert_eq!(&result, b"*0\r\n");
+    }
+
+    #[test]
+    fn one_item_array_to_bytes() {
+        let value = RESPValues::Array(vec![RESPValues::Integer(2)]);
+        let result = value.to_bytes();
+        assert_eq!(&result, b"*1\r\n:2\r\n");
+    }
+
+    #[test]
+    fn nested_items_array_to_bytes() {
+        let value = RESPValues::Array(vec![
+            RESPValues::Integer(2),
+            RESPValues::Array(vec![RESPValues::BulkString(Bytes::from_static(b"PONG"))]),
+        ]);
+        let result = value.to_bytes();
+        assert_eq!(&result, b"*2\r\n:2\r\n*1\r\n$4\r\nPONG\r\n");
+    }
+}
+
+#[cfg(test)]
+mod resp_decoder_tests {
+    use bytes::{Bytes, BytesMut};
+
+    use super::{
+        decode_frame, RESPDecodeError, RESPDecoder, RESPLimits, RESPParseError, RESPValues,
+    };
+
+    #[test]
+    fn decode_empty_buffer_needs_more_data() {
+        let mut decoder = RESPDecoder::new();
+        let result = decoder.decode();
+
+        assert_eq!(result, Err(RESPDecodeError::NeedMoreData));
+    }
+
+    #[test]
+    fn decode_frame_split_across_feeds_correctly() {
+        let mut decoder = RESPDecoder::new();
+
+        decoder.feed(b"*2\r\n$4\r\nECHO\r\n$5\r\nhel");
+        assert_eq!(decoder.decode(), Err(RESPDecodeError::NeedMoreData));
+
+        decoder.feed(b"lo\r\n");
+        assert_eq!(
+            decoder.decode(),
+            Ok(RESPValues::Array(vec![
+                RESPValues::BulkString(Bytes::from_static(b"ECHO")),
+                RESPValues::BulkString(Bytes::from_static(b"hello")),
+            ]))
+        );
+    }
+
+    #[test]
+    fn resume_a_partial_frame_where_it_stopped_correctly() {
+        let mut decoder = RESPDecoder::new();
+
+        decoder.feed(b"*3\r\n$1\r\na\r\n$1\r\n");
+        assert_eq!(decoder.decode(), Err(RESPDecodeError::NeedMoreData));
+        // the header and the first element aren't scanned again
+        assert_eq!(decoder.scan.scanned, 11);
+        assert_eq!(decoder.scan.pending, [0, 2]);
+
+        decoder.feed(b"b\r\n$1\r\nc\r\n");
+        let element = |e: &'static [u8]| RESPValues::BulkString(Bytes::from_static(e));
+        assert_eq!(
+            decoder.decode(),
+            Ok(RESPValues::Array(vec![
+                element(b"a"),
+                element(b"b"),
+                element(b"c")
+            ]))
+        );
+        assert_eq!(decoder.scan.scanned, 0);
+        assert!(decoder.scan.pending.is_empty());
+    }
+
+    #[test]
+    fn decode_nested_frame_fed_byte_by_byte_correctly() {
+        let value = RESPValues::Array(vec![
+            RESPValues::Map(vec![(
+                RESPValues::BulkString(Bytes::from_static(b"key")),
+                RESPValues::Array(vec![RESPValues::Integer(1), RESPValues::Null]),
+            )]),
+            RESPValues::BulkString(Bytes::from_static(b"tail")),
+        ]);
+        let mut encoded = Vec::new();
+        value.encode(&mut encoded);
+        let mut decoder = RESPDecoder::new();
+
+        let (last, rest) = encoded.split_last().unwrap();
+        for byte in rest {
+            decoder.feed(&[*byte]);
+            assert_eq!(decoder.decode(), Err(RESPDecodeError::NeedMoreData));
+        }
+        decoder.feed(&[*last]);
+        assert_eq!(decoder.decode(), Ok(value));
+    }
+
+    #[test]
+    fn decode_header_split_across_feeds_correctly() {
+        let mut decoder = RESPDecoder::new();
+
+        decoder.feed(b"*1\r");
+        assert_eq!(decoder.decode(), Err(RESPDecodeError::NeedMoreData));
+
+        decoder.feed(b"\n$4\r\nPING\r\n");
+        assert_eq!(
+            decoder.decode(),
+            Ok(RESPValues::Array(vec![RESPValues::BulkString(
+                Bytes::from_static(b"PING")
+            )]))
+        );
+    }
+
+    #[test]
+    fn decode_large_bulk_string_read_in_chunks_correctly() {
+        let payload = vec![b'x'; 10 * 1024];
+        let mut input =
+            format!("*3\r\n$3\r\nSET\r\n$1\r\nk\r\n${}\r\n", payload.len()).into_bytes();
+        input.extend_from_slice(&payload);
+        input.extend_from_slice(b"\r\n");
+        let mut decoder = RESPDecoder::new();
+
+        for chunk in input.chunks(512) {
+            assert_eq!(decoder.decode(), Err(RESPDecodeError::NeedMoreData));
+            decoder.buffer_mut().extend_from_slice(chunk);
+        }
+        assert_eq!(
+            decoder.decode(),
+            Ok(RESPValues::Array(vec![
+                RESPValues::BulkString(Bytes::from_static(b"SET")),
+                RESPValues::BulkString(Bytes::from_static(b"k")),
+                RESPValues::BulkString(payload.into()),
+            ]))
+        );
+    }
+
+    #[test]
+    fn decode_consecutive_frames_in_order() {
+        let mut decoder = RESPDecoder::new();
+
+        decoder.feed(b":1\r\n+OK\r\n:2");
+        assert_eq!(decoder.decode(), Ok(RESPValues::Integer(1)));
+        assert_eq!(
+            decoder.decode(),
+            Ok(RESPValues::SimpleString("OK".to_string()))
+        );
+        assert_eq!(decoder.decode(), Err(RESPDecodeError::NeedMoreData));
+    }
+
+    #[test]
+    fn decode_array_with_null_bulk_string_correctly() {
+        let mut decoder = RESPDecoder::new();
+
+        decoder.feed(b"*2\r\n$-1\r\n:1\r\n");
+        assert_eq!(
+            decoder.decode(),
+            Ok(RESPValues::Array(vec![
+                RESPValues::NullBulkString,
+                RESPValues::Integer(1)
+            ]))
+        );
+    }
+
+    #[test]
+    fn decode_map_split_across_feeds_correctly() {
+        let mut decoder = RESPDecoder::new();
+
+        decoder.feed(b"%1\r\n+key\r\n");
+        assert_eq!(decoder.decode(), Err(RESPDecodeError::NeedMoreData));
+
+        decoder.feed(b"=7\r\ntxt:val\r\n");
+        assert_eq!(
+            decoder.decode(),
+            Ok(RESPValues::Map(vec![(
+                RESPValues::SimpleString("key".to_string()),
+                RESPValues::VerbatimString("txt".to_string(), Bytes::from_static(b"val"))
+            )]))
+        );
+    }
+
+    #[test]
+    fn decode_bulk_string_without_copying() {
+        let mut buffer = BytesMut::from(&b"*1\r\n$5\r\nhello\r\n"[..]);
+        let payload = buffer[8..].as_ptr();
+        let result = decode_frame(&mut buffer, &RESPLimits::default());
+
+        assert!(result.is_ok_and(|r| matches!(
+            r,
+            RESPValues::Array(v) if matches!(&v[0], RESPValues::BulkString(s) if s.as_ptr() == payload)
+        )));
+    }
+
+    #[test]
+    fn decode_invalid_length_correctly() {
+        let mut decoder = RESPDecoder::new();
+
+        decoder.feed(b"*abc\r\n");
+        assert_eq!(
+            decoder.decode(),
+            Err(RESPDecodeError::Invalid(
+                RESPParseError::InvalidMultibulkLength
+            ))
+        );
+    }
+}
+
+#[cfg(test)]
+mod resp3_round_trip {
+    use bytes::Bytes;
+
+    use super::RESPValues;
+
+    fn assert_round_trip(encoded: &[u8], value: RESPValues) {
+        assert_eq!(RESPValues::try_from(encoded), Ok(value.clone()));
+        assert_eq!(value.to_bytes(), encoded);
+    }
+
+    #[test]
+    fn boolean_round_trip() {
+        assert_round_trip(b"#t\r\n", RESPValues::Boolean(true));
+        assert_round_trip(b"#f\r\n", RESPValues::Boolean(false));
+    }
+
+    #[test]
+    fn double_round_trip() {
+        assert_round_trip(b",1.5\r\n", RESPValues::Double(1.5));
+        assert_round_trip(b",-0.25\r\n", RESPValues::Double(-0.25));
+        assert_round_trip(b",10\r\n", RESPValues::Double(10.0));
+        assert_round_trip(b",inf\r\n", RESPValues::Double(f64::INFINITY));
+        assert_round_trip(b",-inf\r\n", RESPValues::Double(f64::NEG_INFINITY));
+    }
+
+    #[test]
+    fn nan_double_round_trip() {
+        let value: &[u8] = b",nan\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert!(result.is_ok_and(|r| matches!(r, RESPValues::Double(v) if v.is_nan())));
+        assert_eq!(RESPValues::Double(f64::NAN).to_bytes(), value);
+    }
+
+    #[test]
+    fn parse_double_with_exponent_correctly() {
+        let value: &[u8] = b",1.5e3\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert!(result.is_ok_and(|r| r == RESPValues::Double(1500.0)));
+    }
+
+    #[test]
+    fn big_number_round_trip() {
+        assert_round_trip(
+            b"(3492890328409238509324850943850943825024385\r\n",
+            RESPValues::BigNumber("3492890328409238509324850943850943825024385".to_string()),
+        );
+        assert_round_trip(b"(-12\r\n", RESPValues::BigNumber("-12".to_string()));
+    }
+
+    #[test]
+    fn bulk_error_round_trip() {
+        assert_round_trip(
+            b"!21\r\nSYNTAX invalid syntax\r\n",
+            RESPValues::BulkError(Bytes::from_static(b"SYNTAX invalid syntax")),
+        );
+    }
+
+    #[test]
+    fn verbatim_string_round_trip() {
+        assert_round_trip(
+            b"=15\r\ntxt:Some string\r\n",
+            RESPValues::VerbatimString("txt".to_string(), Bytes::from_static(b"Some string")),
+        );
+    }
+
+    #[test]
+    fn map_round_trip() {
+        assert_round_trip(
+            b"%2\r\n+first\r\n:1\r\n+second\r\n:2\r\n",
+            RESPValues::Map(vec![
+                (
+                    RESPValues::SimpleString("first".to_string()),
+                    RESPValues::Integer(1),
+                ),
+                (
+                    RESPValues::SimpleString("second".to_string()),
+                    RESPValues::Integer(2),
+                ),
+            ]),
+        );
+    }
+
+    #[test]
+    fn set_round_trip() {
+        assert_round_trip(
+            b"~2\r\n$1\r\na\r\n#t\r\n",
+            RESPValues::Set(vec![
+                RESPValues::BulkString(Bytes::from_static(b"a")),
+                RESPValues::Boolean(true),
+            ]),
+        );
+    }
+
+    #[test]
+    fn push_round_trip() {
+        assert_round_trip(
+            b">3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$5\r\nhello\r\n",
+            RESPValues::Push(vec![
+                RESPValues::BulkString(Bytes::from_static(b"message")),
+                RESPValues::BulkString(Bytes::from_static(b"news")),
+                RESPValues::BulkString(Bytes::from_static(b"hello")),
+            ]),
+        );
+    }
+
+    #[test]
+    fn nested_aggregates_round_trip() {
+        assert_round_trip(
+            b"*2\r\n%1\r\n$3\r\nkey\r\n~1\r\n,2.5\r\n_\r\n",
+            RESPValues::Array(vec![
+                RESPValues::Map(vec![(
+                    RESPValues::BulkString(Bytes::from_static(b"key")),
+                    RESPValues::Set(vec![RESPValues::Double(2.5)]),
+                )]),
+                RESPValues::Null,
+            ]),
+        );
+    }
+
+    #[test]
+    fn parse_array_with_non_canonical_double_correctly() {
+        let value: &[u8] = b"*2\r\n,1.50\r\n:1\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert!(result.is_ok_and(
+            |r| r == RESPValues::Array(vec![RESPValues::Double(1.5), RESPValues::Integer(1)])
+        ));
+    }
+}
+
+#[cfg(test)]
+mod to_protocol_tests {
+    use bytes::Bytes;
+
+    use super::{RESPValues, RESPVersion};
+
+    #[test]
+    fn map_to_resp2_is_flat_array() {
+        let value = RESPValues::Map(vec![(
+            RESPValues::BulkString(Bytes::from_static(b"proto")),
+            RESPValues::Integer(2),
+        )]);
+        let result = value.to_protocol(RESPVersion::RESP2);
+
+        assert_eq!(
+            result,
+            RESPValues::Array(vec![
+                RESPValues::BulkString(Bytes::from_static(b"proto")),
+                RESPValues::Integer(2)
+            ])
+        );
+    }
+
+    #[test]
+    fn nested_resp3_values_to_resp2() {
+        let value = RESPValues::Array(vec![
+            RESPValues::Null,
+            RESPValues::Boolean(true),
+            RESPValues::Double(1.5),
+            RESPValues::Set(vec![RESPValues::BigNumber("12".to_string())]),
+            RESPValues::VerbatimString("txt".to_string(), Bytes::from_static(b"text")),
+            RESPValues::BulkError(Bytes::from_static(b"ERR failed")),
+        ]);
+        let result = value.to_protocol(RESPVersion::RESP2);
+
+        assert_eq!(
+            result,
+            RESPValues::Array(vec![
+                RESPValues::NullBulkString,
+                RESPValues::Integer(1),
+                RESPValues::BulkString(Bytes::from_static(b"1.5")),
+                RESPValues::Array(vec![RESPValues::BulkString(Bytes::from_static(b"12"))]),
+                RESPValues::BulkString(Bytes::from_static(b"text")),
+                RESPValues::SimpleError("ERR failed".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn resp2_nulls_to_resp3() {
+        let value = RESPValues::Array(vec![RESPValues::NullBulkString, RESPValues::NullArray]);
+        let result = value.to_protocol(RESPVersion::RESP3);
+
+        assert_eq!(
+            result,
+            RESPValues::Array(vec![RESPValues::Null, RESPValues::Null])
+        );
+    }
+
+    #[test]
+    fn resp3_map_stays_map() {
+        let value = RESPValues::Map(vec![(
+            RESPValues::BulkString(Bytes::from_static(b"proto")),
+            RESPValues::Integer(3),
+        )]);
+        let result = value.clone().to_protocol(RESPVersion::RESP3);
+
+        assert_eq!(result, value);
+    }
+}
+
+#[cfg(test)]
+mod inline_command_tests {
+    use bytes::Bytes;
+
+    use super::{RESPDecodeError, RESPDecoder, RESPParseError, RESPValues};
+
+    fn bulk_strings(values: &[&[u8]]) -> RESPValues {
+        RESPValues::Array(
+            values
+                .iter()
+                .map(|v| RESPValues::BulkString(Bytes::copy_from_slice(v)))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn decode_inline_command_correctly() {
+        let mut decoder = RESPDecoder::new();
+
+        decoder.feed(b"PING\r\n");
+        assert_eq!(decoder.decode(), Ok(bulk_strings(&[b"PING"])));
+    }
+
+    #[test]
+    fn decode_inline_command_with_arguments_correctly() {
+        let mut decoder = RESPDecoder::new();
+
+        decoder.feed(b"ECHO   hello\tworld\n");
+        assert_eq!(
+            decoder.decode(),
+            Ok(bulk_strings(&[b"ECHO", b"hello", b"world"]))
+        );
+    }
+
+    #[test]
+    fn decode_partial_inline_command_needs_more_data() {
+        let mut decoder = RESPDecoder::new();
+
+        decoder.feed(b"PI");
+        assert_eq!(decoder.decode(), Err(RESPDecodeError::NeedMoreData));
+
+        decoder.feed(b"NG\r\n");
+        assert_eq!(decoder.decode(), Ok(bulk_strings(&[b"PING"])));
+    }
+
+    #[test]
+    fn decode_inline_command_skips_empty_lines() {
+        let mut decoder = RESPDecoder::new();
+
+        decoder.feed(b"\r\n  \r\nPING\r\n");
+        assert_eq!(decoder.decode(), Ok(bulk_strings(&[b"PING"])));
+    }
+
+    #[test]
+    fn decode_inline_command_with_quotes_correctly() {
+        let mut decoder = RESPDecoder::new();
+
+        decoder.feed(b"ECHO \"hello world\\n\\x41\" 'it\\'s'\r\n");
+        assert_eq!(
+            decoder.decode(),
+            Ok(bulk_strings(&[b"ECHO", b"hello world\nA", b"it's"]))
+        );
+    }
+
+    #[test]
+    fn decode_inline_command_with_unbalanced_quotes_fails() {
+        let mut decoder = RESPDecoder::new();
+
+        decoder.feed(b"ECHO \"hello\r\n");
+        assert_eq!(
+            decoder.decode(),
+            Err(RESPDecodeError::Invalid(RESPParseError::UnbalancedQuotes))
+        );
+    }
+
+    #[test]
+    fn decode_inline_command_with_text_after_quote_fails() {
+        let mut decoder = RESPDecoder::new();
+
+        decoder.feed(b"ECHO \"hello\"world\r\n");
+        assert_eq!(
+            decoder.decode(),
+            Err(RESPDecodeError::Invalid(RESPParseError::UnbalancedQuotes))
+        );
+    }
+
+    #[test]
+    fn decode_inline_and_multibulk_commands_in_order() {
+        let mut decoder = RESPDecoder::new();
+
+        decoder.feed(b"PING\r\n*1\r\n$4\r\nPING\r\n");
+        assert_eq!(decoder.decode(), Ok(bulk_strings(&[b"PING"])));
+        assert_eq!(decoder.decode(), Ok(bulk_strings(&[b"PING"])));
+    }
+}
+
+#[cfg(test)]
+mod resp_limits_tests {
+    use super::{RESPDecodeError, RESPDecoder, RESPLimits, RESPParseError, RESPValues};
+
+    fn limits() -> RESPLimits {
+        RESPLimits {
+            max_nesting_depth: 2,
+            max_bulk_length: 8,
+            max_inline_length: 16,
+            max_query_buffer_length: 32,
+        }
+    }
+
+    #[test]
+    fn decode_nesting_within_limit_correctly() {
+        let mut decoder = RESPDecoder::with_limits(limits());
+
+        decoder.feed(b"*1\r\n*1\r\n:1\r\n");
+        assert_eq!(
+            decoder.decode(),
+            Ok(RESPValues::Array(vec![RESPValues::Array(vec![
+                RESPValues::Integer(1)
+            ])]))
+        );
+    }
+
+    #[test]
+    fn decode_nesting_too_deep_fails() {
+        let mut decoder = RESPDecoder::with_limits(limits());
+
+        decoder.feed(b"*1\r\n*1\r\n*1\r\n");
+        assert_eq!(
+            decoder.decode(),
+            Err(RESPDecodeError::Invalid(RESPParseError::NestingTooDeep))
+        );
+    }
+
+    #[test]
+    fn decode_bulk_too_long_fails_before_payload_arrives() {
+        let mut decoder = RESPDecoder::with_limits(limits());
+
+        decoder.feed(b"$9\r\n");
+        assert_eq!(
+            decoder.decode(),
+            Err(RESPDecodeError::Invalid(RESPParseError::BulkTooLong))
+        );
+    }
+
+    #[test]
+    fn decode_unterminated_inline_command_too_long_fails() {
+        let mut decoder = RESPDecoder::with_limits(limits());
+
+        decoder.feed(b"ECHO aaaaaaaaaaa");
+        assert_eq!(decoder.decode(), Err(RESPDecodeError::NeedMoreData));
+
+        decoder.feed(b"a");
+        assert_eq!(
+            decoder.decode(),
+            Err(RESPDecodeError::Invalid(RESPParseError::LineTooLong))
+        );
+    }
+
+    #[test]
+    fn decode_unterminated_header_too_long_fails() {
+        let mut decoder = RESPDecoder::with_limits(limits());
+
+        decoder.feed(b"*11111111111111111");
+        assert_eq!(
+            decoder.decode(),
+            Err(RESPDecodeError::Invalid(RESPParseError::LineTooLong))
+        );
+    }
+
+    #[test]
+    fn decode_incomplete_frame_over_query_buffer_limit_fails() {
+        let mut decoder = RESPDecoder::with_limits(limits());
+
+        decoder.feed(b"*100\r\n");
+        decoder.feed(&b":1\r\n".repeat(6));
+        assert_eq!(decoder.decode(), Err(RESPDecodeError::NeedMoreData));
+
+        decoder.feed(b":1\r\n");
+        assert_eq!(
+            decoder.decode(),
+            Err(RESPDecodeError::Invalid(RESPParseError::QueryBufferTooLong))
+        );
+    }
+
+    #[test]
+    fn parse_deeply_nested_array_with_default_limits_fails() {
+        let value = "*1\r\n".repeat(10_000);
+        let result = RESPValues::try_from(value.as_bytes());
+
+        assert_eq!(result, Err(RESPParseError::NestingTooDeep));
+    }
+}
+
+#[cfg(test)]
+mod resp_hostile_input_tests {
+    use bytes::BytesMut;
+
+    use super::{decode_frame, RESPDecodeError, RESPLimits, RESPParseError, RESPValues};
+
+    #[test]
+    fn parse_huge_array_header_fails_without_allocating() {
+        let value: &[u8] = b"*9223372036854775807\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert_eq!(result, Err(RESPParseError::EmptyInput));
+    }
+
+    #[test]
+    fn parse_overflowing_map_header_fails() {
+        let value: &[u8] = b"%18446744073709551615\r\n";
+        let result = RESPValues::try_from(value);
+
+        assert_eq!(result, Err(RESPParseError::InvalidMultibulkLength));
+    }
+
+    #[test]
+    fn parse_huge_bulk_length_fails() {
+        let value: &[u8] = b"$18446744073709551615\r\n";
+        let limits = RESPLimits {
+            max_bulk_length: usize::MAX,
+            ..RESPLimits::default()
+        };
+        let result = super::parse_value(&value.into(), &limits, 0);
+
+        assert_eq!(result, Err(RESPParseError::MissingCRLF));
+    }
+
+    #[test]
+    fn decode_many_empty_lines_correctly() {
+        let mut buffer = BytesMut::from("\r\n".repeat(1_000_000).as_bytes());
+        let result = decode_frame(&mut buffer, &RESPLimits::default());
+
+        assert_eq!(result, Err(RESPDecodeError::NeedMoreData));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn decode_every_truncation_and_corruption_without_panicking() {
+        let frame: &[u8] = b"*4\r\n%1\r\n+k\r\n$1\r\nv\r\n=7\r\ntxt:abc\r\n(-12\r\n~1\r\n,1.5\r\n";
+
+        for end in 0..frame.len() {
+            for byte in [b'\r', b'\n', b'-', b'9', b'*', b'$', b'%', b'"', 0xff] {
+                let mut value = frame[..end].to_vec();
+                value.push(byte);
+                value.extend_from_slice(&frame[end..]);
+
+                let _ = RESPValues::try_from(&value[..end + 1]);
+                let _ = RESPValues::try_from(&value[..]);
+                let mut buffer = BytesMut::from(&value[..]);
+                while decode_frame(&mut buffer, &RESPLimits::default()).is_ok() {}
+            }
+        }
     }
 }
@@ -0,0 +1,74 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use bytes::Bytes;
+
+// Script bodies by the lowercase hex SHA1 EVALSHA addresses them with
+#[derive(Default)]
+pub struct ScriptCache {
+    scripts: Mutex<HashMap<String, Bytes>>,
+}
+
+impl ScriptCache {
+    // Returns the SHA1 the script is cached under
+    pub fn load(&self, body: Bytes) -> String {
+        let sha = sha1(&body);
+        self.scripts.lock().unwrap().insert(sha.clone(), body);
+        sha
+    }
+
+    // SHA1s are matched regardless of case, as Redis does
+    pub fn get(&self, sha: &str) -> Option<Bytes> {
+        let scripts = self.scripts.lock().unwrap();
+        scripts.get(&sha.to_ascii_lowercase()).cloned()
+    }
+
+    pub fn contains(&self, sha: &str) -> bool {
+        self.get(sha).is_some()
+    }
+
+    pub fn flush(&self) {
+        self.scripts.lock().unwrap().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.scripts.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+pub fn sha1(body: &[u8]) -> String {
+    sha1_smol::Sha1::from(body).digest().to_string()
+}
+
+#[cfg(test)]
+mod script_cache_tests {
+    use bytes::Bytes;
+
+    use super::ScriptCache;
+
+    #[test]
+    fn load_script_correctly() {
+        let cache = ScriptCache::default();
+        let sha = cache.load(Bytes::from_static(b"return 1"));
+
+        assert_eq!(sha, "e0e1f9fabfc9d4800c877a703b823ac0578ff8db");
+        assert_eq!(
+            cache.get(&sha.to_ascii_uppercase()),
+            Some(Bytes::from_static(b"return 1"))
+        );
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn flush_scripts_correctly() {
+        let cache = ScriptCache::default();
+        let sha = cache.load(Bytes::from_static(b"return 1"));
+        cache.flush();
+
+        assert!(!cache.contains(&sha));
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,2129 @@
+use std::{
+    collections::HashMap,
+    fmt, future, io,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use bytes::Bytes;
+use socket2::{SockRef, TcpKeepalive};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, watch, Notify},
+    task::JoinSet,
+};
+use tokio_rustls::TlsAcceptor;
+
+#[cfg(unix)]
+use {
+    std::{fs, os::unix::fs::PermissionsExt, path::Path},
+    tokio::net::{UnixListener, UnixStream},
+};
+
+use crate::{
+    aof::{self, Aof, Loaded, Record},
+    cluster::{self, Cluster},
+    commands::{CommandContext, CommandRegistry, ConnectionState, Module},
+    config::{AppendFsync, ClientClass, Config, KeyspaceEvents, LogLevel, OutputBufferLimit},
+    functions::Libraries,
+    pool::BufferPool,
+    pubsub::PubSub,
+    rdb::{self, Snapshots},
+    replay::Recorder,
+    replication::{self, ReplicaState, Replication},
+    resp::{RESPDecodeError, RESPDecoder, RESPLimits, RESPValues},
+    scripts::ScriptCache,
+    store::Store,
+    tls,
+};
+
+mod replies;
+#[cfg(all(feature = "uring", target_os = "linux"))]
+mod uring;
+mod workers;
+
+// bytes read from a connection at once, as Redis' PROTO_IOBUF_LEN
+const READ_BUFFER_SIZE: usize = 16 * 1024;
+
+// most idle buffers kept for new connections, each READ_BUFFER_SIZE to 4 times that
+const POOLED_BUFFERS: usize = 1024;
+
+// how long a shutdown waits for open connections to write their last replies
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+// State shared by every connection, created once and handed to each of them
+pub struct Shared {
+    // CONFIG SET changes it at runtime
+    pub config: RwLock<Config>,
+    pub store: Store,
+    pub clients: ClientRegistry,
+    pub commands: CommandRegistry,
+    pub recorder: Option<Recorder>,
+    pub scripts: ScriptCache,
+    pub functions: Libraries,
+    pub pubsub: PubSub,
+    pub snapshots: Snapshots,
+    pub aof: Aof,
+    pub replication: Replication,
+    pub cluster: Cluster,
+    // while the AOF is replayed at startup, so its commands aren't appended again
+    pub loading: AtomicBool,
+    // held shared by every command and exclusively by EXEC and BGREWRITEAOF,
+    // see CommandRegistry::dispatch
+    pub exec_lock: RwLock<()>,
+    // read and reply buffers, reused across connections
+    pub buffers: BufferPool,
+    pub shutdown: ShutdownHandle,
+    pub started_at: Instant,
+}
+
+impl Shared {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config: RwLock::new(config),
+            store: Store::default(),
+            clients: ClientRegistry::default(),
+            commands: CommandRegistry::builtin(),
+            recorder: None,
+            scripts: ScriptCache::default(),
+            functions: Libraries::default(),
+            pubsub: PubSub::default(),
+            snapshots: Snapshots::default(),
+            aof: Aof::default(),
+            replication: Replication::default(),
+            cluster: Cluster::default(),
+            loading: AtomicBool::new(false),
+            exec_lock: RwLock::new(()),
+            buffers: BufferPool::new(READ_BUFFER_SIZE, POOLED_BUFFERS),
+            shutdown: ShutdownHandle(
+                Arc::new(watch::channel(false).0),
+                Arc::new(AtomicBool::new(false)),
+            ),
+            started_at: Instant::now(),
+        }
+    }
+
+    // Runs the command in `request` on behalf of `connection`
+    pub fn dispatch(&self, request: RESPValues, connection: &mut ConnectionState) -> RESPValues {
+        let mut ctx = CommandContext {
+            connection,
+            server: self,
+        };
+        self.commands.dispatch(request, &mut ctx)
+    }
+
+    // Fills the store from the dump at rdb_path, if there is one. A dump that
+    // can't be read stops the server from starting, as in Redis
+    fn load_rdb(&self) -> io::Result<()> {
+        let started = Instant::now();
+        let (entries, persisted) = match rdb::load(&self.rdb_path(), self.rdb_options()) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            result => result?,
+        };
+        for (key, value) in entries {
+            self.store.set(key, value);
+        }
+        let loglevel = self.config.read().unwrap().loglevel;
+        rdb::warn_persisted(persisted, loglevel);
+        if loglevel <= LogLevel::Notice {
+            eprintln!(
+                "DB loaded from disk: {:.3} seconds",
+                started.elapsed().as_secs_f64()
+            );
+        }
+        Ok(())
+    }
+
+    // Fills the store by running the commands in the AOF through the
+    // dispatcher, as a client of its own. An AOF that can't be loaded stops
+    // the server from starting, as does a command it doesn't know
+    fn load_aof(&self) -> io::Result<()> {
+        let started = Instant::now();
+        let config = self.config.read().unwrap().clone();
+        let mut connection = ConnectionState::new(0, mpsc::unbounded_channel().0);
+        self.loading.store(true, Ordering::Release);
+        let loaded = aof::load(&self.aof_options(), config.aof_load_truncated, |record| {
+            match record {
+                Record::Entry(key, value) => {
+                    self.store.set(key, value);
+                }
+                Record::Persisted(persisted) => rdb::warn_persisted(persisted, config.loglevel),
+                Record::Command(frame) => {
+                    let name = aof::command_name(&frame).unwrap_or_default();
+                    if self.commands.spec(name).is_none() {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!(
+                                "Unknown command '{}' reading the append only file",
+                                String::from_utf8_lossy(name)
+                            ),
+                        ));
+                    }
+                    self.dispatch(frame, &mut connection);
+                }
+            }
+            Ok(())
+        });
+        self.loading.store(false, Ordering::Release);
+
+        match loaded? {
+            Loaded::Truncated(file) if config.loglevel <= LogLevel::Warning => {
+                eprintln!("!!! Warning: short read while loading the AOF file {file}!!!");
+                eprintln!("AOF {file} loaded anyway because aof-load-truncated is enabled");
+            }
+            Loaded::Whole if config.loglevel <= LogLevel::Notice => eprintln!(
+                "DB loaded from append only file: {:.3} seconds",
+                started.elapsed().as_secs_f64()
+            ),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Where SAVE and BGSAVE write the dump, dir/dbfilename
+    pub fn rdb_path(&self) -> PathBuf {
+        let config = self.config.read().unwrap();
+        config.dir.join(&config.dbfilename)
+    }
+
+    // SAVE, writing the dump before returning
+    pub fn save(&self) -> io::Result<()> {
+        self.snapshots
+            .save(&self.store, &self.rdb_path(), self.rdb_options())
+    }
+
+    // What a shutdown does before the process exits, as Redis' finishShutdown:
+    // saves the dump when `save` asks for it, then flushes the AOF to disk
+    pub fn prepare_shutdown(&self, save: bool) -> io::Result<()> {
+        if save {
+            self.save()?;
+        }
+        if self.config.read().unwrap().appendonly {
+            self.aof.sync()?;
+        }
+        Ok(())
+    }
+
+    // The rest of a shutdown once the connections closed, unless SHUTDOWN
+    // did it already. As on SIGTERM in Redis it saves if there are save
+    // points, after the background save or AOF rewrite running finishes.
+    // With no write since the last save the dump on disk is left as it is
+    async fn finish_shutdown(&self) {
+        if self.shutdown.is_finished() {
+            return;
+        }
+        while self.snapshots.in_progress() || self.aof.rewriting() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let (save, loglevel) = {
+            let config = self.config.read().unwrap();
+            (!config.save.is_empty(), config.loglevel)
+        };
+        let save = save && self.snapshots.unsaved_changes(&self.store) > 0;
+        if let Err(error) = self.prepare_shutdown(save) {
+            if loglevel <= LogLevel::Warning {
+                eprintln!("Error trying to save the DB before exiting: {error}");
+            }
+        }
+    }
+
+    // BGSAVE, false if a background save is already running
+    pub fn bgsave(&self) -> bool {
+        let loglevel = self.config.read().unwrap().loglevel;
+        self.snapshots
+            .background(&self.store, self.rdb_path(), self.rdb_options(), loglevel)
+    }
+
+    // Where the AOF is, dir/appenddirname, and how it's written
+    pub fn aof_options(&self) -> aof::Options {
+        let config = self.config.read().unwrap();
+        aof::Options {
+            dir: config.dir.join(&config.appenddirname),
+            filename: config.appendfilename.clone(),
+            fsync: config.appendfsync,
+            rdb_preamble: config.aof_use_rdb_preamble,
+            rdb: rdb::Options {
+                compression: config.rdbcompression,
+                checksum: config.rdbchecksum,
+            },
+        }
+    }
+
+    // Logs a write command that just ran to the AOF, when appendonly is on,
+    // and streams it to the replicas. A replica's replicas get the writes of
+    // its master instead, as they came, see replication::link
+    pub fn propagate(&self, args: &[Bytes]) {
+        let (appendonly, replicating, loglevel, backlog_size) = {
+            let config = self.config.read().unwrap();
+            (
+                config.appendonly,
+                config.replicaof.is_some(),
+                config.loglevel,
+                config.repl_backlog_size,
+            )
+        };
+        if self.loading.load(Ordering::Acquire) {
+            return;
+        }
+        if appendonly {
+            if let Err(error) = self.aof.append(&self.aof_options(), args) {
+                if loglevel <= LogLevel::Warning {
+                    eprintln!("Error writing to the AOF: {error}");
+                }
+            }
+        }
+        if !replicating {
+            let frame =
+                RESPValues::Array(args.iter().cloned().map(RESPValues::BulkString).collect());
+            self.replication.feed(&frame, backlog_size);
+        }
+    }
+
+    // The writes of a transaction, wrapped in MULTI and EXEC so the AOF and
+    // the replicas apply them all or none when there's more than one
+    pub fn propagate_transaction(&self, writes: &[Vec<Bytes>]) {
+        if writes.len() < 2 {
+            writes.iter().for_each(|args| self.propagate(args));
+            return;
+        }
+        self.propagate(&[Bytes::from_static(b"MULTI")]);
+        writes.iter().for_each(|args| self.propagate(args));
+        self.propagate(&[Bytes::from_static(b"EXEC")]);
+    }
+
+    // Whether writes are refused but for the ones from this server's master
+    pub fn read_only_replica(&self) -> bool {
+        let config = self.config.read().unwrap();
+        config.replicaof.is_some()
+            && config.replica_read_only
+            && !self.loading.load(Ordering::Acquire)
+    }
+
+    // Whether writes are refused for want of replicas keeping up, see
+    // min-replicas-to-write. A replica's writes come from its master
+    pub fn too_few_replicas(&self) -> bool {
+        let config = self.config.read().unwrap();
+        if config.min_replicas_to_write == 0
+            || config.min_replicas_max_lag == 0
+            || config.replicaof.is_some()
+            || self.loading.load(Ordering::Acquire)
+        {
+            return false;
+        }
+        let replicas = self.replication.replicas();
+        let good = replicas.iter().filter(|replica| {
+            replica.state == ReplicaState::Online && replica.lag <= config.min_replicas_max_lag
+        });
+        good.count() < config.min_replicas_to_write
+    }
+
+    // BGREWRITEAOF, false if a rewrite is already running. Callers hold
+    // exec_lock exclusively, so the snapshot and the switch of later writes
+    // to a new incremental file happen with no write in between
+    pub fn bgrewriteaof(&self) -> io::Result<bool> {
+        let loglevel = self.config.read().unwrap().loglevel;
+        self.aof
+            .rewrite(self.aof_options(), self.store.snapshot(), loglevel)
+    }
+
+    pub fn rdb_options(&self) -> rdb::Options {
+        let config = self.config.read().unwrap();
+        rdb::Options {
+            compression: config.rdbcompression,
+            checksum: config.rdbchecksum,
+        }
+    }
+
+    // Publishes a keyspace notification as notify-keyspace-events asks, for
+    // commands to call right after they change `key`
+    pub fn notify_keyspace_event(&self, class: KeyspaceEvents, event: &str, key: &Bytes) {
+        let events = self.config.read().unwrap().notify_keyspace_events;
+        self.pubsub.notify(events, class, event, key);
+    }
+
+    // Applies the dynamic parameters in the config file, see Config::reload.
+    // Nothing changes when the file can't be read or holds an invalid value
+    pub fn reload_config(&self) -> io::Result<Vec<String>> {
+        let mut config = self.config.write().unwrap();
+        let (reloaded, skipped) = config.reload()?;
+        *config = reloaded;
+        Ok(skipped)
+    }
+}
+
+impl Default for Shared {
+    fn default() -> Self {
+        Self::new(Config::default())
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum ClientAddr {
+    Tcp(SocketAddr),
+    // the path of the socket the client connected to
+    Unix(PathBuf),
+}
+
+impl fmt::Display for ClientAddr {
+    // as Redis shows them in CLIENT LIST
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "{addr}"),
+            Self::Unix(path) => write!(f, "{}:0", path.display()),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct ClientInfo {
+    pub id: u64,
+    pub addr: ClientAddr,
+    pub connected_at: SystemTime,
+}
+
+// Every connected client, keyed by the id it was given on connect
+#[derive(Default)]
+pub struct ClientRegistry {
+    last_id: AtomicU64,
+    clients: Mutex<HashMap<u64, ClientInfo>>,
+    // what a client's connection waits on to be closed, see ClientRegistry::kill
+    kills: Mutex<HashMap<u64, Arc<Notify>>>,
+}
+
+impl ClientRegistry {
+    // Registers a new client and returns its id, ids start at 1 and are never reused
+    pub fn connect(&self, addr: ClientAddr) -> u64 {
+        let id = self.last_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let client = ClientInfo {
+            id,
+            addr,
+            connected_at: SystemTime::now(),
+        };
+        self.clients.lock().unwrap().insert(id, client);
+        self.kills.lock().unwrap().insert(id, Arc::default());
+        id
+    }
+
+    pub fn disconnect(&self, id: u64) {
+        self.clients.lock().unwrap().remove(&id);
+        self.kills.lock().unwrap().remove(&id);
+    }
+
+    // Closes the connection of client `id`, without the replies it has pending
+    pub fn kill(&self, id: u64) -> bool {
+        match self.kills.lock().unwrap().get(&id) {
+            Some(kill) => {
+                kill.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Resolves once client `id` is killed
+    pub fn killed(&self, id: u64) -> Arc<Notify> {
+        let kills = self.kills.lock().unwrap();
+        kills.get(&id).cloned().unwrap_or_default()
+    }
+
+    pub fn get(&self, id: u64) -> Option<ClientInfo> {
+        self.clients.lock().unwrap().get(&id).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+pub struct RedisServerBuilder {
+    config: Config,
+    recorder: Option<Recorder>,
+    modules: Vec<Box<dyn Module>>,
+}
+
+impl RedisServerBuilder {
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn bind(mut self, bind: IpAddr) -> Self {
+        self.config.bind = bind;
+        self
+    }
+
+    // Port 0 picks a free port, see RedisServer::local_addr
+    pub fn port(mut self, port: u16) -> Self {
+        self.config.port = port;
+        self
+    }
+
+    pub fn limits(mut self, limits: RESPLimits) -> Self {
+        self.config.limits = limits;
+        self
+    }
+
+    // Records every inbound command, see the replay module
+    pub fn recorder(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    // Modules register their commands in the order they were added
+    pub fn module(mut self, module: impl Module + 'static) -> Self {
+        self.modules.push(Box::new(module));
+        self
+    }
+
+    // Binds the listeners, so the address is known before the server runs
+    pub async fn build(self) -> io::Result<RedisServer> {
+        let listener = TcpListener::bind((self.config.bind, self.config.port)).await?;
+        #[cfg(unix)]
+        let unix_listener = match &self.config.unixsocket {
+            Some(path) => Some((bind_unix(path, self.config.unixsocketperm)?, path.clone())),
+            None => None,
+        };
+        #[cfg(not(unix))]
+        if self.config.unixsocket.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "unix sockets aren't supported on this platform",
+            ));
+        }
+
+        let tls_listener = match self.config.tls_port {
+            Some(port) => Some((
+                TcpListener::bind((self.config.bind, port)).await?,
+                tls::acceptor(&self.config)?,
+            )),
+            None => None,
+        };
+
+        let mut shared = Shared::new(self.config);
+        shared.recorder = self.recorder;
+        for module in &self.modules {
+            module.register(&mut shared.commands);
+            if shared.config.read().unwrap().loglevel <= LogLevel::Notice {
+                eprintln!("Module '{}' loaded", module.name());
+            }
+        }
+        let (cluster, file) = {
+            let config = shared.config.read().unwrap();
+            let file = config.dir.join(&config.cluster_config_file);
+            (config.cluster_enabled, file)
+        };
+        if cluster {
+            let addr = listener.local_addr()?;
+            // a node bound to every address goes by the loopback one
+            let ip = match addr.ip() {
+                ip if ip.is_unspecified() => "127.0.0.1".to_string(),
+                ip => ip.to_string(),
+            };
+            shared.cluster.start(&file, ip, addr.port())?;
+        }
+        // after the modules, whose commands the AOF may have
+        if shared.config.read().unwrap().appendonly {
+            shared.load_aof()?;
+        } else {
+            shared.load_rdb()?;
+        }
+
+        Ok(RedisServer {
+            listener,
+            tls_listener,
+            #[cfg(unix)]
+            unix_listener,
+            shared: Arc::new(shared),
+        })
+    }
+}
+
+// Every listener serves connections through this, so they all share handle_connection
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+// An accepted connection, TLS ones still have to complete their handshake
+enum Incoming {
+    Tcp(TcpStream),
+    Tls(TcpStream, TlsAcceptor),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+pub struct RedisServer {
+    listener: TcpListener,
+    tls_listener: Option<(TcpListener, TlsAcceptor)>,
+    #[cfg(unix)]
+    unix_listener: Option<(UnixListener, PathBuf)>,
+    shared: Arc<Shared>,
+}
+
+impl RedisServer {
+    pub fn builder() -> RedisServerBuilder {
+        RedisServerBuilder {
+            config: Config::default(),
+            recorder: None,
+            modules: Vec::new(),
+        }
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // The address TLS clients connect to, when tls-port is set
+    pub fn tls_local_addr(&self) -> io::Result<Option<SocketAddr>> {
+        self.tls_listener
+            .as_ref()
+            .map(|(listener, _)| listener.local_addr())
+            .transpose()
+    }
+
+    pub fn shared(&self) -> &Arc<Shared> {
+        &self.shared
+    }
+
+    // Waits for the next client on any of the listeners
+    async fn accept(&self) -> io::Result<(Incoming, ClientAddr)> {
+        let tcp = async {
+            let (stream, addr) = self.listener.accept().await?;
+            configure(SockRef::from(&stream), &self.shared);
+            Ok((Incoming::Tcp(stream), ClientAddr::Tcp(addr)))
+        };
+        let tls = async {
+            match &self.tls_listener {
+                Some((listener, acceptor)) => {
+                    let (stream, addr) = listener.accept().await?;
+                    configure(SockRef::from(&stream), &self.shared);
+                    Ok((
+                        Incoming::Tls(stream, acceptor.clone()),
+                        ClientAddr::Tcp(addr),
+                    ))
+                }
+                None => future::pending().await,
+            }
+        };
+        #[cfg(unix)]
+        let unix = async {
+            match &self.unix_listener {
+                Some((listener, path)) => listener
+                    .accept()
+                    .await
+                    .map(|(stream, _)| (Incoming::Unix(stream), ClientAddr::Unix(path.clone()))),
+                None => future::pending().await,
+            }
+        };
+        #[cfg(not(unix))]
+        let unix = future::pending();
+
+        tokio::select! {
+            accepted = tcp => accepted,
+            accepted = tls => accepted,
+            accepted = unix => accepted,
+        }
+    }
+
+    // Whether run_uring can serve this config. TLS, unix sockets and
+    // io-threads rely on tokio's streams, they're left to `run`
+    #[cfg(all(feature = "uring", target_os = "linux"))]
+    pub fn uring_supported(&self) -> bool {
+        self.tls_listener.is_none()
+            && self.unix_listener.is_none()
+            && self.shared.config.read().unwrap().io_threads <= 1
+    }
+
+    // Serves plain TCP clients on an io_uring runtime of its own until shut
+    // down, refusing what uring_supported doesn't
+    #[cfg(all(feature = "uring", target_os = "linux"))]
+    pub async fn run_uring(self) -> io::Result<()> {
+        if !self.uring_supported() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the io_uring backend only serves plain TCP on a single thread",
+            ));
+        }
+
+        tokio::spawn(cron(self.shared.clone()));
+        tokio::spawn(replication::link(self.shared.clone()));
+        tokio::spawn(cluster::link(self.shared.clone()));
+        let listener = self.listener.into_std()?;
+        let shared = self.shared;
+        tokio::task::spawn_blocking(move || uring::run(listener, shared)).await?
+    }
+
+    // A handle to stop the server from elsewhere once `run` took ownership of it
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shared.shutdown.clone()
+    }
+
+    // Accepts connections until shut down, then waits for the open ones to close
+    pub async fn run(self) -> io::Result<()> {
+        tokio::spawn(cron(self.shared.clone()));
+        tokio::spawn(replication::link(self.shared.clone()));
+        tokio::spawn(cluster::link(self.shared.clone()));
+        let mut shutdown = self.shared.shutdown.0.subscribe();
+        let mut connections = JoinSet::new();
+        let io_threads = self.shared.config.read().unwrap().io_threads;
+        let mut workers = match io_threads {
+            0 | 1 => None,
+            threads => Some(workers::Workers::spawn(threads, &self.shared)?),
+        };
+
+        loop {
+            let accepted = tokio::select! {
+                accepted = self.accept() => accepted,
+                // reap finished connections so the set doesn't grow forever
+                Some(_) = connections.join_next(), if !connections.is_empty() => continue,
+                _ = shutdown.wait_for(|stop| *stop) => break,
+            };
+
+            let result = accepted.and_then(|(incoming, addr)| match &mut workers {
+                Some(workers) => workers.dispatch(incoming, addr),
+                None => {
+                    spawn_connection(&mut connections, &self.shared, incoming, addr, None);
+                    Ok(())
+                }
+            });
+            if let Err(error) = result {
+                if self.shared.config.read().unwrap().loglevel <= LogLevel::Warning {
+                    eprintln!("Error at accepting connection: {error}");
+                }
+            }
+        }
+
+        // no new connections from here on, the rest are aborted if they don't close in time
+        drop(self.listener);
+        drop(self.tls_listener);
+        #[cfg(unix)]
+        if let Some((unix_listener, path)) = self.unix_listener {
+            drop(unix_listener);
+            fs::remove_file(path)?;
+        }
+        if let Some(workers) = workers {
+            workers.join().await;
+        }
+        drain(connections, &self.shared).await;
+        self.shared.finish_shutdown().await;
+
+        Ok(())
+    }
+}
+
+// Once a second, as Redis' serverCron, starts the background saves and AOF
+// rewrites that came due, pings the replicas and flushes the AOF for
+// appendfsync everysec, until the server shuts down
+async fn cron(shared: Arc<Shared>) {
+    let mut shutdown = shared.shutdown.0.subscribe();
+    let mut ticks = tokio::time::interval(Duration::from_secs(1));
+    let mut seconds: u64 = 0;
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => {}
+            _ = shutdown.wait_for(|stop| *stop) => return,
+        }
+        let config = shared.config.read().unwrap().clone();
+        let notice = config.loglevel <= LogLevel::Notice;
+
+        if shared.snapshots.due(&config.save, &shared.store) && shared.bgsave() && notice {
+            eprintln!("Background saving started");
+        }
+
+        // replicas tell a master gone quiet from a dead link by these
+        seconds += 1;
+        if config.replicaof.is_none() && seconds.is_multiple_of(config.repl_ping_replica_period) {
+            let ping = RESPValues::Array(vec![RESPValues::BulkString(Bytes::from_static(b"PING"))]);
+            shared.replication.feed(&ping, config.repl_backlog_size);
+        }
+
+        // the dumps of diskless replication wait for more replicas to share them
+        if shared.replication.dump_due(config.repl_diskless_sync_delay) {
+            let _exclusive = shared.exec_lock.write().unwrap();
+            let snapshot = shared.store.snapshot();
+            shared
+                .replication
+                .start_diskless(snapshot, shared.rdb_options());
+            if notice {
+                eprintln!("Starting BGSAVE for SYNC with target: replicas sockets");
+            }
+        }
+
+        if !config.appendonly {
+            continue;
+        }
+        let rewrite = shared.aof.due(
+            config.auto_aof_rewrite_percentage,
+            config.auto_aof_rewrite_min_size,
+        );
+        if rewrite {
+            let _exclusive = shared.exec_lock.write().unwrap();
+            match shared.bgrewriteaof() {
+                Ok(true) if notice => eprintln!("Starting automatic rewriting of AOF"),
+                Err(error) if config.loglevel <= LogLevel::Warning => {
+                    eprintln!("Can't rewrite the AOF: {error}")
+                }
+                _ => {}
+            }
+        }
+        if config.appendfsync == AppendFsync::Everysec {
+            if let Err(error) = shared.aof.sync() {
+                if config.loglevel <= LogLevel::Warning {
+                    eprintln!("Error syncing the AOF: {error}");
+                }
+            }
+        }
+    }
+}
+
+// Applies tcp-nodelay and tcp-keepalive to a client socket, a failure
+// only costs the option so the client is served anyway
+fn configure(socket: SockRef, shared: &Shared) {
+    let config = shared.config.read().unwrap();
+    let mut result = socket.set_tcp_nodelay(config.tcp_nodelay);
+    if config.tcp_keepalive > 0 {
+        // as Redis does, the connection is dropped after three unanswered probes
+        let interval = Duration::from_secs(config.tcp_keepalive);
+        let keepalive = TcpKeepalive::new().with_time(interval);
+        #[cfg(any(target_os = "linux", target_os = "macos", target_os = "freebsd"))]
+        let keepalive = keepalive
+            .with_interval(Duration::from_secs((config.tcp_keepalive / 3).max(1)))
+            .with_retries(3);
+        result = result.and(socket.set_tcp_keepalive(&keepalive));
+    }
+
+    if let Err(error) = result {
+        if config.loglevel <= LogLevel::Warning {
+            eprintln!("Error at configuring client socket: {error}");
+        }
+    }
+}
+
+// `router` is there for connections on an io thread, see workers::Router
+fn spawn_connection(
+    connections: &mut JoinSet<io::Result<()>>,
+    shared: &Arc<Shared>,
+    incoming: Incoming,
+    addr: ClientAddr,
+    router: Option<workers::Router>,
+) {
+    let shared = shared.clone();
+    let shutdown = shared.shutdown.0.subscribe();
+    connections.spawn(async move {
+        let id = shared.clients.connect(addr);
+        let result = serve(incoming, id, &shared, shutdown, router.as_ref()).await;
+        shared.clients.disconnect(id);
+        result
+    });
+}
+
+// Waits for the connections to close after a shutdown, aborting those that take too long
+async fn drain(mut connections: JoinSet<io::Result<()>>, shared: &Shared) {
+    let drained = tokio::time::timeout(SHUTDOWN_TIMEOUT, async {
+        while connections.join_next().await.is_some() {}
+    });
+    if drained.await.is_err() && shared.config.read().unwrap().loglevel <= LogLevel::Warning {
+        eprintln!(
+            "Closing {} connections that didn't finish in time",
+            connections.len()
+        );
+    }
+}
+
+// Whether the server shuts down, and whether what's done before exiting was
+// done already, see Shared::finish_shutdown
+#[derive(Clone)]
+pub struct ShutdownHandle(Arc<watch::Sender<bool>>, Arc<AtomicBool>);
+
+impl ShutdownHandle {
+    // Stops accepting connections and closes the open ones once their
+    // pending replies are written, `run` returns when they all closed and
+    // the dump is saved
+    pub fn shutdown(&self) {
+        self.0.send_replace(true);
+    }
+
+    // Shuts down once the caller did Shared::prepare_shutdown, as SHUTDOWN
+    pub fn finish(&self) {
+        self.1.store(true, Ordering::Release);
+        self.shutdown();
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.1.load(Ordering::Acquire)
+    }
+
+    // Resolves once the server starts shutting down
+    pub async fn wait(&self) {
+        let _ = self.0.subscribe().wait_for(|stop| *stop).await;
+    }
+}
+
+// Binds a socket at `path`, replacing the one a previous run may have left behind
+#[cfg(unix)]
+fn bind_unix(path: &Path, mode: u32) -> io::Result<UnixListener> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+
+    let listener = UnixListener::bind(path)?;
+    if mode != 0 {
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    }
+    Ok(listener)
+}
+
+async fn serve(
+    incoming: Incoming,
+    id: u64,
+    shared: &Shared,
+    mut shutdown: watch::Receiver<bool>,
+    router: Option<&workers::Router>,
+) -> io::Result<()> {
+    let mut stream: Box<dyn Stream> = match incoming {
+        Incoming::Tcp(stream) => Box::new(stream),
+        #[cfg(unix)]
+        Incoming::Unix(stream) => Box::new(stream),
+        Incoming::Tls(stream, acceptor) => tokio::select! {
+            stream = acceptor.accept(stream) => Box::new(stream?),
+            _ = shutdown.wait_for(|stop| *stop) => return Ok(()),
+        },
+    };
+
+    if over_maxclients(shared) {
+        return stream
+            .write_all(b"-ERR max number of clients reached\r\n")
+            .await;
+    }
+
+    handle_connection(stream, id, shared, shutdown, router).await
+}
+
+// Whether a client that was just counted takes the server past maxclients
+fn over_maxclients(shared: &Shared) -> bool {
+    shared.clients.len() > shared.config.read().unwrap().maxclients
+}
+
+async fn handle_connection(
+    conn: impl AsyncRead + AsyncWrite + Unpin,
+    id: u64,
+    shared: &Shared,
+    shutdown: watch::Receiver<bool>,
+    router: Option<&workers::Router>,
+) -> io::Result<()> {
+    let limits = shared.config.read().unwrap().limits;
+    let mut decoder = RESPDecoder::with_buffer(limits, shared.buffers.take());
+    let mut out = replies::Replies::new(shared.buffers.take());
+    let (subscriber, mut messages) = mpsc::unbounded_channel();
+    let mut state = ConnectionState::new(id, subscriber);
+
+    let result = command_loop(
+        conn,
+        shared,
+        shutdown,
+        router,
+        &mut state,
+        &mut messages,
+        &mut decoder,
+        &mut out,
+    )
+    .await;
+    shared.pubsub.disconnect(&state);
+    shared.replication.detach(state.id);
+    shared.buffers.put(decoder.into_buffer());
+    shared.buffers.put(out.into_buffer());
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn command_loop(
+    conn: impl AsyncRead + AsyncWrite + Unpin,
+    shared: &Shared,
+    mut shutdown: watch::Receiver<bool>,
+    router: Option<&workers::Router>,
+    state: &mut ConnectionState,
+    messages: &mut mpsc::UnboundedReceiver<RESPValues>,
+    decoder: &mut RESPDecoder,
+    out: &mut replies::Replies,
+) -> io::Result<()> {
+    // replies are written while more commands are read, so they can pile up in `out`
+    let (mut reader, mut writer) = tokio::io::split(conn);
+    let mut output_limit = OutputLimitTracker::default();
+    let id = state.id;
+    let killed = shared.clients.killed(id);
+
+    loop {
+        let closing = match (
+            execute_buffered(decoder, state, shared, out, router)?,
+            router,
+        ) {
+            // the commands after it wait for its reply, so they stay in order
+            (Executed::Routed(request, owner), Some(router)) => {
+                let reply = router.run(owner, request, state).await?;
+                if !push_reply(reply, state, out) {
+                    continue;
+                }
+                true
+            }
+            (executed, _) => matches!(executed, Executed::Closing),
+        };
+
+        let (limit, loglevel) = {
+            let config = shared.config.read().unwrap();
+            let limit = config.client_output_buffer_limit.get(state.class);
+            (limit, config.loglevel)
+        };
+        if output_limit.exceeded(out.len() as u64, limit, Instant::now()) {
+            if loglevel <= LogLevel::Warning {
+                eprintln!("Client {id} closed for overcoming of output buffer limits");
+            }
+            break;
+        }
+
+        if closing {
+            writer.write_all_buf(out).await?;
+            break;
+        }
+
+        // a frame may span any number of reads, the buffer grows until it's complete
+        let buffer = decoder.buffer_mut();
+        buffer.reserve(READ_BUFFER_SIZE);
+        let read = tokio::select! {
+            written = writer.write_buf(out), if !out.is_empty() => {
+                written?;
+                continue;
+            }
+            // published messages go out between replies, subject to the same limits
+            Some(message) = messages.recv() => {
+                out.push(&message.to_protocol(state.protocol));
+                continue;
+            }
+            read = reader.read_buf(buffer) => Some(read?),
+            _ = killed.notified() => break,
+            _ = shutdown.wait_for(|stop| *stop) => None,
+        };
+
+        match read {
+            // the client may only have closed its writing half, it still gets its replies
+            Some(0) => {
+                writer.write_all_buf(out).await?;
+                break;
+            }
+            Some(_) => {}
+            None => {
+                // a client halfway through sending a command won't get its reply
+                if !decoder.buffer_mut().is_empty() {
+                    out.push(&RESPValues::SimpleError(
+                        "ERR Server is shutting down".to_string(),
+                    ));
+                }
+                writer.write_all_buf(out).await?;
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Where execute_buffered stopped
+enum Executed {
+    // every complete command ran
+    Pending,
+    // the connection has to close once the replies are written
+    Closing,
+    // the next command belongs to the io thread owning its keys
+    Routed(RESPValues, usize),
+}
+
+// Runs every complete command already read, batching the replies in order,
+// up to one `router` sends to another io thread
+fn execute_buffered(
+    decoder: &mut RESPDecoder,
+    state: &mut ConnectionState,
+    shared: &Shared,
+    out: &mut replies::Replies,
+    router: Option<&workers::Router>,
+) -> io::Result<Executed> {
+    loop {
+        let client_input = match decoder.decode() {
+            Ok(v) => v,
+            Err(RESPDecodeError::NeedMoreData) => return Ok(Executed::Pending),
+            Err(RESPDecodeError::Invalid(error)) => {
+                out.push(&RESPValues::SimpleError(format!(
+                    "ERR Protocol error: {error}"
+                )));
+                return Ok(Executed::Closing);
+            }
+        };
+
+        if let Some(recorder) = &shared.recorder {
+            recorder.record(state.id, &client_input)?;
+        }
+
+        let owner = router.and_then(|router| router.owner(shared, &client_input, state));
+        if let Some(owner) = owner {
+            return Ok(Executed::Routed(client_input, owner));
+        }
+        let reply = shared.dispatch(client_input, state);
+        if push_reply(reply, state, out) {
+            return Ok(Executed::Closing);
+        }
+    }
+}
+
+// Queues the reply to a command that ran, true if the connection closes
+// once the replies are written
+fn push_reply(reply: RESPValues, state: &mut ConnectionState, out: &mut replies::Replies) -> bool {
+    if state.closing {
+        return true;
+    }
+    // a replica only gets the stream of writes, see the psync command
+    if state.class != ClientClass::Replica {
+        out.push(&reply.to_protocol(state.protocol));
+        for reply in state.extra_replies.drain(..) {
+            out.push(&reply.to_protocol(state.protocol));
+        }
+    }
+    state.quit
+}
+
+// Tells when a connection's pending replies broke its output buffer limit
+#[derive(Default)]
+struct OutputLimitTracker {
+    // when the replies last went over the soft limit
+    soft_since: Option<Instant>,
+}
+
+impl OutputLimitTracker {
+    fn exceeded(&mut self, pending: u64, limit: OutputBufferLimit, now: Instant) -> bool {
+        if limit.hard > 0 && pending >= limit.hard {
+            return true;
+        }
+        if limit.soft == 0 || pending < limit.soft {
+            self.soft_since = None;
+            return false;
+        }
+
+        let since = *self.soft_since.get_or_insert(now);
+        now.duration_since(since) >= Duration::from_secs(limit.soft_seconds)
+    }
+}
+
+#[cfg(test)]
+mod server_tests {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::{ClientAddr, ClientRegistry, OutputLimitTracker, RedisServer};
+    use crate::{
+        commands::{
+            test_dir, CommandContext, CommandDocs, CommandFlag, CommandRegistry, CommandSpec,
+            Module,
+        },
+        config::OutputBufferLimit,
+        rdb,
+        resp::RESPValues,
+        store::Value,
+    };
+
+    // stores its argument under "last", as an embedder's module would, and
+    // loads test libraries
+    struct Remember;
+
+    impl Module for Remember {
+        fn name(&self) -> &'static str {
+            "remember"
+        }
+
+        fn register(&self, commands: &mut CommandRegistry) {
+            let spec = CommandSpec {
+                name: "remember",
+                arity: 2,
+                flags: &[CommandFlag::Write],
+                first_key: 0,
+                last_key: 0,
+                step: 0,
+                docs: CommandDocs {
+                    summary: "Remembers a value.",
+                    since: "1.0.0",
+                    group: "generic",
+                    complexity: "O(1)",
+                    arguments: &[],
+                },
+            };
+            commands.register(spec, |args: &[Bytes], ctx: &mut CommandContext| {
+                let value = Value::String(args[1].clone());
+                ctx.server.store.set(Bytes::from_static(b"last"), value);
+                Ok(RESPValues::SimpleString("OK".to_string()))
+            });
+            commands.register_engine("test", crate::functions::TestEngine);
+        }
+    }
+
+    #[test]
+    fn client_registry_tracks_clients_correctly() {
+        let clients = ClientRegistry::default();
+        let addr = ClientAddr::Tcp("127.0.0.1:1234".parse().unwrap());
+
+        let first = clients.connect(addr.clone());
+        let second = clients.connect(addr.clone());
+        clients.disconnect(first);
+
+        assert_eq!((first, second), (1, 2));
+        assert_eq!(clients.len(), 1);
+        assert!(clients.get(second).is_some_and(|c| c.addr == addr));
+    }
+
+    #[tokio::test]
+    async fn kill_a_client_correctly() {
+        let server = RedisServer::builder().port(0).build().await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let shared = server.shared().clone();
+        tokio::spawn(server.run());
+
+        let mut conn = TcpStream::connect(addr).await.unwrap();
+        conn.write_all(b"PING\r\n").await.unwrap();
+        let mut reply = vec![0; 7];
+        conn.read_exact(&mut reply).await.unwrap();
+        let killed = shared.clients.kill(1);
+        let mut rest = Vec::new();
+        let closed =
+            tokio::time::timeout(Duration::from_secs(5), conn.read_to_end(&mut rest)).await;
+
+        assert!(killed);
+        assert!(closed.is_ok_and(|read| read.is_ok()));
+        assert!(rest.is_empty());
+        assert!(!shared.clients.kill(2));
+    }
+
+    #[tokio::test]
+    async fn serve_pipelined_commands_correctly() {
+        let server = RedisServer::builder().port(0).build().await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let shared = server.shared().clone();
+        tokio::spawn(server.run());
+
+        let mut conn = TcpStream::connect(addr).await.unwrap();
+        conn.write_all(b"*1\r\n$4\r\nPING\r\nECHO hi\r\n")
+            .await
+            .unwrap();
+        let mut reply = vec![0; 15];
+        conn.read_exact(&mut reply).await.unwrap();
+
+        assert_eq!(reply, b"+PONG\r\n$2\r\nhi\r\n");
+        assert_eq!(shared.clients.len(), 1);
+
+        drop(conn);
+        for _ in 0..100 {
+            if shared.clients.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(shared.clients.is_empty());
+    }
+
+    #[tokio::test]
+    async fn serve_module_commands_correctly() {
+        let server = RedisServer::builder()
+            .port(0)
+            .module(Remember)
+            .build()
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        let shared = server.shared().clone();
+        tokio::spawn(server.run());
+
+        let mut conn = TcpStream::connect(addr).await.unwrap();
+        conn.write_all(b"REMEMBER hi\r\n").await.unwrap();
+        let mut reply = vec![0; 5];
+        conn.read_exact(&mut reply).await.unwrap();
+
+        assert_eq!(reply, b"+OK\r\n");
+        assert_eq!(
+            shared.store.get(b"last"),
+            Some(Value::String(Bytes::from_static(b"hi")))
+        );
+        assert!(shared.commands.spec(b"remember").is_some());
+    }
+
+    #[tokio::test]
+    async fn serve_module_functions_correctly() {
+        let server = RedisServer::builder()
+            .port(0)
+            .module(Remember)
+            .build()
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        let shared = server.shared().clone();
+        tokio::spawn(server.run());
+
+        let mut conn = TcpStream::connect(addr).await.unwrap();
+        conn.write_all(
+            b"*3\r\n$8\r\nFUNCTION\r\n$4\r\nLOAD\r\n$24\r\n#!test name=lib\nremember\r\n",
+        )
+        .await
+        .unwrap();
+        conn.write_all(b"FCALL remember 0 REMEMBER hi\r\n")
+            .await
+            .unwrap();
+        let mut reply = vec![0; 14];
+        conn.read_exact(&mut reply).await.unwrap();
+
+        assert_eq!(reply, b"$3\r\nlib\r\n+OK\r\n");
+        assert_eq!(
+            shared.store.get(b"last"),
+            Some(Value::String(Bytes::from_static(b"hi")))
+        );
+    }
+
+    #[tokio::test]
+    async fn append_write_commands_to_the_aof_correctly() {
+        let dir = test_dir("propagate");
+        let config = crate::config::Config {
+            port: 0,
+            dir: dir.path().to_path_buf(),
+            appendonly: true,
+            ..Default::default()
+        };
+        let server = RedisServer::builder()
+            .config(config)
+            .module(Remember)
+            .build()
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(server.run());
+
+        let mut conn = TcpStream::connect(addr).await.unwrap();
+        conn.write_all(b"PING\r\nREMEMBER hi\r\n").await.unwrap();
+        let mut reply = vec![0; 12];
+        conn.read_exact(&mut reply).await.unwrap();
+
+        assert_eq!(reply, b"+PONG\r\n+OK\r\n");
+        assert_eq!(
+            std::fs::read(dir.join("appendonlydir/appendonly.aof.1.incr.aof")).unwrap(),
+            b"*2\r\n$8\r\nREMEMBER\r\n$2\r\nhi\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn wrap_transaction_writes_in_multi_correctly() {
+        let dir = test_dir("multi");
+        let config = crate::config::Config {
+            port: 0,
+            dir: dir.path().to_path_buf(),
+            appendonly: true,
+            ..Default::default()
+        };
+        let server = RedisServer::builder()
+            .config(config)
+            .module(Remember)
+            .build()
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(server.run());
+
+        let mut conn = TcpStream::connect(addr).await.unwrap();
+        conn.write_all(b"MULTI\r\nREMEMBER a\r\nPING\r\nREMEMBER b\r\nEXEC\r\n")
+            .await
+            .unwrap();
+        let expected = b"+OK\r\n+QUEUED\r\n+QUEUED\r\n+QUEUED\r\n*3\r\n+OK\r\n+PONG\r\n+OK\r\n";
+        let mut reply = vec![0; expected.len()];
+        conn.read_exact(&mut reply).await.unwrap();
+
+        assert_eq!(reply, expected);
+        assert_eq!(
+            std::fs::read(dir.join("appendonlydir/appendonly.aof.1.incr.aof")).unwrap(),
+            [
+                &b"*1\r\n$5\r\nMULTI\r\n"[..],
+                b"*2\r\n$8\r\nREMEMBER\r\n$1\r\na\r\n",
+                b"*2\r\n$8\r\nREMEMBER\r\n$1\r\nb\r\n",
+                b"*1\r\n$4\r\nEXEC\r\n",
+            ]
+            .concat()
+        );
+    }
+
+    #[tokio::test]
+    async fn load_rdb_at_startup_correctly() {
+        let dir = test_dir("load");
+        let entries = [(
+            Bytes::from_static(b"k"),
+            Value::String(Bytes::from_static(b"v")),
+        )];
+        crate::rdb::save(&dir.join("dump.rdb"), &entries, Default::default()).unwrap();
+
+        let config = crate::config::Config {
+            port: 0,
+            dir: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let server = RedisServer::builder().config(config).build().await.unwrap();
+
+        assert_eq!(server.shared().store.get(b"k"), Some(entries[0].1.clone()));
+    }
+
+    #[tokio::test]
+    async fn load_aof_at_startup_correctly() {
+        let dir = test_dir("load-aof");
+        let aof = dir.join("appendonlydir");
+        std::fs::create_dir_all(&aof).unwrap();
+        let command = b"*2\r\n$8\r\nREMEMBER\r\n$2\r\nhi\r\n";
+        std::fs::write(aof.join("appendonly.aof.1.incr.aof"), command).unwrap();
+        std::fs::write(
+            aof.join("appendonly.aof.manifest"),
+            "file appendonly.aof.1.incr.aof seq 1 type i\n",
+        )
+        .unwrap();
+
+        let config = crate::config::Config {
+            port: 0,
+            dir: dir.path().to_path_buf(),
+            appendonly: true,
+            ..Default::default()
+        };
+        let server = RedisServer::builder()
+            .config(config)
+            .module(Remember)
+            .build()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            server.shared().store.get(b"last"),
+            Some(Value::String(Bytes::from_static(b"hi")))
+        );
+        assert_eq!(
+            std::fs::read(aof.join("appendonly.aof.1.incr.aof")).unwrap(),
+            command
+        );
+    }
+
+    #[tokio::test]
+    async fn load_aof_in_cluster_mode_correctly() {
+        let dir = test_dir("cluster-aof");
+        let aof = dir.join("appendonlydir");
+        std::fs::create_dir_all(&aof).unwrap();
+        let value = Value::String(Bytes::from_static(b"v"));
+        let mut command = Vec::new();
+        RESPValues::Array(vec![
+            RESPValues::BulkString(Bytes::from_static(b"RESTORE")),
+            RESPValues::BulkString(Bytes::from_static(b"k")),
+            RESPValues::BulkString(Bytes::from_static(b"0")),
+            RESPValues::BulkString(crate::rdb::dump(&value, Default::default()).into()),
+        ])
+        .encode(&mut command);
+        std::fs::write(aof.join("appendonly.aof.1.incr.aof"), command).unwrap();
+        std::fs::write(
+            aof.join("appendonly.aof.manifest"),
+            "file appendonly.aof.1.incr.aof seq 1 type i\n",
+        )
+        .unwrap();
+
+        // no slots are assigned, the keys load all the same
+        let config = crate::config::Config {
+            port: 0,
+            dir: dir.path().to_path_buf(),
+            appendonly: true,
+            cluster_enabled: true,
+            ..Default::default()
+        };
+        let server = RedisServer::builder().config(config).build().await.unwrap();
+        assert_eq!(server.shared().store.get(b"k"), Some(value));
+    }
+
+    #[tokio::test]
+    async fn load_a_rewritten_aof_without_a_preamble_correctly() {
+        let dir = test_dir("rewrite");
+        let config = crate::config::Config {
+            port: 0,
+            dir: dir.path().to_path_buf(),
+            appendonly: true,
+            aof_use_rdb_preamble: false,
+            ..Default::default()
+        };
+        let server = RedisServer::builder()
+            .config(config.clone())
+            .build()
+            .await
+            .unwrap();
+        let value = Value::String(Bytes::from_static(b"v"));
+        server
+            .shared()
+            .store
+            .set(Bytes::from_static(b"k"), value.clone());
+        assert!(server.shared().bgrewriteaof().unwrap());
+        while server.shared().aof.rewriting() {
+            std::thread::yield_now();
+        }
+
+        let restarted = RedisServer::builder().config(config).build().await.unwrap();
+        assert_eq!(restarted.shared().store.get(b"k"), Some(value));
+    }
+
+    #[tokio::test]
+    async fn replicate_writes_to_a_replica_correctly() {
+        let config = crate::config::Config {
+            port: 0,
+            repl_diskless_sync_delay: 0,
+            ..Default::default()
+        };
+        let master = RedisServer::builder()
+            .config(config)
+            .module(Remember)
+            .build()
+            .await
+            .unwrap();
+        let master_addr = master.local_addr().unwrap();
+        let replication = &master.shared().clone().replication;
+        let value = |v: &'static [u8]| Some(Value::String(Bytes::from_static(v)));
+        master
+            .shared()
+            .store
+            .set(Bytes::from_static(b"k"), value(b"v").unwrap());
+        tokio::spawn(master.run());
+
+        let config = crate::config::Config {
+            port: 0,
+            replicaof: Some((master_addr.ip().to_string(), master_addr.port())),
+            ..Default::default()
+        };
+        let replica = RedisServer::builder()
+            .config(config)
+            .module(Remember)
+            .build()
+            .await
+            .unwrap();
+        let replica_addr = replica.local_addr().unwrap();
+        let shared = replica.shared().clone();
+        tokio::spawn(replica.run());
+        let converge = |key: &'static [u8], expected| {
+            let shared = shared.clone();
+            async move {
+                for _ in 0..500 {
+                    if shared.store.get(key) == expected {
+                        return true;
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+                false
+            }
+        };
+        assert!(converge(b"k", value(b"v")).await);
+
+        let mut conn = TcpStream::connect(master_addr).await.unwrap();
+        conn.write_all(b"REMEMBER hi\r\n").await.unwrap();
+        let mut reply = vec![0; 5];
+        conn.read_exact(&mut reply).await.unwrap();
+        assert_eq!(reply, b"+OK\r\n");
+        assert!(converge(b"last", value(b"hi")).await);
+
+        // the replica acknowledges the write within a second
+        let mut acked = false;
+        for _ in 0..300 {
+            let replicas = replication.replicas();
+            if replicas.len() == 1 && replicas[0].offset == replication.offset() {
+                acked = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(acked);
+
+        let mut conn = TcpStream::connect(replica_addr).await.unwrap();
+        conn.write_all(b"REMEMBER hi\r\n").await.unwrap();
+        let expected = b"-READONLY You can't write against a read only replica.\r\n";
+        let mut reply = vec![0; expected.len()];
+        conn.read_exact(&mut reply).await.unwrap();
+        assert_eq!(reply, expected);
+    }
+
+    #[tokio::test]
+    async fn refuse_writes_without_enough_replicas_fails() {
+        let config = crate::config::Config {
+            port: 0,
+            min_replicas_to_write: 1,
+            ..Default::default()
+        };
+        let server = RedisServer::builder()
+            .config(config)
+            .module(Remember)
+            .build()
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        let shared = server.shared().clone();
+        tokio::spawn(server.run());
+
+        let mut conn = TcpStream::connect(addr).await.unwrap();
+        conn.write_all(b"REMEMBER hi\r\nMULTI\r\nPING\r\n")
+            .await
+            .unwrap();
+        let expected = b"-NOREPLICAS Not enough good replicas to write.\r\n+OK\r\n+QUEUED\r\n";
+        let mut reply = vec![0; expected.len()];
+        conn.read_exact(&mut reply).await.unwrap();
+        assert_eq!(reply, expected);
+        assert_eq!(shared.store.get(b"last"), None);
+
+        // a transaction without writes runs all the same
+        conn.write_all(b"EXEC\r\n").await.unwrap();
+        let mut reply = vec![0; 11];
+        conn.read_exact(&mut reply).await.unwrap();
+        assert_eq!(reply, b"*1\r\n+PONG\r\n");
+    }
+
+    #[tokio::test]
+    async fn redirect_to_the_node_serving_a_slot_correctly() {
+        let dir = test_dir("moved");
+        let mut nodes = Vec::new();
+        for name in ["a", "b"] {
+            std::fs::create_dir_all(dir.join(name)).unwrap();
+            let config = crate::config::Config {
+                port: 0,
+                dir: dir.join(name),
+                cluster_enabled: true,
+                ..Default::default()
+            };
+            let server = RedisServer::builder().config(config).build().await.unwrap();
+            nodes.push((server.local_addr().unwrap(), server.shared().clone()));
+            tokio::spawn(server.run());
+        }
+        let (a, b) = (&nodes[0], &nodes[1]);
+        a.1.cluster.add_slots(&[12182]).unwrap();
+        b.1.cluster.meet("127.0.0.1".to_string(), a.0.port());
+
+        // both learn of the other, and b of the slot a serves
+        let mut met = false;
+        for _ in 0..500 {
+            if a.1.cluster.nodes().len() == 2 && b.1.cluster.owner(12182).is_some() {
+                met = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(met);
+
+        let mut conn = TcpStream::connect(b.0).await.unwrap();
+        conn.write_all(b"WATCH foo\r\nWATCH bar\r\n").await.unwrap();
+        let expected = format!(
+            "-MOVED 12182 127.0.0.1:{}\r\n-CLUSTERDOWN Hash slot not served\r\n",
+            a.0.port()
+        );
+        let mut reply = vec![0; expected.len()];
+        conn.read_exact(&mut reply).await.unwrap();
+        assert_eq!(reply, expected.as_bytes());
+
+        let mut conn = TcpStream::connect(a.0).await.unwrap();
+        conn.write_all(b"WATCH foo {foo}bar\r\nWATCH foo bar\r\n")
+            .await
+            .unwrap();
+        let expected = b"+OK\r\n-CROSSSLOT Keys in request don't hash to the same slot\r\n";
+        let mut reply = vec![0; expected.len()];
+        conn.read_exact(&mut reply).await.unwrap();
+        assert_eq!(reply, expected);
+    }
+
+    #[tokio::test]
+    async fn migrate_keys_to_another_server_correctly() {
+        let dir = test_dir("migrate");
+        let config = crate::config::Config {
+            port: 0,
+            dir: dir.path().to_path_buf(),
+            appendonly: true,
+            ..Default::default()
+        };
+        let server = RedisServer::builder()
+            .config(config.clone())
+            .build()
+            .await
+            .unwrap();
+        let source = (server.local_addr().unwrap(), server.shared().clone());
+        tokio::spawn(server.run());
+        // MIGRATE blocks the thread it runs on until the target replies, so
+        // the target gets a runtime of its own
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async move {
+                let server = RedisServer::builder().port(0).build().await.unwrap();
+                let target = (server.local_addr().unwrap(), server.shared().clone());
+                sender.send(target).unwrap();
+                server.run().await
+            })
+        });
+        let target = receiver.recv().unwrap();
+        let (source, target) = (&source, &target);
+        let value = |v: &'static [u8]| Value::String(Bytes::from_static(v));
+        for key in [&b"a"[..], b"b"] {
+            source.1.store.set(Bytes::from(key), value(b"v"));
+        }
+        target.1.store.set(Bytes::from_static(b"b"), value(b"old"));
+
+        let port = target.0.port().to_string();
+        let migrate = |options: &[&str]| {
+            let args = ["MIGRATE", "127.0.0.1", &port, "", "0", "1000"]
+                .iter()
+                .chain(options)
+                .map(|arg| RESPValues::BulkString(Bytes::from(arg.to_string())))
+                .collect();
+            RESPValues::Array(args).to_bytes()
+        };
+        let mut conn = TcpStream::connect(source.0).await.unwrap();
+        conn.write_all(&migrate(&["KEYS", "a", "b"])).await.unwrap();
+        let expected =
+            b"-ERR Target instance replied with error: BUSYKEY Target key name already exists.\r\n";
+        let mut reply = vec![0; expected.len()];
+        conn.read_exact(&mut reply).await.unwrap();
+        assert_eq!(reply, expected);
+        // the key the target took is gone, the other one stays
+        assert_eq!(source.1.store.get(b"a"), None);
+        assert_eq!(target.1.store.get(b"a"), Some(value(b"v")));
+
+        conn.write_all(&migrate(&["REPLACE", "KEYS", "a", "b"]))
+            .await
+            .unwrap();
+        let mut reply = vec![0; 5];
+        conn.read_exact(&mut reply).await.unwrap();
+        assert_eq!(reply, b"+OK\r\n");
+        assert!(source.1.store.is_empty());
+        assert_eq!(target.1.store.get(b"b"), Some(value(b"v")));
+
+        // the deletes MIGRATE propagated replay on a restart
+        let restarted = RedisServer::builder().config(config).build().await.unwrap();
+        assert!(restarted.shared().store.is_empty());
+    }
+
+    #[tokio::test]
+    async fn deliver_published_messages_correctly() {
+        let server = RedisServer::builder().port(0).build().await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(server.run());
+
+        let mut subscriber = TcpStream::connect(addr).await.unwrap();
+        subscriber.write_all(b"SUBSCRIBE news\r\n").await.unwrap();
+        let expected = b"*3\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n:1\r\n";
+        let mut reply = vec![0; expected.len()];
+        subscriber.read_exact(&mut reply).await.unwrap();
+        assert_eq!(reply, expected);
+
+        let mut publisher = TcpStream::connect(addr).await.unwrap();
+        publisher.write_all(b"PUBLISH news hi\r\n").await.unwrap();
+        let mut reply = vec![0; 4];
+        publisher.read_exact(&mut reply).await.unwrap();
+        assert_eq!(reply, b":1\r\n");
+
+        let expected = b"*3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$2\r\nhi\r\n";
+        let mut message = vec![0; expected.len()];
+        subscriber.read_exact(&mut message).await.unwrap();
+        assert_eq!(message, expected);
+    }
+
+    #[tokio::test]
+    async fn quit_and_reset_while_subscribed_correctly() {
+        let server = RedisServer::builder().port(0).build().await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(server.run());
+
+        let mut conn = TcpStream::connect(addr).await.unwrap();
+        conn.write_all(b"SUBSCRIBE news\r\nRESET\r\nQUIT\r\nPING\r\n")
+            .await
+            .unwrap();
+        let mut replies = Vec::new();
+        conn.read_to_end(&mut replies).await.unwrap();
+
+        // the connection closes after QUIT's reply, PING never runs
+        let expected = b"*3\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n:1\r\n+RESET\r\n+OK\r\n";
+        assert_eq!(replies, expected);
+    }
+
+    #[tokio::test]
+    async fn return_connection_buffers_to_the_pool_correctly() {
+        let server = RedisServer::builder().port(0).build().await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let shared = server.shared().clone();
+        tokio::spawn(server.run());
+
+        for _ in 0..3 {
+            let mut conn = TcpStream::connect(addr).await.unwrap();
+            conn.write_all(b"PING\r\n").await.unwrap();
+            let mut reply = vec![0; 7];
+            conn.read_exact(&mut reply).await.unwrap();
+            drop(conn);
+
+            for _ in 0..100 {
+                if shared.clients.is_empty() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+
+        // the read and reply buffers of one connection, reused by the next
+        assert_eq!(shared.buffers.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn shutdown_stops_accepting_connections() {
+        let server = RedisServer::builder().port(0).build().await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let shutdown = server.shutdown_handle();
+        let running = tokio::spawn(server.run());
+
+        shutdown.shutdown();
+        let result = tokio::time::timeout(Duration::from_secs(5), running).await;
+
+        assert!(result.is_ok_and(|r| r.is_ok_and(|r| r.is_ok())));
+        assert!(TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn shutdown_closes_open_connections_correctly() {
+        let server = RedisServer::builder().port(0).build().await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let shutdown = server.shutdown_handle();
+        let running = tokio::spawn(server.run());
+
+        let mut idle = TcpStream::connect(addr).await.unwrap();
+        let mut sending = TcpStream::connect(addr).await.unwrap();
+        sending
+            .write_all(b"*1\r\n$4\r\nPING\r\n*1\r\n$4\r\nPI")
+            .await
+            .unwrap();
+        let mut reply = vec![0; 7];
+        sending.read_exact(&mut reply).await.unwrap();
+
+        shutdown.shutdown();
+        let result = tokio::time::timeout(Duration::from_secs(5), running).await;
+        let mut idle_reply = Vec::new();
+        let mut sending_reply = Vec::new();
+        idle.read_to_end(&mut idle_reply).await.unwrap();
+        sending.read_to_end(&mut sending_reply).await.unwrap();
+
+        assert!(result.is_ok_and(|r| r.is_ok_and(|r| r.is_ok())));
+        assert_eq!(reply, b"+PONG\r\n");
+        assert!(idle_reply.is_empty());
+        assert_eq!(sending_reply, b"-ERR Server is shutting down\r\n");
+    }
+
+    #[tokio::test]
+    async fn shutdown_saves_before_exiting_correctly() {
+        let dir = test_dir("shutdown-save");
+        let config = crate::config::Config {
+            port: 0,
+            dir: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let server = RedisServer::builder()
+            .config(config)
+            .module(Remember)
+            .build()
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        let shutdown = server.shutdown_handle();
+        let running = tokio::spawn(server.run());
+
+        let mut conn = TcpStream::connect(addr).await.unwrap();
+        conn.write_all(b"REMEMBER hi\r\n").await.unwrap();
+        let mut reply = vec![0; 5];
+        conn.read_exact(&mut reply).await.unwrap();
+        shutdown.shutdown();
+        let result = tokio::time::timeout(Duration::from_secs(5), running).await;
+
+        assert!(result.is_ok_and(|r| r.is_ok_and(|r| r.is_ok())));
+        let (entries, _) = rdb::load(&dir.join("dump.rdb"), Default::default()).unwrap();
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn shutdown_command_stops_the_server_correctly() {
+        let server = RedisServer::builder().port(0).build().await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let running = tokio::spawn(server.run());
+
+        let mut conn = TcpStream::connect(addr).await.unwrap();
+        conn.write_all(b"SHUTDOWN NOSAVE\r\n").await.unwrap();
+        let mut reply = Vec::new();
+        conn.read_to_end(&mut reply).await.unwrap();
+        let result = tokio::time::timeout(Duration::from_secs(5), running).await;
+
+        assert!(reply.is_empty());
+        assert!(result.is_ok_and(|r| r.is_ok_and(|r| r.is_ok())));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn serve_unix_socket_correctly() {
+        use std::os::unix::fs::PermissionsExt;
+
+        use tokio::net::UnixStream;
+
+        let dir = test_dir("unixsocket");
+        let path = dir.join("redis.sock");
+        let config = crate::config::Config {
+            port: 0,
+            unixsocket: Some(path.clone()),
+            unixsocketperm: 0o700,
+            ..Default::default()
+        };
+        let server = RedisServer::builder().config(config).build().await.unwrap();
+        let shutdown = server.shutdown_handle();
+        let running = tokio::spawn(server.run());
+
+        let mut conn = UnixStream::connect(&path).await.unwrap();
+        conn.write_all(b"PING\r\n").await.unwrap();
+        let mut reply = vec![0; 7];
+        conn.read_exact(&mut reply).await.unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+
+        shutdown.shutdown();
+        let result = tokio::time::timeout(Duration::from_secs(5), running).await;
+
+        assert_eq!(reply, b"+PONG\r\n");
+        assert_eq!(mode & 0o777, 0o700);
+        assert!(result.is_ok_and(|r| r.is_ok_and(|r| r.is_ok())));
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn refuse_clients_past_maxclients_correctly() {
+        let config = crate::config::Config {
+            port: 0,
+            maxclients: 1,
+            ..Default::default()
+        };
+        let server = RedisServer::builder().config(config).build().await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(server.run());
+
+        let mut first = TcpStream::connect(addr).await.unwrap();
+        first.write_all(b"PING\r\n").await.unwrap();
+        let mut first_reply = vec![0; 7];
+        first.read_exact(&mut first_reply).await.unwrap();
+        let mut second = TcpStream::connect(addr).await.unwrap();
+        let mut second_reply = Vec::new();
+        second.read_to_end(&mut second_reply).await.unwrap();
+
+        assert_eq!(first_reply, b"+PONG\r\n");
+        assert_eq!(second_reply, b"-ERR max number of clients reached\r\n");
+    }
+
+    #[tokio::test]
+    async fn configure_client_sockets_correctly() {
+        let config = crate::config::Config {
+            port: 0,
+            tcp_keepalive: 60,
+            tcp_nodelay: true,
+            ..Default::default()
+        };
+        let server = RedisServer::builder().config(config).build().await.unwrap();
+        let addr = server.local_addr().unwrap();
+
+        let client = TcpStream::connect(addr).await.unwrap();
+        let (accepted, _) = server.listener.accept().await.unwrap();
+        super::configure(socket2::SockRef::from(&accepted), server.shared());
+        let socket = socket2::SockRef::from(&accepted);
+
+        assert!(accepted.nodelay().unwrap());
+        assert!(socket.keepalive().unwrap());
+        #[cfg(target_os = "linux")]
+        assert_eq!(
+            socket.tcp_keepalive_time().unwrap(),
+            Duration::from_secs(60)
+        );
+        drop(client);
+    }
+
+    #[test]
+    fn output_limit_tracker_correctly() {
+        let mut tracker = OutputLimitTracker::default();
+        let limit = OutputBufferLimit {
+            hard: 100,
+            soft: 10,
+            soft_seconds: 5,
+        };
+        let start = std::time::Instant::now();
+
+        assert!(!tracker.exceeded(50, limit, start));
+        assert!(!tracker.exceeded(50, limit, start + Duration::from_secs(4)));
+        assert!(tracker.exceeded(50, limit, start + Duration::from_secs(5)));
+        assert!(!tracker.exceeded(5, limit, start + Duration::from_secs(6)));
+        assert!(!tracker.exceeded(50, limit, start + Duration::from_secs(7)));
+        assert!(tracker.exceeded(100, limit, start + Duration::from_secs(7)));
+    }
+
+    #[tokio::test]
+    async fn disconnect_clients_over_the_hard_limit_correctly() {
+        let mut config = crate::config::Config {
+            port: 0,
+            ..Default::default()
+        };
+        config.client_output_buffer_limit.normal.hard = 1024;
+        let server = RedisServer::builder().config(config).build().await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(server.run());
+
+        let mut conn = TcpStream::connect(addr).await.unwrap();
+        conn.write_all(b"PING\r\n").await.unwrap();
+        let mut reply = vec![0; 7];
+        conn.read_exact(&mut reply).await.unwrap();
+        let big = format!("ECHO {}\r\n", "a".repeat(2048));
+        conn.write_all(big.as_bytes()).await.unwrap();
+        let mut rest = Vec::new();
+        let result = conn.read_to_end(&mut rest).await;
+
+        assert_eq!(reply, b"+PONG\r\n");
+        assert!(result.is_ok_and(|n| n == 0));
+    }
+
+    #[tokio::test]
+    async fn serve_connections_on_io_threads_correctly() {
+        let config = crate::config::Config {
+            port: 0,
+            io_threads: 2,
+            ..Default::default()
+        };
+        let server = RedisServer::builder().config(config).build().await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let shutdown = server.shutdown_handle();
+        let running = tokio::spawn(server.run());
+
+        let mut replies = Vec::new();
+        for _ in 0..3 {
+            let mut conn = TcpStream::connect(addr).await.unwrap();
+            conn.write_all(b"PING\r\n").await.unwrap();
+            let mut reply = vec![0; 7];
+            conn.read_exact(&mut reply).await.unwrap();
+            replies.push((conn, reply));
+        }
+        shutdown.shutdown();
+        let result = tokio::time::timeout(Duration::from_secs(5), running).await;
+
+        assert!(replies.iter().all(|(_, reply)| reply == b"+PONG\r\n"));
+        assert!(result.is_ok_and(|r| r.is_ok_and(|r| r.is_ok())));
+    }
+
+    #[tokio::test]
+    async fn run_commands_on_the_io_thread_owning_their_keys_correctly() {
+        // no save points, so shutting down leaves no dump behind
+        let config = crate::config::Config {
+            port: 0,
+            io_threads: 2,
+            save: vec![],
+            ..Default::default()
+        };
+        let server = RedisServer::builder().config(config).build().await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let shared = server.shared().clone();
+        let shutdown = server.shutdown_handle();
+        let running = tokio::spawn(server.run());
+        let keys: Vec<_> = (0..32).map(|i| Bytes::from(format!("k{i}"))).collect();
+        for key in &keys {
+            shared
+                .store
+                .set(key.clone(), Value::String(Bytes::from_static(b"v")));
+        }
+        // whichever worker takes the connection, some keys belong to the other
+        let owners: std::collections::HashSet<_> =
+            keys.iter().map(|key| shared.store.shard(key) % 2).collect();
+        assert_eq!(owners.len(), 2);
+
+        // one DEL per key, then one spanning both workers
+        let mut pipeline: Vec<u8> = keys[..16]
+            .iter()
+            .flat_map(|key| [b"DEL ", &key[..], b"\r\n"].concat())
+            .collect();
+        pipeline.extend_from_slice(b"DEL");
+        for key in &keys[16..] {
+            pipeline.extend_from_slice(&[b" ", &key[..]].concat());
+        }
+        pipeline.extend_from_slice(b"\r\n");
+        let mut conn = TcpStream::connect(addr).await.unwrap();
+        conn.write_all(&pipeline).await.unwrap();
+        let expected = [&b":1\r\n".repeat(16)[..], b":16\r\n"].concat();
+        let mut reply = vec![0; expected.len()];
+        conn.read_exact(&mut reply).await.unwrap();
+        shutdown.shutdown();
+        let result = tokio::time::timeout(Duration::from_secs(5), running).await;
+
+        assert_eq!(reply, expected);
+        assert!(shared.store.is_empty());
+        assert!(result.is_ok_and(|r| r.is_ok_and(|r| r.is_ok())));
+    }
+
+    #[cfg(all(feature = "uring", target_os = "linux"))]
+    #[tokio::test]
+    async fn serve_on_io_uring_correctly() {
+        let server = RedisServer::builder().port(0).build().await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let shutdown = server.shutdown_handle();
+        let running = tokio::spawn(server.run_uring());
+
+        let mut conn = TcpStream::connect(addr).await.unwrap();
+        conn.write_all(b"*1\r\n$4\r\nPING\r\nECHO hi\r\n")
+            .await
+            .unwrap();
+        let mut reply = vec![0; 15];
+        conn.read_exact(&mut reply).await.unwrap();
+        assert_eq!(reply, b"+PONG\r\n$2\r\nhi\r\n");
+
+        // messages reach a subscriber while its read is in flight
+        conn.write_all(b"SUBSCRIBE news\r\n").await.unwrap();
+        let mut reply = vec![0; 33];
+        conn.read_exact(&mut reply).await.unwrap();
+        let mut publisher = TcpStream::connect(addr).await.unwrap();
+        publisher.write_all(b"PUBLISH news hi\r\n").await.unwrap();
+        let mut message = vec![0; 35];
+        conn.read_exact(&mut message).await.unwrap();
+        assert_eq!(
+            message,
+            b"*3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$2\r\nhi\r\n"
+        );
+
+        shutdown.shutdown();
+        let result = tokio::time::timeout(Duration::from_secs(5), running).await;
+        assert!(result.is_ok_and(|r| r.is_ok_and(|r| r.is_ok())));
+    }
+
+    #[cfg(all(feature = "uring", target_os = "linux"))]
+    #[tokio::test]
+    async fn io_uring_with_io_threads_fails() {
+        let config = crate::config::Config {
+            port: 0,
+            io_threads: 2,
+            ..Default::default()
+        };
+        let server = RedisServer::builder().config(config).build().await.unwrap();
+
+        assert!(!server.uring_supported());
+        let result = server.run_uring().await;
+        assert!(result.is_err_and(|e| e.kind() == std::io::ErrorKind::Unsupported));
+    }
+}
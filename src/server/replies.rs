@@ -0,0 +1,181 @@
+use std::{collections::VecDeque, io::IoSlice};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::resp::{put_header, RESPValues};
+
+// Bulk strings this long are queued as they are rather than copied
+const LARGE_BULK_STRING: usize = 16 * 1024;
+
+// Replies waiting to be written. Small replies are coalesced into one buffer
+// while large bulk strings stay where they are, and the lot goes out in a
+// single vectored write
+pub(super) struct Replies {
+    // coalesced replies and large bulk strings, in the order they are sent
+    chunks: VecDeque<Bytes>,
+    chunks_len: usize,
+    // replies encoded after the last chunk
+    tail: BytesMut,
+}
+
+impl Replies {
+    pub(super) fn new(buffer: BytesMut) -> Self {
+        Self {
+            chunks: VecDeque::new(),
+            chunks_len: 0,
+            tail: buffer,
+        }
+    }
+
+    pub(super) fn push(&mut self, value: &RESPValues) {
+        match value {
+            RESPValues::BulkString(v) if v.len() >= LARGE_BULK_STRING => {
+                put_header(&mut self.tail, b'$', v.len() as i64);
+                self.freeze_tail();
+                self.push_chunk(v.clone());
+                self.tail.put_slice(b"\r\n");
+            }
+            RESPValues::Array(v) => self.push_aggregate(b'*', v),
+            RESPValues::Set(v) => self.push_aggregate(b'~', v),
+            RESPValues::Push(v) => self.push_aggregate(b'>', v),
+            RESPValues::Map(v) => {
+                put_header(&mut self.tail, b'%', v.len() as i64);
+                for (key, value) in v {
+                    self.push(key);
+                    self.push(value);
+                }
+            }
+            v => v.encode(&mut self.tail),
+        }
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.chunks_len + self.tail.len()
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Every queued reply, for writers that take ownership of what they send
+    #[cfg(all(feature = "uring", target_os = "linux"))]
+    pub(super) fn take_chunks(&mut self) -> Vec<Bytes> {
+        self.freeze_tail();
+        self.chunks_len = 0;
+        self.chunks.drain(..).collect()
+    }
+
+    // The buffer small replies were encoded into, to be pooled again
+    pub(super) fn into_buffer(self) -> BytesMut {
+        self.tail
+    }
+
+    fn push_aggregate(&mut self, kind: u8, values: &[RESPValues]) {
+        put_header(&mut self.tail, kind, values.len() as i64);
+        for element in values {
+            self.push(element);
+        }
+    }
+
+    fn push_chunk(&mut self, chunk: Bytes) {
+        self.chunks_len += chunk.len();
+        self.chunks.push_back(chunk);
+    }
+
+    fn freeze_tail(&mut self) {
+        if !self.tail.is_empty() {
+            let chunk = self.tail.split().freeze();
+            self.push_chunk(chunk);
+        }
+    }
+}
+
+impl Buf for Replies {
+    fn remaining(&self) -> usize {
+        self.len()
+    }
+
+    fn chunk(&self) -> &[u8] {
+        self.chunks
+            .front()
+            .map_or(&self.tail[..], |chunk| &chunk[..])
+    }
+
+    fn chunks_vectored<'a>(&'a self, dst: &mut [IoSlice<'a>]) -> usize {
+        let chunks = self.chunks.iter().map(|chunk| &chunk[..]);
+        let tail = Some(&self.tail[..]).filter(|tail| !tail.is_empty());
+
+        let mut filled = 0;
+        for (slot, chunk) in dst.iter_mut().zip(chunks.chain(tail)) {
+            *slot = IoSlice::new(chunk);
+            filled += 1;
+        }
+        filled
+    }
+
+    fn advance(&mut self, mut cnt: usize) {
+        while let Some(front) = self.chunks.front_mut() {
+            if cnt < front.len() {
+                front.advance(cnt);
+                self.chunks_len -= cnt;
+                return;
+            }
+            cnt -= front.len();
+            self.chunks_len -= front.len();
+            self.chunks.pop_front();
+        }
+        self.tail.advance(cnt);
+    }
+}
+
+#[cfg(test)]
+mod replies_tests {
+    use std::io::IoSlice;
+
+    use bytes::{Buf, Bytes, BytesMut};
+
+    use super::{Replies, LARGE_BULK_STRING};
+    use crate::resp::RESPValues;
+
+    #[test]
+    fn coalesce_small_replies_correctly() {
+        let mut replies = Replies::new(BytesMut::new());
+        replies.push(&RESPValues::SimpleString("OK".to_string()));
+        replies.push(&RESPValues::BulkString(Bytes::from_static(b"hi")));
+
+        let mut slices = [IoSlice::new(&[]); 4];
+        assert_eq!(replies.chunks_vectored(&mut slices), 1);
+        assert_eq!(&*slices[0], b"+OK\r\n$2\r\nhi\r\n");
+    }
+
+    #[test]
+    fn queue_large_bulk_strings_without_copying_correctly() {
+        let large = Bytes::from(vec![b'a'; LARGE_BULK_STRING]);
+        let value = RESPValues::Array(vec![
+            RESPValues::Integer(1),
+            RESPValues::BulkString(large.clone()),
+        ]);
+        let mut replies = Replies::new(BytesMut::new());
+        replies.push(&value);
+
+        let mut slices = [IoSlice::new(&[]); 4];
+        assert_eq!(replies.chunks_vectored(&mut slices), 3);
+        assert_eq!(slices[1].as_ptr(), large.as_ptr());
+        assert_eq!(replies.len(), value.to_bytes().len());
+        assert_eq!(replies.copy_to_bytes(replies.len()), value.to_bytes());
+    }
+
+    #[test]
+    fn advance_across_chunks_correctly() {
+        let large = Bytes::from(vec![b'a'; LARGE_BULK_STRING]);
+        let mut replies = Replies::new(BytesMut::new());
+        replies.push(&RESPValues::BulkString(large));
+        replies.push(&RESPValues::Integer(7));
+
+        replies.advance(LARGE_BULK_STRING + 8);
+        assert_eq!(replies.chunk(), b"\r\n:7\r\n");
+        replies.advance(2);
+        assert_eq!(replies.chunk(), b":7\r\n");
+        assert_eq!(replies.len(), 4);
+    }
+}
@@ -0,0 +1,177 @@
+// The io_uring backend behind the `uring` feature. Only the socket layer
+// differs, commands run through the same execute_buffered as on tokio
+use std::{io, net, pin::pin, sync::Arc};
+
+use bytes::{Buf, Bytes, BytesMut};
+use socket2::SockRef;
+use tokio::{
+    sync::{mpsc, watch},
+    task::JoinSet,
+};
+use tokio_uring::net::{TcpListener, TcpStream};
+
+use super::{
+    configure, drain, execute_buffered, over_maxclients, replies::Replies, ClientAddr, Executed,
+    Shared, READ_BUFFER_SIZE,
+};
+use crate::{
+    commands::ConnectionState,
+    config::LogLevel,
+    resp::{RESPDecoder, RESPValues},
+};
+
+pub(super) fn run(listener: net::TcpListener, shared: Arc<Shared>) -> io::Result<()> {
+    // Linux hands the listener's socket options down to every accepted socket
+    configure(SockRef::from(&listener), &shared);
+
+    tokio_uring::start(async move {
+        let listener = TcpListener::from_std(listener);
+        let mut shutdown = shared.shutdown.0.subscribe();
+        let mut connections = JoinSet::new();
+
+        loop {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                Some(_) = connections.join_next(), if !connections.is_empty() => continue,
+                _ = shutdown.wait_for(|stop| *stop) => break,
+            };
+
+            match accepted {
+                Err(error) => {
+                    if shared.config.read().unwrap().loglevel <= LogLevel::Warning {
+                        eprintln!("Error at accepting connection: {error}");
+                    }
+                }
+                Ok((stream, addr)) => {
+                    let shared = shared.clone();
+                    let shutdown = shared.shutdown.0.subscribe();
+                    connections.spawn_local(async move {
+                        let id = shared.clients.connect(ClientAddr::Tcp(addr));
+                        let result = handle_connection(stream, id, &shared, shutdown).await;
+                        shared.clients.disconnect(id);
+                        result
+                    });
+                }
+            }
+        }
+
+        drop(listener);
+        drain(connections, &shared).await;
+        shared.finish_shutdown().await;
+        Ok(())
+    })
+}
+
+// Replies are written before reading again, as io_uring owns the buffer
+// while a read is in flight, so they never queue past a single batch
+async fn handle_connection(
+    stream: TcpStream,
+    id: u64,
+    shared: &Shared,
+    shutdown: watch::Receiver<bool>,
+) -> io::Result<()> {
+    if over_maxclients(shared) {
+        let (result, _) = stream
+            .write_all(&b"-ERR max number of clients reached\r\n"[..])
+            .await;
+        return result;
+    }
+
+    let limits = shared.config.read().unwrap().limits;
+    let mut decoder = RESPDecoder::with_buffer(limits, shared.buffers.take());
+    let (subscriber, mut messages) = mpsc::unbounded_channel();
+    let mut state = ConnectionState::new(id, subscriber);
+
+    let result = command_loop(
+        &stream,
+        shared,
+        shutdown,
+        &mut state,
+        &mut messages,
+        &mut decoder,
+    )
+    .await;
+    shared.pubsub.disconnect(&state);
+    shared.replication.detach(state.id);
+    shared.buffers.put(decoder.into_buffer());
+    result
+}
+
+async fn command_loop(
+    stream: &TcpStream,
+    shared: &Shared,
+    mut shutdown: watch::Receiver<bool>,
+    state: &mut ConnectionState,
+    messages: &mut mpsc::UnboundedReceiver<RESPValues>,
+    decoder: &mut RESPDecoder,
+) -> io::Result<()> {
+    // `out` is handed to the kernel on every write, so only the read side is pooled
+    let mut out = Replies::new(BytesMut::new());
+    // published messages are written while the read stays in flight, dropping
+    // it would also drop the buffer the kernel is reading into
+    let mut read = pin!(stream.read(vec![0; READ_BUFFER_SIZE]));
+    let killed = shared.clients.killed(state.id);
+
+    loop {
+        let closing = matches!(
+            execute_buffered(decoder, state, shared, &mut out, None)?,
+            Executed::Closing
+        );
+        if !out.is_empty() {
+            write_all_vectored(stream, out.take_chunks()).await?;
+        }
+        if closing {
+            break;
+        }
+
+        let (result, returned) = tokio::select! {
+            read = &mut read => read,
+            Some(message) = messages.recv() => {
+                out.push(&message.to_protocol(state.protocol));
+                continue;
+            }
+            _ = killed.notified() => break,
+            _ = shutdown.wait_for(|stop| *stop) => {
+                // a client halfway through sending a command won't get its reply
+                if !decoder.buffer_mut().is_empty() {
+                    out.push(&RESPValues::SimpleError(
+                        "ERR Server is shutting down".to_string(),
+                    ));
+                    write_all_vectored(stream, out.take_chunks()).await?;
+                }
+                break;
+            }
+        };
+
+        match result? {
+            0 => break,
+            read => decoder.feed(&returned[..read]),
+        }
+        read.set(stream.read(returned));
+    }
+
+    Ok(())
+}
+
+// Writes every chunk with as few writev calls as the socket allows
+async fn write_all_vectored(stream: &TcpStream, mut chunks: Vec<Bytes>) -> io::Result<()> {
+    while !chunks.is_empty() {
+        let (result, returned) = stream.writev(chunks).await;
+        let mut written = result?;
+        if written == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+
+        chunks = returned;
+        let mut done = 0;
+        while done < chunks.len() && written >= chunks[done].len() {
+            written -= chunks[done].len();
+            done += 1;
+        }
+        chunks.drain(..done);
+        if let Some(partial) = chunks.first_mut() {
+            partial.advance(written);
+        }
+    }
+    Ok(())
+}
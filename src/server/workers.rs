@@ -0,0 +1,242 @@
+// The io-threads mode: every worker is a thread running its own single
+// threaded runtime, so a connection's reads and writes all stay on the
+// thread it was handed to instead of moving across tokio's pool. Each worker
+// also owns some of the store's shards, and a command on keys of another
+// worker's shards runs on that worker, so a shard's lock is only ever taken
+// by its owner on the hot path
+use std::{io, net, sync::Arc, thread};
+
+use bytes::Bytes;
+use tokio::{
+    net::TcpStream,
+    runtime,
+    sync::{mpsc, oneshot},
+    task::JoinSet,
+};
+use tokio_rustls::TlsAcceptor;
+#[cfg(unix)]
+use {std::os::unix::net as unix, tokio::net::UnixStream};
+
+use super::{drain, spawn_connection, ClientAddr, Incoming, Shared};
+use crate::{commands::ConnectionState, config::LogLevel, resp::RESPValues};
+
+// A connection taken off the reactor of the runtime that accepted it, so
+// the worker can register it with its own
+enum Detached {
+    Tcp(net::TcpStream),
+    Tls(net::TcpStream, TlsAcceptor),
+    #[cfg(unix)]
+    Unix(unix::UnixStream),
+}
+
+impl Incoming {
+    fn detach(self) -> io::Result<Detached> {
+        Ok(match self {
+            Incoming::Tcp(stream) => Detached::Tcp(stream.into_std()?),
+            Incoming::Tls(stream, acceptor) => Detached::Tls(stream.into_std()?, acceptor),
+            #[cfg(unix)]
+            Incoming::Unix(stream) => Detached::Unix(stream.into_std()?),
+        })
+    }
+}
+
+impl Detached {
+    // Must run inside the worker's runtime
+    fn attach(self) -> io::Result<Incoming> {
+        Ok(match self {
+            Detached::Tcp(stream) => Incoming::Tcp(TcpStream::from_std(stream)?),
+            Detached::Tls(stream, acceptor) => {
+                Incoming::Tls(TcpStream::from_std(stream)?, acceptor)
+            }
+            #[cfg(unix)]
+            Detached::Unix(stream) => Incoming::Unix(UnixStream::from_std(stream)?),
+        })
+    }
+}
+
+// A command sent to the worker owning its keys. It runs there on the state
+// of the connection that sent it, which waits for both to come back
+struct Job {
+    request: RESPValues,
+    state: ConnectionState,
+    done: oneshot::Sender<(RESPValues, ConnectionState)>,
+}
+
+impl Job {
+    fn run(self, shared: &Shared) {
+        let mut state = self.state;
+        let reply = shared.dispatch(self.request, &mut state);
+        let _ = self.done.send((reply, state));
+    }
+}
+
+// How the connections of a worker reach the others
+#[derive(Clone)]
+pub(super) struct Router {
+    index: usize,
+    workers: Arc<[mpsc::UnboundedSender<Job>]>,
+}
+
+impl Router {
+    // The worker owning every key `request` names, when it isn't this one.
+    // Shard n belongs to worker n % io-threads. Commands without keys, with
+    // keys spread over several workers or queued by MULTI run where they are
+    pub(super) fn owner(
+        &self,
+        shared: &Shared,
+        request: &RESPValues,
+        state: &ConnectionState,
+    ) -> Option<usize> {
+        if state.transaction.is_some() {
+            return None;
+        }
+        let RESPValues::Array(items) = request else {
+            return None;
+        };
+        let args = items
+            .iter()
+            .map(|item| match item {
+                RESPValues::BulkString(arg) => Some(arg.clone()),
+                _ => None,
+            })
+            .collect::<Option<Vec<Bytes>>>()?;
+        let spec = shared.commands.spec(args.first()?)?;
+        let mut owners =
+            (spec.keys(&args).into_iter()).map(|key| shared.store.shard(key) % self.workers.len());
+        let owner = owners.next()?;
+        (owner != self.index && owners.all(|other| other == owner)).then_some(owner)
+    }
+
+    // Runs `request` on the `owner` worker, handing it the connection's state
+    // for as long as it takes
+    pub(super) async fn run(
+        &self,
+        owner: usize,
+        request: RESPValues,
+        state: &mut ConnectionState,
+    ) -> io::Result<RESPValues> {
+        let stopped = || io::Error::other("io thread stopped");
+        let (done, finished) = oneshot::channel();
+        let placeholder = ConnectionState::new(state.id, state.messages.clone());
+        let job = Job {
+            request,
+            state: std::mem::replace(state, placeholder),
+            done,
+        };
+        if let Err(mpsc::error::SendError(job)) = self.workers[owner].send(job) {
+            *state = job.state;
+            return Err(stopped());
+        }
+        let (reply, returned) = finished.await.map_err(|_| stopped())?;
+        *state = returned;
+        Ok(reply)
+    }
+}
+
+struct Worker {
+    connections: mpsc::UnboundedSender<(Detached, ClientAddr)>,
+    // resolves once the worker drained its connections
+    done: oneshot::Receiver<()>,
+}
+
+pub(super) struct Workers {
+    workers: Vec<Worker>,
+    next: usize,
+}
+
+impl Workers {
+    pub(super) fn spawn(threads: usize, shared: &Arc<Shared>) -> io::Result<Self> {
+        let (senders, receivers): (Vec<_>, Vec<_>) =
+            (0..threads).map(|_| mpsc::unbounded_channel()).unzip();
+        let senders: Arc<[_]> = senders.into();
+        let workers = (receivers.into_iter().enumerate())
+            .map(|(index, jobs)| {
+                let router = Router {
+                    index,
+                    workers: senders.clone(),
+                };
+                spawn_worker(index, shared.clone(), router, jobs)
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Self { workers, next: 0 })
+    }
+
+    // Hands the connection to the workers in turn
+    pub(super) fn dispatch(&mut self, incoming: Incoming, addr: ClientAddr) -> io::Result<()> {
+        let worker = &self.workers[self.next];
+        self.next = (self.next + 1) % self.workers.len();
+
+        worker
+            .connections
+            .send((incoming.detach()?, addr))
+            .map_err(|_| io::Error::other("io thread stopped"))
+    }
+
+    // Stops handing out connections and waits for every worker to drain its own
+    pub(super) async fn join(self) {
+        let done: Vec<_> = self.workers.into_iter().map(|worker| worker.done).collect();
+        for done in done {
+            // an error means the worker panicked, its connections are gone either way
+            let _ = done.await;
+        }
+    }
+}
+
+fn spawn_worker(
+    index: usize,
+    shared: Arc<Shared>,
+    router: Router,
+    mut jobs: mpsc::UnboundedReceiver<Job>,
+) -> io::Result<Worker> {
+    let (sender, mut receiver) = mpsc::unbounded_channel::<(Detached, ClientAddr)>();
+    let (done_sender, done) = oneshot::channel();
+    let runtime = runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    thread::Builder::new()
+        .name(format!("io-thread-{index}"))
+        .spawn(move || {
+            runtime.block_on(async move {
+                let mut connections = JoinSet::new();
+                loop {
+                    tokio::select! {
+                        received = receiver.recv() => match received {
+                            Some((detached, addr)) => match detached.attach() {
+                                Ok(incoming) => spawn_connection(
+                                    &mut connections,
+                                    &shared,
+                                    incoming,
+                                    addr,
+                                    Some(router.clone()),
+                                ),
+                                Err(error) => {
+                                    if shared.config.read().unwrap().loglevel <= LogLevel::Warning {
+                                        eprintln!("Error at accepting connection: {error}");
+                                    }
+                                }
+                            },
+                            None => break,
+                        },
+                        Some(job) = jobs.recv() => job.run(&shared),
+                        Some(_) = connections.join_next(), if !connections.is_empty() => {}
+                    }
+                }
+                // the connections of the other workers may still send theirs
+                // over, until every one of them closed
+                drop(router);
+                let serve_jobs = async {
+                    while let Some(job) = jobs.recv().await {
+                        job.run(&shared);
+                    }
+                };
+                tokio::join!(drain(connections, &shared), serve_jobs);
+            });
+            let _ = done_sender.send(());
+        })?;
+
+    Ok(Worker {
+        connections: sender,
+        done,
+    })
+}
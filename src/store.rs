@@ -0,0 +1,329 @@
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, RandomState},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use bytes::Bytes;
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Value {
+    String(Bytes),
+}
+
+// independently locked parts of the keyspace, so connections touching
+// different keys rarely wait on each other. With io-threads each worker owns
+// some of them, see Router::owner
+const SHARDS: usize = 16;
+
+// a shard's keys are spread further over buckets, which is what a snapshot
+// shares with the store and a write after it copies, see Store::snapshot
+const BUCKETS: usize = 64;
+
+type Bucket = HashMap<Bytes, Entry>;
+
+#[derive(Clone)]
+struct Entry {
+    value: Value,
+    // when the key was last written, see Store::version
+    version: u64,
+}
+
+struct Shard {
+    buckets: Vec<Arc<Bucket>>,
+    // when a key in this shard was last removed
+    removed: u64,
+}
+
+impl Default for Shard {
+    fn default() -> Self {
+        Self {
+            buckets: (0..BUCKETS).map(|_| Arc::default()).collect(),
+            removed: 0,
+        }
+    }
+}
+
+// The keyspace every connection reads and writes
+pub struct Store {
+    shards: Vec<Mutex<Shard>>,
+    hasher: RandomState,
+    // bumped by every write, stamping the keys it touches
+    clock: AtomicU64,
+}
+
+impl Default for Store {
+    fn default() -> Self {
+        Self {
+            shards: (0..SHARDS).map(|_| Mutex::default()).collect(),
+            hasher: RandomState::new(),
+            clock: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Store {
+    pub fn get(&self, key: &[u8]) -> Option<Value> {
+        let (shard, bucket) = self.locate(key);
+        let shard = self.shards[shard].lock().unwrap();
+        shard.buckets[bucket]
+            .get(key)
+            .map(|entry| entry.value.clone())
+    }
+
+    pub fn contains(&self, key: &[u8]) -> bool {
+        let (shard, bucket) = self.locate(key);
+        let shard = self.shards[shard].lock().unwrap();
+        shard.buckets[bucket].contains_key(key)
+    }
+
+    // Returns the value `key` held before
+    pub fn set(&self, key: Bytes, value: Value) -> Option<Value> {
+        let (shard, bucket) = self.locate(&key);
+        let mut shard = self.shards[shard].lock().unwrap();
+        let entry = Entry {
+            value,
+            version: self.tick(),
+        };
+        Arc::make_mut(&mut shard.buckets[bucket])
+            .insert(key, entry)
+            .map(|entry| entry.value)
+    }
+
+    pub fn remove(&self, key: &[u8]) -> Option<Value> {
+        let (shard, bucket) = self.locate(key);
+        let mut shard = self.shards[shard].lock().unwrap();
+        // checked first, so a missing key doesn't copy a bucket a snapshot holds
+        shard.buckets[bucket].get(key)?;
+        let removed = Arc::make_mut(&mut shard.buckets[bucket]).remove(key)?;
+        shard.removed = self.tick();
+        Some(removed.value)
+    }
+
+    // Drops every key, as a replica does before loading its master's dump
+    pub fn clear(&self) {
+        let mut shards: Vec<_> = self.shards.iter().map(|s| s.lock().unwrap()).collect();
+        let removed = self.tick();
+        for shard in &mut shards {
+            // a snapshot may still hold the buckets, so they're replaced rather than emptied
+            shard.buckets = (0..BUCKETS).map(|_| Arc::default()).collect();
+            shard.removed = removed;
+        }
+    }
+
+    // Changes whenever `key` is written or removed, which is how WATCH tells
+    // a key was modified. A missing key goes by when its shard last lost a
+    // key, so removing a neighbour also counts as modifying it
+    pub fn version(&self, key: &[u8]) -> u64 {
+        let (shard, bucket) = self.locate(key);
+        let shard = self.shards[shard].lock().unwrap();
+        shard.buckets[bucket]
+            .get(key)
+            .map_or(shard.removed, |entry| entry.version)
+    }
+
+    // The keyspace as of one instant, for snapshots to write out. It takes
+    // a reference to every bucket rather than copying keys, and the first
+    // write to a bucket after that copies just that bucket, so the store
+    // carries on while the snapshot is read. All shards are held at once,
+    // which nothing else does, so this can't deadlock with the single shard
+    // locks of the other methods
+    pub fn snapshot(&self) -> Snapshot {
+        let shards: Vec<_> = self.shards.iter().map(|s| s.lock().unwrap()).collect();
+        Snapshot {
+            buckets: shards
+                .iter()
+                .flat_map(|shard| shard.buckets.iter().cloned())
+                .collect(),
+            changes: self.changes(),
+        }
+    }
+
+    // Every key with its value as of one instant
+    pub fn entries(&self) -> Vec<(Bytes, Value)> {
+        self.snapshot().entries()
+    }
+
+    // How many writes the store has seen, SAVE points count them
+    pub fn changes(&self) -> u64 {
+        self.clock.load(Ordering::Relaxed)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| {
+                let shard = shard.lock().unwrap();
+                shard
+                    .buckets
+                    .iter()
+                    .map(|bucket| bucket.len())
+                    .sum::<usize>()
+            })
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Which of the SHARDS holds `key`
+    pub fn shard(&self, key: &[u8]) -> usize {
+        self.locate(key).0
+    }
+
+    fn locate(&self, key: &[u8]) -> (usize, usize) {
+        let hash = self.hasher.hash_one(key) as usize;
+        (hash % SHARDS, hash / SHARDS % BUCKETS)
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed) + 1
+    }
+}
+
+// See Store::snapshot
+pub struct Snapshot {
+    buckets: Vec<Arc<Bucket>>,
+    changes: u64,
+}
+
+impl Snapshot {
+    pub fn iter(&self) -> impl Iterator<Item = (&Bytes, &Value)> {
+        (self.buckets.iter())
+            .flat_map(|bucket| bucket.iter())
+            .map(|(key, entry)| (key, &entry.value))
+    }
+
+    pub fn entries(&self) -> Vec<(Bytes, Value)> {
+        self.iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    // Store::changes as of the snapshot
+    pub fn changes(&self) -> u64 {
+        self.changes
+    }
+
+    pub fn len(&self) -> usize {
+        self.buckets.iter().map(|bucket| bucket.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod store_tests {
+    use bytes::Bytes;
+
+    use super::{Store, Value};
+
+    #[test]
+    fn set_and_get_value_correctly() {
+        let store = Store::default();
+        let value = Value::String(Bytes::from_static(b"v"));
+
+        assert_eq!(store.set(Bytes::from_static(b"k"), value.clone()), None);
+        assert_eq!(store.get(b"k"), Some(value));
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn remove_value_correctly() {
+        let store = Store::default();
+        store.set(
+            Bytes::from_static(b"k"),
+            Value::String(Bytes::from_static(b"v")),
+        );
+
+        assert!(store.remove(b"k").is_some());
+        assert_eq!(store.get(b"k"), None);
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn clear_values_correctly() {
+        let store = Store::default();
+        store.set(
+            Bytes::from_static(b"k"),
+            Value::String(Bytes::from_static(b"v")),
+        );
+        let version = store.version(b"k");
+        let snapshot = store.snapshot();
+        store.clear();
+
+        assert!(store.is_empty());
+        assert!(store.version(b"k") > version);
+        assert_eq!(snapshot.len(), 1);
+    }
+
+    #[test]
+    fn spread_keys_over_shards_correctly() {
+        let store = Store::default();
+        for i in 0..100 {
+            store.set(
+                Bytes::from(format!("key:{i}")),
+                Value::String(Bytes::from_static(b"v")),
+            );
+        }
+
+        assert_eq!(store.len(), 100);
+        assert!(
+            store
+                .shards
+                .iter()
+                .filter(|shard| {
+                    let shard = shard.lock().unwrap();
+                    shard.buckets.iter().any(|bucket| !bucket.is_empty())
+                })
+                .count()
+                > 1
+        );
+        assert!((0..100).all(|i| store.get(format!("key:{i}").as_bytes()).is_some()));
+    }
+
+    #[test]
+    fn bump_version_on_every_write_correctly() {
+        let store = Store::default();
+        let value = || Value::String(Bytes::from_static(b"v"));
+        let missing = store.version(b"k");
+
+        store.set(Bytes::from_static(b"k"), value());
+        let written = store.version(b"k");
+        store.set(Bytes::from_static(b"k"), value());
+        let overwritten = store.version(b"k");
+        store.remove(b"k");
+
+        assert!(missing < written && written < overwritten);
+        assert!(store.version(b"k") > overwritten);
+        assert_eq!(store.version(b"k"), store.version(b"k"));
+    }
+
+    #[test]
+    fn keep_snapshot_apart_from_later_writes_correctly() {
+        let store = Store::default();
+        let value = |v: &'static [u8]| Value::String(Bytes::from_static(v));
+        for i in 0..100 {
+            store.set(Bytes::from(format!("key:{i}")), value(b"old"));
+        }
+        let snapshot = store.snapshot();
+
+        store.set(Bytes::from_static(b"key:0"), value(b"new"));
+        store.remove(b"key:1");
+        store.set(Bytes::from_static(b"other"), value(b"new"));
+
+        assert_eq!(snapshot.len(), 100);
+        assert_eq!(snapshot.changes(), 100);
+        assert!(snapshot
+            .iter()
+            .all(|(key, v)| key.starts_with(b"key:") && *v == value(b"old")));
+        assert_eq!(store.get(b"key:0"), Some(value(b"new")));
+        assert_eq!(store.len(), 100);
+    }
+}
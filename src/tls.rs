@@ -0,0 +1,198 @@
+// TLS for the tls-port listener, terminated with rustls on the ring provider
+use std::{io, path::Path, sync::Arc};
+
+use rustls_pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+use tokio_rustls::{
+    rustls::{crypto::ring, server::WebPkiClientVerifier, RootCertStore, ServerConfig},
+    TlsAcceptor,
+};
+
+use crate::config::{Config, TlsAuthClients};
+
+// Builds the acceptor from the tls-* parameters, failing as Redis does when
+// clients must be verified but there's no CA to verify them against
+pub fn acceptor(config: &Config) -> io::Result<TlsAcceptor> {
+    let (cert_file, key_file) = match (&config.tls_cert_file, &config.tls_key_file) {
+        (Some(cert_file), Some(key_file)) => (cert_file, key_file),
+        _ => return Err(invalid("tls-cert-file and tls-key-file must be set")),
+    };
+    let certificates = load_certificates(cert_file)?;
+    let key = PrivateKeyDer::from_pem_file(key_file)
+        .map_err(|e| invalid(format!("{}: {e}", key_file.display())))?;
+
+    let provider = Arc::new(ring::default_provider());
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(invalid)?;
+    let builder = match (&config.tls_ca_cert_file, config.tls_auth_clients) {
+        (_, TlsAuthClients::No) => builder.with_no_client_auth(),
+        (None, _) => {
+            return Err(invalid(
+                "tls-ca-cert-file must be set when tls-auth-clients is enabled",
+            ))
+        }
+        (Some(ca_file), auth) => {
+            let mut roots = RootCertStore::empty();
+            for certificate in load_certificates(ca_file)? {
+                roots.add(certificate).map_err(invalid)?;
+            }
+
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+            let verifier = match auth {
+                TlsAuthClients::Optional => verifier.allow_unauthenticated(),
+                _ => verifier,
+            };
+            builder.with_client_cert_verifier(verifier.build().map_err(invalid)?)
+        }
+    };
+
+    let server_config = builder
+        .with_single_cert(certificates, key)
+        .map_err(invalid)?;
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+fn load_certificates(path: &Path) -> io::Result<Vec<CertificateDer<'static>>> {
+    CertificateDer::pem_file_iter(path)
+        .and_then(|certificates| certificates.collect())
+        .map_err(|e| invalid(format!("{}: {e}", path.display())))
+}
+
+fn invalid(error: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, error.to_string())
+}
+
+#[cfg(test)]
+mod tls_tests {
+    use std::{path::PathBuf, sync::Arc, time::Duration};
+
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+    use rustls_pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use tokio_rustls::{
+        rustls::{crypto::ring, ClientConfig, RootCertStore},
+        TlsConnector,
+    };
+
+    use super::acceptor;
+    use crate::{
+        commands::{test_dir, TestDir},
+        config::{Config, TlsAuthClients},
+        server::RedisServer,
+    };
+
+    // A CA with a server and a client certificate signed by it, written to temporary files
+    struct Certificates {
+        ca: CertificateDer<'static>,
+        client: (CertificateDer<'static>, PrivateKeyDer<'static>),
+        config: Config,
+        // removed with the certificates
+        _dir: TestDir,
+    }
+
+    fn certificates(name: &str, tls_auth_clients: TlsAuthClients) -> Certificates {
+        let dir = test_dir(&format!("tls-{name}"));
+        let path = |file: &str| -> PathBuf { dir.join(file) };
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+        let signed = |names: Vec<String>| {
+            let key = KeyPair::generate().unwrap();
+            let certificate = CertificateParams::new(names)
+                .unwrap()
+                .signed_by(&key, &ca, &ca_key)
+                .unwrap();
+            (certificate, key)
+        };
+        let (server, server_key) = signed(vec!["localhost".to_string()]);
+        let (client, client_key) = signed(vec!["client".to_string()]);
+
+        std::fs::write(path("ca.pem"), ca.pem()).unwrap();
+        std::fs::write(path("cert.pem"), server.pem()).unwrap();
+        std::fs::write(path("key.pem"), server_key.serialize_pem()).unwrap();
+
+        Certificates {
+            ca: ca.der().clone(),
+            client: (
+                client.der().clone(),
+                PrivatePkcs8KeyDer::from(client_key.serialize_der()).into(),
+            ),
+            config: Config {
+                port: 0,
+                tls_port: Some(0),
+                tls_cert_file: Some(path("cert.pem")),
+                tls_key_file: Some(path("key.pem")),
+                tls_ca_cert_file: Some(path("ca.pem")),
+                tls_auth_clients,
+                ..Config::default()
+            },
+            _dir: dir,
+        }
+    }
+
+    async fn ping(certificates: &Certificates, with_client_cert: bool) -> std::io::Result<Vec<u8>> {
+        let server = RedisServer::builder()
+            .config(certificates.config.clone())
+            .build()
+            .await?;
+        let addr = server.tls_local_addr()?.unwrap();
+        tokio::spawn(server.run());
+
+        let mut roots = RootCertStore::empty();
+        roots.add(certificates.ca.clone()).unwrap();
+        let builder = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots);
+        let client_config = if with_client_cert {
+            let (certificate, key) = &certificates.client;
+            builder
+                .with_client_auth_cert(vec![certificate.clone()], key.clone_key())
+                .unwrap()
+        } else {
+            builder.with_no_client_auth()
+        };
+
+        let stream = TcpStream::connect(addr).await?;
+        let mut conn = TlsConnector::from(Arc::new(client_config))
+            .connect(ServerName::try_from("localhost").unwrap(), stream)
+            .await?;
+        conn.write_all(b"PING\r\n").await?;
+        let mut reply = vec![0; 7];
+        tokio::time::timeout(Duration::from_secs(5), conn.read_exact(&mut reply)).await??;
+        Ok(reply)
+    }
+
+    #[tokio::test]
+    async fn serve_tls_correctly() {
+        let certificates = certificates("no-auth", TlsAuthClients::No);
+        let result = ping(&certificates, false).await;
+
+        assert!(result.is_ok_and(|r| r == b"+PONG\r\n"));
+    }
+
+    #[tokio::test]
+    async fn serve_tls_with_client_certificate_correctly() {
+        let certificates = certificates("auth", TlsAuthClients::Yes);
+
+        assert!(ping(&certificates, true)
+            .await
+            .is_ok_and(|r| r == b"+PONG\r\n"));
+        assert!(ping(&certificates, false).await.is_err());
+    }
+
+    #[test]
+    fn acceptor_without_ca_fails() {
+        let certificates = certificates("no-ca", TlsAuthClients::Optional);
+        let mut config = certificates.config.clone();
+        config.tls_ca_cert_file = None;
+
+        assert!(acceptor(&config)
+            .is_err_and(|e| e.to_string()
+                == "tls-ca-cert-file must be set when tls-auth-clients is enabled"));
+    }
+}